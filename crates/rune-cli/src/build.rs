@@ -0,0 +1,50 @@
+use crate::{loader, visitor, Args, Config, ExitCode, Io, SharedFlags};
+use anyhow::{anyhow, Context, Result};
+use rune::Options;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) struct Flags {
+    /// The path to write the compiled unit to, which can be executed with
+    /// `rune run`. Defaults to the path of the script with the `.rnc`
+    /// extension.
+    #[structopt(short, long, value_name = "path")]
+    output: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pub(crate) shared: SharedFlags,
+}
+
+pub(crate) fn run(
+    io: &mut Io<'_>,
+    c: &Config,
+    args: &Args,
+    flags: &Flags,
+    options: &Options,
+    path: &Path,
+) -> Result<ExitCode> {
+    let context = flags.shared.context(c)?;
+    let load = loader::load(io, &context, args, options, path, visitor::Attribute::None)?;
+
+    let output = match &flags.output {
+        Some(output) => output.clone(),
+        None => path.with_extension("rnc"),
+    };
+
+    let f = File::create(&output)
+        .with_context(|| anyhow!("cannot create file: {}", output.display()))?;
+    let mut f = BufWriter::new(f);
+    load.unit.to_writer(&mut f, &context)?;
+    f.flush()?;
+
+    writeln!(
+        io.stdout,
+        "Built: {} -> {}",
+        path.display(),
+        output.display()
+    )?;
+    Ok(ExitCode::Success)
+}
@@ -0,0 +1,882 @@
+//! A [Debug Adapter Protocol] server which steps through scripts.
+//!
+//! The server communicates over stdin and stdout, so any output from the
+//! script is captured and forwarded to the client as `output` events.
+//!
+//! [Debug Adapter Protocol]: https://microsoft.github.io/debug-adapter-protocol/
+
+use crate::{Config, ExitCode, Io, SharedFlags};
+use anyhow::{anyhow, bail, Context as _, Result};
+use rune::compile::FileSourceLoader;
+use rune::runtime::{RuntimeContext, VariantData, VmError, VmExecution};
+use rune::termcolor::NoColor;
+use rune::{Context, Diagnostics, Hash, Options, Source, SourceId, Sources, Unit, Value, Vm};
+use rune_modules::capture_io::CaptureIo;
+use serde_json::{json, Value as Json};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use structopt::StructOpt;
+
+/// The only thread reported to the client.
+const THREAD_ID: i64 = 1;
+
+/// The number of instructions executed between checking for messages from the
+/// client while running.
+const BATCH: usize = 1024;
+
+/// Variable references below this value refer to the stack of a frame, and
+/// above it to values being inspected.
+const VALUE_REFERENCE: i64 = 1 << 20;
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) struct Flags {
+    #[structopt(flatten)]
+    pub(crate) shared: SharedFlags,
+}
+
+pub(crate) async fn run(
+    _: &mut Io<'_>,
+    c: &Config,
+    flags: &Flags,
+    options: &Options,
+) -> Result<ExitCode> {
+    let capture = CaptureIo::new();
+    let context = flags.shared.context_with_capture(c, &capture)?;
+
+    let (tx, rx) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let stdin = io::stdin();
+        let mut stdin = stdin.lock();
+
+        while let Ok(Some(message)) = read_message(&mut stdin) {
+            if tx.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut options = *options;
+    options.debug_info(true);
+
+    let mut session = Session {
+        runtime: Arc::new(context.runtime()),
+        context,
+        options,
+        capture,
+        output: Output::default(),
+        breakpoints: HashMap::new(),
+        program: None,
+        mode: None,
+        fresh: false,
+        terminated: false,
+    };
+
+    loop {
+        if session.terminated {
+            break;
+        }
+
+        if session.mode.is_none() {
+            match rx.recv() {
+                Ok(message) => session.handle(message)?,
+                Err(..) => break,
+            }
+
+            continue;
+        }
+
+        loop {
+            match rx.try_recv() {
+                Ok(message) => session.handle(message)?,
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => return Ok(ExitCode::Success),
+            }
+        }
+
+        session.run_batch().await?;
+    }
+
+    Ok(ExitCode::Success)
+}
+
+/// Read a single message with a `Content-Length` header.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Json>> {
+    let mut length = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim();
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            if key.trim().eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| anyhow!("missing content-length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Writes messages to stdout.
+#[derive(Default)]
+struct Output {
+    seq: i64,
+}
+
+impl Output {
+    fn send(&mut self, mut message: Json) -> Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        let body = serde_json::to_vec(&message)?;
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        write!(stdout, "Content-Length: {}\r\n\r\n", body.len())?;
+        stdout.write_all(&body)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    fn response(&mut self, request: &Json, body: Result<Json>) -> Result<()> {
+        let mut message = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+        });
+
+        match body {
+            Ok(body) => {
+                message["success"] = json!(true);
+                message["body"] = body;
+            }
+            Err(error) => {
+                message["success"] = json!(false);
+                message["message"] = json!(error.to_string());
+            }
+        }
+
+        self.send(message)
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> Result<()> {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }))
+    }
+}
+
+/// How the program is being run.
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    /// Run until a breakpoint is hit.
+    Continue,
+    /// Run until a new line is reached in the same or a calling frame.
+    StepOver { line: Option<Line>, depth: usize },
+    /// Run until a new line is reached in any frame.
+    StepIn { line: Option<Line>, depth: usize },
+    /// Run until the current frame returns.
+    StepOut { depth: usize },
+}
+
+/// A line in a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Line {
+    source_id: SourceId,
+    line: usize,
+}
+
+/// A single frame in the call stack.
+struct Frame {
+    /// The instruction pointer of the frame.
+    ip: usize,
+    /// The range of the stack belonging to the frame.
+    stack: std::ops::Range<usize>,
+}
+
+/// A program being debugged.
+struct Program {
+    sources: Sources,
+    unit: Arc<Unit>,
+    execution: VmExecution<Vm>,
+    /// Instruction pointers with breakpoints.
+    breakpoints: HashSet<usize>,
+    /// Start of every function, used to find the function an instruction
+    /// belongs to.
+    functions: BTreeMap<usize, Hash>,
+    /// Values which have been handed out as variable references since the
+    /// program was last stopped.
+    values: Vec<Value>,
+    /// Stop before executing the first instruction.
+    stop_on_entry: bool,
+}
+
+struct Session {
+    context: Context,
+    runtime: Arc<RuntimeContext>,
+    options: Options,
+    capture: CaptureIo,
+    output: Output,
+    /// Breakpoint lines by source path, one-based.
+    breakpoints: HashMap<PathBuf, Vec<usize>>,
+    program: Option<Program>,
+    /// Set while the program is running.
+    mode: Option<Mode>,
+    /// The program was just resumed, so a stop shouldn't be reported before
+    /// executing at least one instruction.
+    fresh: bool,
+    terminated: bool,
+}
+
+impl Session {
+    /// Handle a single message from the client.
+    fn handle(&mut self, message: Json) -> Result<()> {
+        if message["type"] != "request" {
+            return Ok(());
+        }
+
+        let command = message["command"].as_str().unwrap_or_default().to_owned();
+        let arguments = &message["arguments"];
+
+        let body = match command.as_str() {
+            "initialize" => {
+                let body = json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsTerminateRequest": true,
+                });
+
+                self.output.response(&message, Ok(body))?;
+                return self.output.event("initialized", json!({}));
+            }
+            "launch" => self.launch(arguments),
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            "configurationDone" => {
+                if self.program.as_ref().is_some_and(|p| p.stop_on_entry) {
+                    self.output.response(&message, Ok(json!({})))?;
+                    return self.stopped("entry");
+                }
+
+                self.mode = Some(Mode::Continue);
+                self.fresh = false;
+                Ok(json!({}))
+            }
+            "threads" => Ok(json!({
+                "threads": [{ "id": THREAD_ID, "name": "main" }],
+            })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => self.scopes(arguments),
+            "variables" => self.variables(arguments),
+            "continue" => self
+                .resume(|_, _| Mode::Continue)
+                .map(|()| json!({ "allThreadsContinued": true })),
+            "next" => self
+                .resume(|line, depth| Mode::StepOver { line, depth })
+                .map(|()| json!({})),
+            "stepIn" => self
+                .resume(|line, depth| Mode::StepIn { line, depth })
+                .map(|()| json!({})),
+            "stepOut" => self
+                .resume(|_, depth| Mode::StepOut { depth })
+                .map(|()| json!({})),
+            "pause" => {
+                if self.mode.take().is_some() {
+                    self.output.response(&message, Ok(json!({})))?;
+                    return self.stopped("pause");
+                }
+
+                Ok(json!({}))
+            }
+            "disconnect" | "terminate" => {
+                self.terminated = true;
+                self.mode = None;
+                Ok(json!({}))
+            }
+            command => Err(anyhow!("unsupported request `{}`", command)),
+        };
+
+        self.output.response(&message, body)
+    }
+
+    /// Compile the program and prepare it for execution.
+    fn launch(&mut self, arguments: &Json) -> Result<Json> {
+        let path = arguments["program"]
+            .as_str()
+            .ok_or_else(|| anyhow!("missing `program` argument"))?;
+
+        let path = Path::new(path);
+
+        let mut sources = Sources::new();
+        sources.insert(
+            Source::from_path(path).with_context(|| format!("reading file: {}", path.display()))?,
+        );
+
+        let mut diagnostics = Diagnostics::new();
+        let mut source_loader = FileSourceLoader::new();
+
+        let result = rune::prepare(&mut sources)
+            .with_context(&self.context)
+            .with_diagnostics(&mut diagnostics)
+            .with_options(&self.options)
+            .with_source_loader(&mut source_loader)
+            .build();
+
+        let mut out = NoColor::new(Vec::new());
+        diagnostics.emit(&mut out, &sources)?;
+        self.print("stderr", &String::from_utf8_lossy(&out.into_inner()))?;
+
+        let unit = match result {
+            Ok(unit) => Arc::new(unit),
+            Err(..) => bail!("failed to compile `{}`", path.display()),
+        };
+
+        let mut vm = Vm::new(self.runtime.clone(), unit.clone());
+        let execution = vm.execute(["main"], ())?.into_owned();
+
+        let functions = unit
+            .debug_info()
+            .map(|debug| {
+                debug
+                    .functions_rev
+                    .iter()
+                    .map(|(ip, hash)| (*ip, *hash))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        self.program = Some(Program {
+            sources,
+            unit,
+            execution,
+            breakpoints: HashSet::new(),
+            functions,
+            values: Vec::new(),
+            stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or_default(),
+        });
+
+        self.resolve_breakpoints();
+        Ok(json!({}))
+    }
+
+    /// Set the breakpoints for a single source.
+    fn set_breakpoints(&mut self, arguments: &Json) -> Result<Json> {
+        let path = arguments["source"]["path"]
+            .as_str()
+            .ok_or_else(|| anyhow!("missing source path"))?;
+
+        let path = canonical(Path::new(path));
+
+        let lines = arguments["breakpoints"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|b| b["line"].as_u64())
+            .map(|line| line as usize)
+            .collect::<Vec<_>>();
+
+        self.breakpoints.insert(path.clone(), lines.clone());
+        let verified = self.resolve_breakpoints();
+
+        let breakpoints = lines
+            .iter()
+            .map(|line| {
+                json!({
+                    "verified": verified.contains(&(path.clone(), *line)),
+                    "line": line,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    /// Map breakpoint lines to the instructions they should stop at, returning
+    /// the lines which correspond to at least one instruction.
+    ///
+    /// A line stops at every instruction which starts a run of instructions
+    /// on that line, so that it's hit once each time execution reaches it.
+    fn resolve_breakpoints(&mut self) -> HashSet<(PathBuf, usize)> {
+        let mut verified = HashSet::new();
+
+        let program = match &mut self.program {
+            Some(program) => program,
+            None => return verified,
+        };
+
+        program.breakpoints.clear();
+
+        let debug = match program.unit.debug_info() {
+            Some(debug) => debug,
+            None => return verified,
+        };
+
+        let mut previous = None;
+
+        for (ip, inst) in debug.instructions.iter().enumerate() {
+            let line = line_of(
+                &program.sources,
+                inst.source_id,
+                inst.span.start.into_usize(),
+            );
+            let starts_run = line != previous || program.functions.contains_key(&ip);
+            previous = line;
+
+            let (source, line) = match (program.sources.get(inst.source_id), line) {
+                (Some(source), Some(line)) if starts_run => (source, line),
+                _ => continue,
+            };
+
+            let path = match source.path() {
+                Some(path) => canonical(path),
+                None => continue,
+            };
+
+            let line = line.line + 1;
+
+            if let Some(lines) = self.breakpoints.get(&path) {
+                if lines.contains(&line) {
+                    program.breakpoints.insert(ip);
+                    verified.insert((path, line));
+                }
+            }
+        }
+
+        verified
+    }
+
+    /// Resume execution in the mode constructed from the current line and
+    /// call depth.
+    fn resume(&mut self, mode: impl FnOnce(Option<Line>, usize) -> Mode) -> Result<()> {
+        let program = self
+            .program
+            .as_ref()
+            .ok_or_else(|| anyhow!("no program is running"))?;
+
+        let vm = program.execution.vm();
+        let line = program.line_at(vm.ip());
+        self.mode = Some(mode(line, vm.call_frames().len()));
+        self.fresh = true;
+        Ok(())
+    }
+
+    /// Run a batch of instructions, stopping if a breakpoint is hit or a step
+    /// is completed.
+    async fn run_batch(&mut self) -> Result<()> {
+        let mode = match self.mode {
+            Some(mode) => mode,
+            None => return Ok(()),
+        };
+
+        let program = match &mut self.program {
+            Some(program) => program,
+            None => {
+                self.mode = None;
+                return Ok(());
+            }
+        };
+
+        let mut result = Ok(None);
+        let mut stop = None;
+
+        for _ in 0..BATCH {
+            if !std::mem::take(&mut self.fresh) {
+                stop = program.should_stop(mode);
+
+                if stop.is_some() {
+                    break;
+                }
+            }
+
+            result = program.execution.async_step().await;
+
+            if !matches!(result, Ok(None)) {
+                break;
+            }
+        }
+
+        self.flush_output()?;
+
+        match result {
+            Ok(Some(..)) => self.exit(0),
+            Ok(None) => match stop {
+                Some(reason) => {
+                    self.mode = None;
+                    self.stopped(reason)
+                }
+                None => Ok(()),
+            },
+            Err(error) => {
+                self.report_error(&error)?;
+                self.exit(1)
+            }
+        }
+    }
+
+    /// Report that the program stopped for the given reason.
+    fn stopped(&mut self, reason: &str) -> Result<()> {
+        if let Some(program) = &mut self.program {
+            program.values.clear();
+        }
+
+        self.output.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        )
+    }
+
+    /// Report that the program exited with the given code.
+    fn exit(&mut self, code: i64) -> Result<()> {
+        self.mode = None;
+        self.program = None;
+        self.output.event("exited", json!({ "exitCode": code }))?;
+        self.output.event("terminated", json!({}))
+    }
+
+    fn report_error(&mut self, error: &VmError) -> Result<()> {
+        let mut out = NoColor::new(Vec::new());
+
+        if let Some(program) = &self.program {
+            error.emit(&mut out, &program.sources)?;
+        }
+
+        self.print("stderr", &String::from_utf8_lossy(&out.into_inner()))
+    }
+
+    /// Forward output captured from the script.
+    fn flush_output(&mut self) -> Result<()> {
+        let output = self.capture.drain();
+        self.print("stdout", &String::from_utf8_lossy(&output))
+    }
+
+    fn print(&mut self, category: &str, output: &str) -> Result<()> {
+        if output.is_empty() {
+            return Ok(());
+        }
+
+        self.output.event(
+            "output",
+            json!({
+                "category": category,
+                "output": output,
+            }),
+        )
+    }
+
+    fn stack_trace(&mut self) -> Result<Json> {
+        let program = self.stopped_program()?;
+        let mut frames = Vec::new();
+
+        for (id, frame) in program.frames().iter().enumerate() {
+            let name = match program.function_at(frame.ip) {
+                Some(signature) => signature,
+                None => String::from("<unknown>"),
+            };
+
+            let mut json = json!({
+                "id": id,
+                "name": name,
+                "line": 0,
+                "column": 0,
+            });
+
+            let debug = program
+                .unit
+                .debug_info()
+                .and_then(|debug| debug.instruction_at(frame.ip));
+
+            if let Some(debug) = debug {
+                if let Some(source) = program.sources.get(debug.source_id) {
+                    let (line, column) =
+                        source.pos_to_utf16cu_linecol(debug.span.start.into_usize());
+                    json["line"] = json!(line + 1);
+                    json["column"] = json!(column + 1);
+                    json["source"] = source_json(source);
+                }
+            }
+
+            frames.push(json);
+        }
+
+        Ok(json!({
+            "stackFrames": frames,
+            "totalFrames": frames.len(),
+        }))
+    }
+
+    fn scopes(&mut self, arguments: &Json) -> Result<Json> {
+        let frame = arguments["frameId"].as_i64().unwrap_or_default();
+
+        Ok(json!({
+            "scopes": [{
+                "name": "Locals",
+                "variablesReference": frame * 2 + 1,
+                "expensive": false,
+            }, {
+                "name": "Stack",
+                "variablesReference": frame * 2 + 2,
+                "expensive": false,
+            }],
+        }))
+    }
+
+    fn variables(&mut self, arguments: &Json) -> Result<Json> {
+        let reference = arguments["variablesReference"].as_i64().unwrap_or_default();
+        let program = self.stopped_program()?;
+
+        let variables = if reference >= VALUE_REFERENCE {
+            let value = program
+                .values
+                .get((reference - VALUE_REFERENCE) as usize)
+                .cloned()
+                .ok_or_else(|| anyhow!("no such variable reference"))?;
+
+            children(&value)?
+        } else {
+            let frames = program.frames();
+
+            let frame = frames
+                .get(((reference - 1) / 2) as usize)
+                .ok_or_else(|| anyhow!("no such frame"))?;
+
+            let stack = program.execution.vm().stack();
+
+            let values = stack
+                .get(frame.stack.clone())
+                .ok_or_else(|| anyhow!("frame is out of bounds"))?;
+
+            if reference % 2 == 1 {
+                program.locals(frame, values)
+            } else {
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(n, value)| (format!("${}", n), value.clone()))
+                    .collect()
+            }
+        };
+
+        let mut output = Vec::new();
+
+        for (name, value) in variables {
+            output.push(program.variable(name, value)?);
+        }
+
+        Ok(json!({ "variables": output }))
+    }
+
+    /// Get the program, as long as it's stopped.
+    fn stopped_program(&mut self) -> Result<&mut Program> {
+        if self.mode.is_some() {
+            bail!("the program is running");
+        }
+
+        self.program
+            .as_mut()
+            .ok_or_else(|| anyhow!("no program is running"))
+    }
+}
+
+impl Program {
+    /// Test if execution should stop at the current instruction.
+    fn should_stop(&self, mode: Mode) -> Option<&'static str> {
+        let vm = self.execution.vm();
+        let ip = vm.ip();
+
+        if self.breakpoints.contains(&ip) {
+            return Some("breakpoint");
+        }
+
+        let line = self.line_at(ip)?;
+        let depth = vm.call_frames().len();
+
+        let stop = match mode {
+            Mode::Continue => false,
+            Mode::StepOver {
+                line: from,
+                depth: from_depth,
+            } => depth < from_depth || depth == from_depth && Some(line) != from,
+            Mode::StepIn {
+                line: from,
+                depth: from_depth,
+            } => depth != from_depth || Some(line) != from,
+            Mode::StepOut { depth: from_depth } => depth < from_depth,
+        };
+
+        stop.then_some("step")
+    }
+
+    /// Get the line of the given instruction.
+    fn line_at(&self, ip: usize) -> Option<Line> {
+        let debug = self.unit.debug_info()?.instruction_at(ip)?;
+        line_of(
+            &self.sources,
+            debug.source_id,
+            debug.span.start.into_usize(),
+        )
+    }
+
+    /// Get the signature of the function the given instruction belongs to.
+    fn function_at(&self, ip: usize) -> Option<String> {
+        let (_, hash) = self.functions.range(..=ip).next_back()?;
+        let signature = self.unit.debug_info()?.functions.get(hash)?;
+        Some(signature.to_string())
+    }
+
+    /// Get the named local variables of the given frame, where shadowed
+    /// variables are left out.
+    fn locals(&self, frame: &Frame, values: &[Value]) -> Vec<(String, Value)> {
+        let debug = match self.unit.debug_info() {
+            Some(debug) => debug,
+            None => return Vec::new(),
+        };
+
+        let mut locals = Vec::<(String, Value)>::new();
+
+        for local in debug.locals_at(frame.ip) {
+            if let Some(value) = values.get(local.offset) {
+                locals.retain(|(name, _)| **name != *local.name);
+                locals.push((local.name.to_string(), value.clone()));
+            }
+        }
+
+        locals
+    }
+
+    /// Get the frames of the call stack, innermost first.
+    fn frames(&self) -> Vec<Frame> {
+        let vm = self.execution.vm();
+        let stack = vm.stack();
+        let call_frames = vm.call_frames();
+
+        let mut frames = vec![Frame {
+            ip: vm.ip(),
+            stack: stack.stack_bottom()..stack.len(),
+        }];
+
+        let mut top = stack.stack_bottom();
+
+        for frame in call_frames.iter().rev() {
+            frames.push(Frame {
+                ip: frame.ip(),
+                stack: frame.stack_bottom()..top,
+            });
+
+            top = frame.stack_bottom();
+        }
+
+        frames
+    }
+
+    /// Describe a variable, handing out a reference to it if it has children.
+    fn variable(&mut self, name: String, value: Value) -> Result<Json> {
+        let mut string = String::new();
+
+        let vm = self.execution.vm_mut();
+
+        if !matches!(vm.with(|| value.string_debug(&mut string)), Ok(Ok(()))) {
+            string = format!("{:?}", value);
+        }
+
+        let type_info = value.type_info()?;
+
+        let reference = if children(&value)?.is_empty() {
+            0
+        } else {
+            self.values.push(value);
+            VALUE_REFERENCE + self.values.len() as i64 - 1
+        };
+
+        Ok(json!({
+            "name": name,
+            "value": string,
+            "type": type_info.to_string(),
+            "variablesReference": reference,
+        }))
+    }
+}
+
+/// Get the line that the given offset in a source is on.
+fn line_of(sources: &Sources, source_id: SourceId, offset: usize) -> Option<Line> {
+    let source = sources.get(source_id)?;
+
+    Some(Line {
+        source_id,
+        line: source.line_index(offset),
+    })
+}
+
+/// The values contained in a value which can be inspected.
+fn children(value: &Value) -> Result<Vec<(String, Value)>> {
+    let indexed = |values: &[Value]| {
+        values
+            .iter()
+            .enumerate()
+            .map(|(n, value)| (n.to_string(), value.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    let named = |object: &rune::runtime::Object| {
+        object
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    Ok(match value {
+        Value::Vec(vec) => indexed(&vec.borrow_ref()?),
+        Value::Tuple(tuple) => indexed(&tuple.borrow_ref()?),
+        Value::Object(object) => named(&*object.borrow_ref()?),
+        Value::TupleStruct(tuple) => indexed(tuple.borrow_ref()?.data()),
+        Value::Struct(object) => named(object.borrow_ref()?.data()),
+        Value::Variant(variant) => match variant.borrow_ref()?.data() {
+            VariantData::Unit => Vec::new(),
+            VariantData::Tuple(tuple) => indexed(tuple),
+            VariantData::Struct(object) => named(object),
+        },
+        Value::Option(option) => match &*option.borrow_ref()? {
+            Some(value) => vec![(String::from("0"), value.clone())],
+            None => Vec::new(),
+        },
+        Value::Result(result) => match &*result.borrow_ref()? {
+            Ok(value) | Err(value) => vec![(String::from("0"), value.clone())],
+        },
+        _ => Vec::new(),
+    })
+}
+
+fn source_json(source: &Source) -> Json {
+    match source.path() {
+        Some(path) => json!({
+            "name": source.name(),
+            "path": path.display().to_string(),
+        }),
+        None => json!({ "name": source.name() }),
+    }
+}
+
+/// Canonicalize a path if possible, so that paths from the client can be
+/// compared to the paths of sources.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
@@ -27,6 +27,23 @@ pub(crate) fn load(
 ) -> Result<Load> {
     let shared = args.cmd.shared();
 
+    // NB: units built with `rune build` are loaded as-is. Since they have no
+    // sources to report diagnostics against, there's nothing else to load.
+    if path.extension() == Some(OsStr::new("rnc")) {
+        trace!("loading unit: {}", path.display());
+
+        let f = fs::File::open(path)
+            .with_context(|| anyhow!("cannot read file: {}", path.display()))?;
+        let unit = Unit::from_reader(io::BufReader::new(f), context)
+            .with_context(|| anyhow!("cannot load unit: {}", path.display()))?;
+
+        return Ok(Load {
+            unit: Arc::new(unit),
+            sources: Sources::new(),
+            functions: Vec::new(),
+        });
+    }
+
     let bytecode_path = path.with_extension("rnc");
 
     let source =
@@ -41,7 +58,7 @@ pub(crate) fn load(
     let maybe_unit = if use_cache {
         let f = fs::File::open(&bytecode_path)?;
 
-        match bincode::deserialize_from::<_, Unit>(f) {
+        match Unit::from_reader(io::BufReader::new(f), context) {
             Ok(unit) => {
                 trace!("using cache: {}", bytecode_path.display());
                 Some(Arc::new(unit))
@@ -83,7 +100,7 @@ pub(crate) fn load(
             if options.bytecode {
                 trace!("serializing cache: {}", bytecode_path.display());
                 let f = fs::File::create(&bytecode_path)?;
-                bincode::serialize_into(f, &unit)?;
+                unit.to_writer(io::BufWriter::new(f), context)?;
             }
 
             (Arc::new(unit), functions.into_functions())
@@ -62,9 +62,13 @@ use structopt::StructOpt;
 use tracing_subscriber::filter::EnvFilter;
 
 mod benches;
+mod build;
 mod check;
+mod dap;
 mod doc;
+mod format;
 mod loader;
+mod repl;
 mod run;
 mod tests;
 mod visitor;
@@ -80,6 +84,8 @@ struct Io<'a> {
 enum Command {
     /// Run checks but do not execute
     Check(check::Flags),
+    /// Compile the given script into a unit which can be run later
+    Build(build::Flags),
     /// Build documentation.
     Doc(doc::Flags),
     /// Run all tests but do not execute
@@ -88,12 +94,19 @@ enum Command {
     Bench(benches::Flags),
     /// Run the designated script
     Run(run::Flags),
+    /// Format the given sources
+    Fmt(format::Flags),
+    /// Start an interactive read-eval-print loop
+    Repl(repl::Flags),
+    /// Start a Debug Adapter Protocol server over stdin and stdout
+    Dap(dap::Flags),
 }
 
 impl Command {
     fn propagate_related_flags(&mut self, c: &mut Config) {
         match self {
             Command::Check(..) => {}
+            Command::Build(..) => {}
             Command::Doc(..) => {}
             Command::Test(..) => {
                 c.test = true;
@@ -104,33 +117,48 @@ impl Command {
             Command::Run(args) => {
                 args.propagate_related_flags();
             }
+            Command::Fmt(..) => {}
+            Command::Repl(..) => {}
+            Command::Dap(..) => {}
         }
     }
 
     fn describe(&self) -> &'static str {
         match self {
             Command::Check(..) => "Checking",
+            Command::Build(..) => "Building",
             Command::Doc(..) => "Building documentation",
             Command::Test(..) => "Testing",
             Command::Bench(..) => "Benchmarking",
             Command::Run(..) => "Running",
+            Command::Fmt(..) => "Formatting",
+            Command::Repl(..) => "Evaluating",
+            Command::Dap(..) => "Debugging",
         }
     }
 
     fn shared(&self) -> &SharedFlags {
         match self {
             Command::Check(args) => &args.shared,
+            Command::Build(args) => &args.shared,
             Command::Doc(args) => &args.shared,
             Command::Test(args) => &args.shared,
             Command::Bench(args) => &args.shared,
             Command::Run(args) => &args.shared,
+            Command::Fmt(args) => &args.shared,
+            Command::Repl(args) => &args.shared,
+            Command::Dap(args) => &args.shared,
         }
     }
 
     fn bins_test(&self) -> Option<WorkspaceFilter<'_>> {
         if !matches!(
             self,
-            Command::Run(..) | Command::Check(..) | Command::Doc(..)
+            Command::Run(..)
+                | Command::Build(..)
+                | Command::Check(..)
+                | Command::Doc(..)
+                | Command::Fmt(..)
         ) {
             return None;
         }
@@ -147,7 +175,7 @@ impl Command {
     fn tests_test(&self) -> Option<WorkspaceFilter<'_>> {
         if !matches!(
             self,
-            Command::Test(..) | Command::Check(..) | Command::Doc(..)
+            Command::Test(..) | Command::Check(..) | Command::Doc(..) | Command::Fmt(..)
         ) {
             return None;
         }
@@ -164,7 +192,11 @@ impl Command {
     fn examples_test(&self) -> Option<WorkspaceFilter<'_>> {
         if !matches!(
             self,
-            Command::Run(..) | Command::Check(..) | Command::Doc(..)
+            Command::Run(..)
+                | Command::Build(..)
+                | Command::Check(..)
+                | Command::Doc(..)
+                | Command::Fmt(..)
         ) {
             return None;
         }
@@ -181,7 +213,7 @@ impl Command {
     fn benches_test(&self) -> Option<WorkspaceFilter<'_>> {
         if !matches!(
             self,
-            Command::Bench(..) | Command::Check(..) | Command::Doc(..)
+            Command::Bench(..) | Command::Check(..) | Command::Doc(..) | Command::Fmt(..)
         ) {
             return None;
         }
@@ -223,6 +255,10 @@ struct SharedFlags {
     /// macros[=<true/false>] - Enable or disable macros (experimental).
     ///
     /// bytecode[=<true/false>] - Enable or disable bytecode caching (experimental).
+    ///
+    /// deny-non-exhaustive[=<true/false>] - Report non-exhaustive match expressions as errors.
+    ///
+    /// type-check[=<true/false>] - Check type annotations of functions and let bindings.
     #[structopt(name = "option", short = "O", number_of_values = 1)]
     compiler_options: Vec<String>,
 
@@ -339,7 +375,15 @@ impl Args {
                 options.test(true);
                 options.bytecode(false);
             }
-            Command::Bench(_) | Command::Doc(..) | Command::Run(_) => (),
+            Command::Build(_) => {
+                options.bytecode(false);
+            }
+            Command::Bench(_)
+            | Command::Doc(..)
+            | Command::Run(_)
+            | Command::Fmt(..)
+            | Command::Repl(..)
+            | Command::Dap(..) => (),
         }
 
         for option in &self.cmd.shared().compiler_options {
@@ -546,11 +590,30 @@ fn populate_config(io: &mut Io<'_>, c: &mut Config, args: &Args) -> Result<()> {
 async fn main_with_out(io: &mut Io<'_>, mut args: Args) -> Result<ExitCode> {
     let mut c = Config::default();
     args.cmd.propagate_related_flags(&mut c);
+
+    // The repl and the debug adapter load paths themselves and don't need a
+    // workspace.
+    match &args.cmd {
+        Command::Repl(flags) => {
+            let options = args.options()?;
+            return repl::run(io, &c, flags, &options).await;
+        }
+        Command::Dap(flags) => {
+            let options = args.options()?;
+            return dap::run(io, &c, flags, &options).await;
+        }
+        _ => {}
+    }
+
     populate_config(io, &mut c, &args)?;
 
     let entries = std::mem::take(&mut c.entries);
     let options = args.options()?;
 
+    if let Command::Test(flags) = &args.cmd {
+        tests::create_coverage(flags)?;
+    }
+
     let what = args.cmd.describe();
     let verbose = c.verbose;
     let recursive = args.cmd.shared().recursive;
@@ -597,6 +660,7 @@ async fn run_path(
 ) -> Result<ExitCode> {
     match &args.cmd {
         Command::Check(flags) => check::run(io, c, flags, options, path),
+        Command::Build(flags) => build::run(io, c, args, flags, options, path),
         Command::Doc(flags) => doc::run(io, c, flags, options, path),
         Command::Test(flags) => {
             let capture_io = rune_modules::capture_io::CaptureIo::new();
@@ -637,5 +701,9 @@ async fn run_path(
             let load = loader::load(io, &context, args, options, path, visitor::Attribute::None)?;
             run::run(io, c, flags, &context, load.unit, &load.sources).await
         }
+        Command::Fmt(flags) => format::run(io, flags, path),
+        Command::Repl(..) | Command::Dap(..) => {
+            unreachable!("the repl and the debug adapter are run before any paths are loaded")
+        }
     }
 }
@@ -0,0 +1,427 @@
+use crate::run::{self, Dump};
+use crate::{Config, ExitCode, Io, SharedFlags};
+use anyhow::{Context as _, Result};
+use rune::ast::{self, Spanned};
+use rune::compile::FileSourceLoader;
+use rune::parse::{Expectation, ParseError, ParseErrorKind, Parser};
+use rune::runtime::RuntimeContext;
+use rune::{Context, Diagnostics, Options, Source, SourceId, Sources, Unit, Value, Vm};
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+use structopt::StructOpt;
+
+/// The name of the function that input is evaluated in.
+const EVAL_FN: &str = "repl_eval";
+
+const HELP: &str = "\
+Enter items to define them, or statements and expressions to evaluate them.
+Items are kept between inputs, while variables declared with `let` only live
+for the input they're declared in. Input continues on the next line while it's
+incomplete, an empty line forces it to be evaluated.
+
+Commands:
+  :dump [what]  Dump information about the current unit, where `what` is one
+                of `all` (default), `instructions`, `functions`, `constants`,
+                `native-functions`, or `native-types`.
+  :type <expr>  Evaluate an expression and print the type of its value.
+  :help         Print this help.
+  :quit         Exit the repl.";
+
+#[derive(StructOpt, Debug, Clone)]
+pub(crate) struct Flags {
+    #[structopt(flatten)]
+    pub(crate) shared: SharedFlags,
+}
+
+/// Parsed input, split into items which are kept between inputs and
+/// statements which are evaluated.
+struct Input {
+    /// Source text of the items in the input.
+    items: String,
+    /// The statements in the input, with the items blanked out so that spans
+    /// still correspond to the input.
+    statements: Option<String>,
+}
+
+/// The state of the repl, which is kept between inputs.
+struct Repl<'a> {
+    flags: &'a Flags,
+    options: &'a Options,
+    context: Context,
+    runtime: Arc<RuntimeContext>,
+    /// Items defined so far.
+    items: Vec<Source>,
+    /// The virtual machine for the last unit that was built.
+    vm: Vm,
+    /// Sources of the last unit that was built.
+    sources: Sources,
+    /// The number of inputs evaluated, used to name sources.
+    count: usize,
+}
+
+pub(crate) async fn run(
+    io: &mut Io<'_>,
+    c: &Config,
+    flags: &Flags,
+    options: &Options,
+) -> Result<ExitCode> {
+    let context = flags.shared.context(c)?;
+    let runtime = Arc::new(context.runtime());
+    let vm = Vm::new(runtime.clone(), Arc::new(Unit::default()));
+
+    let mut repl = Repl {
+        flags,
+        options,
+        context,
+        runtime,
+        items: Vec::new(),
+        vm,
+        sources: Sources::new(),
+        count: 0,
+    };
+
+    for path in &flags.shared.paths {
+        let source =
+            Source::from_path(path).with_context(|| format!("reading file: {}", path.display()))?;
+
+        let mut items = repl.items.clone();
+        items.push(source);
+
+        if repl.build(io, &items, None)?.is_none() {
+            return Ok(ExitCode::Failure);
+        }
+
+        repl.items = items;
+    }
+
+    writeln!(
+        io.stdout,
+        "Rune {} (enter `:help` for help)",
+        crate::VERSION.trim()
+    )?;
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut buffer = String::new();
+
+    loop {
+        write!(io.stdout, "{}", if buffer.is_empty() { "> " } else { ". " })?;
+        io.stdout.flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => {
+                writeln!(io.stdout)?;
+                break;
+            }
+        };
+
+        if buffer.is_empty() {
+            let trimmed = line.trim();
+
+            if let Some(command) = trimmed.strip_prefix(':') {
+                let (command, rest) = match command.split_once(char::is_whitespace) {
+                    Some((command, rest)) => (command, rest.trim()),
+                    None => (command, ""),
+                };
+
+                match command {
+                    "q" | "quit" => break,
+                    "h" | "help" => writeln!(io.stdout, "{}", HELP)?,
+                    "dump" => repl.dump(io, rest)?,
+                    "t" | "type" => repl.type_of(io, rest).await?,
+                    _ => writeln!(io.stdout, "unknown command `:{}`, see `:help`", command)?,
+                }
+
+                continue;
+            }
+
+            if trimmed.is_empty() {
+                continue;
+            }
+        }
+
+        let force = line.trim().is_empty();
+        buffer.push_str(&line);
+        buffer.push('\n');
+
+        let input = match parse(&buffer) {
+            Ok(input) => input,
+            Err(error) if !force && is_incomplete(&error) => continue,
+            Err(error) => {
+                let source = Source::new(repl.next_name(), &buffer);
+                emit_parse_error(io, source, error)?;
+                buffer.clear();
+                continue;
+            }
+        };
+
+        buffer.clear();
+        repl.eval(io, input).await?;
+    }
+
+    Ok(ExitCode::Success)
+}
+
+impl Repl<'_> {
+    /// Get the name of the source for the next input.
+    fn next_name(&mut self) -> String {
+        self.count += 1;
+        format!("<repl:{}>", self.count)
+    }
+
+    /// Build the given items along with statements to evaluate, returning the
+    /// unit if it was built successfully.
+    fn build(
+        &mut self,
+        io: &mut Io<'_>,
+        items: &[Source],
+        statements: Option<Source>,
+    ) -> Result<Option<Arc<Unit>>> {
+        let mut sources = Sources::new();
+
+        for source in items {
+            sources.insert(source.clone());
+        }
+
+        if let Some(statements) = statements {
+            sources.insert(statements);
+        }
+
+        let mut diagnostics = if self.flags.shared.warnings {
+            Diagnostics::new()
+        } else {
+            Diagnostics::without_warnings()
+        };
+
+        let mut source_loader = FileSourceLoader::new();
+
+        let result = rune::prepare(&mut sources)
+            .with_context(&self.context)
+            .with_diagnostics(&mut diagnostics)
+            .with_options(self.options)
+            .with_source_loader(&mut source_loader)
+            .build();
+
+        diagnostics.emit(io.stdout, &sources)?;
+
+        let unit = match result {
+            Ok(unit) => Arc::new(unit),
+            Err(..) => return Ok(None),
+        };
+
+        self.vm = Vm::new(self.runtime.clone(), unit.clone());
+        self.sources = sources;
+        Ok(Some(unit))
+    }
+
+    /// Evaluate the given input, defining its items and printing the value
+    /// of its statements.
+    async fn eval(&mut self, io: &mut Io<'_>, input: Input) -> Result<()> {
+        let name = self.next_name();
+        let mut items = self.items.clone();
+
+        if !input.items.trim().is_empty() {
+            items.push(Source::new(format!("{} (items)", name), &input.items));
+        }
+
+        let statements = input
+            .statements
+            .map(|statements| Source::new(name, wrap(&statements)));
+
+        let has_statements = statements.is_some();
+
+        if self.build(io, &items, statements)?.is_none() {
+            return Ok(());
+        }
+
+        self.items = items;
+
+        if !has_statements {
+            return Ok(());
+        }
+
+        if let Some(value) = self.call(io).await? {
+            if !matches!(value, Value::Unit) {
+                let string = self.debug(&value)?;
+                writeln!(io.stdout, "{}", string)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evaluate an expression and print the type of its value.
+    async fn type_of(&mut self, io: &mut Io<'_>, expr: &str) -> Result<()> {
+        if expr.is_empty() {
+            writeln!(io.stdout, "usage: `:type <expr>`")?;
+            return Ok(());
+        }
+
+        let source = Source::new(self.next_name(), wrap(expr));
+        let items = self.items.clone();
+
+        if self.build(io, &items, Some(source))?.is_none() {
+            return Ok(());
+        }
+
+        if let Some(value) = self.call(io).await? {
+            let type_info = value.type_info()?;
+            let type_hash = value.type_hash()?;
+            writeln!(io.stdout, "{} ({})", type_info, type_hash)?;
+        }
+
+        Ok(())
+    }
+
+    /// Call the function that statements are evaluated in, emitting any
+    /// errors raised.
+    async fn call(&mut self, io: &mut Io<'_>) -> Result<Option<Value>> {
+        match self.vm.async_call(&[EVAL_FN], ()).await {
+            Ok(value) => Ok(Some(value)),
+            Err(error) => {
+                error.emit(io.stdout, &self.sources)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Debug format a value using the `STRING_DEBUG` protocol.
+    fn debug(&mut self, value: &Value) -> Result<String> {
+        let mut s = String::new();
+
+        if self.vm.with(|| value.string_debug(&mut s))?.is_err() {
+            s = format!("{:?}", value);
+        }
+
+        Ok(s)
+    }
+
+    /// Dump information about the last unit which was built.
+    fn dump(&mut self, io: &mut Io<'_>, what: &str) -> Result<()> {
+        let mut dump = Dump {
+            with_source: true,
+            ..Dump::default()
+        };
+
+        match what {
+            "" | "all" => {
+                dump.unit = true;
+                dump.instructions = true;
+                dump.functions = true;
+                dump.constants = true;
+            }
+            "instructions" => {
+                dump.unit = true;
+                dump.instructions = true;
+            }
+            "functions" => {
+                dump.unit = true;
+                dump.functions = true;
+            }
+            "constants" => {
+                dump.unit = true;
+                dump.constants = true;
+            }
+            "native-functions" => {
+                dump.native_functions = true;
+            }
+            "native-types" => {
+                dump.native_types = true;
+            }
+            what => {
+                writeln!(io.stdout, "cannot dump `{}`, see `:help`", what)?;
+                return Ok(());
+            }
+        }
+
+        let unit = self.vm.unit().clone();
+        run::dump(io, &self.context, &unit, &self.sources, &dump)
+    }
+}
+
+/// Wrap statements in the function that they're evaluated in.
+///
+/// The function is opened on the same line as the statements so that line
+/// numbers in diagnostics correspond to the input.
+fn wrap(statements: &str) -> String {
+    format!("pub fn {}() {{ {}\n}}", EVAL_FN, statements)
+}
+
+/// Parse the given input, splitting it into items and statements.
+fn parse(source: &str) -> Result<Input, ParseError> {
+    let mut parser = Parser::new(source, SourceId::empty(), false);
+
+    let mut items = String::new();
+    let mut statements = source.to_owned();
+    let mut has_statements = false;
+
+    while !parser.is_eof()? {
+        let stmt = parser.parse::<ast::Stmt>()?;
+
+        let item = match &stmt {
+            ast::Stmt::Item(ast::Item::MacroCall(..), _) => None,
+            ast::Stmt::Item(item, semi) => Some((item, semi)),
+            _ => None,
+        };
+
+        let (item, semi) = match item {
+            Some(item) => item,
+            None => {
+                has_statements = true;
+                continue;
+            }
+        };
+
+        let mut span = item.span();
+
+        if let Some(semi) = semi {
+            span = span.join(semi.span());
+        }
+
+        let range = span.range();
+        items.push_str(&source[range.clone()]);
+        items.push('\n');
+
+        // Blank out the item while keeping line breaks, so that spans in
+        // diagnostics still correspond to the input.
+        let blank = source[range.clone()]
+            .chars()
+            .map(|c| if c == '\n' { '\n' } else { ' ' })
+            .collect::<String>();
+
+        statements.replace_range(range, &blank);
+    }
+
+    Ok(Input {
+        items,
+        statements: has_statements.then_some(statements),
+    })
+}
+
+/// Test if the parse error indicates that the input is incomplete, in which
+/// case more input is read before evaluating it.
+fn is_incomplete(error: &ParseError) -> bool {
+    matches!(
+        error.kind(),
+        ParseErrorKind::UnexpectedEof
+            | ParseErrorKind::Expected {
+                actual: Expectation::Description("eof"),
+                ..
+            }
+            | ParseErrorKind::UnterminatedStrLit
+            | ParseErrorKind::UnterminatedByteStrLit
+            | ParseErrorKind::ExpectedMultilineCommentTerm
+    )
+}
+
+/// Emit a parse error in the given source.
+fn emit_parse_error(io: &mut Io<'_>, source: Source, error: ParseError) -> Result<()> {
+    let mut sources = Sources::new();
+    let source_id = sources.insert(source);
+
+    let mut diagnostics = Diagnostics::new();
+    diagnostics.error(source_id, error);
+    diagnostics.emit(io.stdout, &sources)?;
+    Ok(())
+}
@@ -1,8 +1,10 @@
 use crate::{Config, ExitCode, Io, SharedFlags};
-use anyhow::Result;
-use rune::runtime::{VmError, VmExecution};
+use anyhow::{Context as _, Result};
+use rune::runtime::{Profiler, VmError, VmExecution};
 use rune::{Context, Sources, Unit, Value, Vm};
-use std::io::Write;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
 use structopt::StructOpt;
@@ -50,6 +52,11 @@ pub(crate) struct Flags {
     #[structopt(long)]
     with_source: bool,
 
+    /// Profile the program, writing the time spent in each function to the
+    /// given path in the collapsed stack format used by flamegraph tools.
+    #[structopt(long, value_name = "path")]
+    profile: Option<PathBuf>,
+
     #[structopt(flatten)]
     pub(crate) shared: SharedFlags,
 }
@@ -67,6 +74,18 @@ impl Flags {
         }
     }
 
+    fn dump_options(&self) -> Dump {
+        Dump {
+            native_functions: self.dump_native_functions,
+            native_types: self.dump_native_types,
+            unit: self.dump_unit(),
+            instructions: self.emit_instructions(),
+            functions: self.dump_functions,
+            constants: self.dump_constants,
+            with_source: self.with_source,
+        }
+    }
+
     fn emit_instructions(&self) -> bool {
         self.dump_unit || self.emit_instructions
     }
@@ -82,6 +101,25 @@ impl Flags {
     }
 }
 
+/// What to dump about a context and unit.
+#[derive(Default)]
+pub(crate) struct Dump {
+    /// Dump native functions.
+    pub(crate) native_functions: bool,
+    /// Dump native types.
+    pub(crate) native_types: bool,
+    /// Dump default information about the unit.
+    pub(crate) unit: bool,
+    /// Dump unit instructions.
+    pub(crate) instructions: bool,
+    /// Dump dynamic functions.
+    pub(crate) functions: bool,
+    /// Dump constants from the unit.
+    pub(crate) constants: bool,
+    /// Include source code references where appropriate.
+    pub(crate) with_source: bool,
+}
+
 enum TraceError {
     Io(std::io::Error),
     VmError(VmError),
@@ -101,76 +139,20 @@ pub(crate) async fn run(
     unit: Arc<Unit>,
     sources: &Sources,
 ) -> Result<ExitCode> {
-    if args.dump_native_functions {
-        writeln!(io.stdout, "# functions")?;
-
-        for (i, (hash, f)) in context.iter_functions().enumerate() {
-            writeln!(io.stdout, "{:04} = {} ({})", i, f, hash)?;
-        }
-    }
-
-    if args.dump_native_types {
-        writeln!(io.stdout, "# types")?;
-
-        for (i, (hash, ty)) in context.iter_types().enumerate() {
-            writeln!(io.stdout, "{:04} = {} ({})", i, ty, hash)?;
-        }
-    }
-
-    if args.dump_unit() {
-        if args.emit_instructions() {
-            let mut o = io.stdout.lock();
-            writeln!(o, "# instructions")?;
-            unit.emit_instructions(&mut o, sources, args.with_source)?;
-        }
-
-        let mut functions = unit.iter_functions().peekable();
-        let mut strings = unit.iter_static_strings().peekable();
-        let mut keys = unit.iter_static_object_keys().peekable();
-        let mut constants = unit.iter_constants().peekable();
-
-        if args.dump_functions && functions.peek().is_some() {
-            writeln!(io.stdout, "# dynamic functions")?;
+    dump(io, context, &unit, sources, &args.dump_options())?;
 
-            for (hash, kind) in functions {
-                if let Some(signature) = unit.debug_info().and_then(|d| d.functions.get(&hash)) {
-                    writeln!(io.stdout, "{} = {}", hash, signature)?;
-                } else {
-                    writeln!(io.stdout, "{} = {}", hash, kind)?;
-                }
-            }
-        }
-
-        if strings.peek().is_some() {
-            writeln!(io.stdout, "# strings")?;
-
-            for string in strings {
-                writeln!(io.stdout, "{} = {:?}", string.hash(), string)?;
-            }
-        }
+    let runtime = Arc::new(context.runtime());
 
-        if args.dump_constants && constants.peek().is_some() {
-            writeln!(io.stdout, "# constants")?;
+    let last = Instant::now();
 
-            for constant in constants {
-                writeln!(io.stdout, "{} = {:?}", constant.0, constant.1)?;
-            }
-        }
+    let mut vm = Vm::new(runtime, unit);
 
-        if keys.peek().is_some() {
-            writeln!(io.stdout, "# object keys")?;
+    let profiler = args.profile.as_ref().map(|_| Profiler::new());
 
-            for (hash, keys) in keys {
-                writeln!(io.stdout, "{} = {:?}", hash, keys)?;
-            }
-        }
+    if let Some(profiler) = &profiler {
+        vm.set_profiler(profiler.clone());
     }
 
-    let runtime = Arc::new(context.runtime());
-
-    let last = Instant::now();
-
-    let mut vm = Vm::new(runtime, unit);
     let mut execution: VmExecution<_> = vm.execute(&["main"], ())?;
     let result = if args.trace {
         match do_trace(
@@ -211,6 +193,18 @@ pub(crate) async fn run(
         }
     };
 
+    if let (Some(path), Some(profiler)) = (&args.profile, &profiler) {
+        let file =
+            File::create(path).with_context(|| format!("creating profile: {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        profiler.write_folded(&mut out)?;
+        out.flush()?;
+
+        if c.verbose {
+            writeln!(io.stderr, "== profile written to {}", path.display())?;
+        }
+    }
+
     if args.dump_stack {
         writeln!(io.stdout, "# full stack dump after halting")?;
 
@@ -275,6 +269,82 @@ pub(crate) async fn run(
     }
 }
 
+/// Dump information about a context and the unit compiled with it.
+pub(crate) fn dump(
+    io: &mut Io<'_>,
+    context: &Context,
+    unit: &Unit,
+    sources: &Sources,
+    dump: &Dump,
+) -> Result<()> {
+    if dump.native_functions {
+        writeln!(io.stdout, "# functions")?;
+
+        for (i, (hash, f)) in context.iter_functions().enumerate() {
+            writeln!(io.stdout, "{:04} = {} ({})", i, f, hash)?;
+        }
+    }
+
+    if dump.native_types {
+        writeln!(io.stdout, "# types")?;
+
+        for (i, (hash, ty)) in context.iter_types().enumerate() {
+            writeln!(io.stdout, "{:04} = {} ({})", i, ty, hash)?;
+        }
+    }
+
+    if dump.unit {
+        if dump.instructions {
+            let mut o = io.stdout.lock();
+            writeln!(o, "# instructions")?;
+            unit.emit_instructions(&mut o, sources, dump.with_source)?;
+        }
+
+        let mut functions = unit.iter_functions().peekable();
+        let mut strings = unit.iter_static_strings().peekable();
+        let mut keys = unit.iter_static_object_keys().peekable();
+        let mut constants = unit.iter_constants().peekable();
+
+        if dump.functions && functions.peek().is_some() {
+            writeln!(io.stdout, "# dynamic functions")?;
+
+            for (hash, kind) in functions {
+                if let Some(signature) = unit.debug_info().and_then(|d| d.functions.get(&hash)) {
+                    writeln!(io.stdout, "{} = {}", hash, signature)?;
+                } else {
+                    writeln!(io.stdout, "{} = {}", hash, kind)?;
+                }
+            }
+        }
+
+        if strings.peek().is_some() {
+            writeln!(io.stdout, "# strings")?;
+
+            for string in strings {
+                writeln!(io.stdout, "{} = {:?}", string.hash(), string)?;
+            }
+        }
+
+        if dump.constants && constants.peek().is_some() {
+            writeln!(io.stdout, "# constants")?;
+
+            for constant in constants {
+                writeln!(io.stdout, "{} = {:?}", constant.0, constant.1)?;
+            }
+        }
+
+        if keys.peek().is_some() {
+            writeln!(io.stdout, "# object keys")?;
+
+            for (hash, keys) in keys {
+                writeln!(io.stdout, "{} = {:?}", hash, keys)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Perform a detailed trace of the program.
 async fn do_trace<T>(
     io: &mut Io<'_>,
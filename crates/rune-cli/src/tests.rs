@@ -1,10 +1,12 @@
 use crate::{ExitCode, Io, SharedFlags};
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use rune::compile::ItemBuf;
-use rune::runtime::{Unit, Value, Vm, VmError};
+use rune::runtime::{Coverage, Unit, Value, Vm, VmError};
 use rune::{Context, Hash, Sources};
 use rune_modules::capture_io::CaptureIo;
-use std::io::Write;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Instant;
 use structopt::StructOpt;
@@ -19,6 +21,11 @@ pub(crate) struct Flags {
     #[structopt(long)]
     no_fail_fast: bool,
 
+    /// Record which lines and branches the tests execute, writing an LCOV
+    /// report to the given path.
+    #[structopt(long, value_name = "path")]
+    coverage: Option<PathBuf>,
+
     #[structopt(flatten)]
     pub(crate) shared: SharedFlags,
 }
@@ -170,6 +177,12 @@ pub(crate) async fn run(
 
     let mut vm = Vm::new(runtime.clone(), unit.clone());
 
+    let coverage = flags.coverage.as_ref().map(|_| Coverage::new(unit.clone()));
+
+    if let Some(coverage) = &coverage {
+        vm.set_coverage(coverage.clone());
+    }
+
     for test in &mut cases {
         executed_count += 1;
 
@@ -204,9 +217,67 @@ pub(crate) async fn run(
         elapsed.as_secs_f64()
     )?;
 
+    if let (Some(path), Some(coverage)) = (&flags.coverage, &coverage) {
+        emit_coverage(io, path, coverage, sources)?;
+    }
+
     if failure_count == 0 {
         Ok(ExitCode::Success)
     } else {
         Ok(ExitCode::Failure)
     }
 }
+
+/// Create the coverage report for the given flags, if requested. Every path
+/// tested appends to it.
+pub(crate) fn create_coverage(flags: &Flags) -> Result<()> {
+    if let Some(path) = &flags.coverage {
+        File::create(path).with_context(|| format!("creating coverage: {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Append coverage to the report at the given path and summarize it per file.
+fn emit_coverage(
+    io: &mut Io<'_>,
+    path: &Path,
+    coverage: &Coverage,
+    sources: &Sources,
+) -> Result<()> {
+    let file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .with_context(|| format!("opening coverage: {}", path.display()))?;
+
+    let mut out = BufWriter::new(file);
+    coverage.write_lcov(sources, &mut out)?;
+    out.flush()?;
+
+    writeln!(io.stdout, "==== coverage")?;
+
+    for file in coverage.files(sources) {
+        writeln!(
+            io.stdout,
+            "{}: lines {}/{} ({}), branches {}/{} ({})",
+            file.name,
+            file.lines_hit(),
+            file.lines.len(),
+            percent(file.lines_hit(), file.lines.len()),
+            file.branches_hit(),
+            file.branches_found(),
+            percent(file.branches_hit(), file.branches_found()),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn percent(hit: usize, found: usize) -> String {
+    if found == 0 {
+        return String::from("-");
+    }
+
+    format!("{:.1}%", hit as f64 * 100.0 / found as f64)
+}
@@ -0,0 +1,41 @@
+//! Runs `rune build` and then `rune run` on the unit it writes.
+
+use std::process::Command;
+
+const PROGRAM: &str = r#"
+pub fn main() {
+    let s = String::from_str("hello");
+    println!("len: {}", s.len());
+}
+"#;
+
+#[test]
+fn build_and_run() {
+    let dir = std::env::temp_dir();
+    let program = dir.join(format!("rune-build-{}.rn", std::process::id()));
+    let unit = dir.join(format!("rune-build-{}-out.rnc", std::process::id()));
+    std::fs::write(&program, PROGRAM).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_rune"))
+        .arg("build")
+        .arg("-o")
+        .arg(&unit)
+        .arg(&program)
+        .status()
+        .expect("running rune");
+
+    assert!(status.success());
+    let _ = std::fs::remove_file(&program);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rune"))
+        .arg("run")
+        .arg(&unit)
+        .output()
+        .expect("running rune");
+
+    let _ = std::fs::remove_file(&unit);
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("len: 5"), "{}", stdout);
+}
@@ -0,0 +1,55 @@
+//! Runs `rune test --coverage` and checks the LCOV report it writes.
+
+use std::process::Command;
+
+const PROGRAM: &str = r#"
+fn sign(n) {
+    if n < 0 {
+        return -1;
+    }
+
+    1
+}
+
+#[test]
+fn test_positive() {
+    sign(10)
+}
+"#;
+
+#[test]
+fn test_coverage() {
+    let dir = std::env::temp_dir();
+    let program = dir.join(format!("rune-coverage-{}.rn", std::process::id()));
+    let report = dir.join(format!("rune-coverage-{}.info", std::process::id()));
+    std::fs::write(&program, PROGRAM).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rune"))
+        .arg("test")
+        .arg("--coverage")
+        .arg(&report)
+        .arg(&program)
+        .output()
+        .expect("running rune");
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{}", stdout);
+
+    let lcov = std::fs::read_to_string(&report).unwrap();
+    let _ = std::fs::remove_file(&program);
+    let _ = std::fs::remove_file(&report);
+
+    assert!(
+        lcov.starts_with(&format!("SF:{}\n", program.display())),
+        "{}",
+        lcov
+    );
+    assert!(lcov.contains("DA:3,1\n"), "{}", lcov);
+    assert!(lcov.contains("DA:4,0\n"), "{}", lcov);
+    assert!(lcov.contains("DA:7,1\n"), "{}", lcov);
+    assert!(lcov.contains("BRF:2\nBRH:1\n"), "{}", lcov);
+
+    let summary = format!("{}: lines ", program.display());
+    assert!(stdout.contains(&summary), "{}", stdout);
+    assert!(stdout.contains("branches 1/2 (50.0%)"), "{}", stdout);
+}
@@ -0,0 +1,207 @@
+//! Drives `rune dap` with a scripted client over stdio.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const PROGRAM: &str = r#"
+fn add(a, b) {
+    a + b
+}
+
+pub fn main() {
+    let v = [1, 2];
+    let x = add(v[0], 2);
+    println!("x = {}", x);
+    x
+}
+"#;
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    seq: i64,
+    output: String,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rune"))
+            .arg("dap")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("spawning rune dap");
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        Self {
+            child,
+            stdin,
+            stdout,
+            seq: 0,
+            output: String::new(),
+        }
+    }
+
+    fn read(&mut self) -> Value {
+        let mut length = 0;
+
+        loop {
+            let mut line = String::new();
+            assert_ne!(
+                self.stdout.read_line(&mut line).unwrap(),
+                0,
+                "unexpected eof"
+            );
+            let line = line.trim();
+
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some(value) = line.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        let message: Value = serde_json::from_slice(&body).unwrap();
+
+        if message["event"] == "output" {
+            self.output
+                .push_str(message["body"]["output"].as_str().unwrap());
+        }
+
+        message
+    }
+
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+
+        let body = serde_json::to_vec(&json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        }))
+        .unwrap();
+
+        write!(self.stdin, "Content-Length: {}\r\n\r\n", body.len()).unwrap();
+        self.stdin.write_all(&body).unwrap();
+        self.stdin.flush().unwrap();
+
+        loop {
+            let message = self.read();
+
+            if message["type"] == "response" && message["request_seq"] == self.seq {
+                assert_eq!(message["success"], true, "{}: {}", command, message);
+                return message["body"].clone();
+            }
+        }
+    }
+
+    fn event(&mut self, event: &str) -> Value {
+        loop {
+            let message = self.read();
+
+            if message["type"] == "event" && message["event"] == event {
+                return message["body"].clone();
+            }
+        }
+    }
+
+    /// The function name and line of every frame on the stack.
+    fn stack(&mut self) -> Vec<(String, u64)> {
+        let body = self.request("stackTrace", json!({ "threadId": 1 }));
+
+        body["stackFrames"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| {
+                let name = f["name"].as_str().unwrap().to_owned();
+                (name, f["line"].as_u64().unwrap())
+            })
+            .collect()
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+fn program_path() -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rune-dap-{}.rn", std::process::id()));
+    std::fs::write(&path, PROGRAM).unwrap();
+    path
+}
+
+#[test]
+fn test_breakpoints_and_stepping() {
+    let path = program_path();
+    let mut client = Client::start();
+
+    client.request("initialize", json!({ "adapterID": "rune" }));
+    client.event("initialized");
+    client.request("launch", json!({ "program": path }));
+
+    let body = client.request(
+        "setBreakpoints",
+        json!({
+            "source": { "path": path },
+            "breakpoints": [{ "line": 8 }, { "line": 5 }],
+        }),
+    );
+
+    assert_eq!(body["breakpoints"][0]["verified"], true);
+    assert_eq!(body["breakpoints"][1]["verified"], false);
+
+    client.request("configurationDone", json!({}));
+
+    assert_eq!(client.event("stopped")["reason"], "breakpoint");
+    assert_eq!(client.stack(), vec![(String::from("main()"), 8)]);
+
+    let scopes = client.request("scopes", json!({ "frameId": 0 }));
+    let reference = scopes["scopes"][0]["variablesReference"].clone();
+    let variables = client.request("variables", json!({ "variablesReference": reference }));
+    let variables = variables["variables"].as_array().unwrap();
+
+    assert_eq!(variables[0]["name"], "v");
+    assert_eq!(variables[0]["value"], "[1, 2]");
+
+    let children = client.request(
+        "variables",
+        json!({ "variablesReference": variables[0]["variablesReference"] }),
+    );
+
+    assert_eq!(children["variables"][1]["value"], "2");
+
+    client.request("stepIn", json!({}));
+    client.event("stopped");
+    let stack = client.stack();
+    assert_eq!(stack.len(), 2);
+    assert_eq!(stack[0].0, "add(a, b)");
+
+    // The call is the last instruction on its line, so stepping out stops at
+    // the line after it.
+    client.request("stepOut", json!({}));
+    client.event("stopped");
+    assert_eq!(client.stack(), vec![(String::from("main()"), 9)]);
+
+    client.request("next", json!({}));
+    client.event("stopped");
+    assert_eq!(client.stack(), vec![(String::from("main()"), 10)]);
+
+    client.request("continue", json!({}));
+    assert_eq!(client.event("exited")["exitCode"], 0);
+    assert_eq!(client.output, "x = 3\n");
+
+    let _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,46 @@
+//! Runs `rune run --profile` and checks the collapsed stacks it writes.
+
+use std::process::Command;
+
+const PROGRAM: &str = r#"
+fn fib(n) {
+    if n < 2 {
+        return n;
+    }
+
+    fib(n - 1) + fib(n - 2)
+}
+
+pub fn main() {
+    fib(10)
+}
+"#;
+
+#[test]
+fn run_profile() {
+    let dir = std::env::temp_dir();
+    let program = dir.join(format!("rune-profile-{}.rn", std::process::id()));
+    let profile = dir.join(format!("rune-profile-{}.folded", std::process::id()));
+    std::fs::write(&program, PROGRAM).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_rune"))
+        .arg("run")
+        .arg(format!("--profile={}", profile.display()))
+        .arg(&program)
+        .status()
+        .expect("running rune");
+
+    assert!(status.success());
+
+    let folded = std::fs::read_to_string(&profile).unwrap();
+    let _ = std::fs::remove_file(&program);
+    let _ = std::fs::remove_file(&profile);
+
+    let paths = folded
+        .lines()
+        .map(|line| line.rsplit_once(' ').expect("weight").0)
+        .collect::<Vec<_>>();
+
+    assert!(paths.contains(&"main"), "{}", folded);
+    assert!(paths.contains(&"main;fib;fib"), "{}", folded);
+}
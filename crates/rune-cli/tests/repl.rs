@@ -0,0 +1,59 @@
+//! Runs `rune repl` with input piped to it.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run the repl with the given input, returning what it printed.
+fn repl(input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rune"))
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("running rune");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{}", stdout);
+    stdout
+}
+
+#[test]
+fn test_repl_multiline() {
+    let stdout = repl("fn double(n) {\n    n * 2\n}\ndouble(21)\n");
+
+    // Input continues while it's incomplete, which is shown by the prompt.
+    assert!(stdout.ends_with("> . . > 42\n> \n"), "{}", stdout);
+}
+
+#[test]
+fn test_repl_errors() {
+    let stdout = repl("1 +\n\nmissing()\n1 + 2\n");
+
+    // An empty line forces incomplete input to be evaluated.
+    assert!(
+        stdout.contains("expected expression, but got eof"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("missing item `missing`"), "{}", stdout);
+
+    // The repl keeps going after errors.
+    assert!(stdout.ends_with("> 3\n> \n"), "{}", stdout);
+}
+
+#[test]
+fn test_repl_state() {
+    let stdout = repl("fn add(a, b) { a + b }\nadd(2, 3)\nlet x = 1;\nx\n");
+
+    // Items are kept between inputs, while variables aren't.
+    assert!(stdout.contains("> > 5\n"), "{}", stdout);
+    assert!(stdout.contains("no local variable `x`"), "{}", stdout);
+}
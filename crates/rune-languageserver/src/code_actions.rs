@@ -0,0 +1,78 @@
+//! Quick-fix code actions for diagnostics with suggested edits.
+
+use lsp::Url;
+use rune::diagnostics::SuggestedEdit;
+use serde::{Deserialize, Serialize};
+
+use crate::state::span_to_lsp_range;
+
+/// A suggested edit, stored in the data of the diagnostic it resolves so that
+/// it's sent back by the client when requesting code actions.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EditData {
+    /// The title of the code action.
+    title: String,
+    /// The range being replaced.
+    range: lsp::Range,
+    /// The text to replace the range with.
+    new_text: String,
+}
+
+/// Convert a suggested edit to the data stored in a diagnostic.
+pub(crate) fn edit_data(source: &rune::Source, edit: &SuggestedEdit) -> Option<serde_json::Value> {
+    let data = EditData {
+        title: capitalize(edit.description()),
+        range: span_to_lsp_range(source, edit.span())?,
+        new_text: edit.replacement().to_owned(),
+    };
+
+    serde_json::to_value(data).ok()
+}
+
+/// Collect quick-fixes for the given diagnostics in the document at the
+/// given uri.
+pub(crate) fn code_actions(
+    uri: &Url,
+    diagnostics: &[lsp::Diagnostic],
+) -> Vec<lsp::CodeActionOrCommand> {
+    let mut output = Vec::new();
+
+    for diagnostic in diagnostics {
+        let data = match &diagnostic.data {
+            Some(data) => data,
+            None => continue,
+        };
+
+        let data = match EditData::deserialize(data) {
+            Ok(data) => data,
+            Err(..) => continue,
+        };
+
+        let edit = lsp::TextEdit::new(data.range, data.new_text);
+
+        let mut changes = std::collections::HashMap::new();
+        changes.insert(uri.clone(), vec![edit]);
+
+        output.push(lsp::CodeActionOrCommand::CodeAction(lsp::CodeAction {
+            title: data.title,
+            kind: Some(lsp::CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(lsp::WorkspaceEdit::new(changes)),
+            is_preferred: Some(true),
+            ..Default::default()
+        }));
+    }
+
+    output
+}
+
+/// Capitalize the first letter of the given string.
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
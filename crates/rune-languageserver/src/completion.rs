@@ -0,0 +1,474 @@
+//! Code completion for the language server.
+
+use std::collections::BTreeMap;
+
+use rune::ast::{Kind, Spanned, StrSource};
+use rune::compile::{ComponentRef, ContextSignature, Item, MetaKind};
+use rune::parse::Lexer;
+use rune::runtime::debug::DebugArgs;
+use rune::{Context, InstFnKind, SourceId};
+
+use crate::signature;
+use crate::state::Source;
+
+/// What is being completed, as determined by the text preceding the cursor.
+#[derive(Debug, PartialEq, Eq)]
+enum Target<'a> {
+    /// A plain identifier, like `fo`.
+    Ident(&'a str),
+    /// The last component in a path, like `std::collections::Ha`.
+    Path(Vec<&'a str>, &'a str),
+    /// An instance function following a `.`, like `value.le`.
+    Instance(&'a str),
+}
+
+/// Collect completions for the given source at the given offset, where
+/// `prefix` is the text on the current line preceding the cursor.
+pub(crate) fn complete(
+    context: &Context,
+    source: &Source,
+    prefix: &str,
+    offset: usize,
+) -> Vec<lsp::CompletionItem> {
+    let mut results = BTreeMap::new();
+
+    if is_in_literal(&source.to_string(), offset) {
+        return Vec::new();
+    }
+
+    match parse_target(prefix) {
+        Target::Ident(partial) => {
+            complete_locals(&mut results, source, partial, source.indexed_offset(offset));
+            complete_items(&mut results, context, source, partial);
+        }
+        Target::Path(path, partial) => {
+            complete_path(&mut results, context, source, &path, partial);
+        }
+        Target::Instance(partial) => {
+            let receiver = offset
+                .checked_sub(partial.len())
+                .and_then(|end| signature::receiver_at(context, source, &source.to_string(), end));
+
+            complete_instance(&mut results, context, source, receiver.as_deref(), partial);
+        }
+    }
+
+    results.into_values().collect()
+}
+
+/// Test if the given offset is inside of a comment or a literal, where nothing
+/// is completed.
+fn is_in_literal(text: &str, offset: usize) -> bool {
+    let mut lexer = Lexer::new(text, SourceId::empty(), true);
+
+    loop {
+        let token = match lexer.next() {
+            Ok(Some(token)) => token,
+            Ok(None) => return false,
+            // NB: a literal which hasn't been terminated yet fails to lex, so
+            // anything following the error is considered to be inside of it.
+            Err(error) => return error.span().start.into_usize() < offset,
+        };
+
+        let start = token.span.start.into_usize();
+        let end = token.span.end.into_usize();
+
+        if start > offset {
+            return false;
+        }
+
+        let inside = match token.kind {
+            // Line comments extend up until the end of the line.
+            Kind::Comment => start < offset && offset <= end,
+            // Text in template strings doesn't include the surrounding
+            // delimiters.
+            Kind::Str(StrSource::Text(text)) if !text.wrapped => start <= offset && offset <= end,
+            Kind::MultilineComment(..)
+            | Kind::Str(..)
+            | Kind::ByteStr(..)
+            | Kind::Char(..)
+            | Kind::Byte(..) => start < offset && offset < end,
+            _ => false,
+        };
+
+        if inside {
+            return true;
+        }
+    }
+}
+
+/// Parse out what is being completed from the text preceding the cursor.
+fn parse_target(prefix: &str) -> Target<'_> {
+    let (rest, partial) = split_ident(prefix);
+
+    if rest.ends_with('.') {
+        return Target::Instance(partial);
+    }
+
+    let mut path = Vec::new();
+    let mut rest = rest;
+
+    while let Some(head) = rest.strip_suffix("::") {
+        let (head, component) = split_ident(head);
+
+        if component.is_empty() {
+            break;
+        }
+
+        path.push(component);
+        rest = head;
+    }
+
+    if path.is_empty() {
+        return Target::Ident(partial);
+    }
+
+    path.reverse();
+    Target::Path(path, partial)
+}
+
+/// Split off the trailing identifier of the given string.
+fn split_ident(s: &str) -> (&str, &str) {
+    let start = s
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !is_ident(*c))
+        .map(|(n, c)| n + c.len_utf8())
+        .unwrap_or_default();
+
+    s.split_at(start)
+}
+
+/// Test if the given character is part of an identifier.
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Complete local variables which are declared before the cursor in the
+/// function it is positioned in, where `offset` is the offset of the cursor in
+/// the indexed content of the source.
+fn complete_locals(
+    results: &mut BTreeMap<String, lsp::CompletionItem>,
+    source: &Source,
+    partial: &str,
+    offset: usize,
+) {
+    let build_sources = match source.build_sources() {
+        Some(build_sources) => build_sources,
+        None => return,
+    };
+
+    let scope = source.function_span_at(offset);
+
+    for span in &source.index().locals {
+        if span.start.into_usize() > offset {
+            continue;
+        }
+
+        if let Some(scope) = scope {
+            if span.start < scope.start || span.end > scope.end {
+                continue;
+            }
+        }
+
+        let name = match build_sources.source(SourceId::new(0), *span) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        if !name.starts_with(partial) {
+            continue;
+        }
+
+        results.insert(
+            name.to_owned(),
+            completion_item(name, lsp::CompletionItemKind::VARIABLE, None),
+        );
+    }
+}
+
+/// Complete items visible at the root of the current source, including
+/// imports and crates provided by the context.
+fn complete_items(
+    results: &mut BTreeMap<String, lsp::CompletionItem>,
+    context: &Context,
+    source: &Source,
+    partial: &str,
+) {
+    for (item, info) in &source.index().items {
+        if info.source.is_none() && !matches!(info.kind, MetaKind::Import) {
+            continue;
+        }
+
+        let name = match single_component(item) {
+            Some(name) => name,
+            None => continue,
+        };
+
+        if !name.starts_with(partial) {
+            continue;
+        }
+
+        let kind = match completion_kind(info.kind) {
+            Some(kind) => kind,
+            None => continue,
+        };
+
+        let detail = script_detail(source, info.kind, item);
+        results.insert(name.to_owned(), completion_item(name, kind, detail));
+    }
+
+    for item in context_items(context) {
+        if let Some(ComponentRef::Crate(name)) = item.first() {
+            if name.starts_with(partial) && !results.contains_key(name) {
+                results.insert(
+                    name.to_owned(),
+                    completion_item(name, lsp::CompletionItemKind::MODULE, None),
+                );
+            }
+        }
+    }
+}
+
+/// Complete the last component of a path.
+fn complete_path(
+    results: &mut BTreeMap<String, lsp::CompletionItem>,
+    context: &Context,
+    source: &Source,
+    path: &[&str],
+    partial: &str,
+) {
+    for (_, signature) in context.iter_functions() {
+        if let ContextSignature::Function { item, .. } = signature {
+            if let Some((name, is_last)) = next_component(item, path, true) {
+                let (kind, detail) = if is_last {
+                    (
+                        lsp::CompletionItemKind::FUNCTION,
+                        Some(signature.to_string()),
+                    )
+                } else {
+                    (lsp::CompletionItemKind::MODULE, None)
+                };
+
+                insert_candidate(results, name, partial, kind, detail);
+            }
+        }
+    }
+
+    for (_, ty) in context.iter_types() {
+        if let Some((name, is_last)) = next_component(&ty.item, path, true) {
+            let kind = if is_last {
+                lsp::CompletionItemKind::STRUCT
+            } else {
+                lsp::CompletionItemKind::MODULE
+            };
+
+            insert_candidate(results, name, partial, kind, None);
+        }
+    }
+
+    for (item, info) in &source.index().items {
+        if info.source.is_none() {
+            continue;
+        }
+
+        if let Some((name, is_last)) = next_component(item, path, false) {
+            let (kind, detail) = if is_last {
+                match completion_kind(info.kind) {
+                    Some(kind) => (kind, script_detail(source, info.kind, item)),
+                    None => continue,
+                }
+            } else {
+                (lsp::CompletionItemKind::MODULE, None)
+            };
+
+            insert_candidate(results, name, partial, kind, detail);
+        }
+    }
+}
+
+/// Complete instance functions, both those provided natively through the
+/// context and those declared in script `impl` blocks. If the type of the
+/// receiver is known, only functions associated with it are completed.
+fn complete_instance(
+    results: &mut BTreeMap<String, lsp::CompletionItem>,
+    context: &Context,
+    source: &Source,
+    receiver: Option<&Item>,
+    partial: &str,
+) {
+    // Script functions take precedence over native ones with the same name.
+    let debug_info = source.unit().and_then(|unit| unit.debug_info());
+    let signatures = debug_info.into_iter().flat_map(|d| d.functions.values());
+
+    for signature in signatures {
+        let is_instance = match &signature.args {
+            DebugArgs::Named(args) => args.first().map(|a| &**a) == Some("self"),
+            _ => false,
+        };
+
+        if !is_instance {
+            continue;
+        }
+
+        if receiver.is_some() && signature.path.parent() != receiver {
+            continue;
+        }
+
+        if let Some(ComponentRef::Str(name)) = signature.path.last() {
+            insert_candidate(
+                results,
+                name,
+                partial,
+                lsp::CompletionItemKind::METHOD,
+                Some(signature.to_string()),
+            );
+        }
+    }
+
+    for (_, signature) in context.iter_functions() {
+        if let ContextSignature::Instance {
+            item,
+            name: InstFnKind::Instance(name),
+            ..
+        } = signature
+        {
+            if matches!(receiver, Some(ty) if **item != *ty) {
+                continue;
+            }
+
+            insert_candidate(
+                results,
+                name,
+                partial,
+                lsp::CompletionItemKind::METHOD,
+                Some(signature.to_string()),
+            );
+        }
+    }
+}
+
+/// Insert a completion candidate unless it doesn't match the partial
+/// identifier being completed.
+fn insert_candidate(
+    results: &mut BTreeMap<String, lsp::CompletionItem>,
+    name: &str,
+    partial: &str,
+    kind: lsp::CompletionItemKind,
+    detail: Option<String>,
+) {
+    if !name.starts_with(partial) || results.contains_key(name) {
+        return;
+    }
+
+    results.insert(name.to_owned(), completion_item(name, kind, detail));
+}
+
+/// Construct a completion item.
+fn completion_item(
+    name: &str,
+    kind: lsp::CompletionItemKind,
+    detail: Option<String>,
+) -> lsp::CompletionItem {
+    lsp::CompletionItem {
+        label: name.to_owned(),
+        kind: Some(kind),
+        detail,
+        ..Default::default()
+    }
+}
+
+/// Find the component following `path` in `item`, and whether or not it's
+/// the last component of the item.
+///
+/// If `suffix` is set, the path is also allowed to match a sequence of
+/// components in the middle of the item, so that `String::` matches
+/// `::std::string::String::new`.
+fn next_component<'a>(item: &'a Item, path: &[&str], suffix: bool) -> Option<(&'a str, bool)> {
+    let components = item
+        .iter()
+        .map(|c| match c {
+            ComponentRef::Crate(s) | ComponentRef::Str(s) => Some(s),
+            ComponentRef::Id(..) => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    if components.len() > path.len() && components[..path.len()] == *path {
+        return Some((components[path.len()], components.len() == path.len() + 1));
+    }
+
+    if suffix && components.len() > path.len() {
+        let last = components.len() - 1;
+
+        if components[last - path.len()..last] == *path {
+            return Some((components[last], true));
+        }
+    }
+
+    None
+}
+
+/// Get the name of the item if it consists of a single component.
+fn single_component(item: &Item) -> Option<&str> {
+    let mut it = item.iter();
+
+    match (it.next(), it.next()) {
+        (Some(ComponentRef::Str(name)), None) => Some(name),
+        _ => None,
+    }
+}
+
+/// Iterate over all items known to the context.
+fn context_items(context: &Context) -> impl Iterator<Item = &Item> {
+    let functions = context
+        .iter_functions()
+        .filter_map(|(_, signature)| match signature {
+            ContextSignature::Function { item, .. } => Some(&**item),
+            ContextSignature::Instance { .. } => None,
+        });
+
+    let types = context.iter_types().map(|(_, ty)| &*ty.item);
+    functions.chain(types)
+}
+
+/// Describe an item declared in a script.
+fn script_detail(source: &Source, kind: MetaKind, item: &Item) -> Option<String> {
+    if let MetaKind::Function { type_hash, .. } = kind {
+        let debug_info = source.unit()?.debug_info()?;
+        let signature = debug_info.functions.get(&type_hash)?;
+        return Some(format!("fn {}", signature));
+    }
+
+    let keyword = match kind {
+        MetaKind::UnitStruct | MetaKind::TupleStruct | MetaKind::Struct => "struct",
+        MetaKind::UnitVariant | MetaKind::TupleVariant | MetaKind::StructVariant => "variant",
+        MetaKind::Enum => "enum",
+        MetaKind::Const => "const",
+        MetaKind::ConstFn => "const fn",
+        MetaKind::Import => "use",
+        MetaKind::Module => "mod",
+        MetaKind::Trait => "trait",
+        _ => return None,
+    };
+
+    Some(format!("{} {}", keyword, item))
+}
+
+/// Map a meta kind to the kind of completion it corresponds to.
+fn completion_kind(kind: MetaKind) -> Option<lsp::CompletionItemKind> {
+    Some(match kind {
+        MetaKind::UnitStruct | MetaKind::TupleStruct | MetaKind::Struct => {
+            lsp::CompletionItemKind::STRUCT
+        }
+        MetaKind::UnitVariant | MetaKind::TupleVariant | MetaKind::StructVariant => {
+            lsp::CompletionItemKind::ENUM_MEMBER
+        }
+        MetaKind::Enum => lsp::CompletionItemKind::ENUM,
+        MetaKind::Function { .. } | MetaKind::ConstFn => lsp::CompletionItemKind::FUNCTION,
+        MetaKind::Const => lsp::CompletionItemKind::CONSTANT,
+        MetaKind::Import => lsp::CompletionItemKind::REFERENCE,
+        MetaKind::Module => lsp::CompletionItemKind::MODULE,
+        MetaKind::Trait => lsp::CompletionItemKind::INTERFACE,
+        _ => return None,
+    })
+}
@@ -0,0 +1,142 @@
+//! Document and range formatting.
+//!
+//! The whole document is always formatted, and the result is diffed line by
+//! line against the current content so that the edits sent to the client are
+//! minimal. Range formatting only keeps the edits which touch the requested
+//! lines.
+
+/// The maximum number of line comparisons performed when diffing, past which
+/// the changed region is replaced in full.
+const MAX_DIFF: usize = 1 << 22;
+
+/// Compute the edits needed to turn `text` into `formatted`, optionally
+/// limited to the edits which touch the given range.
+pub(crate) fn edits(text: &str, formatted: &str, range: Option<lsp::Range>) -> Vec<lsp::TextEdit> {
+    let a = text.split_inclusive('\n').collect::<Vec<_>>();
+    let b = formatted.split_inclusive('\n').collect::<Vec<_>>();
+
+    let mut output = Vec::new();
+
+    for hunk in diff(&a, &b) {
+        if let Some(range) = range {
+            let first = range.start.line as usize;
+            let last = range.end.line as usize;
+
+            // An insertion touches the line it's inserted before, a
+            // replacement touches every line it replaces.
+            let end = hunk.a.end.max(hunk.a.start + 1) - 1;
+
+            if end < first || hunk.a.start > last {
+                continue;
+            }
+        }
+
+        let range = lsp::Range::new(position(&a, hunk.a.start), position(&a, hunk.a.end));
+        let new_text = b[hunk.b].concat();
+        output.push(lsp::TextEdit::new(range, new_text));
+    }
+
+    output
+}
+
+/// A region of lines in the old text which is replaced with a region of lines
+/// in the new text.
+#[derive(Debug)]
+struct Hunk {
+    a: std::ops::Range<usize>,
+    b: std::ops::Range<usize>,
+}
+
+/// Diff two sequences of lines.
+fn diff(a: &[&str], b: &[&str]) -> Vec<Hunk> {
+    let prefix = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let (a_end, b_end) = (a.len() - suffix, b.len() - suffix);
+
+    if prefix == a_end && prefix == b_end {
+        return Vec::new();
+    }
+
+    let (n, m) = (a_end - prefix, b_end - prefix);
+
+    if n.saturating_mul(m) > MAX_DIFF {
+        return vec![Hunk {
+            a: prefix..a_end,
+            b: prefix..b_end,
+        }];
+    }
+
+    // Longest common subsequence of the remaining lines, where `lcs[i][j]` is
+    // the length of the subsequence of `a[i..]` and `b[j..]`.
+    let a = &a[prefix..a_end];
+    let b = &b[prefix..b_end];
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let mut start = None;
+
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            if let Some((si, sj)) = start.take() {
+                hunks.push(Hunk {
+                    a: prefix + si..prefix + i,
+                    b: prefix + sj..prefix + j,
+                });
+            }
+
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        if start.is_none() {
+            start = Some((i, j));
+        }
+
+        if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            j += 1;
+        } else {
+            i += 1;
+        }
+    }
+
+    if let Some((si, sj)) = start {
+        hunks.push(Hunk {
+            a: prefix + si..prefix + n,
+            b: prefix + sj..prefix + m,
+        });
+    }
+
+    hunks
+}
+
+/// The position of the start of the given line, where the line past the end
+/// of a text without a trailing newline is the end of its last line.
+fn position(lines: &[&str], line: usize) -> lsp::Position {
+    if line == lines.len() {
+        if let Some(last) = lines.last().filter(|last| !last.ends_with('\n')) {
+            let character = last.encode_utf16().count();
+            return lsp::Position::new((line - 1) as u32, character as u32);
+        }
+    }
+
+    lsp::Position::new(line as u32, 0)
+}
@@ -44,10 +44,18 @@
 //!
 //! [Rune Language]: https://rune-rs.github.io
 
+mod code_actions;
+mod completion;
 mod connection;
 pub mod envelope;
+mod formatting;
+mod references;
+mod semantic_tokens;
 mod server;
+mod signature;
 mod state;
+mod symbols;
+mod workspace;
 
 pub const VERSION: &str = include_str!(concat!(env!("OUT_DIR"), "/version.txt"));
 
@@ -69,6 +77,21 @@ pub fn run(context: Context, options: Options) -> Result<()> {
     server.request_handler::<lsp::request::Initialize, _, _>(initialize);
 
     server.request_handler::<lsp::request::GotoDefinition, _, _>(goto_definition);
+    server.request_handler::<lsp::request::HoverRequest, _, _>(hover);
+    server.request_handler::<lsp::request::Completion, _, _>(completion);
+    server.request_handler::<lsp::request::References, _, _>(references);
+    server.request_handler::<lsp::request::PrepareRenameRequest, _, _>(prepare_rename);
+    server.request_handler::<lsp::request::Rename, _, _>(rename);
+    server.request_handler::<lsp::request::DocumentSymbolRequest, _, _>(document_symbol);
+    server.request_handler::<lsp::request::WorkspaceSymbol, _, _>(workspace_symbol);
+    server.request_handler::<lsp::request::SignatureHelpRequest, _, _>(signature_help);
+    server.request_handler::<lsp::request::CodeActionRequest, _, _>(code_action);
+    server.request_handler::<lsp::request::Formatting, _, _>(formatting);
+    server.request_handler::<lsp::request::RangeFormatting, _, _>(range_formatting);
+    server.request_handler::<lsp::request::SemanticTokensFullRequest, _, _>(semantic_tokens_full);
+    server.request_handler::<lsp::request::SemanticTokensFullDeltaRequest, _, _>(
+        semantic_tokens_full_delta,
+    );
 
     server.notification_handler::<lsp::notification::DidOpenTextDocument, _, _>(
         did_open_text_document,
@@ -111,10 +134,21 @@ pub fn run(context: Context, options: Options) -> Result<()> {
 async fn initialize(
     state: State,
     output: Output,
-    _: lsp::InitializeParams,
+    params: lsp::InitializeParams,
 ) -> Result<lsp::InitializeResult> {
     state.initialize();
 
+    #[allow(deprecated)]
+    let root = params
+        .workspace_folders
+        .as_ref()
+        .and_then(|folders| folders.first())
+        .map(|folder| &folder.uri)
+        .or(params.root_uri.as_ref())
+        .and_then(|uri| uri.to_file_path().ok());
+
+    state.set_root(root).await;
+
     output
         .log(lsp::MessageType::INFO, "Starting language server")
         .await?;
@@ -124,6 +158,40 @@ async fn initialize(
             lsp::TextDocumentSyncKind::INCREMENTAL,
         )),
         definition_provider: Some(lsp::OneOf::Left(true)),
+        hover_provider: Some(lsp::HoverProviderCapability::Simple(true)),
+        completion_provider: Some(lsp::CompletionOptions {
+            trigger_characters: Some(vec![String::from("."), String::from(":")]),
+            ..Default::default()
+        }),
+        references_provider: Some(lsp::OneOf::Left(true)),
+        rename_provider: Some(lsp::OneOf::Right(lsp::RenameOptions {
+            prepare_provider: Some(true),
+            work_done_progress_options: Default::default(),
+        })),
+        document_symbol_provider: Some(lsp::OneOf::Left(true)),
+        workspace_symbol_provider: Some(lsp::OneOf::Left(true)),
+        code_action_provider: Some(lsp::CodeActionProviderCapability::Options(
+            lsp::CodeActionOptions {
+                code_action_kinds: Some(vec![lsp::CodeActionKind::QUICKFIX]),
+                work_done_progress_options: Default::default(),
+                resolve_provider: None,
+            },
+        )),
+        document_formatting_provider: Some(lsp::OneOf::Left(true)),
+        document_range_formatting_provider: Some(lsp::OneOf::Left(true)),
+        signature_help_provider: Some(lsp::SignatureHelpOptions {
+            trigger_characters: Some(vec![String::from("("), String::from(",")]),
+            retrigger_characters: None,
+            work_done_progress_options: Default::default(),
+        }),
+        semantic_tokens_provider: Some(
+            lsp::SemanticTokensOptions {
+                legend: semantic_tokens::legend(),
+                full: Some(lsp::SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                ..Default::default()
+            }
+            .into(),
+        ),
         ..Default::default()
     };
 
@@ -160,6 +228,162 @@ async fn goto_definition(
     Ok(position.map(lsp::GotoDefinitionResponse::Scalar))
 }
 
+/// Handle hover requests.
+async fn hover(state: State, _: Output, params: lsp::HoverParams) -> Result<Option<lsp::Hover>> {
+    Ok(state
+        .hover(
+            &params.text_document_position_params.text_document.uri,
+            params.text_document_position_params.position,
+        )
+        .await)
+}
+
+/// Handle completion requests.
+async fn completion(
+    state: State,
+    _: Output,
+    params: lsp::CompletionParams,
+) -> Result<Option<lsp::CompletionResponse>> {
+    let items = state
+        .complete(
+            &params.text_document_position.text_document.uri,
+            params.text_document_position.position,
+        )
+        .await;
+
+    Ok(items.map(lsp::CompletionResponse::Array))
+}
+
+/// Handle find references requests.
+async fn references(
+    state: State,
+    _: Output,
+    params: lsp::ReferenceParams,
+) -> Result<Option<Vec<lsp::Location>>> {
+    Ok(state
+        .find_references(
+            &params.text_document_position.text_document.uri,
+            params.text_document_position.position,
+            params.context.include_declaration,
+        )
+        .await)
+}
+
+/// Handle requests to test if something can be renamed.
+async fn prepare_rename(
+    state: State,
+    _: Output,
+    params: lsp::TextDocumentPositionParams,
+) -> Result<Option<lsp::PrepareRenameResponse>> {
+    let range = state
+        .prepare_rename(&params.text_document.uri, params.position)
+        .await;
+
+    Ok(range.map(lsp::PrepareRenameResponse::Range))
+}
+
+/// Handle rename requests.
+async fn rename(
+    state: State,
+    _: Output,
+    params: lsp::RenameParams,
+) -> Result<Option<lsp::WorkspaceEdit>> {
+    Ok(state
+        .rename(
+            &params.text_document_position.text_document.uri,
+            params.text_document_position.position,
+            &params.new_name,
+        )
+        .await)
+}
+
+/// Handle document symbol requests.
+async fn document_symbol(
+    state: State,
+    _: Output,
+    params: lsp::DocumentSymbolParams,
+) -> Result<Option<lsp::DocumentSymbolResponse>> {
+    let symbols = state.document_symbols(&params.text_document.uri).await;
+    Ok(symbols.map(lsp::DocumentSymbolResponse::Nested))
+}
+
+/// Handle workspace symbol requests.
+async fn workspace_symbol(
+    state: State,
+    _: Output,
+    params: lsp::WorkspaceSymbolParams,
+) -> Result<Option<Vec<lsp::SymbolInformation>>> {
+    Ok(Some(state.workspace_symbols(&params.query).await))
+}
+
+/// Handle signature help requests.
+async fn signature_help(
+    state: State,
+    _: Output,
+    params: lsp::SignatureHelpParams,
+) -> Result<Option<lsp::SignatureHelp>> {
+    Ok(state
+        .signature_help(
+            &params.text_document_position_params.text_document.uri,
+            params.text_document_position_params.position,
+        )
+        .await)
+}
+
+/// Handle code action requests.
+async fn code_action(
+    _: State,
+    _: Output,
+    params: lsp::CodeActionParams,
+) -> Result<Option<lsp::CodeActionResponse>> {
+    Ok(Some(code_actions::code_actions(
+        &params.text_document.uri,
+        &params.context.diagnostics,
+    )))
+}
+
+/// Handle document formatting requests.
+async fn formatting(
+    state: State,
+    _: Output,
+    params: lsp::DocumentFormattingParams,
+) -> Result<Option<Vec<lsp::TextEdit>>> {
+    Ok(state.format(&params.text_document.uri, None).await)
+}
+
+/// Handle range formatting requests.
+async fn range_formatting(
+    state: State,
+    _: Output,
+    params: lsp::DocumentRangeFormattingParams,
+) -> Result<Option<Vec<lsp::TextEdit>>> {
+    Ok(state
+        .format(&params.text_document.uri, Some(params.range))
+        .await)
+}
+
+/// Handle requests for all semantic tokens in a document.
+async fn semantic_tokens_full(
+    state: State,
+    _: Output,
+    params: lsp::SemanticTokensParams,
+) -> Result<Option<lsp::SemanticTokensResult>> {
+    let tokens = state.semantic_tokens_full(&params.text_document.uri).await;
+    Ok(tokens.map(lsp::SemanticTokensResult::Tokens))
+}
+
+/// Handle requests for semantic tokens in a document relative to a previous
+/// result.
+async fn semantic_tokens_full_delta(
+    state: State,
+    _: Output,
+    params: lsp::SemanticTokensDeltaParams,
+) -> Result<Option<lsp::SemanticTokensFullDeltaResult>> {
+    Ok(state
+        .semantic_tokens_delta(&params.text_document.uri, &params.previous_result_id)
+        .await)
+}
+
 /// Handle open text document.
 async fn did_open_text_document(
     state: State,
@@ -0,0 +1,597 @@
+//! Finding references to, and renaming symbols across all loaded sources.
+
+use std::collections::{BTreeMap, HashSet};
+
+use hashbrown::HashMap;
+use lsp::Url;
+use rune::ast::{Kind, LitSource, Span};
+use rune::compile::{ComponentRef, Location, MetaKind};
+use rune::parse::Lexer;
+use rune::SourceId;
+
+use crate::state::{span_to_lsp_range, Source, Sources};
+
+/// A symbol which can be referenced.
+#[derive(Debug, Clone)]
+pub(crate) enum Symbol {
+    /// An item, like a function, a struct, or an enum variant.
+    Item {
+        /// The name of the item.
+        name: Box<str>,
+        /// The location of the item declaration.
+        declaration: Location,
+    },
+    /// A local variable.
+    Local {
+        /// The location of the variable declaration.
+        declaration: Location,
+    },
+    /// A field in a struct or a struct variant.
+    Field {
+        /// The name of the field.
+        name: Box<str>,
+        /// The location of the declaration of the struct or variant the field
+        /// belongs to.
+        declaration: Location,
+    },
+}
+
+impl Symbol {
+    /// The location of the declaration of the symbol.
+    fn declaration(&self) -> Location {
+        match self {
+            Self::Item { declaration, .. } => *declaration,
+            Self::Local { declaration } => *declaration,
+            Self::Field { declaration, .. } => *declaration,
+        }
+    }
+
+    /// The name of the field being referenced, if the symbol is a field.
+    fn field(&self) -> Option<&str> {
+        match self {
+            Self::Field { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// A reference to a symbol recorded during a build.
+#[derive(Debug, Clone)]
+pub(crate) struct Reference {
+    /// The symbol being referenced.
+    pub(crate) symbol: Symbol,
+    /// Where the symbol is being referenced.
+    pub(crate) usage: Location,
+}
+
+/// How an occurrence of a symbol is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Shorthand {
+    /// The name is written out in full.
+    None,
+    /// A field written in shorthand form, like `x` in `Point { x }`.
+    Field,
+    /// A local written in shorthand form, like `x` in `Point { x }`, which
+    /// also names the field it's bound to or assigned from.
+    Local,
+}
+
+/// An occurrence of a symbol in a source.
+#[derive(Debug, Clone)]
+pub(crate) struct Occurrence {
+    /// The url of the source the symbol occurs in.
+    pub(crate) url: Url,
+    /// The range of the name of the symbol.
+    pub(crate) range: lsp::Range,
+    /// If this is the declaration of the symbol.
+    pub(crate) declaration: bool,
+    /// How the symbol is written.
+    pub(crate) shorthand: Shorthand,
+}
+
+/// Identifies a symbol across builds, since source ids are local to each
+/// build.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct SymbolKey {
+    url: Url,
+    span: Span,
+    field: Option<Box<str>>,
+}
+
+/// An identifier token.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Ident {
+    /// The span of the identifier.
+    span: Span,
+    /// If the identifier immediately follows a `.`.
+    after_dot: bool,
+    /// If the identifier immediately follows a `{` or `,`.
+    after_separator: bool,
+    /// If the identifier is immediately followed by a single `:`.
+    before_colon: bool,
+}
+
+/// Identifier tokens of sources, cached by url.
+#[derive(Default)]
+struct Tokens {
+    cache: HashMap<Url, Vec<Ident>>,
+}
+
+impl Tokens {
+    /// Get all identifiers in the given source.
+    fn idents(&mut self, url: &Url, source: &rune::Source) -> &[Ident] {
+        self.cache
+            .entry(url.clone())
+            .or_insert_with(|| lex_idents(source.as_str()))
+    }
+}
+
+/// Lex all identifiers in the given source.
+pub(crate) fn lex_idents(source: &str) -> Vec<Ident> {
+    let mut lexer = Lexer::new(source, SourceId::empty(), true);
+    let mut idents = Vec::<Ident>::new();
+    let mut last = None;
+
+    loop {
+        let token = match lexer.next() {
+            Ok(Some(token)) => token,
+            Ok(None) => break,
+            // Stop at the first lexer error, but keep whatever was found up
+            // until that point.
+            Err(..) => break,
+        };
+
+        match token.kind {
+            Kind::Whitespace | Kind::Comment | Kind::MultilineComment(..) => continue,
+            Kind::Ident(LitSource::Text(..)) => {
+                idents.push(Ident {
+                    span: token.span,
+                    after_dot: matches!(last, Some(Kind::Dot)),
+                    after_separator: matches!(last, Some(Kind::Open(..) | Kind::Comma)),
+                    before_colon: false,
+                });
+            }
+            Kind::Colon => {
+                if let (Some(Kind::Ident(..)), Some(ident)) = (last, idents.last_mut()) {
+                    ident.before_colon = true;
+                }
+            }
+            _ => (),
+        }
+
+        last = Some(token.kind);
+    }
+
+    idents
+}
+
+/// Find the symbol at the given byte offset in the given source.
+pub(crate) fn symbol_at(source: &Source, offset: usize) -> Option<Symbol> {
+    let index = source.index();
+    let build_sources = source.build_sources()?;
+    let text = build_sources.get(SourceId::new(0))?.as_str();
+
+    // The narrowest use of a symbol which contains the offset.
+    let mut found = None::<&Reference>;
+
+    for reference in &index.references {
+        let usage = reference.usage;
+
+        if usage.source_id.into_index() != 0 || !contains(usage.span, offset) {
+            continue;
+        }
+
+        let is_narrower = match found {
+            Some(found) => usage.span.range().len() < found.usage.span.range().len(),
+            None => true,
+        };
+
+        if is_narrower {
+            found = Some(reference);
+        }
+    }
+
+    if let Some(reference) = found {
+        return Some(reference.symbol.clone());
+    }
+
+    let idents = lex_idents(text);
+    let ident = idents.iter().find(|i| contains(i.span, offset))?;
+    let ident_text = text.get(ident.span.range())?;
+
+    // Declarations of locals.
+    for reference in &index.references {
+        if let Symbol::Local { declaration } = &reference.symbol {
+            if declaration.source_id.into_index() == 0 && contains(declaration.span, offset) {
+                return Some(reference.symbol.clone());
+            }
+        }
+    }
+
+    // Declarations of items, or fields in them.
+    for (item, info) in &index.items {
+        let location = match &info.source {
+            Some(source) if source.location.source_id.into_index() == 0 => source.location,
+            _ => continue,
+        };
+
+        if !contains(location.span, offset) || !is_renameable(info.kind) {
+            continue;
+        }
+
+        let name = match item.last() {
+            Some(ComponentRef::Str(name)) => name,
+            _ => continue,
+        };
+
+        let name_span = find_ident(text, &idents, location.span, name, false);
+
+        if name_span == Some(ident.span) {
+            return Some(Symbol::Item {
+                name: name.into(),
+                declaration: location,
+            });
+        }
+
+        let is_struct = matches!(info.kind, MetaKind::Struct | MetaKind::StructVariant);
+
+        if is_struct && ident.after_separator {
+            let field = find_field_declaration(text, &idents, location.span, ident_text);
+
+            if field == Some(ident.span) {
+                return Some(Symbol::Field {
+                    name: ident_text.into(),
+                    declaration: location,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// Find all occurrences of the given symbol, which was found in the `origin`
+/// source, across all sources.
+pub(crate) fn occurrences(sources: &Sources, origin: &Source, symbol: &Symbol) -> Vec<Occurrence> {
+    let mut tokens = Tokens::default();
+    let mut output = BTreeMap::new();
+
+    let origin_sources = match origin.build_sources() {
+        Some(build_sources) => build_sources,
+        None => return Vec::new(),
+    };
+
+    let key = match symbol_key(origin_sources, symbol) {
+        Some(key) => key,
+        None => return Vec::new(),
+    };
+
+    let declaration = symbol.declaration();
+
+    if let Some((url, source)) = source_with_url(origin_sources, declaration.source_id) {
+        let idents = tokens.idents(&url, source);
+        let text = source.as_str();
+
+        let span = match symbol {
+            Symbol::Item { name, .. } => find_ident(text, idents, declaration.span, name, false),
+            Symbol::Local { .. } => {
+                let name = text.get(declaration.span.range()).unwrap_or_default();
+                find_ident(text, idents, declaration.span, name.trim(), false)
+            }
+            Symbol::Field { name, .. } => {
+                find_field_declaration(text, idents, declaration.span, name)
+            }
+        };
+
+        if let Some(span) = span {
+            let shorthand = match symbol {
+                Symbol::Local { .. } if is_field_shorthand(origin, declaration) => Shorthand::Local,
+                _ => Shorthand::None,
+            };
+
+            insert_occurrence(&mut output, sources, &url, source, span, true, shorthand);
+        }
+    }
+
+    for source in sources.iter() {
+        let build_sources = match source.build_sources() {
+            Some(build_sources) => build_sources,
+            None => continue,
+        };
+
+        for reference in &source.index().references {
+            if symbol_key(build_sources, &reference.symbol).as_ref() != Some(&key) {
+                continue;
+            }
+
+            let usage = reference.usage;
+
+            let (url, rune_source) = match source_with_url(build_sources, usage.source_id) {
+                Some(source) => source,
+                None => continue,
+            };
+
+            let text = rune_source.as_str();
+            let idents = tokens.idents(&url, rune_source);
+
+            let (span, shorthand) = match &reference.symbol {
+                Symbol::Item { name, .. } => {
+                    match find_ident(text, idents, usage.span, name, true) {
+                        Some(span) => (span, Shorthand::None),
+                        None => continue,
+                    }
+                }
+                Symbol::Local { declaration } => {
+                    let name = build_sources
+                        .source(declaration.source_id, declaration.span)
+                        .unwrap_or_default()
+                        .trim();
+
+                    let span = match find_ident(text, idents, usage.span, name, false) {
+                        Some(span) => span,
+                        None => continue,
+                    };
+
+                    if is_field_shorthand(source, usage) {
+                        (span, Shorthand::Local)
+                    } else {
+                        (span, Shorthand::None)
+                    }
+                }
+                Symbol::Field { name, .. } => {
+                    let ident = idents.iter().find(|i| {
+                        i.span.start >= usage.span.start
+                            && i.span.end <= usage.span.end
+                            && text.get(i.span.range()) == Some(&**name)
+                    });
+
+                    match ident {
+                        Some(ident) if ident.before_colon => (ident.span, Shorthand::None),
+                        Some(ident) => (ident.span, Shorthand::Field),
+                        None => continue,
+                    }
+                }
+            };
+
+            insert_occurrence(
+                &mut output,
+                sources,
+                &url,
+                rune_source,
+                span,
+                false,
+                shorthand,
+            );
+        }
+
+        // Field accesses are resolved dynamically, so every access through a
+        // field with a matching name is considered an occurrence.
+        if let Some(field) = symbol.field() {
+            for (id, rune_source) in iter_sources(build_sources) {
+                let url = match source_with_url(build_sources, id) {
+                    Some((url, _)) => url,
+                    None => continue,
+                };
+
+                let text = rune_source.as_str();
+
+                for ident in tokens.idents(&url, rune_source) {
+                    if ident.after_dot && text.get(ident.span.range()) == Some(field) {
+                        insert_occurrence(
+                            &mut output,
+                            sources,
+                            &url,
+                            rune_source,
+                            ident.span,
+                            false,
+                            Shorthand::None,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    output.into_values().collect()
+}
+
+/// Get the name of the given symbol, which was found in the given source.
+pub(crate) fn symbol_name<'a>(source: &'a Source, symbol: &'a Symbol) -> Option<&'a str> {
+    match symbol {
+        Symbol::Item { name, .. } | Symbol::Field { name, .. } => Some(name),
+        Symbol::Local { declaration } => {
+            let build_sources = source.build_sources()?;
+            let name = build_sources.source(declaration.source_id, declaration.span)?;
+            Some(name.trim())
+        }
+    }
+}
+
+/// Test if the given symbol can be renamed.
+///
+/// Fields are accessed dynamically, so there's no telling which type an access
+/// belongs to and renaming them could change unrelated code.
+pub(crate) fn can_rename(symbol: &Symbol) -> bool {
+    !matches!(symbol, Symbol::Field { .. })
+}
+
+/// Test if the given string is a valid identifier which a symbol can be
+/// renamed to.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    let mut lexer = Lexer::new(name, SourceId::empty(), false);
+
+    match (lexer.next(), lexer.next()) {
+        (Ok(Some(token)), Ok(None)) => {
+            matches!(token.kind, Kind::Ident(..)) && token.span.range() == (0..name.len())
+        }
+        _ => false,
+    }
+}
+
+/// Insert an occurrence, deduplicating it by url and span, unless the source
+/// it was found in has changed since.
+fn insert_occurrence(
+    output: &mut BTreeMap<(Url, Span), Occurrence>,
+    sources: &Sources,
+    url: &Url,
+    source: &rune::Source,
+    span: Span,
+    declaration: bool,
+    shorthand: Shorthand,
+) {
+    // NB: the span is only usable if the source it was found in is what's
+    // currently open.
+    if let Some(open) = sources.get(url) {
+        if !open.has_content(source.as_str()) {
+            return;
+        }
+    }
+
+    let range = match span_to_lsp_range(source, span) {
+        Some(range) => range,
+        None => return,
+    };
+
+    output
+        .entry((url.clone(), span))
+        .or_insert_with(|| Occurrence {
+            url: url.clone(),
+            range,
+            declaration,
+            shorthand,
+        });
+}
+
+/// Test if the given location is a field written in shorthand form, in which
+/// case it also refers to a local variable.
+fn is_field_shorthand(source: &Source, location: Location) -> bool {
+    source.index().references.iter().any(|reference| {
+        matches!(reference.symbol, Symbol::Field { .. })
+            && reference.usage.source_id == location.source_id
+            && reference.usage.span == location.span
+    })
+}
+
+/// Build a key for the given symbol which is comparable across builds.
+fn symbol_key(sources: &rune::Sources, symbol: &Symbol) -> Option<SymbolKey> {
+    let declaration = symbol.declaration();
+    let (url, _) = source_with_url(sources, declaration.source_id)?;
+
+    Some(SymbolKey {
+        url,
+        span: declaration.span,
+        field: symbol.field().map(Box::from),
+    })
+}
+
+/// Find the first, or the last if `last` is set, identifier in the given span
+/// which matches `name`.
+pub(crate) fn find_ident(
+    text: &str,
+    idents: &[Ident],
+    span: Span,
+    name: &str,
+    last: bool,
+) -> Option<Span> {
+    let mut it = idents.iter().filter(|i| {
+        i.span.start >= span.start
+            && i.span.end <= span.end
+            && text.get(i.span.range()) == Some(name)
+    });
+
+    let ident = if last { it.next_back() } else { it.next() };
+    Some(ident?.span)
+}
+
+/// Find the declaration of the field named `name` in the struct declared in
+/// the given span.
+fn find_field_declaration(text: &str, idents: &[Ident], span: Span, name: &str) -> Option<Span> {
+    let ident = idents.iter().find(|i| {
+        i.span.start >= span.start
+            && i.span.end <= span.end
+            && i.after_separator
+            && text.get(i.span.range()) == Some(name)
+    })?;
+
+    Some(ident.span)
+}
+
+/// Get a source together with the url it's loaded from.
+pub(crate) fn source_with_url(
+    sources: &rune::Sources,
+    source_id: SourceId,
+) -> Option<(Url, &rune::Source)> {
+    let source = sources.get(source_id)?;
+    let url = Url::from_file_path(source.path()?).ok()?;
+    Some((url, source))
+}
+
+/// Iterate over all sources in a build.
+pub(crate) fn iter_sources(
+    sources: &rune::Sources,
+) -> impl Iterator<Item = (SourceId, &rune::Source)> {
+    let mut n = 0u32;
+
+    std::iter::from_fn(move || {
+        let id = SourceId::new(n);
+        let source = sources.get(id)?;
+        n += 1;
+        Some((id, source))
+    })
+}
+
+/// Test if the span contains the given offset.
+fn contains(span: Span, offset: usize) -> bool {
+    span.start.into_usize() <= offset && offset <= span.end.into_usize()
+}
+
+/// Test if the given kind of item can be referenced and renamed.
+pub(crate) fn is_renameable(kind: MetaKind) -> bool {
+    matches!(
+        kind,
+        MetaKind::UnitStruct
+            | MetaKind::TupleStruct
+            | MetaKind::Struct
+            | MetaKind::UnitVariant
+            | MetaKind::TupleVariant
+            | MetaKind::StructVariant
+            | MetaKind::Enum
+            | MetaKind::Function { .. }
+            | MetaKind::Const
+            | MetaKind::ConstFn
+    )
+}
+
+/// Collect the given occurrences into a workspace edit renaming them to
+/// `new_name`.
+pub(crate) fn rename_edit(
+    occurrences: Vec<Occurrence>,
+    old_name: &str,
+    new_name: &str,
+) -> lsp::WorkspaceEdit {
+    let mut changes = std::collections::HashMap::<Url, Vec<lsp::TextEdit>>::new();
+    let mut seen = HashSet::new();
+
+    for occurrence in occurrences {
+        if !seen.insert((
+            occurrence.url.clone(),
+            occurrence.range.start.line,
+            occurrence.range.start.character,
+        )) {
+            continue;
+        }
+
+        let new_text = match occurrence.shorthand {
+            Shorthand::None => new_name.to_owned(),
+            Shorthand::Field => format!("{}: {}", new_name, old_name),
+            Shorthand::Local => format!("{}: {}", old_name, new_name),
+        };
+
+        changes
+            .entry(occurrence.url)
+            .or_default()
+            .push(lsp::TextEdit::new(occurrence.range, new_text));
+    }
+
+    lsp::WorkspaceEdit::new(changes)
+}
@@ -0,0 +1,580 @@
+//! Semantic token highlighting for the language server.
+//!
+//! Tokens are classified lexically using [Lexer], and refined using the
+//! metadata recorded during the last build when it corresponds to the current
+//! content of the source.
+
+use std::collections::{HashMap, HashSet};
+
+use rune::ast::{Delimiter, Kind, LitSource, Span};
+use rune::compile::{Item, MetaKind};
+use rune::parse::Lexer;
+use rune::{Context, SourceId};
+
+use crate::state::{DefinitionKind, DefinitionSource, Index, Source};
+
+/// Classes of semantic tokens, in the order they're declared in the legend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenType {
+    Namespace,
+    Struct,
+    Enum,
+    EnumMember,
+    Function,
+    Method,
+    Macro,
+    Variable,
+    Parameter,
+    Property,
+    Keyword,
+    Comment,
+    String,
+    Number,
+    Operator,
+}
+
+/// Token types in the legend.
+const TOKEN_TYPES: [lsp::SemanticTokenType; 15] = [
+    lsp::SemanticTokenType::NAMESPACE,
+    lsp::SemanticTokenType::STRUCT,
+    lsp::SemanticTokenType::ENUM,
+    lsp::SemanticTokenType::ENUM_MEMBER,
+    lsp::SemanticTokenType::FUNCTION,
+    lsp::SemanticTokenType::METHOD,
+    lsp::SemanticTokenType::MACRO,
+    lsp::SemanticTokenType::VARIABLE,
+    lsp::SemanticTokenType::PARAMETER,
+    lsp::SemanticTokenType::PROPERTY,
+    lsp::SemanticTokenType::KEYWORD,
+    lsp::SemanticTokenType::COMMENT,
+    lsp::SemanticTokenType::STRING,
+    lsp::SemanticTokenType::NUMBER,
+    lsp::SemanticTokenType::OPERATOR,
+];
+
+/// Token modifiers in the legend, where each modifier corresponds to a bit
+/// in the order they're declared.
+const TOKEN_MODIFIERS: [lsp::SemanticTokenModifier; 4] = [
+    lsp::SemanticTokenModifier::DECLARATION,
+    lsp::SemanticTokenModifier::READONLY,
+    lsp::SemanticTokenModifier::DEFAULT_LIBRARY,
+    lsp::SemanticTokenModifier::DOCUMENTATION,
+];
+
+const DECLARATION: u32 = 1 << 0;
+const READONLY: u32 = 1 << 1;
+const DEFAULT_LIBRARY: u32 = 1 << 2;
+const DOCUMENTATION: u32 = 1 << 3;
+
+/// The legend of semantic tokens produced by the language server.
+pub(crate) fn legend() -> lsp::SemanticTokensLegend {
+    lsp::SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+/// Semantic tokens previously sent for a source, used to compute deltas.
+#[derive(Default)]
+pub(crate) struct Cache {
+    /// The identifier of the last result.
+    id: u64,
+    /// The last tokens sent.
+    data: Vec<lsp::SemanticToken>,
+}
+
+impl Cache {
+    /// Store the given tokens, returning the result identifier they're
+    /// stored under.
+    pub(crate) fn store(&mut self, data: Vec<lsp::SemanticToken>) -> String {
+        self.id += 1;
+        self.data = data;
+        self.id.to_string()
+    }
+
+    /// Get the tokens stored under the given result identifier.
+    pub(crate) fn get(&self, id: &str) -> Option<&[lsp::SemanticToken]> {
+        if self.id.to_string() == id {
+            Some(&self.data)
+        } else {
+            None
+        }
+    }
+}
+
+/// A token which has been lexed.
+#[derive(Debug, Clone, Copy)]
+struct Lexed {
+    kind: Kind,
+    span: Span,
+}
+
+/// A classified range of text.
+#[derive(Debug, Clone, Copy)]
+struct Class {
+    span: Span,
+    ty: TokenType,
+    modifiers: u32,
+}
+
+/// Compute semantic tokens for the given source.
+pub(crate) fn full(context: &Context, source: &Source) -> Vec<lsp::SemanticToken> {
+    let text = source.to_string();
+
+    // Metadata is only usable if the spans it refers to still line up with
+    // the content of the source.
+    let index = if source.is_indexed() {
+        Some(source.index())
+    } else {
+        None
+    };
+
+    encode(&text, &classify(context, &text, index))
+}
+
+/// Compute the edits which transform the `previous` tokens into `current`.
+pub(crate) fn delta(
+    previous: &[lsp::SemanticToken],
+    current: &[lsp::SemanticToken],
+) -> Vec<lsp::SemanticTokensEdit> {
+    let prefix = previous
+        .iter()
+        .zip(current)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let suffix = previous[prefix..]
+        .iter()
+        .rev()
+        .zip(current[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let deleted = previous.len() - prefix - suffix;
+    let inserted = &current[prefix..current.len() - suffix];
+
+    if deleted == 0 && inserted.is_empty() {
+        return Vec::new();
+    }
+
+    // Edits index into the flattened array of integers, where every token
+    // takes up five.
+    vec![lsp::SemanticTokensEdit {
+        start: (prefix * 5) as u32,
+        delete_count: (deleted * 5) as u32,
+        data: Some(inserted.to_vec()),
+    }]
+}
+
+/// Classify all tokens in the given text.
+fn classify(context: &Context, text: &str, index: Option<&Index>) -> Vec<Class> {
+    let (tokens, braces) = lex(text);
+
+    let idents = tokens
+        .iter()
+        .filter(|t| matches!(t.kind, Kind::Ident(LitSource::Text(..))))
+        .map(|t| t.span)
+        .collect::<Vec<_>>();
+
+    let resolved = match index {
+        Some(index) => resolve(context, index, &tokens, &idents),
+        None => HashMap::new(),
+    };
+
+    let mut output = Vec::new();
+    let mut braces = braces.into_iter().peekable();
+
+    for (n, token) in tokens.iter().enumerate() {
+        while let Some(span) = braces.next_if(|s| s.start < token.span.start) {
+            output.push(Class {
+                span,
+                ty: TokenType::Operator,
+                modifiers: 0,
+            });
+        }
+
+        let prev = n.checked_sub(1).and_then(|n| tokens.get(n)).map(|t| t.kind);
+        let next = tokens.get(n + 1).map(|t| t.kind);
+        let token_text = text.get(token.span.range()).unwrap_or_default();
+
+        let class = match token.kind {
+            Kind::Ident(LitSource::Text(..)) => match (resolved.get(&token.span), next) {
+                (_, Some(Kind::Bang)) => Some((TokenType::Macro, 0)),
+                (Some(&(ty, modifiers)), _) => Some((ty, modifiers)),
+                (None, _) => classify_ident(prev, next),
+            },
+            Kind::Comment | Kind::MultilineComment(..) | Kind::Shebang(..) => {
+                Some((TokenType::Comment, 0))
+            }
+            Kind::Str(..) | Kind::ByteStr(..) | Kind::Char(..) | Kind::Byte(..) => {
+                Some((TokenType::String, 0))
+            }
+            Kind::Number(..) => Some((TokenType::Number, 0)),
+            Kind::Ident(..) | Kind::Label(..) => None,
+            _ => classify_text(token.kind, token_text),
+        };
+
+        if let Some((ty, modifiers)) = class {
+            output.push(Class {
+                span: token.span,
+                ty,
+                modifiers,
+            });
+        }
+    }
+
+    for span in braces {
+        output.push(Class {
+            span,
+            ty: TokenType::Operator,
+            modifiers: 0,
+        });
+    }
+
+    output
+}
+
+/// Lex the given text, returning all non-trivia tokens and the spans of the
+/// braces closing expressions in template strings, which don't correspond to
+/// any tokens.
+///
+/// Tokens which the lexer synthesizes, like the attributes that doc comments
+/// and template strings desugar into, share the span of the first token and
+/// are skipped.
+fn lex(text: &str) -> (Vec<Lexed>, Vec<Span>) {
+    let mut lexer = Lexer::new(text, SourceId::empty(), true);
+    let mut tokens = Vec::new();
+    let mut braces = Vec::new();
+    let mut cursor = 0;
+
+    // Stop at the first lexer error, but keep whatever was found up until
+    // that point.
+    while let Ok(Some(token)) = lexer.next() {
+        let start = token.span.start.into_usize();
+        let end = token.span.end.into_usize();
+
+        if start < cursor || start == end {
+            continue;
+        }
+
+        if let Some(gap) = text.get(cursor..start) {
+            for (n, _) in gap.match_indices('}') {
+                braces.push(Span::new(cursor + n, cursor + n + 1));
+            }
+        }
+
+        cursor = end;
+
+        if !matches!(token.kind, Kind::Whitespace) {
+            tokens.push(Lexed {
+                kind: token.kind,
+                span: token.span,
+            });
+        }
+    }
+
+    (tokens, braces)
+}
+
+/// Classify an identifier that hasn't been resolved based on the tokens
+/// surrounding it.
+fn classify_ident(prev: Option<Kind>, next: Option<Kind>) -> Option<(TokenType, u32)> {
+    let ty = match (prev, next) {
+        (_, Some(Kind::Bang)) => TokenType::Macro,
+        (Some(Kind::Dot), Some(Kind::Open(Delimiter::Parenthesis))) => TokenType::Method,
+        (Some(Kind::Dot), _) => TokenType::Property,
+        (_, Some(Kind::ColonColon)) => TokenType::Namespace,
+        (Some(Kind::Open(Delimiter::Brace) | Kind::Comma), Some(Kind::Colon)) => {
+            TokenType::Property
+        }
+        _ => return None,
+    };
+
+    Some((ty, 0))
+}
+
+/// Classify a token which isn't an identifier or a literal by its text,
+/// since the tokens synthesized by the lexer have the span of the text they
+/// were desugared from.
+fn classify_text(kind: Kind, text: &str) -> Option<(TokenType, u32)> {
+    if text.starts_with("//") || text.starts_with("/*") {
+        return Some((TokenType::Comment, DOCUMENTATION));
+    }
+
+    if text == "`" {
+        return Some((TokenType::String, 0));
+    }
+
+    if text.starts_with("${") {
+        return Some((TokenType::Operator, 0));
+    }
+
+    if !text.is_empty() && text.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Some((TokenType::Keyword, 0));
+    }
+
+    // A comma which doesn't correspond to a comma in the text is the
+    // separator preceding a string in a template.
+    if matches!(kind, Kind::Comma) && text != "," {
+        return Some((TokenType::String, 0));
+    }
+
+    None
+}
+
+/// Resolve identifiers using the metadata in the given index.
+fn resolve(
+    context: &Context,
+    index: &Index,
+    tokens: &[Lexed],
+    idents: &[Span],
+) -> HashMap<Span, (TokenType, u32)> {
+    let mut output = HashMap::new();
+    let context_types = context
+        .iter_types()
+        .map(|(_, ty)| &*ty.item)
+        .collect::<HashSet<_>>();
+
+    let parameters = parameter_spans(index, tokens);
+    let is_parameter = |span: Span| {
+        parameters
+            .iter()
+            .any(|p| p.start <= span.start && span.end <= p.end)
+    };
+
+    // Declarations of items in this source.
+    for info in index.items.values() {
+        let location = match &info.source {
+            Some(source) if source.location.source_id.into_index() == 0 => source.location,
+            _ => continue,
+        };
+
+        let ty = match meta_token_type(info.kind) {
+            Some(ty) => ty,
+            None => continue,
+        };
+
+        let modifiers = match info.kind {
+            MetaKind::Const => DECLARATION | READONLY,
+            _ => DECLARATION,
+        };
+
+        if let Some(span) = idents_in(idents, location.span).first() {
+            output.entry(*span).or_insert((ty, modifiers));
+        }
+    }
+
+    for (span, definition) in &index.definitions {
+        let path = leading_path(tokens, *span);
+
+        let (last, rest) = match path.split_last() {
+            Some(split) => split,
+            None => continue,
+        };
+
+        let mut modifiers = 0;
+
+        if let DefinitionSource::Context = definition.source {
+            modifiers |= DEFAULT_LIBRARY;
+        }
+
+        let ty = match definition.kind {
+            DefinitionKind::Local => {
+                let declaration = match &definition.source {
+                    DefinitionSource::Location(location)
+                        if location.source_id.into_index() == 0 =>
+                    {
+                        idents_in(idents, location.span).first().copied()
+                    }
+                    _ => None,
+                };
+
+                let ty = match declaration {
+                    Some(declaration) if is_parameter(declaration) => TokenType::Parameter,
+                    _ => TokenType::Variable,
+                };
+
+                if let Some(declaration) = declaration {
+                    output.insert(declaration, (ty, DECLARATION));
+                }
+
+                ty
+            }
+            kind => definition_token_type(kind),
+        };
+
+        output.insert(*last, (ty, modifiers));
+
+        // Leading components of a path name the items the definition is
+        // nested in.
+        let mut parent = definition.item.as_deref().and_then(Item::parent);
+
+        for span in rest.iter().rev() {
+            let ty = match parent {
+                Some(p) if context_types.contains(p) => TokenType::Struct,
+                Some(p) => index
+                    .items
+                    .get(p)
+                    .and_then(|info| meta_token_type(info.kind))
+                    .unwrap_or(TokenType::Namespace),
+                None => TokenType::Namespace,
+            };
+
+            output.entry(*span).or_insert((ty, modifiers));
+            parent = parent.and_then(Item::parent);
+        }
+    }
+
+    output
+}
+
+/// Find the spans of parameter lists of functions and closures declared in
+/// the current source.
+fn parameter_spans(index: &Index, tokens: &[Lexed]) -> Vec<Span> {
+    let mut output = Vec::new();
+
+    for info in index.items.values() {
+        let location = match &info.source {
+            Some(source) if source.location.source_id.into_index() == 0 => source.location,
+            _ => continue,
+        };
+
+        let (open, close) = match info.kind {
+            MetaKind::Function { .. } | MetaKind::ConstFn => (
+                Kind::Open(Delimiter::Parenthesis),
+                Kind::Close(Delimiter::Parenthesis),
+            ),
+            MetaKind::Closure => (Kind::Pipe, Kind::Pipe),
+            _ => continue,
+        };
+
+        let start = tokens.partition_point(|t| t.span.start < location.span.start);
+        let mut it = tokens[start..]
+            .iter()
+            .take_while(|t| t.span.end <= location.span.end);
+
+        let first = match it.find(|t| t.kind == open) {
+            Some(first) => first,
+            None => continue,
+        };
+
+        if let Some(last) = it.find(|t| t.kind == close) {
+            output.push(first.span.join(last.span));
+        }
+    }
+
+    output
+}
+
+/// Get the identifiers in the path which the given span starts with, like
+/// `std` and `println` in `std::println!("{}", value)`.
+fn leading_path(tokens: &[Lexed], span: Span) -> Vec<Span> {
+    let start = tokens.partition_point(|t| t.span.start < span.start);
+    let mut output = Vec::new();
+
+    let mut it = tokens[start..]
+        .iter()
+        .take_while(|t| t.span.end <= span.end)
+        .skip_while(|t| matches!(t.kind, Kind::ColonColon));
+
+    while let Some(token) = it.next() {
+        if !matches!(token.kind, Kind::Ident(LitSource::Text(..))) {
+            break;
+        }
+
+        output.push(token.span);
+
+        if !matches!(it.next(), Some(t) if matches!(t.kind, Kind::ColonColon)) {
+            break;
+        }
+    }
+
+    output
+}
+
+/// Get the identifiers which are inside of the given span.
+fn idents_in(idents: &[Span], span: Span) -> &[Span] {
+    let start = idents.partition_point(|s| s.start < span.start);
+    let end = idents.partition_point(|s| s.end <= span.end);
+    idents.get(start..end.max(start)).unwrap_or_default()
+}
+
+/// Map a meta kind to a token type.
+fn meta_token_type(kind: MetaKind) -> Option<TokenType> {
+    Some(match kind {
+        MetaKind::UnitStruct | MetaKind::TupleStruct | MetaKind::Struct => TokenType::Struct,
+        MetaKind::UnitVariant | MetaKind::TupleVariant | MetaKind::StructVariant => {
+            TokenType::EnumMember
+        }
+        MetaKind::Enum => TokenType::Enum,
+        MetaKind::Function { .. } | MetaKind::ConstFn => TokenType::Function,
+        MetaKind::Const => TokenType::Variable,
+        MetaKind::Module => TokenType::Namespace,
+        _ => return None,
+    })
+}
+
+/// Map the kind of a definition to a token type.
+fn definition_token_type(kind: DefinitionKind) -> TokenType {
+    match kind {
+        DefinitionKind::UnitStruct | DefinitionKind::TupleStruct | DefinitionKind::Struct => {
+            TokenType::Struct
+        }
+        DefinitionKind::UnitVariant
+        | DefinitionKind::TupleVariant
+        | DefinitionKind::StructVariant => TokenType::EnumMember,
+        DefinitionKind::Enum => TokenType::Enum,
+        DefinitionKind::Function { .. } => TokenType::Function,
+        DefinitionKind::Local => TokenType::Variable,
+        DefinitionKind::Module => TokenType::Namespace,
+    }
+}
+
+/// Encode classified spans into relative semantic tokens, splitting the ones
+/// which span multiple lines since not all clients support multiline tokens.
+fn encode(text: &str, classes: &[Class]) -> Vec<lsp::SemanticToken> {
+    let source = rune::Source::new("", text);
+    let mut output = Vec::new();
+    let mut last_line = 0;
+    let mut last_start = 0;
+
+    for class in classes {
+        let mut start = class.span.start.into_usize();
+        let end = class.span.end.into_usize();
+
+        while start < end {
+            let line_end = text[start..end]
+                .find('\n')
+                .map(|n| start + n)
+                .unwrap_or(end);
+
+            let (line, character) = source.pos_to_utf16cu_linecol(start);
+            let length = text[start..line_end].encode_utf16().count();
+
+            if length > 0 {
+                let (line, character) = (line as u32, character as u32);
+
+                let delta_start = if line == last_line {
+                    character - last_start
+                } else {
+                    character
+                };
+
+                output.push(lsp::SemanticToken {
+                    delta_line: line - last_line,
+                    delta_start,
+                    length: length as u32,
+                    token_type: class.ty as u32,
+                    token_modifiers_bitset: class.modifiers,
+                });
+
+                last_line = line;
+                last_start = character;
+            }
+
+            start = line_end + 1;
+        }
+    }
+
+    output
+}
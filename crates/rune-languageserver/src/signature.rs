@@ -0,0 +1,540 @@
+//! Signature help for calls being typed.
+
+use std::collections::HashSet;
+
+use rune::ast::{Delimiter, Kind, LitSource, Span};
+use rune::compile::{ComponentRef, ContextSignature, Item, ItemBuf, MetaKind};
+use rune::parse::Lexer;
+use rune::runtime::debug::{DebugArgs, DebugSignature};
+use rune::{Context, InstFnKind, SourceId};
+
+use crate::state::{join_docs, Source};
+
+/// A call surrounding the cursor.
+struct Call<'a> {
+    /// The path being called, like `["foo", "bar"]` for `foo::bar(`.
+    path: Vec<&'a str>,
+    /// The type of the receiver if this is an instance call, like `value` in
+    /// `value.bar(`. The outer option indicates if it's an instance call.
+    receiver: Option<Option<ItemBuf>>,
+    /// The number of arguments preceding the cursor.
+    active: u32,
+}
+
+/// A lexed token together with its text.
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    kind: Kind,
+    text: &'a str,
+}
+
+/// Compute signature help for a call surrounding the given byte offset in the
+/// text of the given source.
+pub(crate) fn signature_help(
+    context: &Context,
+    source: &Source,
+    text: &str,
+    offset: usize,
+) -> Option<lsp::SignatureHelp> {
+    let tokens = lex(text.get(..offset)?)?;
+    let call = find_call(context, source, &tokens, offset)?;
+
+    let mut signatures = Vec::new();
+
+    match &call.receiver {
+        None => free_signatures(&mut signatures, context, source, &call),
+        Some(Some(ty)) => instance_signatures(&mut signatures, context, source, &call, ty),
+        // The type of the receiver is unknown, so we can't tell which
+        // function is being called.
+        Some(None) => return None,
+    }
+
+    if signatures.is_empty() {
+        return None;
+    }
+
+    Some(lsp::SignatureHelp {
+        signatures,
+        active_signature: Some(0),
+        active_parameter: None,
+    })
+}
+
+/// Collect signatures of free functions matching the call.
+fn free_signatures(
+    output: &mut Vec<lsp::SignatureInformation>,
+    context: &Context,
+    source: &Source,
+    call: &Call<'_>,
+) {
+    for signature in script_signatures(source) {
+        if ends_with(&signature.path, &call.path) {
+            output.push(script_signature(source, signature, false, call.active));
+        }
+    }
+
+    for (_, signature) in context.iter_functions() {
+        if let ContextSignature::Function { item, args, .. } = signature {
+            if ends_with(item, &call.path) {
+                let name = call.path.last().copied().unwrap_or_default();
+                output.push(native_signature(name, *args, false, call.active));
+            }
+        }
+    }
+}
+
+/// Collect signatures of instance functions on the given type matching the
+/// call.
+fn instance_signatures(
+    output: &mut Vec<lsp::SignatureInformation>,
+    context: &Context,
+    source: &Source,
+    call: &Call<'_>,
+    ty: &Item,
+) {
+    let name = match call.path.last() {
+        Some(name) => *name,
+        None => return,
+    };
+
+    let item = ty.extended(name);
+
+    for signature in script_signatures(source) {
+        if signature.path == item {
+            output.push(script_signature(source, signature, true, call.active));
+        }
+    }
+
+    for (_, signature) in context.iter_functions() {
+        if let ContextSignature::Instance {
+            item,
+            name: InstFnKind::Instance(instance_name),
+            args,
+            ..
+        } = signature
+        {
+            if **item == *ty && **instance_name == *name {
+                output.push(native_signature(name, *args, true, call.active));
+            }
+        }
+    }
+}
+
+/// Iterate over the signatures of all script functions in the last build.
+///
+/// Instance functions are recorded under both their instance and their free
+/// function hash, so signatures are deduplicated by path.
+fn script_signatures(source: &Source) -> impl Iterator<Item = &DebugSignature> {
+    let debug_info = source.unit().and_then(|unit| unit.debug_info());
+    let mut seen = HashSet::new();
+
+    debug_info
+        .into_iter()
+        .flat_map(|d| d.functions.values())
+        .filter(move |signature| seen.insert(&signature.path))
+}
+
+/// Build signature information for a script function.
+fn script_signature(
+    source: &Source,
+    signature: &DebugSignature,
+    is_instance: bool,
+    active: u32,
+) -> lsp::SignatureInformation {
+    let args = match &signature.args {
+        DebugArgs::EmptyArgs => Vec::new(),
+        DebugArgs::TupleArgs(n) => (0..*n).map(|n| format!("#{}", n)).collect(),
+        DebugArgs::Named(names) => names.iter().map(|name| name.to_string()).collect(),
+    };
+
+    let name = match signature.path.last() {
+        Some(ComponentRef::Str(name)) => name,
+        _ => "",
+    };
+
+    // The receiver is passed as the first argument to instance functions, so
+    // it doesn't correspond to any argument in the call.
+    let active = if is_instance { active + 1 } else { active };
+
+    let documentation = source
+        .index()
+        .docs(&signature.path)
+        .map(join_docs)
+        .map(|value| {
+            lsp::Documentation::MarkupContent(lsp::MarkupContent {
+                kind: lsp::MarkupKind::Markdown,
+                value,
+            })
+        });
+
+    let mut information = signature_information(name, &args, active);
+    information.documentation = documentation;
+    information
+}
+
+/// Build signature information for a native function, where only the number
+/// of arguments is known.
+fn native_signature(
+    name: &str,
+    args: Option<usize>,
+    is_instance: bool,
+    active: u32,
+) -> lsp::SignatureInformation {
+    let args = match args {
+        // Instance functions count the receiver as an argument.
+        Some(args) if is_instance => std::iter::once(String::from("self"))
+            .chain((1..args).map(|n| format!("#{}", n - 1)))
+            .collect::<Vec<_>>(),
+        Some(args) => (0..args).map(|n| format!("#{}", n)).collect(),
+        None => {
+            let mut information = signature_information(name, &[String::from("...")], 0);
+            information.parameters = None;
+            information.active_parameter = None;
+            return information;
+        }
+    };
+
+    let active = if is_instance { active + 1 } else { active };
+    signature_information(name, &args, active)
+}
+
+/// Construct signature information for a function with the given name and
+/// arguments.
+fn signature_information(name: &str, args: &[String], active: u32) -> lsp::SignatureInformation {
+    let mut label = format!("fn {}(", name);
+    let mut parameters = Vec::new();
+
+    for (n, arg) in args.iter().enumerate() {
+        if n > 0 {
+            label.push_str(", ");
+        }
+
+        let start = label.encode_utf16().count() as u32;
+        label.push_str(arg);
+        let end = label.encode_utf16().count() as u32;
+
+        parameters.push(lsp::ParameterInformation {
+            label: lsp::ParameterLabel::LabelOffsets([start, end]),
+            documentation: None,
+        });
+    }
+
+    label.push(')');
+
+    lsp::SignatureInformation {
+        label,
+        documentation: None,
+        parameters: Some(parameters),
+        active_parameter: Some(active),
+    }
+}
+
+/// Lex the given text, skipping trivia.
+fn lex(text: &str) -> Option<Vec<Token<'_>>> {
+    let mut lexer = Lexer::new(text, SourceId::empty(), true);
+    let mut output = Vec::new();
+    let mut cursor = 0;
+
+    while let Some(token) = lexer.next().ok()? {
+        let span = token.span;
+
+        // Skip tokens synthesized by the lexer, which share their span with
+        // the token preceding them.
+        if span.start.into_usize() < cursor {
+            continue;
+        }
+
+        cursor = span.end.into_usize();
+
+        if matches!(
+            token.kind,
+            Kind::Whitespace | Kind::Comment | Kind::MultilineComment(..)
+        ) {
+            continue;
+        }
+
+        output.push(Token {
+            kind: token.kind,
+            text: text.get(span.range())?,
+        });
+    }
+
+    Some(output)
+}
+
+/// Find the innermost call whose arguments are being typed at the end of the
+/// given tokens.
+fn find_call<'a>(
+    context: &Context,
+    source: &Source,
+    tokens: &[Token<'a>],
+    offset: usize,
+) -> Option<Call<'a>> {
+    // Open delimiters, with the index of the token opening them and the
+    // number of commas encountered directly inside of them.
+    let mut stack = Vec::<(Delimiter, usize, u32)>::new();
+
+    for (n, token) in tokens.iter().enumerate() {
+        match token.kind {
+            Kind::Open(delimiter) => stack.push((delimiter, n, 0)),
+            Kind::Close(..) => {
+                stack.pop();
+            }
+            Kind::Comma => {
+                if let Some((_, _, commas)) = stack.last_mut() {
+                    *commas += 1;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    for &(delimiter, open, commas) in stack.iter().rev() {
+        if delimiter != Delimiter::Parenthesis {
+            continue;
+        }
+
+        let (path, start) = match callee(&tokens[..open]) {
+            Some(callee) => callee,
+            None => continue,
+        };
+
+        let receiver = match start.checked_sub(1).map(|n| tokens[n].kind) {
+            Some(Kind::Dot) => Some(receiver_type(context, source, &tokens[..start - 1], offset)),
+            _ => None,
+        };
+
+        return Some(Call {
+            path,
+            receiver,
+            active: commas,
+        });
+    }
+
+    None
+}
+
+/// Parse the path being called at the end of the given tokens, returning it
+/// together with the index of the token it starts at.
+fn callee<'a>(tokens: &[Token<'a>]) -> Option<(Vec<&'a str>, usize)> {
+    let mut path = Vec::new();
+    let mut n = tokens.len();
+
+    loop {
+        let token = tokens.get(n.checked_sub(1)?)?;
+
+        if !matches!(token.kind, Kind::Ident(LitSource::Text(..))) {
+            break;
+        }
+
+        path.push(token.text);
+        n -= 1;
+
+        match n.checked_sub(1).map(|n| tokens[n].kind) {
+            Some(Kind::ColonColon) => n -= 1,
+            _ => break,
+        }
+    }
+
+    if path.is_empty() {
+        return None;
+    }
+
+    path.reverse();
+    Some((path, n))
+}
+
+/// Determine the type of the receiver preceding the `.` which ends at the
+/// given byte offset in the text of the given source.
+pub(crate) fn receiver_at(
+    context: &Context,
+    source: &Source,
+    text: &str,
+    offset: usize,
+) -> Option<ItemBuf> {
+    let tokens = lex(text.get(..offset)?)?;
+    let (dot, tokens) = tokens.split_last()?;
+
+    if !matches!(dot.kind, Kind::Dot) {
+        return None;
+    }
+
+    receiver_type(context, source, tokens, offset)
+}
+
+/// Determine the type of the receiver of an instance call, which ends the
+/// given tokens.
+fn receiver_type(
+    context: &Context,
+    source: &Source,
+    tokens: &[Token<'_>],
+    offset: usize,
+) -> Option<ItemBuf> {
+    let last = tokens.last()?;
+
+    match last.kind {
+        Kind::SelfValue => {
+            let span = source.function_span_at(source.indexed_offset(offset))?;
+            self_type(source, span)
+        }
+        Kind::Ident(LitSource::Text(..)) => {
+            if matches!(
+                tokens.len().checked_sub(2).map(|n| tokens[n].kind),
+                Some(Kind::Dot)
+            ) {
+                return None;
+            }
+
+            let init = local_initializer(tokens, last.text)?;
+            expression_type(context, source, init)
+        }
+        _ => expression_type(context, source, std::slice::from_ref(last)),
+    }
+}
+
+/// Find the type of `self` in the function declared in the given span.
+fn self_type(source: &Source, span: Span) -> Option<ItemBuf> {
+    let (item, _) = source.index().items.iter().find(|(_, info)| {
+        matches!(info.kind, MetaKind::Function { .. })
+            && matches!(&info.source, Some(s) if s.location.source_id.into_index() == 0 && s.location.span == span)
+    })?;
+
+    let parent = item.parent()?;
+    let info = source.index().items.get(parent)?;
+
+    if is_type(info.kind) {
+        Some(parent.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Find the tokens of the expression the most recent local variable with the
+/// given name was initialized with.
+fn local_initializer<'a, 'b>(tokens: &'b [Token<'a>], name: &str) -> Option<&'b [Token<'a>]> {
+    let n = tokens.windows(3).rposition(|w| {
+        matches!(w[0].kind, Kind::Let)
+            && matches!(w[1].kind, Kind::Ident(..))
+            && w[1].text == name
+            && matches!(w[2].kind, Kind::Eq)
+    })?;
+
+    tokens.get(n + 3..)
+}
+
+/// Determine the type of the expression at the start of the given tokens.
+fn expression_type(context: &Context, source: &Source, tokens: &[Token<'_>]) -> Option<ItemBuf> {
+    let first = tokens.first()?;
+
+    let std = |path: &[&str]| Some(ItemBuf::with_crate_item("std", path));
+
+    match first.kind {
+        Kind::Str(..) => return std(&["string", "String"]),
+        Kind::Number(..) => {
+            if first.text.contains(['.', 'e', 'E']) && !first.text.starts_with("0x") {
+                return std(&["float"]);
+            }
+
+            return std(&["int"]);
+        }
+        Kind::Open(Delimiter::Bracket) => return std(&["vec", "Vec"]),
+        Kind::Pound => return std(&["object", "Object"]),
+        _ => (),
+    }
+
+    let (path, end) = leading_path(tokens);
+
+    if path.is_empty() {
+        return None;
+    }
+
+    let is_call = matches!(
+        tokens.get(end).map(|t| t.kind),
+        Some(Kind::Open(Delimiter::Parenthesis | Delimiter::Brace))
+    );
+
+    if !is_call {
+        return None;
+    }
+
+    // A struct, or a variant which is being constructed.
+    if let Some((item, kind)) = resolve_script(source, &path) {
+        return match kind {
+            kind if is_type(kind) => Some(item.to_owned()),
+            MetaKind::TupleVariant | MetaKind::StructVariant => Some(item.parent()?.to_owned()),
+            // A constructor function associated with a type, like
+            // `Foo::new`.
+            MetaKind::Function { .. } => {
+                let parent = item.parent()?;
+                let info = source.index().items.get(parent)?;
+                is_type(info.kind).then(|| parent.to_owned())
+            }
+            _ => None,
+        };
+    }
+
+    // A native type, or a constructor function associated with one.
+    context.iter_types().find_map(|(_, ty)| {
+        if ends_with(&ty.item, &path) || ends_with(&ty.item, &path[..path.len() - 1]) {
+            Some(ty.item.clone())
+        } else {
+            None
+        }
+    })
+}
+
+/// Parse the path at the start of the given tokens, returning its components
+/// and the index of the token following it.
+fn leading_path<'a>(tokens: &[Token<'a>]) -> (Vec<&'a str>, usize) {
+    let mut path = Vec::new();
+    let mut n = 0;
+
+    while let Some(token) = tokens.get(n) {
+        if !matches!(token.kind, Kind::Ident(LitSource::Text(..))) {
+            break;
+        }
+
+        path.push(token.text);
+        n += 1;
+
+        match tokens.get(n).map(|t| t.kind) {
+            Some(Kind::ColonColon) => n += 1,
+            _ => break,
+        }
+    }
+
+    (path, n)
+}
+
+/// Resolve the given path to an item declared in a script.
+fn resolve_script<'a>(source: &'a Source, path: &[&str]) -> Option<(&'a Item, MetaKind)> {
+    source
+        .index()
+        .items
+        .iter()
+        .filter(|(_, info)| info.source.is_some())
+        .find(|(item, _)| ends_with(item, path))
+        .map(|(item, info)| (&**item, info.kind))
+}
+
+/// Test if the given item ends with the given path.
+fn ends_with(item: &Item, path: &[&str]) -> bool {
+    let components = item.iter().collect::<Vec<_>>();
+
+    if components.len() < path.len() {
+        return false;
+    }
+
+    components[components.len() - path.len()..]
+        .iter()
+        .zip(path)
+        .all(|(c, p)| matches!(c, ComponentRef::Str(s) | ComponentRef::Crate(s) if s == p))
+}
+
+/// Test if the given kind of item is a type which can have instance functions.
+fn is_type(kind: MetaKind) -> bool {
+    matches!(
+        kind,
+        MetaKind::UnitStruct | MetaKind::TupleStruct | MetaKind::Struct | MetaKind::Enum
+    )
+}
@@ -1,6 +1,7 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
@@ -10,14 +11,18 @@ use lsp::Url;
 use ropey::Rope;
 use rune::ast::{Span, Spanned};
 use rune::compile::{
-    CompileError, CompileVisitor, ComponentRef, FileSourceLoader, Item, LinkerError, Location,
-    MetaKind, MetaRef, SourceMeta,
+    CompileError, CompileVisitor, ComponentRef, FileSourceLoader, Item, ItemBuf, LinkerError,
+    Location, MetaKind, MetaRef, SourceMeta,
 };
 use rune::diagnostics::{Diagnostic, FatalDiagnosticKind};
-use rune::{Context, Options, SourceId};
+use rune::{Context, Hash, Options, SourceId, Unit};
 use tokio::sync::RwLockWriteGuard;
 use tokio::sync::{mpsc, RwLock};
 
+use crate::code_actions;
+use crate::references::{self, Reference, Symbol};
+use crate::semantic_tokens;
+use crate::workspace;
 use crate::Output;
 
 /// Shared server state.
@@ -36,6 +41,7 @@ impl State {
                 options,
                 initialized: Default::default(),
                 sources: Default::default(),
+                root: Default::default(),
             }),
         }
     }
@@ -50,6 +56,11 @@ impl State {
         self.inner.initialized.load(Ordering::Acquire)
     }
 
+    /// Set the root directory of the workspace.
+    pub async fn set_root(&self, root: Option<PathBuf>) {
+        *self.inner.root.write().await = root;
+    }
+
     /// Access sources in the current state.
     pub async fn sources_mut(&self) -> RwLockWriteGuard<'_, Sources> {
         self.inner.sources.write().await
@@ -59,12 +70,15 @@ impl State {
     ///
     /// Sources that have been modified will be marked as dirty.
     pub async fn rebuild_interest(&self) -> Result<()> {
-        self.inner
-            .rebuild_tx
-            .clone()
-            .send(())
-            .await
-            .map_err(|_| anyhow!("failed to send rebuild interest"))
+        // Note: this is called from within the loop which drives rebuilds,
+        // so we can't wait for capacity. If the channel is full a rebuild is
+        // already pending.
+        match self.inner.rebuild_tx.try_send(()) {
+            Ok(()) | Err(mpsc::error::TrySendError::Full(())) => Ok(()),
+            Err(mpsc::error::TrySendError::Closed(())) => {
+                Err(anyhow!("failed to send rebuild interest"))
+            }
+        }
     }
 
     /// Find definition at the given uri and LSP position.
@@ -75,16 +89,19 @@ impl State {
     ) -> Option<lsp::Location> {
         let sources = self.inner.sources.read().await;
 
-        let source = sources.get(uri)?;
+        let source = sources.get(uri).filter(|source| source.is_indexed())?;
         let offset = source.lsp_position_to_offset(position);
-        let def = source.find_definition_at(Span::point(offset))?;
+        let (_, def) = source.find_definition_at(Span::point(offset))?;
 
         let url = match def.source.path() {
             Some(path) => Url::from_file_path(path).ok()?,
             None => uri.clone(),
         };
 
-        let source = source.build_sources.as_ref()?.get(def.source.source_id())?;
+        let source = source
+            .build_sources
+            .as_ref()?
+            .get(def.source.source_id()?)?;
 
         let (l, c) = source.pos_to_utf16cu_linecol(def.source.span().start.into_usize());
         let start = lsp::Position {
@@ -106,135 +123,422 @@ impl State {
         Some(location)
     }
 
+    /// Find hover information at the given uri and LSP position.
+    pub async fn hover(&self, uri: &Url, position: lsp::Position) -> Option<lsp::Hover> {
+        let sources = self.inner.sources.read().await;
+
+        let source = sources.get(uri).filter(|source| source.is_indexed())?;
+        let offset = source.lsp_position_to_offset(position);
+        let (span, def) = source.find_definition_at(Span::point(offset))?;
+
+        let mut value = String::new();
+
+        value.push_str("```rune\n");
+
+        match def.kind {
+            DefinitionKind::Local => {
+                let build_sources = source.build_sources.as_ref()?;
+                let name = build_sources.source(def.source.source_id()?, def.source.span())?;
+                value.push_str(&format!("let {}", name));
+            }
+            DefinitionKind::Function { type_hash } => {
+                let item = def.item.as_ref()?;
+
+                let signature = source
+                    .unit
+                    .as_ref()
+                    .and_then(|unit| unit.debug_info())
+                    .and_then(|debug| debug.functions.get(&type_hash));
+
+                if let Some(signature) = signature {
+                    value.push_str(&format!("fn {}", signature));
+                } else if let Some(signature) = self.inner.context.lookup_signature(type_hash) {
+                    value.push_str(&format!("fn {}", signature));
+                } else {
+                    value.push_str(&format!("fn {}", item));
+                }
+            }
+            kind => {
+                let item = def.item.as_ref()?;
+                value.push_str(&format!("{} {}", kind.keyword(), item));
+            }
+        }
+
+        value.push_str("\n```");
+
+        if let Some(item) = &def.item {
+            if let Some(docs) = source.index.docs.get(item) {
+                value.push_str("\n\n");
+                value.push_str(&join_docs(docs));
+            }
+
+            if let Some(fields) = source.index.field_docs.get(item) {
+                value.push_str("\n\n**Fields**\n");
+
+                for (field, docs) in fields {
+                    value.push_str(&format!("\n* `{}`: {}", field, join_docs(docs).trim()));
+                }
+            }
+        }
+
+        Some(lsp::Hover {
+            contents: lsp::HoverContents::Markup(lsp::MarkupContent {
+                kind: lsp::MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(source.span_to_lsp_range(span)),
+        })
+    }
+
+    /// Find all references to the symbol at the given uri and LSP position.
+    pub async fn find_references(
+        &self,
+        uri: &Url,
+        position: lsp::Position,
+        include_declaration: bool,
+    ) -> Option<Vec<lsp::Location>> {
+        let sources = self.inner.sources.read().await;
+
+        let source = sources.get(uri).filter(|source| source.is_indexed())?;
+        let offset = source.lsp_position_to_offset(position);
+        let symbol = references::symbol_at(source, offset)?;
+
+        let locations = references::occurrences(&sources, source, &symbol)
+            .into_iter()
+            .filter(|o| include_declaration || !o.declaration)
+            .map(|o| lsp::Location::new(o.url, o.range))
+            .collect();
+
+        Some(locations)
+    }
+
+    /// Test if the symbol at the given uri and LSP position can be renamed,
+    /// and if so return the range of its name.
+    pub async fn prepare_rename(&self, uri: &Url, position: lsp::Position) -> Option<lsp::Range> {
+        let sources = self.inner.sources.read().await;
+
+        let source = sources.get(uri).filter(|source| source.is_indexed())?;
+        let offset = source.lsp_position_to_offset(position);
+        let symbol = references::symbol_at(source, offset)?;
+
+        if !references::can_rename(&symbol) {
+            return None;
+        }
+
+        references::occurrences(&sources, source, &symbol)
+            .into_iter()
+            .filter(|o| o.url == *uri)
+            .map(|o| o.range)
+            .find(|range| range.start <= position && position <= range.end)
+    }
+
+    /// Rename the symbol at the given uri and LSP position.
+    pub async fn rename(
+        &self,
+        uri: &Url,
+        position: lsp::Position,
+        new_name: &str,
+    ) -> Option<lsp::WorkspaceEdit> {
+        if !references::is_valid_name(new_name) {
+            return None;
+        }
+
+        let sources = self.inner.sources.read().await;
+
+        let source = sources.get(uri).filter(|source| source.is_indexed())?;
+        let offset = source.lsp_position_to_offset(position);
+        let symbol = references::symbol_at(source, offset)?;
+
+        if !references::can_rename(&symbol) {
+            return None;
+        }
+
+        let occurrences = references::occurrences(&sources, source, &symbol);
+        let old_name = references::symbol_name(source, &symbol)?;
+
+        // NB: an edit which doesn't line up with the text as it currently is
+        // would corrupt it, so nothing is renamed unless every occurrence
+        // still reads as the old name.
+        let lines_up = occurrences
+            .iter()
+            .all(|o| sources.text_at(&o.url, o.range).as_deref() == Some(old_name));
+
+        if !lines_up {
+            return None;
+        }
+
+        Some(references::rename_edit(occurrences, old_name, new_name))
+    }
+
+    /// Compute all semantic tokens for the source at the given uri.
+    pub async fn semantic_tokens_full(&self, uri: &Url) -> Option<lsp::SemanticTokens> {
+        let mut sources = self.inner.sources.write().await;
+        let source = sources.get_mut(uri)?;
+
+        let data = semantic_tokens::full(&self.inner.context, source);
+        let result_id = source.semantic_tokens.store(data.clone());
+
+        Some(lsp::SemanticTokens {
+            result_id: Some(result_id),
+            data,
+        })
+    }
+
+    /// Compute semantic tokens for the source at the given uri as a delta
+    /// relative to the result previously sent, if it's still available.
+    pub async fn semantic_tokens_delta(
+        &self,
+        uri: &Url,
+        previous_result_id: &str,
+    ) -> Option<lsp::SemanticTokensFullDeltaResult> {
+        let mut sources = self.inner.sources.write().await;
+        let source = sources.get_mut(uri)?;
+
+        let data = semantic_tokens::full(&self.inner.context, source);
+
+        let edits = source
+            .semantic_tokens
+            .get(previous_result_id)
+            .map(|previous| semantic_tokens::delta(previous, &data));
+
+        let result_id = source.semantic_tokens.store(data.clone());
+
+        Some(match edits {
+            Some(edits) => {
+                lsp::SemanticTokensFullDeltaResult::TokensDelta(lsp::SemanticTokensDelta {
+                    result_id: Some(result_id),
+                    edits,
+                })
+            }
+            None => lsp::SemanticTokensFullDeltaResult::Tokens(lsp::SemanticTokens {
+                result_id: Some(result_id),
+                data,
+            }),
+        })
+    }
+
+    /// Compute signature help for a call surrounding the given uri and LSP
+    /// position.
+    pub async fn signature_help(
+        &self,
+        uri: &Url,
+        position: lsp::Position,
+    ) -> Option<lsp::SignatureHelp> {
+        let sources = self.inner.sources.read().await;
+
+        let source = sources.get(uri)?;
+        let text = source.to_string();
+        let offset = source.lsp_position_to_offset(position);
+
+        crate::signature::signature_help(&self.inner.context, source, &text, offset)
+    }
+
+    /// Format the source at the given uri, optionally only keeping the edits
+    /// which touch the given range.
+    ///
+    /// Sources which can't be parsed aren't formatted.
+    pub async fn format(&self, uri: &Url, range: Option<lsp::Range>) -> Option<Vec<lsp::TextEdit>> {
+        let sources = self.inner.sources.read().await;
+
+        let source = sources.get(uri)?;
+        let text = source.to_string();
+
+        let formatted = match rune::fmt::layout_source(&text) {
+            Ok(formatted) => formatted,
+            Err(error) => {
+                tracing::trace!("not formatting {}: {}", uri, error);
+                return None;
+            }
+        };
+
+        Some(crate::formatting::edits(&text, &formatted, range))
+    }
+
+    /// Collect the symbols declared in the source at the given uri.
+    pub async fn document_symbols(&self, uri: &Url) -> Option<Vec<lsp::DocumentSymbol>> {
+        let sources = self.inner.sources.read().await;
+        let source = sources.get(uri)?;
+        Some(crate::symbols::document_symbols(source))
+    }
+
+    /// Find symbols across the workspace matching the given query.
+    pub async fn workspace_symbols(&self, query: &str) -> Vec<lsp::SymbolInformation> {
+        let sources = self.inner.sources.read().await;
+        crate::symbols::workspace_symbols(&sources, query)
+    }
+
+    /// Collect completions at the given uri and LSP position.
+    pub async fn complete(
+        &self,
+        uri: &Url,
+        position: lsp::Position,
+    ) -> Option<Vec<lsp::CompletionItem>> {
+        let sources = self.inner.sources.read().await;
+
+        let source = sources.get(uri)?;
+        let prefix = source.line_prefix(position);
+        let offset = source.lsp_position_to_offset(position);
+
+        Some(crate::completion::complete(
+            &self.inner.context,
+            source,
+            &prefix,
+            offset,
+        ))
+    }
+
     /// Rebuild the current project.
+    ///
+    /// If the workspace root contains a `Rune.toml` manifest, every entry
+    /// point in it is built separately. Open sources which aren't entry points
+    /// are built on their own, but if they're included by an entry point their
+    /// diagnostics are reported through it instead.
     pub async fn rebuild(&self, output: &Output) -> Result<()> {
         let mut inner = self.inner.sources.write().await;
 
         let mut by_url = HashMap::<Url, Vec<lsp::Diagnostic>>::new();
 
+        for url in inner.published.drain() {
+            by_url.insert(url, Vec::new());
+        }
+
         for (url, _) in inner.removed.drain(..) {
             by_url.insert(url.clone(), Vec::new());
         }
 
         let mut builds = Vec::new();
+        let mut entries = HashSet::new();
+        let mut included = HashSet::new();
 
         let mut source_loader = SourceLoader::new(&inner.sources);
 
-        for (url, source) in &inner.sources {
-            tracing::trace!("build: {}", url);
+        let manifest = self
+            .inner
+            .root
+            .read()
+            .await
+            .as_deref()
+            .and_then(workspace::find_manifest);
+
+        if let Some(manifest) = manifest {
+            match workspace::load(&manifest) {
+                Ok(workspace) => {
+                    for diagnostic in workspace.diagnostics.diagnostics() {
+                        if let rune::workspace::Diagnostic::Fatal(fatal) = diagnostic {
+                            report(
+                                &workspace.sources,
+                                &mut by_url,
+                                fatal.error().span(),
+                                fatal.source_id(),
+                                fatal.error(),
+                                display_to_error,
+                            );
+                        }
+                    }
 
-            by_url.insert(url.clone(), Default::default());
-
-            let mut sources = rune::Sources::new();
-            let input = rune::Source::with_path(url, source.to_string(), url.to_file_path().ok());
-
-            sources.insert(input);
-
-            let mut diagnostics = rune::Diagnostics::new();
-            let mut visitor = Visitor::new(Index::default());
-
-            let _ = rune::prepare(&mut sources)
-                .with_context(&self.inner.context)
-                .with_diagnostics(&mut diagnostics)
-                .with_options(&self.inner.options)
-                .with_visitor(&mut visitor)
-                .with_source_loader(&mut source_loader)
-                .build();
-
-            for diagnostic in diagnostics.diagnostics() {
-                match diagnostic {
-                    Diagnostic::Fatal(fatal) => {
-                        let source_id = fatal.source_id();
-
-                        match fatal.kind() {
-                            FatalDiagnosticKind::ParseError(error) => {
-                                report(
-                                    &sources,
-                                    &mut by_url,
-                                    error.span(),
-                                    source_id,
-                                    error,
-                                    display_to_error,
-                                );
-                            }
-                            FatalDiagnosticKind::CompileError(error) => {
-                                report(
-                                    &sources,
-                                    &mut by_url,
-                                    error.span(),
-                                    source_id,
-                                    error,
-                                    display_to_error,
-                                );
-                            }
-                            FatalDiagnosticKind::QueryError(error) => {
-                                report(
-                                    &sources,
-                                    &mut by_url,
-                                    error.span(),
-                                    source_id,
-                                    error,
-                                    display_to_error,
-                                );
-                            }
-                            FatalDiagnosticKind::LinkError(error) => match error {
-                                LinkerError::MissingFunction { hash, spans } => {
-                                    for (span, _) in spans {
-                                        let diagnostics = by_url.entry(url.clone()).or_default();
-
-                                        let range = source.span_to_lsp_range(*span);
-
-                                        diagnostics.push(display_to_error(
-                                            range,
-                                            format!("missing function with hash `{}`", hash),
-                                        ));
-                                    }
-                                }
-                                error => {
-                                    let diagnostics = by_url.entry(url.clone()).or_default();
-                                    let range = lsp::Range::default();
-                                    diagnostics.push(display_to_error(range, error));
+                    for path in workspace.entries {
+                        let url = match Url::from_file_path(&path) {
+                            Ok(url) => url,
+                            Err(()) => continue,
+                        };
+
+                        let text = match inner.sources.get(&url) {
+                            Some(source) => source.to_string(),
+                            None => match fs::read_to_string(&path) {
+                                Ok(text) => text,
+                                Err(error) => {
+                                    tracing::warn!(
+                                        "failed to read `{}`: {}",
+                                        path.display(),
+                                        error
+                                    );
+                                    continue;
                                 }
                             },
-                            FatalDiagnosticKind::Internal(message) => {
-                                let diagnostics = by_url.entry(url.clone()).or_default();
-                                let range = lsp::Range::default();
-                                diagnostics.push(display_to_error(range, message));
-                            }
-                            error => {
-                                let diagnostics = by_url.entry(url.clone()).or_default();
-                                let range = lsp::Range::default();
-                                diagnostics.push(display_to_error(range, error));
+                        };
+
+                        tracing::trace!("build entry: {}", url);
+
+                        let mut build = self.build(&url, text, &mut source_loader);
+
+                        for (url, diagnostics) in build.diagnostics.drain() {
+                            by_url.entry(url).or_default().extend(diagnostics);
+                        }
+
+                        for (source_id, _) in references::iter_sources(&build.sources) {
+                            if let Some((url, _)) =
+                                references::source_with_url(&build.sources, source_id)
+                            {
+                                included.insert(url);
                             }
                         }
+
+                        entries.insert(url);
+                        builds.push(build);
                     }
-                    Diagnostic::Warning(warning) => {
-                        report(
-                            &sources,
-                            &mut by_url,
-                            warning.span(),
-                            warning.source_id(),
-                            warning.kind(),
-                            display_to_warning,
-                        );
-                    }
+                }
+                Err(error) => {
+                    tracing::warn!("failed to load `{}`: {}", manifest.display(), error);
                 }
             }
+        }
 
-            builds.push((url.clone(), sources, visitor.into_index()));
+        for (url, source) in &inner.sources {
+            by_url.entry(url.clone()).or_default();
+
+            if entries.contains(url) {
+                continue;
+            }
+
+            tracing::trace!("build: {}", url);
+
+            let mut build = self.build(url, source.to_string(), &mut source_loader);
+
+            // Sources included by an entry point are diagnosed through it,
+            // since they might not build on their own.
+            if !included.contains(url) {
+                for (url, diagnostics) in build.diagnostics.drain() {
+                    by_url.entry(url).or_default().extend(diagnostics);
+                }
+            }
+
+            builds.push(build);
         }
 
-        for (url, build_sources, index) in builds {
-            if let Some(source) = inner.sources.get_mut(&url) {
-                source.index = index;
-                source.build_sources = Some(build_sources);
+        for build in builds {
+            if let Some(source) = inner.sources.get_mut(&build.url) {
+                // Keep the index, sources and unit from the last successful
+                // build around if this one failed. A source being edited is
+                // frequently broken, and stale information is more useful
+                // than none when completing. Anything which relies on the
+                // spans in the index checks that they're still current
+                // through `Source::is_indexed`.
+                if build.unit.is_some() || source.unit.is_none() {
+                    source.index = build.index;
+                    source.build_sources = Some(build.sources);
+                    source.unit = build.unit;
+                }
             }
         }
 
-        for (url, diagnostics) in by_url {
+        for (url, mut diagnostics) in by_url {
+            // Sources included by multiple entry points are diagnosed once per
+            // entry point.
+            let mut unique = Vec::with_capacity(diagnostics.len());
+
+            for diagnostic in diagnostics.drain(..) {
+                if !unique.contains(&diagnostic) {
+                    unique.push(diagnostic);
+                }
+            }
+
+            if !unique.is_empty() {
+                inner.published.insert(url.clone());
+            }
+
             let diagnostics = lsp::PublishDiagnosticsParams {
                 uri: url.clone(),
-                diagnostics,
+                diagnostics: unique,
                 version: None,
             };
 
@@ -245,6 +549,136 @@ impl State {
 
         Ok(())
     }
+
+    /// Build a single entry point.
+    fn build(&self, url: &Url, text: String, source_loader: &mut SourceLoader<'_>) -> Build {
+        let mut sources = rune::Sources::new();
+        let input = rune::Source::with_path(url, text, url.to_file_path().ok());
+
+        sources.insert(input);
+
+        let mut diagnostics = rune::Diagnostics::new();
+        let mut visitor = Visitor::new(Index::default());
+        let mut reported = HashMap::new();
+        let by_url = &mut reported;
+
+        let unit = rune::prepare(&mut sources)
+            .with_context(&self.inner.context)
+            .with_diagnostics(&mut diagnostics)
+            .with_options(&self.inner.options)
+            .with_visitor(&mut visitor)
+            .with_source_loader(source_loader)
+            .build()
+            .ok();
+
+        for diagnostic in diagnostics.diagnostics() {
+            match diagnostic {
+                Diagnostic::Fatal(fatal) => {
+                    let source_id = fatal.source_id();
+
+                    match fatal.kind() {
+                        FatalDiagnosticKind::ParseError(error) => {
+                            report(
+                                &sources,
+                                by_url,
+                                error.span(),
+                                source_id,
+                                error,
+                                display_to_error,
+                            );
+                        }
+                        FatalDiagnosticKind::CompileError(error) => {
+                            report(
+                                &sources,
+                                by_url,
+                                error.span(),
+                                source_id,
+                                error,
+                                display_to_error,
+                            );
+                        }
+                        FatalDiagnosticKind::QueryError(error) => {
+                            report(
+                                &sources,
+                                by_url,
+                                error.span(),
+                                source_id,
+                                error,
+                                display_to_error,
+                            );
+                        }
+                        FatalDiagnosticKind::LinkError(error) => match error {
+                            LinkerError::MissingFunction { hash, spans } => {
+                                for (span, source_id) in spans {
+                                    report(
+                                        &sources,
+                                        by_url,
+                                        *span,
+                                        *source_id,
+                                        format!("missing function with hash `{}`", hash),
+                                        display_to_error,
+                                    );
+                                }
+                            }
+                            error => {
+                                let diagnostics = by_url.entry(url.clone()).or_default();
+                                let range = lsp::Range::default();
+                                diagnostics.push(display_to_error(range, error));
+                            }
+                        },
+                        FatalDiagnosticKind::Internal(message) => {
+                            let diagnostics = by_url.entry(url.clone()).or_default();
+                            let range = lsp::Range::default();
+                            diagnostics.push(display_to_error(range, message));
+                        }
+                        error => {
+                            let diagnostics = by_url.entry(url.clone()).or_default();
+                            let range = lsp::Range::default();
+                            diagnostics.push(display_to_error(range, error));
+                        }
+                    }
+                }
+                Diagnostic::Warning(warning) => {
+                    let diagnostic = report(
+                        &sources,
+                        by_url,
+                        warning.span(),
+                        warning.source_id(),
+                        warning.kind(),
+                        display_to_warning,
+                    );
+
+                    if let (Some(diagnostic), Some(edit)) = (diagnostic, warning.suggested_edit()) {
+                        diagnostic.data = sources
+                            .get(warning.source_id())
+                            .and_then(|source| code_actions::edit_data(source, edit));
+                    }
+                }
+            }
+        }
+
+        Build {
+            url: url.clone(),
+            sources,
+            index: visitor.into_index(),
+            unit,
+            diagnostics: reported,
+        }
+    }
+}
+
+/// The result of building a single entry point.
+struct Build {
+    /// The url of the entry point.
+    url: Url,
+    /// Sources loaded during the build.
+    sources: rune::Sources,
+    /// The index collected during the build.
+    index: Index,
+    /// The unit, if the build was successful.
+    unit: Option<Unit>,
+    /// Diagnostics reported by the build, by url.
+    diagnostics: HashMap<Url, Vec<lsp::Diagnostic>>,
 }
 
 struct Inner {
@@ -259,6 +693,9 @@ struct Inner {
     initialized: AtomicBool,
     /// Sources used in the project.
     sources: RwLock<Sources>,
+    /// The root directory of the workspace, used to find the nearest
+    /// `Rune.toml` manifest.
+    root: RwLock<Option<PathBuf>>,
 }
 
 /// A collection of open sources.
@@ -268,6 +705,8 @@ pub struct Sources {
     sources: HashMap<Url, Source>,
     /// A source that has been removed.
     removed: Vec<(Url, Source)>,
+    /// Urls which diagnostics were published for in the last build.
+    published: HashSet<Url>,
 }
 
 impl Sources {
@@ -277,6 +716,8 @@ impl Sources {
             content: Rope::from(text),
             index: Default::default(),
             build_sources: None,
+            unit: None,
+            semantic_tokens: Default::default(),
         };
 
         self.sources.insert(url, source)
@@ -287,6 +728,22 @@ impl Sources {
         self.sources.get(url)
     }
 
+    /// Iterate over all open sources.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Source> {
+        self.sources.values()
+    }
+
+    /// Get the current text in the given range of the source at the given
+    /// url, reading it from disk unless it's open.
+    pub(crate) fn text_at(&self, url: &Url, range: lsp::Range) -> Option<String> {
+        if let Some(source) = self.sources.get(url) {
+            return rope_text_at(&source.content, range);
+        }
+
+        let text = fs::read_to_string(url.to_file_path().ok()?).ok()?;
+        rope_text_at(&Rope::from(text), range)
+    }
+
     /// Get the mutable source at the given url.
     pub fn get_mut(&mut self, url: &Url) -> Option<&mut Source> {
         self.sources.get_mut(url)
@@ -309,21 +766,142 @@ pub struct Source {
     /// Loaded Rune sources for this source file. Will be present after the
     /// source file has been built.
     build_sources: Option<rune::Sources>,
+    /// The last unit which was successfully built from this source.
+    unit: Option<Unit>,
+    /// Semantic tokens last sent for this source.
+    semantic_tokens: semantic_tokens::Cache,
 }
 
 impl Source {
-    /// Find the definition at the given span.
-    pub fn find_definition_at(&self, span: Span) -> Option<&Definition> {
+    /// Find the definition at the given span, and the span of the reference
+    /// to it.
+    pub fn find_definition_at(&self, span: Span) -> Option<(Span, &Definition)> {
         let (found_span, definition) = self.index.definitions.range(..=span).rev().next()?;
 
         if span.start >= found_span.start && span.end <= found_span.end {
             tracing::trace!("found {:?}", definition);
-            return Some(definition);
+            return Some((*found_span, definition));
         }
 
         None
     }
 
+    /// Test if the content of the source hasn't changed since it was last
+    /// indexed, so that the spans in its index line up with it.
+    pub(crate) fn is_indexed(&self) -> bool {
+        match self.indexed_text() {
+            Some(text) => self.has_content(text),
+            None => false,
+        }
+    }
+
+    /// Test if the source currently has the given content.
+    pub(crate) fn has_content(&self, text: &str) -> bool {
+        self.content == text
+    }
+
+    /// Map an offset in the content of the source to the content it was last
+    /// indexed from. Offsets inside of text which has changed since are
+    /// mapped to where the change starts.
+    pub(crate) fn indexed_offset(&self, offset: usize) -> usize {
+        let indexed = match self.indexed_text() {
+            Some(indexed) => indexed,
+            None => return offset,
+        };
+
+        let current = self.content.to_string();
+        let prefix = common_len(indexed.chars(), current.chars());
+        let suffix = common_len(
+            indexed[prefix..].chars().rev(),
+            current[prefix..].chars().rev(),
+        );
+
+        if offset <= prefix {
+            offset
+        } else if offset >= current.len() - suffix {
+            offset - (current.len() - suffix) + (indexed.len() - suffix)
+        } else {
+            prefix
+        }
+    }
+
+    /// The content the source was last indexed from.
+    fn indexed_text(&self) -> Option<&str> {
+        let build_sources = self.build_sources.as_ref()?;
+        Some(build_sources.get(SourceId::new(0))?.as_str())
+    }
+
+    /// Find the span of the innermost function which contains the given
+    /// offset in the indexed content.
+    pub(crate) fn function_span_at(&self, offset: usize) -> Option<Span> {
+        let mut found = None::<Span>;
+
+        for info in self.index.items.values() {
+            let location = match (info.kind, &info.source) {
+                (MetaKind::Function { .. }, Some(source)) => source.location,
+                _ => continue,
+            };
+
+            if location.source_id.into_index() != 0 {
+                continue;
+            }
+
+            let span = location.span;
+
+            if span.start.into_usize() > offset || span.end.into_usize() < offset {
+                continue;
+            }
+
+            let is_narrower = match found {
+                Some(found) => span.range().len() < found.range().len(),
+                None => true,
+            };
+
+            if is_narrower {
+                found = Some(span);
+            }
+        }
+
+        found
+    }
+
+    /// Access the index of the source.
+    pub(crate) fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Access the sources loaded during the last build.
+    pub(crate) fn build_sources(&self) -> Option<&rune::Sources> {
+        self.build_sources.as_ref()
+    }
+
+    /// Access the last successfully built unit.
+    pub(crate) fn unit(&self) -> Option<&Unit> {
+        self.unit.as_ref()
+    }
+
+    /// Get the text on the line of the given position which precedes it.
+    fn line_prefix(&self, position: lsp::Position) -> String {
+        let line = match self.content.get_line(position.line as usize) {
+            Some(line) => line,
+            None => return String::new(),
+        };
+
+        let mut prefix = String::new();
+        let mut utf16_offset = 0usize;
+
+        for c in line.chars() {
+            if utf16_offset >= position.character as usize {
+                break;
+            }
+
+            utf16_offset += c.len_utf16();
+            prefix.push(c);
+        }
+
+        prefix
+    }
+
     /// Modify the given lsp range in the file.
     pub fn modify_lsp_range(&mut self, range: lsp::Range, content: &str) -> Result<()> {
         let start = rope_utf16_position(&self.content, range.start)?;
@@ -360,11 +938,14 @@ impl Source {
         lsp::Position::new(line as u32, col_char as u32)
     }
 
-    /// Offset in the rope to lsp position.
+    /// Lsp position to byte offset in the rope.
     fn lsp_position_to_offset(&self, position: lsp::Position) -> usize {
         let line = self.content.line_to_char(position.line as usize);
-        self.content
-            .utf16_cu_to_char(line + position.character as usize)
+        let line = self.content.char_to_utf16_cu(line);
+        let offset = self
+            .content
+            .utf16_cu_to_char(line + position.character as usize);
+        self.content.char_to_byte(offset)
     }
 
     /// Iterate over the text chunks in the source.
@@ -379,8 +960,17 @@ impl fmt::Display for Source {
     }
 }
 
+/// The number of bytes of the characters which two iterators have in common
+/// before they differ.
+fn common_len(a: impl Iterator<Item = char>, b: impl Iterator<Item = char>) -> usize {
+    a.zip(b)
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum()
+}
+
 /// Convert the given span into an lsp range.
-fn span_to_lsp_range(source: &rune::Source, span: Span) -> Option<lsp::Range> {
+pub(crate) fn span_to_lsp_range(source: &rune::Source, span: Span) -> Option<lsp::Range> {
     let (line, character) = source.pos_to_utf16cu_linecol(span.start.into_usize());
     let start = lsp::Position::new(line as u32, character as u32);
     let (line, character) = source.pos_to_utf16cu_linecol(span.end.into_usize());
@@ -417,38 +1007,53 @@ fn rope_utf16_position(rope: &Rope, position: lsp::Position) -> Result<usize> {
     Ok(rope.line_to_char(position.line as usize) + char_offset)
 }
 
+/// Get the text in the given lsp::Range of the rope.
+fn rope_text_at(rope: &Rope, range: lsp::Range) -> Option<String> {
+    if range.start.line as usize >= rope.len_lines() || range.end.line as usize >= rope.len_lines()
+    {
+        return None;
+    }
+
+    let start = rope_utf16_position(rope, range.start).ok()?;
+    let end = rope_utf16_position(rope, range.end).ok()?;
+    Some(rope.get_slice(start..end)?.to_string())
+}
+
+/// Join a sequence of doc comment lines into a single markdown string.
+pub(crate) fn join_docs(docs: &[String]) -> String {
+    let mut out = String::new();
+
+    for line in docs {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+
+        out.push_str(line.strip_prefix(' ').unwrap_or(line).trim_end());
+    }
+
+    out
+}
+
 /// Convert the given span and error into an error diagnostic.
-fn report<E, R>(
+fn report<'a, E, R>(
     sources: &rune::Sources,
-    by_url: &mut HashMap<Url, Vec<lsp::Diagnostic>>,
+    by_url: &'a mut HashMap<Url, Vec<lsp::Diagnostic>>,
     span: Span,
     source_id: SourceId,
     error: E,
     report: R,
-) where
+) -> Option<&'a mut lsp::Diagnostic>
+where
     E: fmt::Display,
     R: Fn(lsp::Range, E) -> lsp::Diagnostic,
 {
-    let source = match sources.get(source_id) {
-        Some(source) => source,
-        None => return,
-    };
-
-    let url = match source.path() {
-        Some(path) => match Url::from_file_path(path) {
-            Ok(url) => url,
-            Err(()) => return,
-        },
-        None => return,
-    };
-
-    let range = match span_to_lsp_range(source, span) {
-        Some(range) => range,
-        None => return,
-    };
+    let source = sources.get(source_id)?;
+    let url = Url::from_file_path(source.path()?).ok()?;
+    let range = span_to_lsp_range(source, span)?;
 
     let diagnostics = by_url.entry(url).or_default();
     diagnostics.push(report(range, error));
+    diagnostics.last_mut()
 }
 
 /// Convert the given span and error into an error diagnostic.
@@ -492,12 +1097,40 @@ where
 #[derive(Default)]
 pub struct Index {
     /// Spans mapping to their corresponding definitions.
-    definitions: BTreeMap<Span, Definition>,
+    pub(crate) definitions: BTreeMap<Span, Definition>,
+    /// Items registered during the build.
+    pub(crate) items: HashMap<ItemBuf, ItemInfo>,
+    /// References to symbols, recorded for every source in the build.
+    pub(crate) references: Vec<Reference>,
+    /// Spans of local variables declared in the entry source.
+    pub(crate) locals: Vec<Span>,
+    /// Doc comments associated with items.
+    docs: HashMap<ItemBuf, Vec<String>>,
+    /// Doc comments associated with fields of items.
+    field_docs: HashMap<ItemBuf, BTreeMap<String, Vec<String>>>,
+}
+
+impl Index {
+    /// Get the doc comments associated with the given item.
+    pub(crate) fn docs(&self, item: &Item) -> Option<&[String]> {
+        Some(self.docs.get(item)?)
+    }
+}
+
+/// Information on an item registered during the build.
+#[derive(Debug, Clone)]
+pub struct ItemInfo {
+    /// The kind of the item.
+    pub(crate) kind: MetaKind,
+    /// Where the item is declared, unless it's provided by the context.
+    pub(crate) source: Option<SourceMeta>,
 }
 
 /// A definition source.
 #[derive(Debug, Clone)]
 pub enum DefinitionSource {
+    /// Defined natively in the context, without a source location.
+    Context,
     /// Only a file source.
     Source(SourceId),
     /// A location definition (source and span).
@@ -509,17 +1142,18 @@ pub enum DefinitionSource {
 impl DefinitionSource {
     fn span(&self) -> Span {
         match self {
-            Self::Source(..) => Span::empty(),
+            Self::Context | Self::Source(..) => Span::empty(),
             Self::Location(location) => location.span,
             Self::SourceMeta(compile_source) => compile_source.location.span,
         }
     }
 
-    fn source_id(&self) -> SourceId {
+    fn source_id(&self) -> Option<SourceId> {
         match self {
-            Self::Source(source_id) => *source_id,
-            Self::Location(location) => location.source_id,
-            Self::SourceMeta(compile_source) => compile_source.location.source_id,
+            Self::Context => None,
+            Self::Source(source_id) => Some(*source_id),
+            Self::Location(location) => Some(location.source_id),
+            Self::SourceMeta(compile_source) => Some(compile_source.location.source_id),
         }
     }
 
@@ -537,6 +1171,8 @@ pub struct Definition {
     pub(crate) kind: DefinitionKind,
     /// The id of the source id the definition corresponds to.
     pub(crate) source: DefinitionSource,
+    /// The item being defined, if the definition corresponds to one.
+    pub(crate) item: Option<ItemBuf>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -556,13 +1192,30 @@ pub enum DefinitionKind {
     /// An enum.
     Enum,
     /// A function.
-    Function,
+    Function {
+        /// The type hash of the function.
+        type_hash: Hash,
+    },
     /// A local variable.
     Local,
     /// A module that can be jumped to.
     Module,
 }
 
+impl DefinitionKind {
+    /// The keyword used to describe the definition.
+    fn keyword(self) -> &'static str {
+        match self {
+            Self::UnitStruct | Self::TupleStruct | Self::Struct => "struct",
+            Self::UnitVariant | Self::TupleVariant | Self::StructVariant => "variant",
+            Self::Enum => "enum",
+            Self::Function { .. } => "fn",
+            Self::Local => "let",
+            Self::Module => "mod",
+        }
+    }
+}
+
 struct Visitor {
     index: Index,
 }
@@ -580,16 +1233,36 @@ impl Visitor {
 }
 
 impl CompileVisitor for Visitor {
+    fn visit_imports(&self) -> bool {
+        true
+    }
+
+    fn register_meta(&mut self, meta: MetaRef<'_>) {
+        let info = ItemInfo {
+            kind: meta.kind,
+            source: meta.source.cloned(),
+        };
+
+        self.index.items.insert(meta.item.to_owned(), info);
+    }
+
     fn visit_meta(&mut self, location: Location, meta: MetaRef<'_>) {
+        if let (Some(source), Some(ComponentRef::Str(name))) = (meta.source, meta.item.last()) {
+            if references::is_renameable(meta.kind) {
+                self.index.references.push(Reference {
+                    symbol: Symbol::Item {
+                        name: name.into(),
+                        declaration: source.location,
+                    },
+                    usage: location,
+                });
+            }
+        }
+
         if location.source_id.into_index() != 0 {
             return;
         }
 
-        let source = match meta.source {
-            Some(source) => source,
-            None => return,
-        };
-
         let kind = match &meta.kind {
             MetaKind::UnitStruct { .. } => DefinitionKind::UnitStruct,
             MetaKind::TupleStruct { .. } => DefinitionKind::TupleStruct,
@@ -598,13 +1271,21 @@ impl CompileVisitor for Visitor {
             MetaKind::TupleVariant { .. } => DefinitionKind::TupleVariant,
             MetaKind::StructVariant { .. } => DefinitionKind::StructVariant,
             MetaKind::Enum { .. } => DefinitionKind::Enum,
-            MetaKind::Function { .. } => DefinitionKind::Function,
+            MetaKind::Function { type_hash, .. } => DefinitionKind::Function {
+                type_hash: *type_hash,
+            },
             _ => return,
         };
 
+        let source = match meta.source {
+            Some(source) => DefinitionSource::SourceMeta(source.clone()),
+            None => DefinitionSource::Context,
+        };
+
         let definition = Definition {
             kind,
-            source: DefinitionSource::SourceMeta(source.clone()),
+            source,
+            item: Some(meta.item.to_owned()),
         };
 
         if let Some(d) = self.index.definitions.insert(location.span, definition) {
@@ -612,7 +1293,20 @@ impl CompileVisitor for Visitor {
         }
     }
 
+    fn visit_variable_decl(&mut self, source_id: SourceId, span: Span) {
+        if source_id.into_index() == 0 {
+            self.index.locals.push(span);
+        }
+    }
+
     fn visit_variable_use(&mut self, source_id: SourceId, var_span: Span, span: Span) {
+        self.index.references.push(Reference {
+            symbol: Symbol::Local {
+                declaration: Location::new(source_id, var_span),
+            },
+            usage: Location::new(source_id, span),
+        });
+
         if source_id.into_index() != 0 {
             return;
         }
@@ -620,6 +1314,7 @@ impl CompileVisitor for Visitor {
         let definition = Definition {
             kind: DefinitionKind::Local,
             source: DefinitionSource::Location(Location::new(source_id, var_span)),
+            item: None,
         };
 
         if let Some(d) = self.index.definitions.insert(span, definition) {
@@ -635,12 +1330,46 @@ impl CompileVisitor for Visitor {
         let definition = Definition {
             kind: DefinitionKind::Module,
             source: DefinitionSource::Source(source_id),
+            item: None,
         };
 
         if let Some(d) = self.index.definitions.insert(span, definition) {
             tracing::warn!("replaced definition: {:?}", d.kind)
         }
     }
+
+    fn visit_field_use(&mut self, location: Location, item: &Item, field: &str) {
+        let declaration = match self.index.items.get(item).and_then(|i| i.source.as_ref()) {
+            Some(source) => source.location,
+            None => return,
+        };
+
+        self.index.references.push(Reference {
+            symbol: Symbol::Field {
+                name: field.into(),
+                declaration,
+            },
+            usage: location,
+        });
+    }
+
+    fn visit_doc_comment(&mut self, _: Location, item: &Item, docstr: &str) {
+        self.index
+            .docs
+            .entry(item.to_owned())
+            .or_default()
+            .push(docstr.to_owned());
+    }
+
+    fn visit_field_doc_comment(&mut self, _: Location, item: &Item, field: &str, docstr: &str) {
+        self.index
+            .field_docs
+            .entry(item.to_owned())
+            .or_default()
+            .entry(field.to_owned())
+            .or_default()
+            .push(docstr.to_owned());
+    }
 }
 
 struct SourceLoader<'a> {
@@ -659,7 +1388,9 @@ impl<'a> SourceLoader<'a> {
 
     /// Generate a collection of URl candidates.
     fn candidates(root: &Path, item: &Item) -> Option<[Url; 2]> {
-        let mut base = root.to_owned();
+        // The root is the path of the source doing the loading, so modules
+        // are resolved relative to its directory.
+        let mut base = root.parent()?.to_owned();
 
         let mut it = item.iter().peekable();
         let mut last = None;
@@ -704,7 +1435,8 @@ impl<'a> rune::compile::SourceLoader for SourceLoader<'a> {
         if let Some(candidates) = Self::candidates(root, item) {
             for url in candidates.iter() {
                 if let Some(s) = self.sources.get(url) {
-                    return Ok(rune::Source::new(url, s.to_string()));
+                    let path = url.to_file_path().ok();
+                    return Ok(rune::Source::with_path(url, s.to_string(), path));
                 }
             }
         }
@@ -0,0 +1,287 @@
+//! Document and workspace symbols for the language server.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use lsp::Url;
+use rune::ast::Span;
+use rune::compile::{ComponentRef, Item, MetaKind};
+use rune::SourceId;
+
+use crate::references;
+use crate::state::{span_to_lsp_range, Source, Sources};
+
+/// The maximum number of workspace symbols to return.
+const WORKSPACE_SYMBOL_LIMIT: usize = 256;
+
+/// A symbol declared in a source.
+struct Declared<'a> {
+    /// The item of the symbol.
+    item: &'a Item,
+    /// The name of the symbol.
+    name: &'a str,
+    /// The kind of the symbol.
+    kind: MetaKind,
+    /// The span of the whole declaration.
+    span: Span,
+}
+
+/// Build the nested tree of symbols declared in the given source.
+pub(crate) fn document_symbols(source: &Source) -> Vec<lsp::DocumentSymbol> {
+    let build_sources = match source.build_sources() {
+        Some(build_sources) => build_sources,
+        None => return Vec::new(),
+    };
+
+    let rune_source = match build_sources.get(SourceId::new(0)) {
+        Some(rune_source) => rune_source,
+        None => return Vec::new(),
+    };
+
+    let text = rune_source.as_str();
+    let idents = references::lex_idents(text);
+
+    let declared = declared(source)
+        .filter(|d| d.1.into_index() == 0)
+        .map(|d| d.0)
+        .collect::<Vec<_>>();
+
+    let items = declared.iter().map(|d| d.item).collect::<HashSet<_>>();
+
+    // Symbols keyed by their parent item, where `None` is the root.
+    let mut children = BTreeMap::<Option<&Item>, Vec<&Declared<'_>>>::new();
+
+    for d in &declared {
+        let mut parent = d.item.parent();
+
+        while let Some(p) = parent {
+            if items.contains(p) {
+                break;
+            }
+
+            parent = p.parent();
+        }
+
+        children.entry(parent).or_default().push(d);
+    }
+
+    build_tree(rune_source, text, &idents, &mut children, None)
+}
+
+/// Recursively build document symbols for the children of `parent`.
+fn build_tree<'a>(
+    rune_source: &rune::Source,
+    text: &str,
+    idents: &[references::Ident],
+    children: &mut BTreeMap<Option<&'a Item>, Vec<&'a Declared<'a>>>,
+    parent: Option<&'a Declared<'a>>,
+) -> Vec<lsp::DocumentSymbol> {
+    let mut declared = children.remove(&parent.map(|p| p.item)).unwrap_or_default();
+    declared.sort_by_key(|d| d.span.start);
+
+    let mut output = Vec::new();
+
+    for d in declared {
+        let range = match span_to_lsp_range(rune_source, d.span) {
+            Some(range) => range,
+            None => continue,
+        };
+
+        let selection_range = references::find_ident(text, idents, d.span, d.name, false)
+            .and_then(|span| span_to_lsp_range(rune_source, span))
+            .unwrap_or(range);
+
+        let nested = build_tree(rune_source, text, idents, children, Some(d));
+
+        #[allow(deprecated)]
+        output.push(lsp::DocumentSymbol {
+            name: d.name.to_owned(),
+            detail: None,
+            kind: symbol_kind(d.kind, matches!(parent, Some(p) if is_type(p.kind))),
+            tags: None,
+            deprecated: None,
+            range,
+            selection_range,
+            children: (!nested.is_empty()).then_some(nested),
+        });
+    }
+
+    output
+}
+
+/// Find symbols across all open sources, and the sources they load, which
+/// fuzzily match the given query.
+pub(crate) fn workspace_symbols(sources: &Sources, query: &str) -> Vec<lsp::SymbolInformation> {
+    // NB: a source can be part of the build of several sources. The symbols
+    // declared in it are named most fully by the source which loads it as a
+    // module, so that's the one which is kept.
+    let mut found = HashMap::<(Url, Span), (usize, usize, lsp::SymbolInformation)>::new();
+
+    for source in sources.iter() {
+        let build_sources = match source.build_sources() {
+            Some(build_sources) => build_sources,
+            None => continue,
+        };
+
+        for (d, source_id) in declared(source) {
+            let score = match fuzzy_score(query, d.name) {
+                Some(score) => score,
+                None => continue,
+            };
+
+            let (url, rune_source) = match references::source_with_url(build_sources, source_id) {
+                Some(source) => source,
+                None => continue,
+            };
+
+            let range = match span_to_lsp_range(rune_source, d.span) {
+                Some(range) => range,
+                None => continue,
+            };
+
+            let container_name = d
+                .item
+                .parent()
+                .filter(|parent| !parent.is_empty())
+                .map(|parent| parent.to_string());
+
+            let is_nested = matches!(
+                d.item.parent().and_then(|p| source.index().items.get(p)),
+                Some(info) if is_type(info.kind)
+            );
+
+            #[allow(deprecated)]
+            let symbol = lsp::SymbolInformation {
+                name: d.name.to_owned(),
+                kind: symbol_kind(d.kind, is_nested),
+                tags: None,
+                deprecated: None,
+                location: lsp::Location::new(url.clone(), range),
+                container_name,
+            };
+
+            let depth = d.item.iter().count();
+
+            match found.entry((url, d.span)) {
+                Entry::Occupied(mut e) => {
+                    if e.get().1 < depth {
+                        e.insert((score, depth, symbol));
+                    }
+                }
+                Entry::Vacant(e) => {
+                    e.insert((score, depth, symbol));
+                }
+            }
+        }
+    }
+
+    let mut output = found
+        .into_values()
+        .map(|(score, _, symbol)| (score, symbol))
+        .collect::<Vec<_>>();
+
+    output.sort_by(|(a, a_symbol), (b, b_symbol)| {
+        b.cmp(a).then_with(|| a_symbol.name.cmp(&b_symbol.name))
+    });
+
+    output
+        .into_iter()
+        .take(WORKSPACE_SYMBOL_LIMIT)
+        .map(|(_, symbol)| symbol)
+        .collect()
+}
+
+/// Iterate over all symbols declared in scripts which are part of the build
+/// of the given source, together with the id of the source they're declared
+/// in.
+fn declared(source: &Source) -> impl Iterator<Item = (Declared<'_>, SourceId)> {
+    source.index().items.iter().filter_map(|(item, info)| {
+        let location = info.source.as_ref()?.location;
+
+        if !is_symbol(info.kind) || item.iter().any(|c| matches!(c, ComponentRef::Id(..))) {
+            return None;
+        }
+
+        let name = match item.last()? {
+            ComponentRef::Str(name) => name,
+            _ => return None,
+        };
+
+        let declared = Declared {
+            item,
+            name,
+            kind: info.kind,
+            span: location.span,
+        };
+
+        Some((declared, location.source_id))
+    })
+}
+
+/// Score how well `name` matches the `query`, where the query has to be a
+/// case-insensitive subsequence of the name. Consecutive and leading matches
+/// score higher.
+fn fuzzy_score(query: &str, name: &str) -> Option<usize> {
+    let mut score = 0;
+    let mut chars = name.chars().enumerate();
+    let mut last = None;
+
+    for q in query.chars() {
+        let (n, _) = chars.find(|(_, c)| c.to_lowercase().eq(q.to_lowercase()))?;
+
+        score += match last {
+            _ if n == 0 => 3,
+            Some(last) if last + 1 == n => 2,
+            _ => 1,
+        };
+
+        last = Some(n);
+    }
+
+    Some(score)
+}
+
+/// Test if the given kind of item should be presented as a symbol.
+fn is_symbol(kind: MetaKind) -> bool {
+    matches!(
+        kind,
+        MetaKind::UnitStruct
+            | MetaKind::TupleStruct
+            | MetaKind::Struct
+            | MetaKind::UnitVariant
+            | MetaKind::TupleVariant
+            | MetaKind::StructVariant
+            | MetaKind::Enum
+            | MetaKind::Function { .. }
+            | MetaKind::Const
+            | MetaKind::ConstFn
+            | MetaKind::Module
+            | MetaKind::Trait
+    )
+}
+
+/// Test if the given kind of item is a type which can have functions
+/// associated with it.
+fn is_type(kind: MetaKind) -> bool {
+    matches!(
+        kind,
+        MetaKind::UnitStruct | MetaKind::TupleStruct | MetaKind::Struct | MetaKind::Enum
+    )
+}
+
+/// Map a meta kind to the kind of symbol it corresponds to.
+fn symbol_kind(kind: MetaKind, is_nested: bool) -> lsp::SymbolKind {
+    match kind {
+        MetaKind::UnitStruct | MetaKind::TupleStruct | MetaKind::Struct => lsp::SymbolKind::STRUCT,
+        MetaKind::UnitVariant | MetaKind::TupleVariant | MetaKind::StructVariant => {
+            lsp::SymbolKind::ENUM_MEMBER
+        }
+        MetaKind::Enum => lsp::SymbolKind::ENUM,
+        MetaKind::Function { .. } | MetaKind::ConstFn if is_nested => lsp::SymbolKind::METHOD,
+        MetaKind::Function { .. } | MetaKind::ConstFn => lsp::SymbolKind::FUNCTION,
+        MetaKind::Const => lsp::SymbolKind::CONSTANT,
+        MetaKind::Module => lsp::SymbolKind::MODULE,
+        MetaKind::Trait => lsp::SymbolKind::INTERFACE,
+        _ => lsp::SymbolKind::NULL,
+    }
+}
@@ -0,0 +1,57 @@
+//! Support for building `Rune.toml` workspaces in the language server.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use rune::workspace::{Diagnostics, WorkspaceFilter, MANIFEST_FILE};
+
+/// A loaded workspace manifest.
+pub(crate) struct Workspace {
+    /// Sources of all manifests loaded as part of the workspace.
+    pub(crate) sources: rune::Sources,
+    /// Diagnostics produced when loading the manifest.
+    pub(crate) diagnostics: Diagnostics,
+    /// Every entry point found in the workspace.
+    pub(crate) entries: Vec<PathBuf>,
+}
+
+/// Find the nearest manifest in the given directory or one of its parents.
+pub(crate) fn find_manifest(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(MANIFEST_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Load the manifest at the given path, and collect all bins, tests, examples
+/// and benches of every package in it.
+pub(crate) fn load(path: &Path) -> Result<Workspace> {
+    let mut sources = rune::Sources::new();
+    sources.insert(rune::Source::from_path(path)?);
+
+    let mut diagnostics = Diagnostics::new();
+
+    let result = rune::workspace::prepare(&mut sources)
+        .with_diagnostics(&mut diagnostics)
+        .build();
+
+    let mut entries = Vec::new();
+
+    if let Ok(manifest) = result {
+        let found = [
+            manifest.find_bins(WorkspaceFilter::All)?,
+            manifest.find_tests(WorkspaceFilter::All)?,
+            manifest.find_examples(WorkspaceFilter::All)?,
+            manifest.find_benches(WorkspaceFilter::All)?,
+        ];
+
+        for found in found.into_iter().flatten() {
+            entries.push(found.path.into());
+        }
+    }
+
+    Ok(Workspace {
+        sources,
+        diagnostics,
+        entries,
+    })
+}
@@ -0,0 +1,1289 @@
+//! Drives `rune-languageserver` with a scripted client over stdio.
+
+use lsp::Url;
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// Marks the cursor in sources opened by the client.
+const CURSOR: &str = "$0";
+
+const FOO: &str = r#"
+/// Greet someone.
+pub fn greet(name) {
+    `Hello ${name}`
+}
+"#;
+
+const MAIN: &str = r#"
+mod foo;
+
+pub fn main() {
+    // greet is called below.
+    let name = "greet";
+    foo::greet(name)
+}
+"#;
+
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    id: i64,
+    root: PathBuf,
+    /// Documents which are open, by url.
+    open: BTreeSet<Url>,
+    /// The last diagnostics published for each document, by url.
+    diagnostics: HashMap<Url, Value>,
+    /// The semantic token types announced by the server, in legend order.
+    token_types: Vec<String>,
+}
+
+impl Client {
+    fn start() -> Self {
+        Self::start_in(None)
+    }
+
+    /// Start a server, with the given directory under the root as the root
+    /// of its workspace.
+    fn start_in(workspace: Option<&str>) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rune-languageserver"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("spawning rune-languageserver");
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+
+        let root = std::env::temp_dir().join(format!("rune-languageserver-{}", std::process::id()));
+
+        let mut client = Self {
+            child,
+            stdin,
+            stdout,
+            id: 0,
+            root,
+            open: BTreeSet::new(),
+            diagnostics: HashMap::new(),
+            token_types: Vec::new(),
+        };
+
+        let mut params = json!({ "capabilities": {} });
+
+        if let Some(workspace) = workspace {
+            params["rootUri"] = json!(client.url(workspace));
+        }
+
+        let result = client.request("initialize", params);
+        let legend = &result["capabilities"]["semanticTokensProvider"]["legend"];
+
+        client.token_types = legend["tokenTypes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|ty| ty.as_str().unwrap().to_owned())
+            .collect();
+
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn url(&self, name: &str) -> Url {
+        Url::from_file_path(self.root.join(name)).unwrap()
+    }
+
+    fn read(&mut self) -> Value {
+        let mut length = 0;
+
+        loop {
+            let mut line = String::new();
+            assert_ne!(
+                self.stdout.read_line(&mut line).unwrap(),
+                0,
+                "unexpected eof"
+            );
+            let line = line.trim();
+
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some(value) = line.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn write(&mut self, message: Value) {
+        let body = serde_json::to_vec(&message).unwrap();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n", body.len()).unwrap();
+        self.stdin.write_all(&body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.id += 1;
+
+        self.write(json!({
+            "jsonrpc": "2.0",
+            "id": self.id,
+            "method": method,
+            "params": params,
+        }));
+
+        loop {
+            let message = self.read();
+
+            if message.get("method").is_none() && message["id"] == self.id {
+                assert!(message["error"].is_null(), "{}: {}", method, message);
+                return message["result"].clone();
+            }
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.write(json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        }));
+    }
+
+    /// Wait until the diagnostics of every open document have been published,
+    /// which happens once the sources have been rebuilt.
+    fn rebuilt(&mut self) {
+        let mut pending = self.open.clone();
+
+        while !pending.is_empty() {
+            let url = self.read_diagnostics();
+            pending.remove(&url);
+        }
+    }
+
+    /// Wait for the next diagnostics to be published, returning the url of
+    /// the document they were published for.
+    fn read_diagnostics(&mut self) -> Url {
+        loop {
+            let message = self.read();
+
+            if message["method"] == "textDocument/publishDiagnostics" {
+                let uri = message["params"]["uri"].as_str().unwrap();
+                let url = Url::parse(uri).unwrap();
+
+                self.diagnostics
+                    .insert(url.clone(), message["params"]["diagnostics"].clone());
+
+                return url;
+            }
+        }
+    }
+
+    fn open(&mut self, name: &str, text: &str) {
+        let url = self.url(name);
+
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": url,
+                    "languageId": "rune",
+                    "version": 0,
+                    "text": text,
+                },
+            }),
+        );
+
+        self.open.insert(url);
+        self.rebuilt();
+    }
+
+    /// Replace the text in the given range of a document.
+    fn change(&mut self, name: &str, range: Value, text: &str) {
+        let url = self.url(name);
+
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": url, "version": 1 },
+                "contentChanges": [{ "range": range, "text": text }],
+            }),
+        );
+
+        self.rebuilt();
+    }
+
+    /// Open a document which has the cursor in it, returning the position of
+    /// the cursor.
+    fn open_at(&mut self, name: &str, text: &str) -> Value {
+        let (text, position) = cursor(text);
+        self.open(name, &text);
+        position
+    }
+
+    fn params(&self, name: &str, position: Value) -> Value {
+        json!({
+            "textDocument": { "uri": self.url(name) },
+            "position": position,
+        })
+    }
+
+    /// The labels of the completions at the given position.
+    fn complete(&mut self, name: &str, position: Value) -> Vec<String> {
+        let result = self.request("textDocument/completion", self.params(name, position));
+
+        let items = match result.as_array() {
+            Some(items) => items,
+            None => return Vec::new(),
+        };
+
+        items
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_owned())
+            .collect()
+    }
+
+    /// The markdown shown when hovering the given position, if any.
+    fn hover(&mut self, name: &str, position: Value) -> Option<String> {
+        let result = self.request("textDocument/hover", self.params(name, position));
+        Some(result["contents"]["value"].as_str()?.to_owned())
+    }
+
+    /// The messages of the diagnostics last published for the given
+    /// document, waiting for them if none have been published yet.
+    fn diagnostics(&mut self, name: &str) -> Vec<String> {
+        let url = self.url(name);
+
+        while !self.diagnostics.contains_key(&url) {
+            self.read_diagnostics();
+        }
+
+        let diagnostics = &self.diagnostics[&url];
+
+        diagnostics
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|d| d["message"].as_str().unwrap().to_owned())
+            .collect()
+    }
+
+    /// The quick-fixes for the diagnostics last published for the given
+    /// document, as their titles and the edits they make to it.
+    fn code_actions(&mut self, name: &str) -> Vec<(String, Vec<(Value, String)>)> {
+        let url = self.url(name);
+
+        let params = json!({
+            "textDocument": { "uri": url },
+            "range": { "start": position("", 0), "end": position("", 0) },
+            "context": { "diagnostics": self.diagnostics[&url] },
+        });
+
+        let result = self.request("textDocument/codeAction", params);
+
+        result
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|action| {
+                let edits = action["edit"]["changes"][url.as_str()]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|edit| {
+                        let new_text = edit["newText"].as_str().unwrap().to_owned();
+                        (edit["range"].clone(), new_text)
+                    })
+                    .collect();
+
+                (action["title"].as_str().unwrap().to_owned(), edits)
+            })
+            .collect()
+    }
+
+    /// The edits formatting the given document, optionally limited to the
+    /// given range, as the ranges they replace and their new text. Returns
+    /// `None` if the server refuses to format the document.
+    fn format(&mut self, name: &str, range: Option<Value>) -> Option<Vec<(Value, String)>> {
+        let mut params = json!({
+            "textDocument": { "uri": self.url(name) },
+            "options": { "tabSize": 4, "insertSpaces": true },
+        });
+
+        let result = match range {
+            Some(range) => {
+                params["range"] = range;
+                self.request("textDocument/rangeFormatting", params)
+            }
+            None => self.request("textDocument/formatting", params),
+        };
+
+        let edits = result
+            .as_array()?
+            .iter()
+            .map(|edit| {
+                let new_text = edit["newText"].as_str().unwrap().to_owned();
+                (edit["range"].clone(), new_text)
+            })
+            .collect();
+
+        Some(edits)
+    }
+
+    /// The labels of the signatures shown at the given position, together
+    /// with the label of their active parameter.
+    fn signature_help(&mut self, name: &str, position: Value) -> Vec<(String, Option<String>)> {
+        let result = self.request("textDocument/signatureHelp", self.params(name, position));
+
+        let signatures = match result["signatures"].as_array() {
+            Some(signatures) => signatures,
+            None => return Vec::new(),
+        };
+
+        signatures
+            .iter()
+            .map(|signature| {
+                let label = signature["label"].as_str().unwrap();
+                let labels = label.encode_utf16().collect::<Vec<_>>();
+
+                let active = signature["activeParameter"].as_u64().and_then(|n| {
+                    let offsets = &signature["parameters"][n as usize]["label"];
+                    let start = offsets[0].as_u64()? as usize;
+                    let end = offsets[1].as_u64()? as usize;
+                    Some(String::from_utf16(&labels[start..end]).unwrap())
+                });
+
+                (label.to_owned(), active)
+            })
+            .collect()
+    }
+
+    /// The outline of the document symbols in the given document, with one
+    /// line per symbol indented by how deeply it's nested.
+    fn document_symbols(&mut self, name: &str) -> Vec<String> {
+        let params = json!({ "textDocument": { "uri": self.url(name) } });
+        let result = self.request("textDocument/documentSymbol", params);
+
+        let mut output = Vec::new();
+        outline(&mut output, &result, 0);
+        output
+    }
+
+    /// The text and type of every semantic token in the given document, which
+    /// has the given text, decoded from their relative positions.
+    fn semantic_tokens(&mut self, name: &str, text: &str) -> Vec<(String, String)> {
+        let params = json!({ "textDocument": { "uri": self.url(name) } });
+        let result = self.request("textDocument/semanticTokens/full", params);
+
+        let data = result["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n.as_u64().unwrap() as usize)
+            .collect::<Vec<_>>();
+
+        let lines = text.split('\n').collect::<Vec<_>>();
+        let mut output = Vec::new();
+        let (mut line, mut start) = (0, 0);
+
+        for token in data.chunks(5) {
+            if token[0] > 0 {
+                start = 0;
+            }
+
+            line += token[0];
+            start += token[1];
+
+            output.push((
+                lines[line][start..start + token[2]].to_owned(),
+                self.token_types[token[3]].clone(),
+            ));
+        }
+
+        output
+    }
+
+    /// The names, kinds and containers of the workspace symbols matching the
+    /// given query.
+    fn workspace_symbols(&mut self, query: &str) -> Vec<(String, String, Option<String>)> {
+        let result = self.request("workspace/symbol", json!({ "query": query }));
+
+        let symbols = match result.as_array() {
+            Some(symbols) => symbols,
+            None => return Vec::new(),
+        };
+
+        symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol["name"].as_str().unwrap().to_owned(),
+                    kind(&symbol["kind"]),
+                    symbol["containerName"].as_str().map(String::from),
+                )
+            })
+            .collect()
+    }
+
+    /// The names of the documents and the lines of the references at the
+    /// given position.
+    fn references(&mut self, name: &str, position: Value) -> Vec<(String, u64)> {
+        let mut params = self.params(name, position);
+        params["context"] = json!({ "includeDeclaration": true });
+
+        let result = self.request("textDocument/references", params);
+
+        let locations = match result.as_array() {
+            Some(locations) => locations,
+            None => return Vec::new(),
+        };
+
+        let mut references = locations
+            .iter()
+            .map(|location| self.location(location))
+            .collect::<Vec<_>>();
+
+        references.sort();
+        references
+    }
+
+    /// The names of the documents, the lines and the new text of the edits
+    /// renaming the symbol at the given position.
+    fn rename(
+        &mut self,
+        name: &str,
+        position: Value,
+        new_name: &str,
+    ) -> Vec<(String, u64, String)> {
+        let mut params = self.params(name, position);
+        params["newName"] = json!(new_name);
+
+        let result = self.request("textDocument/rename", params);
+
+        let changes = match result["changes"].as_object() {
+            Some(changes) => changes,
+            None => return Vec::new(),
+        };
+
+        let mut edits = Vec::new();
+
+        for (uri, changes) in changes {
+            let name = self.name(uri);
+
+            for edit in changes.as_array().unwrap() {
+                let line = edit["range"]["start"]["line"].as_u64().unwrap();
+                let text = edit["newText"].as_str().unwrap().to_owned();
+                edits.push((name.clone(), line, text));
+            }
+        }
+
+        edits.sort();
+        edits
+    }
+
+    fn location(&self, location: &Value) -> (String, u64) {
+        let name = self.name(location["uri"].as_str().unwrap());
+        let line = location["range"]["start"]["line"].as_u64().unwrap();
+        (name, line)
+    }
+
+    /// The name of the document with the given uri.
+    fn name(&self, uri: &str) -> String {
+        let path = Url::parse(uri).unwrap().to_file_path().unwrap();
+        let name = path.strip_prefix(&self.root).unwrap();
+        name.to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+/// Append an outline of the given document symbols, where each line holds the
+/// name and the kind of a symbol.
+fn outline(output: &mut Vec<String>, symbols: &Value, depth: usize) {
+    for symbol in symbols.as_array().into_iter().flatten() {
+        output.push(format!(
+            "{}{} {}",
+            "  ".repeat(depth),
+            symbol["name"].as_str().unwrap(),
+            kind(&symbol["kind"])
+        ));
+
+        outline(output, &symbol["children"], depth + 1);
+    }
+}
+
+/// The name of the given symbol kind.
+fn kind(kind: &Value) -> String {
+    let kind = serde_json::from_value::<lsp::SymbolKind>(kind.clone()).unwrap();
+    format!("{:?}", kind)
+}
+
+/// The position of the given byte offset in the given text.
+fn position(text: &str, offset: usize) -> Value {
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let character = offset - before.rfind('\n').map_or(0, |n| n + 1);
+    json!({ "line": line, "character": character })
+}
+
+/// The position `offset` bytes into the first occurrence of `pattern` in the
+/// given text.
+fn find(text: &str, pattern: &str, offset: usize) -> Value {
+    let start = text.find(pattern).expect("missing pattern");
+    position(text, start + offset)
+}
+
+/// Remove the cursor from the given text, returning the text and the position
+/// of the cursor.
+fn cursor(text: &str) -> (String, Value) {
+    let offset = text.find(CURSOR).expect("missing cursor");
+    (text.replacen(CURSOR, "", 1), position(text, offset))
+}
+
+#[test]
+fn test_references_across_files() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+    client.open("main.rn", MAIN);
+
+    let expected = vec![(String::from("foo.rn"), 2), (String::from("main.rn"), 6)];
+
+    let position = find(MAIN, "greet(name)", 1);
+    assert_eq!(client.references("main.rn", position), expected);
+
+    let position = find(FOO, "greet(name)", 1);
+    assert_eq!(client.references("foo.rn", position), expected);
+}
+
+#[test]
+fn test_rename_across_files() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+    client.open("main.rn", MAIN);
+
+    let position = find(MAIN, "greet(name)", 1);
+
+    assert_eq!(
+        client.rename("main.rn", position, "welcome"),
+        vec![
+            (String::from("foo.rn"), 2, String::from("welcome")),
+            (String::from("main.rn"), 6, String::from("welcome")),
+        ]
+    );
+
+    let position = find(MAIN, "name)", 0);
+
+    assert_eq!(
+        client.rename("main.rn", position, "who"),
+        vec![
+            (String::from("main.rn"), 5, String::from("who")),
+            (String::from("main.rn"), 6, String::from("who")),
+        ]
+    );
+
+    assert!(client
+        .rename("main.rn", find(MAIN, "main", 0), "not valid")
+        .is_empty());
+}
+
+#[test]
+fn test_rename_refused() {
+    let mut client = Client::start();
+
+    // Only the entry is open, so the module is loaded from disk.
+    let root = client.root.join("disk");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("foo.rn"), FOO).unwrap();
+    client.open("disk/main.rn", MAIN);
+
+    let position = find(MAIN, "greet(name)", 1);
+
+    assert_eq!(
+        client.rename("disk/main.rn", position.clone(), "welcome"),
+        vec![
+            (String::from("disk/foo.rn"), 2, String::from("welcome")),
+            (String::from("disk/main.rn"), 6, String::from("welcome")),
+        ]
+    );
+
+    // The module changes on disk without being rebuilt, so the function is no
+    // longer where it was when the sources were indexed.
+    std::fs::write(root.join("foo.rn"), format!("\n{}", FOO)).unwrap();
+    assert!(client
+        .rename("disk/main.rn", position, "welcome")
+        .is_empty());
+
+    // Fields are accessed dynamically, so they aren't renamed.
+    let (text, position) = cursor(
+        r#"
+struct Point { $0x }
+
+pub fn main() {
+    let p = Point { x: 1 };
+    p.x
+}
+"#,
+    );
+
+    client.open("point.rn", &text);
+    assert!(client.rename("point.rn", position, "y").is_empty());
+}
+
+#[test]
+fn test_workspace() {
+    let mut client = Client::start_in(Some("workspace"));
+
+    let root = client.root.join("workspace");
+    std::fs::create_dir_all(root.join("bin").join("util")).unwrap();
+
+    std::fs::write(
+        root.join("Rune.toml"),
+        "[package]\nname = \"app\"\nversion = \"0.0.0\"\n",
+    )
+    .unwrap();
+
+    std::fs::write(
+        root.join("bin").join("app.rn"),
+        "mod util;\n\nconst ANSWER = 42;\n\npub fn main() {\n    util::helper()\n}\n",
+    )
+    .unwrap();
+
+    std::fs::write(
+        root.join("bin").join("other.rn"),
+        "pub fn main() {\n    let unused = 1;\n}\n",
+    )
+    .unwrap();
+
+    // The module only builds when it's included by its entry point, since it
+    // refers to an item in it.
+    let util = "pub fn helper() {\n    crate::ANSWER\n}\n";
+    std::fs::write(root.join("bin").join("util").join("mod.rn"), util).unwrap();
+
+    client.open("workspace/bin/util/mod.rn", util);
+
+    assert!(client.diagnostics("workspace/bin/util/mod.rn").is_empty());
+
+    // Entry points are built even if they aren't open.
+    assert_eq!(client.diagnostics("workspace/bin/other.rn"), ["not used"]);
+
+    // Editing the module diagnoses it through its entry point.
+    let range = json!({
+        "start": find(util, "ANSWER", 0),
+        "end": find(util, "ANSWER", 6),
+    });
+
+    client.change("workspace/bin/util/mod.rn", range, "MISSING");
+    assert_eq!(
+        client.diagnostics("workspace/bin/util/mod.rn"),
+        ["missing item `MISSING`"]
+    );
+}
+
+#[test]
+fn test_references_after_edits_across_files() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+    client.open("main.rn", MAIN);
+
+    // Push the declaration down by two lines, and add a second call.
+    client.change(
+        "foo.rn",
+        json!({ "start": { "line": 1, "character": 0 }, "end": { "line": 1, "character": 0 } }),
+        "pub fn other() {\n}\n",
+    );
+
+    client.change(
+        "main.rn",
+        json!({ "start": { "line": 8, "character": 0 }, "end": { "line": 8, "character": 0 } }),
+        "\npub fn again() {\n    foo::greet(\"again\")\n}\n",
+    );
+
+    // The call in the added function.
+    let position = json!({ "line": 10, "character": 11 });
+
+    assert_eq!(
+        client.references("main.rn", position),
+        vec![
+            (String::from("foo.rn"), 4),
+            (String::from("main.rn"), 6),
+            (String::from("main.rn"), 10),
+        ]
+    );
+}
+
+#[test]
+fn test_no_references_in_strings_and_comments() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+    client.open("main.rn", MAIN);
+
+    let in_comment = find(MAIN, "greet is", 1);
+    assert!(client.references("main.rn", in_comment.clone()).is_empty());
+    assert!(client.rename("main.rn", in_comment, "welcome").is_empty());
+
+    let in_string = find(MAIN, "greet\";", 1);
+    assert!(client.references("main.rn", in_string.clone()).is_empty());
+    assert!(client.rename("main.rn", in_string, "welcome").is_empty());
+
+    let in_template = find(FOO, "Hello", 1);
+    assert!(client.references("foo.rn", in_template).is_empty());
+
+    let in_doc = find(FOO, "Greet someone", 1);
+    assert!(client.references("foo.rn", in_doc).is_empty());
+}
+
+#[test]
+fn test_hover() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+
+    let text = r#"
+mod foo;
+
+pub fn main() {
+    let name = "world";
+    let values = Vec::new();
+    values.push(foo::greet(name));
+    values
+}
+"#;
+
+    client.open("main.rn", text);
+
+    let hover = client.hover("main.rn", find(text, "name));", 1));
+    assert_eq!(hover.as_deref(), Some("```rune\nlet name\n```"));
+
+    let hover = client.hover("main.rn", find(text, "greet(", 1)).unwrap();
+    assert!(
+        hover.starts_with("```rune\nfn foo::greet(name)"),
+        "{}",
+        hover
+    );
+    assert!(hover.ends_with("Greet someone."), "{}", hover);
+
+    let hover = client.hover("main.rn", find(text, "new()", 1)).unwrap();
+    assert!(hover.contains("Vec::new"), "{}", hover);
+
+    // Nothing is shown for whitespace.
+    assert_eq!(client.hover("main.rn", find(text, "    let name", 1)), None);
+}
+
+#[test]
+fn test_signature_help() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+
+    let text = r#"
+mod foo;
+
+struct Point { x, y }
+
+impl Point {
+    fn dist(self, other, scale) {
+        0
+    }
+}
+
+fn add(a, b) {
+    a + b
+}
+
+pub fn main() {
+    let p = Point { x: 1, y: 2 };
+    let values = [];
+    add(1, foo::greet("a"));
+    p.dist(p, 2);
+    values.push(1);
+}
+"#;
+
+    client.open("main.rn", text);
+
+    let signature =
+        |label: &str, active: Option<&str>| (String::from(label), active.map(String::from));
+
+    // The active parameter follows the commas preceding the cursor.
+    assert_eq!(
+        client.signature_help("main.rn", find(text, "add(1", 4)),
+        [signature("fn add(a, b)", Some("a"))]
+    );
+    assert_eq!(
+        client.signature_help("main.rn", find(text, "add(1", 6)),
+        [signature("fn add(a, b)", Some("b"))]
+    );
+
+    // Nested calls show the innermost call until it's closed.
+    assert_eq!(
+        client.signature_help("main.rn", find(text, "greet(\"a", 6)),
+        [signature("fn greet(name)", Some("name"))]
+    );
+    assert_eq!(
+        client.signature_help("main.rn", find(text, "greet(\"a\")", 10)),
+        [signature("fn add(a, b)", Some("b"))]
+    );
+
+    // The receiver of instance functions is skipped.
+    assert_eq!(
+        client.signature_help("main.rn", find(text, "p.dist(p", 7)),
+        [signature("fn dist(self, other, scale)", Some("other"))]
+    );
+    assert_eq!(
+        client.signature_help("main.rn", find(text, "p.dist(p,", 10)),
+        [signature("fn dist(self, other, scale)", Some("scale"))]
+    );
+    assert_eq!(
+        client.signature_help("main.rn", find(text, "values.push(", 12)),
+        [signature("fn push(self, #0)", Some("#0"))]
+    );
+
+    // Nothing is shown outside of calls.
+    assert!(client
+        .signature_help("main.rn", find(text, "let values", 0))
+        .is_empty());
+}
+
+#[test]
+fn test_code_action() {
+    let mut client = Client::start();
+
+    let text = r#"
+pub fn main() {
+    let value = 1;
+    let other = 2;
+    other
+}
+"#;
+
+    client.open("main.rn", text);
+    assert_eq!(client.diagnostics("main.rn"), ["not used"]);
+
+    let at = find(text, "value", 0);
+    let range = json!({ "start": at, "end": at });
+
+    let actions = client.code_actions("main.rn");
+    assert_eq!(
+        actions,
+        [(
+            String::from("Prefix with an underscore"),
+            vec![(range, String::from("_"))]
+        )]
+    );
+
+    // Applying the edit resolves the diagnostic.
+    for (range, text) in actions.into_iter().flat_map(|(_, edits)| edits) {
+        client.change("main.rn", range, &text);
+    }
+
+    assert!(client.diagnostics("main.rn").is_empty());
+    assert!(client.code_actions("main.rn").is_empty());
+}
+
+#[test]
+fn test_formatting() {
+    let mut client = Client::start();
+
+    let text = "pub fn first() {\n1\n}\n\npub fn second() {\n  2\n}\n";
+    client.open("main.rn", text);
+
+    let line = |start: u64, end: u64, text: &str| {
+        let range = json!({
+            "start": { "line": start, "character": 0 },
+            "end": { "line": end, "character": 0 },
+        });
+
+        (range, String::from(text))
+    };
+
+    // Only the lines which change are replaced.
+    let edits = client.format("main.rn", None).unwrap();
+    assert_eq!(edits, [line(1, 2, "    1\n"), line(5, 6, "    2\n")]);
+
+    // Range formatting only keeps the edits touching the range.
+    let range = json!({ "start": find(text, "  2", 0), "end": find(text, "  2", 3) });
+    assert_eq!(
+        client.format("main.rn", Some(range)).unwrap(),
+        [line(5, 6, "    2\n")]
+    );
+
+    // Edits are applied from the end so that earlier ranges stay valid.
+    for (range, text) in edits.into_iter().rev() {
+        client.change("main.rn", range, &text);
+    }
+
+    assert_eq!(client.format("main.rn", None), Some(Vec::new()));
+
+    // Formatting the closure would turn `| |` into `||`, which verification
+    // catches, so no edits are returned.
+    client.open("closure.rn", "pub fn main() {\nlet f = | | 1;\n}\n");
+    assert_eq!(client.format("closure.rn", None), None);
+}
+
+#[test]
+fn test_symbols() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+
+    client.open(
+        "main.rn",
+        r#"
+mod foo;
+
+trait Shape {
+    fn area(self);
+}
+
+struct Square { side }
+
+impl Square {
+    fn new(side) {
+        Square { side }
+    }
+}
+
+impl Shape for Square {
+    fn area(self) {
+        self.side * self.side
+    }
+}
+
+enum Color {
+    Red,
+    Green,
+}
+
+const SIDE = 2;
+
+pub fn main() {
+    Square::new(SIDE).area()
+}
+"#,
+    );
+
+    assert_eq!(
+        client.document_symbols("main.rn"),
+        [
+            "foo Module",
+            "Shape Interface",
+            "Square Struct",
+            "  new Method",
+            "  area Method",
+            "Color Enum",
+            "  Red EnumMember",
+            "  Green EnumMember",
+            "SIDE Constant",
+            "main Function",
+        ]
+    );
+
+    let symbol = |name: &str, kind: &str, container: Option<&str>| {
+        (
+            String::from(name),
+            String::from(kind),
+            container.map(String::from),
+        )
+    };
+
+    // Symbols are ordered by how well they match, and include those declared
+    // in other sources.
+    assert_eq!(
+        client.workspace_symbols("re"),
+        [
+            symbol("Red", "EnumMember", Some("Color")),
+            symbol("Green", "EnumMember", Some("Color")),
+            symbol("Square", "Struct", None),
+            symbol("area", "Method", Some("Square")),
+            symbol("greet", "Function", Some("foo")),
+        ]
+    );
+
+    assert_eq!(
+        client.workspace_symbols("shape"),
+        [symbol("Shape", "Interface", None)]
+    );
+}
+
+#[test]
+fn test_semantic_tokens() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+
+    let text = r#"
+mod foo;
+
+/* A comment
+   over two lines. */
+pub fn main() {
+    let name = "multi
+line";
+    foo::greet(name)
+}
+"#;
+
+    client.open("main.rn", text);
+
+    let tokens = client.semantic_tokens("main.rn", text);
+    let tokens = tokens
+        .iter()
+        .map(|(text, ty)| (text.as_str(), ty.as_str()))
+        .collect::<Vec<_>>();
+
+    // Tokens spanning several lines are split into one token per line.
+    assert_eq!(
+        tokens,
+        [
+            ("mod", "keyword"),
+            ("foo", "namespace"),
+            ("/* A comment", "comment"),
+            ("   over two lines. */", "comment"),
+            ("pub", "keyword"),
+            ("fn", "keyword"),
+            ("main", "function"),
+            ("let", "keyword"),
+            ("name", "variable"),
+            ("\"multi", "string"),
+            ("line\"", "string"),
+            ("foo", "namespace"),
+            ("greet", "function"),
+            ("name", "variable"),
+        ]
+    );
+}
+
+#[test]
+fn test_completion() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+
+    let position = client.open_at(
+        "main.rn",
+        r#"
+mod foo;
+
+pub fn main() {
+    let value = 42;
+    let s = "hello";
+    s.le$0;
+    value
+}
+"#,
+    );
+
+    let completions = client.complete("main.rn", position);
+    assert!(
+        completions.contains(&String::from("len")),
+        "{:?}",
+        completions
+    );
+
+    let position = json!({ "line": 7, "character": 4 });
+    let completions = client.complete("main.rn", position);
+    assert!(
+        completions.contains(&String::from("value")),
+        "{:?}",
+        completions
+    );
+    assert!(
+        completions.contains(&String::from("main")),
+        "{:?}",
+        completions
+    );
+}
+
+#[test]
+fn test_completion_of_unused_locals() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+
+    // Neither local is used after it's been declared.
+    let position = client.open_at(
+        "main.rn",
+        r#"
+mod foo;
+
+pub fn main() {
+    let first = 42;
+    let #{ second } = #{ second: 1 };
+    $0
+}
+"#,
+    );
+
+    let completions = client.complete("main.rn", position);
+    assert!(
+        completions.contains(&String::from("first")),
+        "{:?}",
+        completions
+    );
+    assert!(
+        completions.contains(&String::from("second")),
+        "{:?}",
+        completions
+    );
+}
+
+#[test]
+fn test_completion_by_receiver_type() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+
+    let position = client.open_at(
+        "main.rn",
+        r#"
+mod foo;
+
+pub fn main() {
+    let s = "hello";
+    let v = [1, 2];
+    s.p$0;
+    v.p;
+}
+"#,
+    );
+
+    let completions = client.complete("main.rn", position);
+    assert!(
+        completions.contains(&String::from("push_str")),
+        "{:?}",
+        completions
+    );
+    assert!(
+        !completions.contains(&String::from("pop")),
+        "{:?}",
+        completions
+    );
+
+    let position = json!({ "line": 7, "character": 7 });
+    let completions = client.complete("main.rn", position);
+    assert!(
+        completions.contains(&String::from("pop")),
+        "{:?}",
+        completions
+    );
+    assert!(
+        !completions.contains(&String::from("push_str")),
+        "{:?}",
+        completions
+    );
+}
+
+#[test]
+fn test_completion_across_files() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+
+    let position = client.open_at(
+        "main.rn",
+        r#"
+mod foo;
+
+pub fn main() {
+    foo::gr$0
+}
+"#,
+    );
+
+    let completions = client.complete("main.rn", position.clone());
+    assert_eq!(completions, vec![String::from("greet")]);
+
+    // Add a function to the other file, which should be completed once it's
+    // been rebuilt.
+    client.change(
+        "foo.rn",
+        json!({ "start": { "line": 5, "character": 0 }, "end": { "line": 5, "character": 0 } }),
+        "\npub fn grow() {\n}\n",
+    );
+
+    let completions = client.complete("main.rn", position);
+    assert_eq!(
+        completions,
+        vec![String::from("greet"), String::from("grow")]
+    );
+}
+
+#[test]
+fn test_no_completion_in_strings_and_comments() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+
+    let source = r#"
+mod foo;
+
+pub fn main() {
+    let value = 42;
+    // val
+    let s = "val";
+    let t = `val ${value}`;
+}
+"#;
+
+    client.open("main.rn", source);
+
+    let in_comment = find(source, "val\n", 3);
+    assert!(client.complete("main.rn", in_comment).is_empty());
+
+    let in_string = find(source, "val\";", 3);
+    assert!(client.complete("main.rn", in_string).is_empty());
+
+    let in_template = find(source, "val ${", 3);
+    assert!(client.complete("main.rn", in_template).is_empty());
+
+    // Expressions in templates are code.
+    let in_expression = find(source, "value}", 3);
+    let completions = client.complete("main.rn", in_expression);
+    assert!(
+        completions.contains(&String::from("value")),
+        "{:?}",
+        completions
+    );
+}
+
+#[test]
+fn test_stale_index_after_failed_build() {
+    let mut client = Client::start();
+    client.open("foo.rn", FOO);
+    client.open("main.rn", MAIN);
+
+    let position = find(MAIN, "name)", 1);
+    let hover = client.request("textDocument/hover", client.params("main.rn", position));
+    assert!(!hover.is_null());
+
+    // Break the build with a line which shifts everything following it.
+    client.change(
+        "main.rn",
+        json!({ "start": { "line": 6, "character": 0 }, "end": { "line": 6, "character": 0 } }),
+        "    let broken = ;\n",
+    );
+
+    let position = json!({ "line": 7, "character": 16 });
+    let hover = client.request(
+        "textDocument/hover",
+        client.params("main.rn", position.clone()),
+    );
+    assert!(hover.is_null(), "{}", hover);
+
+    let definition = client.request(
+        "textDocument/definition",
+        client.params("main.rn", position.clone()),
+    );
+    assert!(definition.is_null(), "{}", definition);
+    assert!(client.references("main.rn", position).is_empty());
+
+    // Locals are still completed from the last successful build.
+    let position = json!({ "line": 7, "character": 17 });
+    let completions = client.complete("main.rn", position);
+    assert!(
+        completions.contains(&String::from("name")),
+        "{:?}",
+        completions
+    );
+}
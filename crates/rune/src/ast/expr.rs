@@ -229,33 +229,6 @@ impl Expr {
         }
     }
 
-    /// Check if this expression is a literal expression.
-    ///
-    /// There are exactly two kinds of literal expressions:
-    /// * Ones that are ExprLit
-    /// * Unary expressions which are the negate operation.
-    pub(crate) fn is_lit(&self) -> bool {
-        match self {
-            Self::Lit(..) => return true,
-            Self::Unary(ast::ExprUnary {
-                op: ast::UnOp::Neg(..),
-                expr,
-                ..
-            }) => {
-                return matches!(
-                    &**expr,
-                    Self::Lit(ast::ExprLit {
-                        lit: ast::Lit::Number(..),
-                        ..
-                    })
-                );
-            }
-            _ => (),
-        }
-
-        false
-    }
-
     /// Internal function to construct a literal expression.
     pub(crate) fn from_lit(lit: ast::Lit) -> Self {
         Self::Lit(ast::ExprLit {
@@ -366,6 +339,10 @@ impl Expr {
 /// testing::roundtrip::<ast::Expr>("var[\"foo\"] = \"bar\"");
 /// testing::roundtrip::<ast::Expr>("let var = objects[\"foo\"] + 1");
 /// testing::roundtrip::<ast::Expr>("var = 42");
+/// testing::roundtrip::<ast::Expr>("let _var = _0 + __");
+///
+/// let expr = testing::roundtrip::<ast::Expr>("_var");
+/// assert!(matches!(expr, ast::Expr::Path(..)));
 ///
 /// let expr = testing::roundtrip::<ast::Expr>(r#"
 ///     if 1 { } else { if 2 { } else { } }
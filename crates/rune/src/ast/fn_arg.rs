@@ -10,6 +10,8 @@ use crate::ast::prelude::*;
 /// testing::roundtrip::<ast::FnArg>("self");
 /// testing::roundtrip::<ast::FnArg>("_");
 /// testing::roundtrip::<ast::FnArg>("abc");
+/// testing::roundtrip::<ast::FnArg>("abc: int");
+/// testing::roundtrip::<ast::FnArg>("(a, b): (int, String)");
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, ToTokens, Spanned)]
 #[non_exhaustive]
@@ -18,13 +20,64 @@ pub enum FnArg {
     SelfValue(T![self]),
     /// Function argument is a pattern binding.
     Pat(ast::Pat),
+    /// Function argument is a pattern binding with a type annotation.
+    Typed(FnArgTyped),
 }
 
 impl Parse for FnArg {
     fn parse(p: &mut Parser<'_>) -> Result<Self, ParseError> {
-        Ok(match p.nth(0)? {
-            K![self] => Self::SelfValue(p.parse()?),
-            _ => Self::Pat(p.parse()?),
+        if let K![self] = p.nth(0)? {
+            return Ok(Self::SelfValue(p.parse()?));
+        }
+
+        let pat = match ast::Pat::parse_annotated_binding(p)? {
+            Some(pat) => pat,
+            None => ast::Pat::parse_without_alternatives(p)?,
+        };
+
+        Ok(match p.parse::<Option<T![:]>>()? {
+            Some(colon) => Self::Typed(FnArgTyped {
+                pat,
+                colon,
+                ty: p.parse()?,
+            }),
+            None => Self::Pat(pat),
+        })
+    }
+}
+
+/// A function argument with a type annotation `a: int`.
+///
+/// # Examples
+///
+/// ```
+/// use rune::{ast, testing};
+///
+/// testing::roundtrip::<ast::FnArgTyped>("a: int");
+/// testing::roundtrip::<ast::FnArgTyped>("shape: shapes::Shape");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, ToTokens, Spanned)]
+#[non_exhaustive]
+pub struct FnArgTyped {
+    /// The pattern being bound.
+    pub pat: ast::Pat,
+    /// The `:` separating the pattern from the type.
+    pub colon: T![:],
+    /// The type of the argument.
+    pub ty: ast::Type,
+}
+
+impl Parse for FnArgTyped {
+    fn parse(p: &mut Parser<'_>) -> Result<Self, ParseError> {
+        let pat = match ast::Pat::parse_annotated_binding(p)? {
+            Some(pat) => pat,
+            None => ast::Pat::parse_without_alternatives(p)?,
+        };
+
+        Ok(Self {
+            pat,
+            colon: p.parse()?,
+            ty: p.parse()?,
         })
     }
 }
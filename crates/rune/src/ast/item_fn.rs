@@ -22,6 +22,7 @@ use crate::ast::prelude::*;
 /// testing::roundtrip::<ast::ItemFn>("pub fn hello(foo, bar) {}");
 /// testing::roundtrip::<ast::ItemFn>("pub async fn hello(foo, bar) {}");
 /// testing::roundtrip::<ast::ItemFn>("#[inline] fn hello(foo, bar) {}");
+/// testing::roundtrip::<ast::ItemFn>("fn hello(foo: int, bar: String) -> Vec {}");
 ///
 /// let item = testing::roundtrip::<ast::ItemFn>("#[inline] pub async fn hello(foo, bar) {}");
 /// assert!(matches!(item.visibility, ast::Visibility::Public(..)));
@@ -64,6 +65,9 @@ pub struct ItemFn {
     pub name: ast::Ident,
     /// The arguments of the function.
     pub args: ast::Parenthesized<ast::FnArg, T![,]>,
+    /// The optional return type of the function.
+    #[rune(iter)]
+    pub output: Option<(T![->], ast::Type)>,
     /// The body of the function.
     pub body: ast::Block,
 }
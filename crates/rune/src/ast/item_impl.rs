@@ -11,6 +11,9 @@ use crate::ast::prelude::*;
 /// testing::roundtrip::<ast::ItemImpl>("impl Foo { fn test(self) { } }");
 /// testing::roundtrip::<ast::ItemImpl>("#[variant(enum_= \"SuperHero\", x = \"1\")] impl Foo { fn test(self) { } }");
 /// testing::roundtrip::<ast::ItemImpl>("#[xyz] impl Foo { #[jit] fn test(self) { } }");
+///
+/// let item = testing::roundtrip::<ast::ItemImpl>("impl Shape for Square { fn area(self) { } }");
+/// assert!(item.trait_.is_some());
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, ToTokens, Spanned)]
 #[non_exhaustive]
@@ -20,6 +23,10 @@ pub struct ItemImpl {
     pub attributes: Vec<ast::Attribute>,
     /// The `impl` keyword.
     pub impl_: T![impl],
+    /// The trait being implemented and the `for` keyword, if this implements
+    /// a trait.
+    #[rune(iter)]
+    pub trait_: Option<(ast::Path, T![for])>,
     /// Path of the implementation.
     pub path: ast::Path,
     /// The open brace.
@@ -37,7 +44,13 @@ impl ItemImpl {
         attributes: Vec<ast::Attribute>,
     ) -> Result<Self, ParseError> {
         let impl_ = parser.parse()?;
-        let path = parser.parse()?;
+        let mut path = parser.parse()?;
+
+        let trait_ = match parser.parse::<Option<T![for]>>()? {
+            Some(for_) => Some((std::mem::replace(&mut path, parser.parse()?), for_)),
+            None => None,
+        };
+
         let open = parser.parse()?;
 
         let mut functions = vec![];
@@ -51,6 +64,7 @@ impl ItemImpl {
         Ok(Self {
             attributes,
             impl_,
+            trait_,
             path,
             open,
             functions,
@@ -0,0 +1,180 @@
+use crate::ast::prelude::*;
+
+/// A trait item.
+///
+/// # Examples
+///
+/// ```
+/// use rune::{ast, testing};
+///
+/// testing::roundtrip::<ast::ItemTrait>("trait Foo {}");
+/// testing::roundtrip::<ast::ItemTrait>("trait Foo { fn test(self); }");
+/// testing::roundtrip::<ast::ItemTrait>("pub trait Foo { fn test(self); fn other(self) { self.test() } }");
+/// testing::roundtrip::<ast::ItemTrait>("#[doc = \"A trait\"] trait Foo { #[doc = \"A method\"] fn test(self, a, b); }");
+///
+/// let item = testing::roundtrip::<ast::ItemTrait>("trait Foo { fn a(self); async fn b(self) {} }");
+/// assert_eq!(item.functions.len(), 2);
+/// assert!(matches!(item.functions[0], ast::TraitFn::Required(..)));
+/// assert!(matches!(item.functions[1], ast::TraitFn::Provided(..)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, ToTokens, Spanned, Opaque)]
+#[non_exhaustive]
+pub struct ItemTrait {
+    /// Opaque identifier of the trait.
+    #[rune(id)]
+    pub(crate) id: Id,
+    /// The attributes of the trait.
+    #[rune(iter)]
+    pub attributes: Vec<ast::Attribute>,
+    /// The visibility of the `trait` item.
+    #[rune(optional)]
+    pub visibility: ast::Visibility,
+    /// The `trait` keyword.
+    pub trait_token: T![trait],
+    /// The name of the trait.
+    pub ident: ast::Ident,
+    /// The open brace.
+    pub open: T!['{'],
+    /// The functions declared in the trait.
+    pub functions: Vec<TraitFn>,
+    /// The close brace.
+    pub close: T!['}'],
+}
+
+impl ItemTrait {
+    /// Parse a `trait` item with the given attributes and visibility.
+    pub(crate) fn parse_with_meta(
+        parser: &mut Parser<'_>,
+        attributes: Vec<ast::Attribute>,
+        visibility: ast::Visibility,
+    ) -> Result<Self, ParseError> {
+        let trait_token = parser.parse()?;
+        let ident = parser.parse()?;
+        let open = parser.parse()?;
+
+        let mut functions = vec![];
+
+        while !parser.peek::<ast::CloseBrace>()? {
+            functions.push(parser.parse()?);
+        }
+
+        let close = parser.parse()?;
+
+        Ok(Self {
+            id: Default::default(),
+            attributes,
+            visibility,
+            trait_token,
+            ident,
+            open,
+            functions,
+            close,
+        })
+    }
+}
+
+item_parse!(Trait, ItemTrait, "trait item");
+
+/// A function in a trait, which is either required or provided with a
+/// default implementation.
+#[derive(Debug, Clone, PartialEq, Eq, ToTokens, Spanned)]
+#[non_exhaustive]
+pub enum TraitFn {
+    /// A function which implementors are required to provide.
+    Required(TraitFnDecl),
+    /// A function with a default implementation.
+    Provided(ast::ItemFn),
+}
+
+impl TraitFn {
+    /// The name of the function.
+    pub(crate) fn name(&self) -> &ast::Ident {
+        match self {
+            Self::Required(decl) => &decl.name,
+            Self::Provided(item_fn) => &item_fn.name,
+        }
+    }
+}
+
+impl Parse for TraitFn {
+    fn parse(p: &mut Parser<'_>) -> Result<Self, ParseError> {
+        let attributes = p.parse()?;
+        let const_token = p.parse::<Option<T![const]>>()?;
+        let async_token = p.parse::<Option<T![async]>>()?;
+        let fn_token = p.parse()?;
+        let name = p.parse()?;
+        let args = p.parse()?;
+        let output = p.parse()?;
+
+        if let Some(semi) = p.parse::<Option<T![;]>>()? {
+            if let Some(span) = const_token.option_span() {
+                return Err(ParseError::unsupported(span, "const modifier"));
+            }
+
+            if let Some(span) = async_token.option_span() {
+                return Err(ParseError::unsupported(span, "async modifier"));
+            }
+
+            return Ok(Self::Required(TraitFnDecl {
+                attributes,
+                fn_token,
+                name,
+                args,
+                output,
+                semi,
+            }));
+        }
+
+        Ok(Self::Provided(ast::ItemFn {
+            id: Default::default(),
+            attributes,
+            visibility: ast::Visibility::Inherited,
+            const_token,
+            async_token,
+            fn_token,
+            name,
+            args,
+            output,
+            body: p.parse()?,
+        }))
+    }
+}
+
+/// The declaration of a function in a trait without a default implementation.
+///
+/// # Examples
+///
+/// ```
+/// use rune::{ast, testing};
+///
+/// let item = testing::roundtrip::<ast::TraitFnDecl>("fn test(self, a);");
+/// assert_eq!(item.args.len(), 2);
+///
+/// let item = testing::roundtrip::<ast::TraitFnDecl>("fn area(self) -> float;");
+/// assert!(item.output.is_some());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Parse, ToTokens, Spanned)]
+#[non_exhaustive]
+pub struct TraitFnDecl {
+    /// The attributes of the function.
+    #[rune(iter)]
+    pub attributes: Vec<ast::Attribute>,
+    /// The `fn` token.
+    pub fn_token: T![fn],
+    /// The name of the function.
+    pub name: ast::Ident,
+    /// The arguments of the function.
+    pub args: ast::Parenthesized<ast::FnArg, T![,]>,
+    /// The optional return type of the function.
+    #[rune(iter)]
+    pub output: Option<(T![->], ast::Type)>,
+    /// The terminating semicolon.
+    pub semi: T![;],
+}
+
+impl TraitFnDecl {
+    /// Test if the function takes `self`.
+    pub(crate) fn is_instance(&self) -> bool {
+        matches!(self.args.first(), Some((ast::FnArg::SelfValue(..), _)))
+    }
+}
@@ -10,6 +10,8 @@ use crate::ast::prelude::*;
 /// testing::roundtrip::<ast::Local>("let x = 1;");
 /// testing::roundtrip::<ast::Local>("#[attr] let a = f();");
 /// testing::roundtrip::<ast::Local>("let a = b{}().foo[0].await;");
+/// testing::roundtrip::<ast::Local>("let a: int = 1;");
+/// testing::roundtrip::<ast::Local>("let (a, b): (int, String) = f();");
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, ToTokens, Parse, Spanned)]
 #[non_exhaustive]
@@ -20,7 +22,11 @@ pub struct Local {
     /// The `let` keyword.
     pub let_token: T![let],
     /// The name of the binding.
+    #[rune(parse_with = "parse_pat")]
     pub pat: ast::Pat,
+    /// The optional type annotation of the binding.
+    #[rune(iter)]
+    pub ty: Option<(T![:], ast::Type)>,
     /// The equality keyword.
     pub eq: T![=],
     /// The expression the binding is assigned to.
@@ -30,6 +36,13 @@ pub struct Local {
     pub semi: T![;],
 }
 
+fn parse_pat(p: &mut Parser<'_>) -> Result<ast::Pat, ParseError> {
+    match ast::Pat::parse_annotated_binding(p)? {
+        Some(pat) => Ok(pat),
+        None => p.parse(),
+    }
+}
+
 fn parse_expr(p: &mut Parser<'_>) -> Result<ast::Expr, ParseError> {
     ast::Expr::parse_with(
         p,
@@ -20,6 +20,10 @@ pub enum Pat {
     PatBinding(PatBinding),
     /// The rest pattern `..`.
     PatRest(PatRest),
+    /// A range pattern `1..=9`.
+    PatRange(PatRange),
+    /// Alternative patterns `A | B`.
+    PatOr(PatOr),
 }
 
 /// Parsing a block expression.
@@ -41,23 +45,70 @@ pub enum Pat {
 /// testing::roundtrip::<ast::Pat>("var");
 /// testing::roundtrip::<ast::Pat>("_");
 /// testing::roundtrip::<ast::Pat>("Foo(n)");
+/// testing::roundtrip::<ast::Pat>("1..=9");
+/// testing::roundtrip::<ast::Pat>("-10..0");
+/// testing::roundtrip::<ast::Pat>("'a'..='z'");
+/// testing::roundtrip::<ast::Pat>("Some(1) | None");
+/// testing::roundtrip::<ast::Pat>("1 | 2..=4 | _");
+/// testing::roundtrip::<ast::Pat>("Foo(_, _var)");
+///
+/// // A single underscore is ignored, while anything following it makes it an
+/// // identifier.
+/// let pat = testing::roundtrip::<ast::Pat>("_");
+/// assert!(matches!(pat, ast::Pat::PatIgnore(..)));
+/// let pat = testing::roundtrip::<ast::Pat>("_var");
+/// assert!(matches!(pat, ast::Pat::PatPath(..)));
+/// let pat = testing::roundtrip::<ast::Pat>("_0");
+/// assert!(matches!(pat, ast::Pat::PatPath(..)));
 /// ```
 impl Parse for Pat {
     fn parse(p: &mut Parser<'_>) -> Result<Self, ParseError> {
+        let first = Self::parse_without_alternatives(p)?;
+
+        if !p.peek::<T![|]>()? {
+            return Ok(first);
+        }
+
+        let mut rest = Vec::new();
+
+        while let Some(pipe) = p.parse::<Option<T![|]>>()? {
+            rest.push((pipe, Self::parse_without_alternatives(p)?));
+        }
+
+        Ok(Self::PatOr(PatOr {
+            first: Box::new(first),
+            rest,
+        }))
+    }
+}
+
+impl Pat {
+    /// Parse a variable binding which is followed by a type annotation, like
+    /// `a: int`, which would otherwise be parsed as an object binding.
+    pub(crate) fn parse_annotated_binding(p: &mut Parser<'_>) -> Result<Option<Self>, ParseError> {
+        if !matches!((p.nth(0)?, p.nth(1)?), (K![ident], K![:])) {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::PatPath(PatPath {
+            attributes: Vec::new(),
+            path: p.parse()?,
+        })))
+    }
+
+    /// Parse a pattern which is not allowed to have top-level alternatives,
+    /// like the arguments of a closure `|a, b|`.
+    pub(crate) fn parse_without_alternatives(p: &mut Parser<'_>) -> Result<Self, ParseError> {
         let attributes = p.parse::<Vec<ast::Attribute>>()?;
 
         match p.nth(0)? {
             K![byte] => {
-                return Ok(Self::PatLit(PatLit {
-                    attributes,
-                    expr: Box::new(ast::Expr::from_lit(ast::Lit::Byte(p.parse()?))),
-                }));
+                let expr = ast::Expr::from_lit(ast::Lit::Byte(p.parse()?));
+                return Self::parse_lit_or_range(p, attributes, expr);
             }
             K![char] => {
-                return Ok(Self::PatLit(PatLit {
-                    attributes,
-                    expr: Box::new(ast::Expr::from_lit(ast::Lit::Char(p.parse()?))),
-                }));
+                let expr = ast::Expr::from_lit(ast::Lit::Char(p.parse()?));
+                return Self::parse_lit_or_range(p, attributes, expr);
             }
             K![bytestr] => {
                 return Ok(Self::PatLit(PatLit {
@@ -86,10 +137,8 @@ impl Parse for Pat {
                 });
             }
             K![number] => {
-                return Ok(Self::PatLit(PatLit {
-                    attributes,
-                    expr: Box::new(ast::Expr::from_lit(ast::Lit::Number(p.parse()?))),
-                }));
+                let expr = ast::Expr::from_lit(ast::Lit::Number(p.parse()?));
+                return Self::parse_lit_or_range(p, attributes, expr);
             }
             K![..] => {
                 return Ok(Self::PatRest(PatRest {
@@ -121,15 +170,9 @@ impl Parse for Pat {
                     items: p.parse()?,
                 }))
             }
-            K![-] => {
-                let expr: ast::Expr = p.parse()?;
-
-                if expr.is_lit() {
-                    return Ok(Self::PatLit(PatLit {
-                        attributes,
-                        expr: Box::new(expr),
-                    }));
-                }
+            K![-] if matches!(p.nth(1)?, K![number]) => {
+                let expr = parse_neg_number(p)?;
+                return Self::parse_lit_or_range(p, attributes, expr);
             }
             K![_] => {
                 return Ok(Self::PatIgnore(PatIgnore {
@@ -165,6 +208,47 @@ impl Parse for Pat {
 
         Err(ParseError::expected(p.tok_at(0)?, "pattern"))
     }
+
+    /// Parse the remainder of a literal pattern, which might be the start of
+    /// a range pattern like `1..=9`.
+    fn parse_lit_or_range(
+        p: &mut Parser<'_>,
+        attributes: Vec<ast::Attribute>,
+        start: ast::Expr,
+    ) -> Result<Self, ParseError> {
+        if !matches!(p.nth(0)?, K![..] | K![..=]) {
+            return Ok(Self::PatLit(PatLit {
+                attributes,
+                expr: Box::new(start),
+            }));
+        }
+
+        let limits = p.parse()?;
+
+        let end = match p.nth(0)? {
+            K![byte] => ast::Expr::from_lit(ast::Lit::Byte(p.parse()?)),
+            K![char] => ast::Expr::from_lit(ast::Lit::Char(p.parse()?)),
+            K![number] => ast::Expr::from_lit(ast::Lit::Number(p.parse()?)),
+            K![-] if matches!(p.nth(1)?, K![number]) => parse_neg_number(p)?,
+            _ => return Err(ParseError::expected(p.tok_at(0)?, "end of range pattern")),
+        };
+
+        Ok(Self::PatRange(PatRange {
+            attributes,
+            start: Box::new(start),
+            limits,
+            end: Box::new(end),
+        }))
+    }
+}
+
+/// Parse a negative number literal `-42`.
+fn parse_neg_number(p: &mut Parser<'_>) -> Result<ast::Expr, ParseError> {
+    Ok(ast::Expr::Unary(ast::ExprUnary {
+        attributes: Vec::new(),
+        op: ast::UnOp::Neg(p.parse()?),
+        expr: Box::new(ast::Expr::from_lit(ast::Lit::Number(p.parse()?))),
+    }))
 }
 
 impl Peek for Pat {
@@ -194,6 +278,32 @@ pub struct PatLit {
     pub expr: Box<ast::Expr>,
 }
 
+/// A range pattern `1..=9` or `'a'..'z'`.
+#[derive(Debug, Clone, PartialEq, Eq, ToTokens, Spanned)]
+#[non_exhaustive]
+pub struct PatRange {
+    /// Attributes associated with the pattern.
+    #[rune(iter)]
+    pub attributes: Vec<ast::Attribute>,
+    /// The start of the range.
+    pub start: Box<ast::Expr>,
+    /// The range limits.
+    pub limits: ast::ExprRangeLimits,
+    /// The end of the range.
+    pub end: Box<ast::Expr>,
+}
+
+/// Alternative patterns `A | B`.
+#[derive(Debug, Clone, PartialEq, Eq, ToTokens, Spanned)]
+#[non_exhaustive]
+pub struct PatOr {
+    /// The first alternative.
+    pub first: Box<ast::Pat>,
+    /// The rest of the alternatives.
+    #[rune(iter)]
+    pub rest: Vec<(T![|], ast::Pat)>,
+}
+
 /// The rest pattern `..` and associated attributes.
 #[derive(Debug, Clone, PartialEq, Eq, ToTokens, Spanned)]
 #[non_exhaustive]
@@ -0,0 +1,38 @@
+use crate::ast::prelude::*;
+
+/// A type annotation.
+///
+/// # Examples
+///
+/// ```
+/// use rune::{ast, testing};
+///
+/// testing::roundtrip::<ast::Type>("int");
+/// testing::roundtrip::<ast::Type>("std::option::Option");
+/// testing::roundtrip::<ast::Type>("()");
+/// testing::roundtrip::<ast::Type>("(int, String)");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, ToTokens, Spanned)]
+#[non_exhaustive]
+pub enum Type {
+    /// A type referenced by path, like `int` or `Vec`.
+    Path(ast::Path),
+    /// A tuple type, like `()` or `(int, String)`.
+    Tuple(ast::Parenthesized<ast::Type, T![,]>),
+}
+
+impl Parse for Type {
+    fn parse(p: &mut Parser<'_>) -> Result<Self, ParseError> {
+        Ok(match p.nth(0)? {
+            K!['('] => Self::Tuple(p.parse()?),
+            _ if ast::Path::peek(p.peeker()) => Self::Path(p.parse()?),
+            _ => return Err(ParseError::expected(p.tok_at(0)?, "type")),
+        })
+    }
+}
+
+impl Peek for Type {
+    fn peek(p: &mut Peeker<'_>) -> bool {
+        matches!(p.nth(0), K!['(']) || ast::Path::peek(p)
+    }
+}
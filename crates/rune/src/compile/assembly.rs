@@ -3,7 +3,7 @@
 use crate::ast::Span;
 use crate::collections::HashMap;
 use crate::compile::{CompileError, CompileErrorKind, Location};
-use crate::runtime::{Inst, Label};
+use crate::runtime::{DebugLocal, Inst, Label};
 use crate::{Hash, SourceId};
 
 #[derive(Debug, Clone)]
@@ -35,6 +35,9 @@ pub(crate) struct Assembly {
     pub(crate) label_count: usize,
     /// The collection of functions required by this assembly.
     pub(crate) required_functions: HashMap<Hash, Vec<(Span, SourceId)>>,
+    /// Named local variables, with ranges relative to the start of the
+    /// assembly.
+    pub(crate) locals: Vec<DebugLocal>,
 }
 
 impl Assembly {
@@ -48,6 +51,7 @@ impl Assembly {
             comments: Default::default(),
             label_count,
             required_functions: Default::default(),
+            locals: Default::default(),
         }
     }
 
@@ -177,3 +177,22 @@ impl Attribute for Doc {
     /// Must match the specified name.
     const PATH: &'static str = "doc";
 }
+
+/// The `#[protocol(NAME)]` attribute, which registers an instance function as
+/// the implementation of the protocol named `NAME`, like `ADD`.
+#[derive(Parse)]
+pub(crate) struct Protocol {
+    /// The open parenthesis.
+    #[allow(dead_code)]
+    pub open: T!['('],
+    /// The name of the protocol.
+    pub name: ast::Ident,
+    /// The close parenthesis.
+    #[allow(dead_code)]
+    pub close: T![')'],
+}
+
+impl Attribute for Protocol {
+    /// Must match the specified name.
+    const PATH: &'static str = "protocol";
+}
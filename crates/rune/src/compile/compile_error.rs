@@ -170,6 +170,15 @@ pub enum CompileErrorKind {
     UnsupportedBinding,
     #[error("floating point numbers cannot be used in patterns")]
     MatchFloatInPattern,
+    #[error("variable `{name}` is not bound in all alternatives of the pattern")]
+    OrPatternBindingMismatch { name: Box<str> },
+    #[error("match is not exhaustive, missing `{}`", .missing.join("`, `"))]
+    MatchNotExhaustive { missing: Box<[Box<str>]> },
+    #[error("expected type `{expected}` but found `{actual}`")]
+    TypeMismatch {
+        expected: Box<str>,
+        actual: Box<str>,
+    },
     #[error("duplicate key in literal object")]
     DuplicateObjectKey { existing: Span, object: Span },
     #[error("`yield` must be used in function or closure")]
@@ -292,6 +301,20 @@ pub enum CompileErrorKind {
         item: ItemBuf,
         fields: Box<[Box<str>]>,
     },
+    #[error("trait function `{name}` must take `self` as its first argument")]
+    TraitFnNotInstance { name: Box<str> },
+    #[error("trait function `{name}` is declared more than once")]
+    TraitFnConflict { name: Box<str>, existing: Span },
+    #[error("missing function `{name}` in implementation of `{trait_item}` for `{item}`")]
+    MissingTraitFn {
+        trait_item: ItemBuf,
+        item: ItemBuf,
+        name: Box<str>,
+    },
+    #[error("function `{name}` is not a member of trait `{trait_item}`")]
+    NotTraitFn { trait_item: ItemBuf, name: Box<str> },
+    #[error("no protocol named `{name}`")]
+    MissingProtocol { name: Box<str> },
 }
 
 /// A single step in an import.
@@ -10,12 +10,29 @@ pub trait CompileVisitor {
     /// Mark that we've resolved a specific compile meta at the given location.
     fn visit_meta(&mut self, _location: Location, _meta: MetaRef<'_>) {}
 
+    /// Indicates if imported items should be visited through
+    /// [CompileVisitor::visit_meta] at the location of their `use`
+    /// declarations.
+    ///
+    /// This requires every queried import to be built, which is additional
+    /// work that is skipped unless a visitor asks for it.
+    fn visit_imports(&self) -> bool {
+        false
+    }
+
+    /// Visit a variable declaration.
+    fn visit_variable_decl(&mut self, _source_id: SourceId, _span: Span) {}
+
     /// Visit a variable use.
     fn visit_variable_use(&mut self, _source_id: SourceId, _var_span: Span, _span: Span) {}
 
     /// Visit something that is a module.
     fn visit_mod(&mut self, _source_id: SourceId, _span: Span) {}
 
+    /// Visit a use of a named field in a struct or struct variant, such as a
+    /// key in an object literal or an object pattern.
+    fn visit_field_use(&mut self, _location: Location, _item: &Item, _field: &str) {}
+
     /// Visit anterior `///`-style comments, and interior `//!`-style doc
     /// comments for an item.
     ///
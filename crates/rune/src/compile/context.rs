@@ -116,6 +116,16 @@ pub enum ContextSignature {
     },
 }
 
+impl ContextSignature {
+    /// The name of the function, without its arguments.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Function { item, .. } => item.to_string(),
+            Self::Instance { item, name, .. } => format!("{}::{}", item, name),
+        }
+    }
+}
+
 impl fmt::Display for ContextSignature {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -271,7 +281,17 @@ impl Context {
     /// # Ok(()) }
     /// ```
     pub fn runtime(&self) -> RuntimeContext {
-        RuntimeContext::new(self.functions.clone(), self.constants.clone())
+        let function_names = self
+            .functions_info
+            .iter()
+            .map(|(hash, signature)| (*hash, signature.name().into()))
+            .collect();
+
+        RuntimeContext::new(
+            self.functions.clone(),
+            self.constants.clone(),
+            function_names,
+        )
     }
 
     /// Install the specified module.
@@ -325,6 +345,11 @@ impl Context {
         })
     }
 
+    /// Lookup the signature of the native function with the given hash.
+    pub fn lookup_signature(&self, hash: Hash) -> Option<&ContextSignature> {
+        self.functions_info.get(&hash)
+    }
+
     /// Iterate over all available types in the [Context].
     pub fn iter_types(&self) -> impl Iterator<Item = (Hash, &ContextTypeInfo)> {
         let mut it = self.types.iter();
@@ -362,6 +387,18 @@ impl Context {
         self.functions.get(&hash)
     }
 
+    /// Lookup the given native type in the context.
+    pub(crate) fn lookup_type(&self, hash: Hash) -> Option<&ContextTypeInfo> {
+        self.types.get(&hash)
+    }
+
+    /// Lookup the native type with the given runtime type hash, like the one
+    /// associated with [crate::runtime::INTEGER_TYPE].
+    pub(crate) fn lookup_type_by_type_hash(&self, type_hash: Hash) -> Option<&ContextTypeInfo> {
+        let hash = self.types_rev.get(&type_hash)?;
+        self.types.get(hash)
+    }
+
     /// Lookup the given macro handler.
     pub(crate) fn lookup_macro(&self, hash: Hash) -> Option<&Arc<MacroHandler>> {
         self.macros.get(&hash)
@@ -496,7 +533,14 @@ impl Context {
                         self.install_meta(ContextMeta { item, kind })?;
                     }
 
-                    ContextMetaKind::Enum { type_hash }
+                    ContextMetaKind::Enum {
+                        type_hash,
+                        variants: en
+                            .variants
+                            .iter()
+                            .map(|(name, _)| name.as_ref().into())
+                            .collect(),
+                    }
                 }
             }
         } else {
@@ -738,6 +782,11 @@ impl Context {
             item: enum_item.clone(),
             kind: ContextMetaKind::Enum {
                 type_hash: internal_enum.static_type.hash,
+                variants: internal_enum
+                    .variants
+                    .iter()
+                    .map(|variant| variant.name.into())
+                    .collect(),
             },
         })?;
 
@@ -156,4 +156,6 @@ pub enum IrErrorKind {
     ArgumentCountMismatch { actual: usize, expected: usize },
     #[error("value `{value}` is outside of the supported integer range")]
     NotInteger { value: num::BigInt },
+    #[error("variable `{name}` is not bound in all alternatives of the pattern")]
+    OrPatternBindingMismatch { name: Box<str> },
 }
@@ -25,6 +25,7 @@ use crate::compile::ir::eval::IrEvalBreak;
 use crate::compile::ItemMeta;
 use crate::hir;
 use crate::query::Used;
+use crate::runtime::{Bytes, Shared};
 
 /// Context used for [IrEval].
 pub struct IrEvalContext<'a> {
@@ -191,6 +192,14 @@ impl IrFn {
             if let hir::FnArg::Pat(hir::Pat {
                 kind: hir::PatKind::PatPath(path),
                 ..
+            })
+            | hir::FnArg::Typed(hir::FnArgTyped {
+                pat:
+                    hir::Pat {
+                        kind: hir::PatKind::PatPath(path),
+                        ..
+                    },
+                ..
             }) = arg
             {
                 if let Some(ident) = path.try_as_ident() {
@@ -335,6 +344,19 @@ pub enum IrPat {
     Ignore,
     /// A named binding.
     Binding(Box<str>),
+    /// A literal pattern.
+    Lit(IrValue),
+    /// A range pattern `1..=9`.
+    Range {
+        /// The start of the range.
+        start: IrValue,
+        /// The end of the range.
+        end: IrValue,
+        /// The limits of the range.
+        limits: hir::ExprRangeLimits,
+    },
+    /// Alternative patterns `A | B`.
+    Or(Vec<IrPat>),
 }
 
 impl IrPat {
@@ -347,12 +369,110 @@ impl IrPat {
                     return Ok(ir::IrPat::Binding(name.into()));
                 }
             }
+            hir::PatKind::PatLit(hir) => {
+                return Ok(ir::IrPat::Lit(Self::compile_lit(hir, c)?));
+            }
+            hir::PatKind::PatRange(hir) => {
+                return Ok(ir::IrPat::Range {
+                    start: Self::compile_lit(hir.start, c)?,
+                    end: Self::compile_lit(hir.end, c)?,
+                    limits: hir.limits,
+                });
+            }
+            hir::PatKind::PatOr(branches) => {
+                let mut pats = Vec::with_capacity(branches.len());
+
+                for branch in branches {
+                    pats.push(Self::compile_ast(branch, c)?);
+                }
+
+                let mut expected = Vec::new();
+
+                for (index, (pat, branch)) in pats.iter().zip(branches).enumerate() {
+                    let mut names = Vec::new();
+                    pat.bindings(&mut names);
+
+                    if index == 0 {
+                        expected = names;
+                        continue;
+                    }
+
+                    if let Some(name) = names
+                        .iter()
+                        .chain(expected.iter())
+                        .find(|name| !names.contains(name) || !expected.contains(name))
+                    {
+                        return Err(IrError::new(
+                            branch,
+                            IrErrorKind::OrPatternBindingMismatch {
+                                name: (*name).into(),
+                            },
+                        ));
+                    }
+                }
+
+                return Ok(ir::IrPat::Or(pats));
+            }
             _ => (),
         }
 
         Err(IrError::msg(hir, "pattern not supported yet"))
     }
 
+    /// Compile the literal used in a pattern.
+    fn compile_lit(hir: &hir::Expr<'_>, c: &mut IrCompiler<'_>) -> Result<IrValue, IrError> {
+        match hir.kind {
+            hir::ExprKind::Unary(hir::ExprUnary {
+                op: ast::UnOp::Neg(..),
+                expr:
+                    hir::Expr {
+                        kind: hir::ExprKind::Lit(ast::Lit::Number(lit)),
+                        ..
+                    },
+                ..
+            }) => {
+                if let ast::Number::Integer(n) = c.resolve(lit)? {
+                    return Ok(IrValue::Integer(-n));
+                }
+            }
+            hir::ExprKind::Lit(lit) => match lit {
+                ast::Lit::Bool(lit) => return Ok(IrValue::Bool(lit.value)),
+                ast::Lit::Byte(lit) => return Ok(IrValue::Byte(c.resolve(lit)?)),
+                ast::Lit::Char(lit) => return Ok(IrValue::Char(c.resolve(lit)?)),
+                ast::Lit::Str(lit) => {
+                    let s = c.resolve(lit)?;
+                    return Ok(IrValue::String(Shared::new(s.into_owned())));
+                }
+                ast::Lit::ByteStr(lit) => {
+                    let bytes = c.resolve(lit)?;
+                    let bytes = Bytes::from_vec(bytes.into_owned());
+                    return Ok(IrValue::Bytes(Shared::new(bytes)));
+                }
+                ast::Lit::Number(lit) => {
+                    if let ast::Number::Integer(n) = c.resolve(lit)? {
+                        return Ok(IrValue::Integer(n));
+                    }
+                }
+            },
+            _ => (),
+        }
+
+        Err(IrError::msg(hir, "pattern not supported yet"))
+    }
+
+    /// Collect the names of the variables bound by this pattern.
+    fn bindings<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            IrPat::Binding(name) => out.push(name),
+            IrPat::Or(pats) => {
+                if let Some(pat) = pats.first() {
+                    pat.bindings(out);
+                }
+            }
+            _ => (),
+        }
+    }
+
     fn matches<S>(
         &self,
         interp: &mut IrInterpreter<'_>,
@@ -362,12 +482,65 @@ impl IrPat {
     where
         S: Spanned,
     {
+        let span = spanned.span();
+
         match self {
             IrPat::Ignore => Ok(true),
             IrPat::Binding(name) => {
-                interp.scopes.decl(name, value, spanned)?;
+                interp.scopes.decl(name, value, span)?;
                 Ok(true)
             }
+            IrPat::Lit(expected) => Ok(match (expected, &value) {
+                (IrValue::Unit, IrValue::Unit) => true,
+                (IrValue::Bool(a), IrValue::Bool(b)) => a == b,
+                (IrValue::Byte(a), IrValue::Byte(b)) => a == b,
+                (IrValue::Char(a), IrValue::Char(b)) => a == b,
+                (IrValue::Integer(a), IrValue::Integer(b)) => a == b,
+                (IrValue::String(a), IrValue::String(b)) => {
+                    let a = a.borrow_ref().map_err(IrError::access(span))?;
+                    let b = b.borrow_ref().map_err(IrError::access(span))?;
+                    *a == *b
+                }
+                (IrValue::Bytes(a), IrValue::Bytes(b)) => {
+                    let a = a.borrow_ref().map_err(IrError::access(span))?;
+                    let b = b.borrow_ref().map_err(IrError::access(span))?;
+                    *a == *b
+                }
+                _ => false,
+            }),
+            IrPat::Range { start, end, limits } => {
+                fn contains<T>(start: &T, end: &T, limits: hir::ExprRangeLimits, value: &T) -> bool
+                where
+                    T: PartialOrd,
+                {
+                    match limits {
+                        hir::ExprRangeLimits::HalfOpen => start <= value && value < end,
+                        hir::ExprRangeLimits::Closed => start <= value && value <= end,
+                    }
+                }
+
+                Ok(match (start, end, &value) {
+                    (IrValue::Byte(start), IrValue::Byte(end), IrValue::Byte(value)) => {
+                        contains(start, end, *limits, value)
+                    }
+                    (IrValue::Char(start), IrValue::Char(end), IrValue::Char(value)) => {
+                        contains(start, end, *limits, value)
+                    }
+                    (IrValue::Integer(start), IrValue::Integer(end), IrValue::Integer(value)) => {
+                        contains(start, end, *limits, value)
+                    }
+                    _ => false,
+                })
+            }
+            IrPat::Or(pats) => {
+                for pat in pats {
+                    if pat.matches(interp, value.clone(), span)? {
+                        return Ok(true);
+                    }
+                }
+
+                Ok(false)
+            }
         }
     }
 }
@@ -77,6 +77,8 @@ pub enum MetaKind {
     Import,
     /// Item describes a module.
     Module,
+    /// Item describes a trait.
+    Trait,
 }
 
 impl fmt::Display for Meta {
@@ -127,6 +129,9 @@ impl fmt::Display for Meta {
             MetaKind::Module => {
                 write!(fmt, "module {}", self.item)?;
             }
+            MetaKind::Trait => {
+                write!(fmt, "trait {}", self.item)?;
+            }
         }
 
         Ok(())
@@ -225,6 +230,8 @@ pub(crate) enum ContextMetaKind {
     Enum {
         /// The type hash associated with this meta kind.
         type_hash: Hash,
+        /// The names of the variants of the enum, in declaration order.
+        variants: Box<[Box<str>]>,
     },
     /// A function declaration.
     Function {
@@ -318,6 +325,7 @@ impl PrivMeta {
             PrivMetaKind::Unknown { type_hash, .. } => Some(*type_hash),
             PrivMetaKind::Struct { type_hash, .. } => Some(*type_hash),
             PrivMetaKind::Enum { type_hash, .. } => Some(*type_hash),
+            PrivMetaKind::Trait { type_hash, .. } => Some(*type_hash),
             PrivMetaKind::Function { type_hash, .. } => Some(*type_hash),
             PrivMetaKind::Closure { type_hash, .. } => Some(*type_hash),
             PrivMetaKind::AsyncBlock { type_hash, .. } => Some(*type_hash),
@@ -368,6 +376,8 @@ pub(crate) enum PrivMetaKind {
     Enum {
         /// The type hash associated with this meta kind.
         type_hash: Hash,
+        /// The names of the variants of the enum, in declaration order.
+        variants: Box<[Box<str>]>,
     },
     /// A function declaration.
     Function {
@@ -415,6 +425,12 @@ pub(crate) enum PrivMetaKind {
     },
     /// A module.
     Module,
+    /// A trait.
+    Trait {
+        /// The type hash associated with this meta kind, which is used to test
+        /// if a value implements the trait.
+        type_hash: Hash,
+    },
 }
 
 impl PrivMetaKind {
@@ -463,6 +479,7 @@ impl PrivMetaKind {
             PrivMetaKind::ConstFn { .. } => MetaKind::ConstFn,
             PrivMetaKind::Import { .. } => MetaKind::Import,
             PrivMetaKind::Module => MetaKind::Module,
+            PrivMetaKind::Trait { .. } => MetaKind::Trait,
         }
     }
 }
@@ -5,6 +5,7 @@
 
 use crate::ast;
 use crate::ast::{Span, Spanned};
+use crate::diagnostics::{SuggestedEdit, WarningDiagnosticKind};
 use crate::hir;
 use crate::macros::Storage;
 use crate::parse::Resolve;
@@ -245,7 +246,24 @@ impl CompileBuildEntry<'_> {
                 assemble::fn_from_item_fn(&hir, &mut c, false)?;
 
                 if used.is_unused() {
-                    self.diagnostics.not_used(location.source_id, span, None);
+                    let kind = WarningDiagnosticKind::NotUsed {
+                        span,
+                        context: None,
+                    };
+
+                    let name = f.ast.name.resolve(resolve_context!(self.q))?;
+
+                    if name.starts_with('_') {
+                        self.diagnostics.warning(location.source_id, kind);
+                    } else {
+                        let name = f.ast.name.span().head();
+
+                        self.diagnostics.warning_with_edit(
+                            location.source_id,
+                            kind,
+                            SuggestedEdit::replace("prefix with an underscore", name, "_"),
+                        );
+                    }
                 } else {
                     self.q.unit.new_function(
                         location,
@@ -298,6 +316,126 @@ impl CompileBuildEntry<'_> {
                         f.function.call,
                         args,
                     )?;
+
+                    if let Some(protocol) = f.protocol {
+                        self.q.unit.new_protocol_function(
+                            location,
+                            self.q.pool.item(item_meta.item),
+                            type_hash,
+                            protocol,
+                        )?;
+                    }
+                }
+            }
+            Build::TraitImpl(t) => {
+                tracing::trace!("trait impl: {}", self.q.pool.item(item_meta.item));
+
+                use self::v1::assemble;
+
+                let span = t.path.span();
+
+                let mut c = self.compiler1(location, span, &mut asm);
+
+                let arena = hir::Arena::new();
+                let ctx = hir::lowering::Ctx::new(&arena, c.q.borrow());
+                let path = hir::lowering::path(&ctx, &t.path)?;
+                let named = c.convert_path(&path)?;
+                named.assert_not_generic()?;
+
+                let meta = c.lookup_meta(span, named.item)?;
+
+                let trait_hash = match meta.kind {
+                    PrivMetaKind::Trait { type_hash } => type_hash,
+                    _ => {
+                        return Err(CompileError::expected_meta(
+                            span,
+                            meta.info(c.q.pool),
+                            "trait",
+                        ));
+                    }
+                };
+
+                let impl_meta = c.lookup_meta(t.impl_span, t.impl_item)?;
+
+                let type_hash = impl_meta.type_hash_of().ok_or_else(|| {
+                    CompileError::expected_meta(t.impl_span, impl_meta.info(c.q.pool), "type")
+                })?;
+
+                let trait_ = match c.q.trait_for(named.item) {
+                    Some(trait_) => trait_,
+                    None => {
+                        return Err(CompileError::new(
+                            span,
+                            CompileErrorKind::MissingItem {
+                                item: c.q.pool.item(named.item).to_owned(),
+                            },
+                        ));
+                    }
+                };
+
+                for (span, name) in &t.functions {
+                    if !trait_.contains(name) {
+                        return Err(CompileError::new(
+                            span,
+                            CompileErrorKind::NotTraitFn {
+                                trait_item: c.q.pool.item(named.item).to_owned(),
+                                name: name.clone(),
+                            },
+                        ));
+                    }
+                }
+
+                let defined = |name: &str| t.functions.iter().any(|(_, n)| &**n == name);
+
+                for name in &trait_.required {
+                    if !defined(name) {
+                        return Err(CompileError::new(
+                            t.impl_span,
+                            CompileErrorKind::MissingTraitFn {
+                                trait_item: c.q.pool.item(named.item).to_owned(),
+                                item: c.q.pool.item(t.impl_item).to_owned(),
+                                name: name.clone(),
+                            },
+                        ));
+                    }
+                }
+
+                self.q.unit.insert_trait_impl(type_hash, trait_hash);
+
+                // Default implementations which haven't been overridden are
+                // compiled separately for each implementing type.
+                for f in trait_.provided.iter().filter(|f| !defined(&f.name)) {
+                    let location = f.location;
+                    let span = f.function.ast.span();
+                    let count = f.function.ast.args.len();
+
+                    let args = format_fn_args(
+                        self.q.sources,
+                        location,
+                        f.function.ast.args.iter().map(|(a, _)| a),
+                    )?;
+
+                    let item = self.q.pool.item(t.impl_item).extended(&*f.name);
+                    let item = self.q.pool.alloc_item(item);
+
+                    let mut asm = self.q.unit.new_assembly(location);
+
+                    let arena = hir::Arena::new();
+                    let ctx = hir::lowering::Ctx::new(&arena, self.q.borrow());
+                    let hir = hir::lowering::item_fn(&ctx, &f.function.ast)?;
+                    let mut c = self.compiler1(location, span, &mut asm);
+                    assemble::fn_from_item_fn(&hir, &mut c, true)?;
+
+                    self.q.unit.new_instance_function(
+                        location,
+                        self.q.pool.item(item),
+                        type_hash,
+                        &f.name,
+                        count,
+                        asm,
+                        f.function.call,
+                        args,
+                    )?;
                 }
             }
             Build::Closure(closure) => {
@@ -403,6 +541,25 @@ impl CompileBuildEntry<'_> {
                         },
                     ));
                 }
+
+                // Visit the imported item at the location of the import.
+                let visited = result.filter(|_| self.q.visitor.visit_imports());
+
+                if let Some(item_id) = visited {
+                    if let Some(meta) = self.q.query_meta(location.span, item_id, used)? {
+                        self.q
+                            .visitor
+                            .visit_meta(location, meta.as_meta_ref(self.q.pool));
+                    } else if let Some(meta) = self.context.lookup_meta(self.q.pool.item(item_id)) {
+                        let meta = MetaRef {
+                            item: &meta.item,
+                            kind: meta.kind.as_meta_info_kind(),
+                            source: None,
+                        };
+
+                        self.q.visitor.visit_meta(location, meta);
+                    }
+                }
             }
             Build::ReExport => {
                 tracing::trace!("re-export: {}", self.q.pool.item(item_meta.item));
@@ -450,7 +607,7 @@ where
             ast::FnArg::SelfValue(..) => {
                 args.push("self".into());
             }
-            ast::FnArg::Pat(pat) => {
+            ast::FnArg::Pat(pat) | ast::FnArg::Typed(ast::FnArgTyped { pat, .. }) => {
                 let span = pat.span();
 
                 if let Some(s) = sources.source(location.source_id, span) {
@@ -22,6 +22,10 @@ pub struct Options {
     pub(crate) macros: bool,
     /// Support (experimental) bytecode caching.
     pub bytecode: bool,
+    /// Report non-exhaustive match expressions as errors instead of warnings.
+    pub(crate) deny_non_exhaustive: bool,
+    /// Check type annotations.
+    pub(crate) type_check: bool,
 
     /// Compile for and enable test features
     pub cfg_test: bool,
@@ -55,6 +59,12 @@ impl Options {
             Some("bytecode") => {
                 self.bytecode = it.next() != Some("false");
             }
+            Some("deny-non-exhaustive") => {
+                self.deny_non_exhaustive = it.next() != Some("false");
+            }
+            Some("type-check") => {
+                self.type_check = it.next() != Some("false");
+            }
             Some("test") => {
                 self.cfg_test = it.next() != Some("false");
             }
@@ -98,6 +108,23 @@ impl Options {
         self.bytecode = enabled;
     }
 
+    /// Set if non-exhaustive match expressions should be reported as errors
+    /// instead of warnings. Defaults to `false`.
+    pub fn deny_non_exhaustive(&mut self, enabled: bool) {
+        self.deny_non_exhaustive = enabled;
+    }
+
+    /// Set if type annotations should be checked. Defaults to `false`.
+    ///
+    /// Type annotations never affect how a program runs, so this only
+    /// determines if mismatches are reported as compile errors.
+    ///
+    /// Calls to native functions only have their number of arguments checked,
+    /// since the context doesn't record the types they accept or return.
+    pub fn type_check(&mut self, enabled: bool) {
+        self.type_check = enabled;
+    }
+
     /// Memoize the instance function in a loop. Defaults to `false`.
     pub fn memoize_instance_fn(&mut self, enabled: bool) {
         self.memoize_instance_fn = enabled;
@@ -112,6 +139,8 @@ impl Default for Options {
             debug_info: true,
             macros: true,
             bytecode: false,
+            deny_non_exhaustive: false,
+            type_check: false,
             cfg_test: false,
             v2: false,
         }
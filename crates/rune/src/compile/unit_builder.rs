@@ -4,7 +4,7 @@
 //! metadata like function locations.
 
 use crate::ast::Span;
-use crate::collections::HashMap;
+use crate::collections::{BTreeMap, HashMap, HashSet};
 use crate::compile::{
     Assembly, AssemblyInst, CompileError, CompileErrorKind, Item, ItemBuf, Location, Pool,
     PrivMeta, PrivMetaKind, PrivVariantMeta,
@@ -41,7 +41,7 @@ pub(crate) struct UnitBuilder {
     /// Where functions are located in the collection of instructions.
     functions: HashMap<Hash, UnitFn>,
     /// Function by address.
-    functions_rev: HashMap<usize, Hash>,
+    functions_rev: BTreeMap<usize, Hash>,
     /// A static string.
     static_strings: Vec<Arc<StaticString>>,
     /// Reverse lookup for static strings.
@@ -71,6 +71,8 @@ pub(crate) struct UnitBuilder {
     debug: Option<Box<DebugInfo>>,
     /// Constant values
     constants: HashMap<Hash, ConstValue>,
+    /// Traits implemented by types, as pairs of type and trait hashes.
+    impls: HashSet<(Hash, Hash)>,
 }
 
 impl UnitBuilder {
@@ -124,6 +126,7 @@ impl UnitBuilder {
             self.variant_rtti,
             self.debug,
             self.constants,
+            self.impls,
         ))
     }
 
@@ -506,7 +509,7 @@ impl UnitBuilder {
                     ));
                 }
             }
-            PrivMetaKind::Enum { type_hash } => {
+            PrivMetaKind::Enum { type_hash, .. } => {
                 self.constants.insert(
                     Hash::instance_function(type_hash, Protocol::INTO_TYPE_NAME),
                     ConstValue::String(pool.item(meta.item_meta.item).to_string()),
@@ -523,6 +526,7 @@ impl UnitBuilder {
             }
             PrivMetaKind::ConstFn { .. } => (),
             PrivMetaKind::Import { .. } => (),
+            PrivMetaKind::Trait { .. } => (),
             PrivMetaKind::Module { .. } => (),
         }
 
@@ -567,7 +571,7 @@ impl UnitBuilder {
 
         self.debug_info_mut().functions.insert(hash, signature);
 
-        self.add_assembly(location, assembly)?;
+        self.add_assembly(location, hash, assembly)?;
         Ok(())
     }
 
@@ -635,14 +639,59 @@ impl UnitBuilder {
             ConstValue::String(signature.path.to_string()),
         );
 
-        self.debug_info_mut()
-            .functions
-            .insert(instance_fn, signature);
+        let debug = self.debug_info_mut();
+        debug.functions.insert(hash, signature.clone());
+        debug.functions.insert(instance_fn, signature);
         self.functions_rev.insert(offset, hash);
-        self.add_assembly(location, assembly)?;
+        self.add_assembly(location, hash, assembly)?;
         Ok(())
     }
 
+    /// Register an instance function which has already been declared as the
+    /// implementation of the given protocol for the type with the given hash.
+    pub(crate) fn new_protocol_function(
+        &mut self,
+        location: Location,
+        item: &Item,
+        type_hash: Hash,
+        protocol: Protocol,
+    ) -> Result<(), CompileError> {
+        let hash = Hash::type_hash(item);
+        let protocol_fn = Hash::instance_function(type_hash, protocol);
+
+        let info = match self.functions.get(&hash) {
+            Some(info) => *info,
+            None => {
+                return Err(CompileError::new(
+                    location.span,
+                    CompileErrorKind::MissingFunctionHash { hash },
+                ));
+            }
+        };
+
+        let signature = self.debug_info_mut().functions.get(&hash).cloned();
+
+        if self.functions.insert(protocol_fn, info).is_some() {
+            return Err(CompileError::new(
+                location.span,
+                CompileErrorKind::FunctionConflictHash { hash: protocol_fn },
+            ));
+        }
+
+        if let Some(signature) = signature {
+            self.debug_info_mut()
+                .functions
+                .insert(protocol_fn, signature);
+        }
+
+        Ok(())
+    }
+
+    /// Record that the type with the given hash implements the given trait.
+    pub(crate) fn insert_trait_impl(&mut self, type_hash: Hash, trait_hash: Hash) {
+        self.impls.insert((type_hash, trait_hash));
+    }
+
     /// Try to link the unit with the context, checking that all necessary
     /// functions are provided.
     ///
@@ -666,9 +715,16 @@ impl UnitBuilder {
         self.debug.get_or_insert_with(Default::default)
     }
 
-    /// Translate the given assembly into instructions.
-    fn add_assembly(&mut self, location: Location, assembly: Assembly) -> Result<(), CompileError> {
+    /// Translate the given assembly of the function with the given hash into
+    /// instructions.
+    fn add_assembly(
+        &mut self,
+        location: Location,
+        hash: Hash,
+        assembly: Assembly,
+    ) -> Result<(), CompileError> {
         self.label_count = assembly.label_count;
+        let base = self.instructions.len();
 
         self.required_functions.extend(assembly.required_functions);
 
@@ -739,6 +795,17 @@ impl UnitBuilder {
             ));
         }
 
+        let mut locals = assembly.locals;
+
+        if !locals.is_empty() {
+            for local in &mut locals {
+                local.range = base + local.range.start..base + local.range.end;
+            }
+
+            locals.sort_by_key(|local| (local.range.start, local.offset));
+            self.debug_info_mut().locals.insert(hash, locals.into());
+        }
+
         return Ok(());
 
         fn translate_offset(
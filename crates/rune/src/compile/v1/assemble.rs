@@ -7,18 +7,19 @@ use rune_macros::__instrument_ast as instrument;
 use crate::ast;
 use crate::ast::{Span, Spanned};
 use crate::collections::{HashMap, HashSet};
-use crate::compile::v1::{Assembler, Loop, Needs, Scope, Var};
+use crate::compile::v1::{exhaustive, typeck, Assembler, Loop, Needs, Scope, Var};
 use crate::compile::{
-    CaptureMeta, CompileError, CompileErrorKind, CompileResult, Item, PrivMeta, PrivMetaKind,
-    PrivStructMeta, PrivVariantMeta,
+    CaptureMeta, CompileError, CompileErrorKind, CompileResult, Item, Location, PrivMeta,
+    PrivMetaKind, PrivStructMeta, PrivVariantMeta,
 };
+use crate::diagnostics::{SuggestedEdit, WarningDiagnosticKind};
 use crate::hash::ParametersBuilder;
 use crate::hir;
 use crate::parse::{Id, ParseErrorKind, Resolve};
 use crate::query::Named;
 use crate::runtime::{
-    ConstValue, Inst, InstAddress, InstAssignOp, InstOp, InstRangeLimits, InstTarget, InstValue,
-    InstVariant, Label, PanicReason, Protocol, TypeCheck,
+    ConstValue, Inst, InstAddress, InstAssignOp, InstOp, InstRange, InstRangeLimits, InstTarget,
+    InstValue, InstVariant, Label, PanicReason, Protocol, TypeCheck,
 };
 use crate::Hash;
 
@@ -294,7 +295,8 @@ fn pat(
 
             if let Some(ident) = named.as_local() {
                 load(c, Needs::Value)?;
-                c.scopes.decl_var(ident, span)?;
+                c.scopes
+                    .decl_var(c.q.visitor, c.asm, ident, c.source_id, span)?;
                 return Ok(false);
             }
 
@@ -316,6 +318,11 @@ fn pat(
             pat_object(span, c, hir, false_label, &load)?;
             Ok(true)
         }
+        hir::PatKind::PatRange(hir) => {
+            pat_range(span, c, hir, false_label, load)?;
+            Ok(true)
+        }
+        hir::PatKind::PatOr(branches) => Ok(pat_or(span, c, branches, false_label, load)?),
         _ => Err(CompileError::new(
             hir,
             CompileErrorKind::UnsupportedPatternExpr,
@@ -402,6 +409,205 @@ fn pat_lit_inst(
     Ok(None)
 }
 
+/// Assemble a range pattern like `1..=9`.
+#[instrument]
+fn pat_range(
+    span: Span,
+    c: &mut Assembler<'_>,
+    hir: &hir::PatRange<'_>,
+    false_label: Label,
+    load: &dyn Fn(&mut Assembler<'_>, Needs) -> CompileResult<()>,
+) -> CompileResult<()> {
+    let limits = match hir.limits {
+        hir::ExprRangeLimits::HalfOpen => InstRangeLimits::HalfOpen,
+        hir::ExprRangeLimits::Closed => InstRangeLimits::Closed,
+    };
+
+    let start = pat_lit_inst(span, c, hir.start)?;
+    let end = pat_lit_inst(span, c, hir.end)?;
+
+    let range = match (start, end) {
+        (Some(Inst::EqByte { byte: start }), Some(Inst::EqByte { byte: end })) => {
+            InstRange::Byte { start, end, limits }
+        }
+        (Some(Inst::EqChar { char: start }), Some(Inst::EqChar { char: end })) => {
+            InstRange::Char { start, end, limits }
+        }
+        (Some(Inst::EqInteger { integer: start }), Some(Inst::EqInteger { integer: end })) => {
+            InstRange::Integer { start, end, limits }
+        }
+        _ => {
+            return Err(CompileError::new(
+                span,
+                CompileErrorKind::UnsupportedPatternExpr,
+            ));
+        }
+    };
+
+    load(c, Needs::Value)?;
+    c.asm.push(Inst::MatchRange { range }, span);
+    c.asm
+        .pop_and_jump_if_not(c.scopes.local_var_count(span)?, false_label, span);
+    Ok(())
+}
+
+/// Assemble alternative patterns `A | B`.
+///
+/// The value being matched is stored in an anonymous slot and each
+/// alternative is tested against it in a child scope. The variables bound by
+/// the alternative which matched are then copied into slots shared by all
+/// alternatives.
+#[instrument]
+fn pat_or(
+    span: Span,
+    c: &mut Assembler<'_>,
+    branches: &[hir::Pat<'_>],
+    false_label: Label,
+    load: &dyn Fn(&mut Assembler<'_>, Needs) -> CompileResult<()>,
+) -> CompileResult<bool> {
+    let mut bindings = Vec::new();
+
+    for (index, branch) in branches.iter().enumerate() {
+        let mut names = Vec::new();
+        pat_bindings(branch, c, &mut names)?;
+
+        if index == 0 {
+            bindings = names;
+            continue;
+        }
+
+        for (name, span) in &names {
+            if !bindings.iter().any(|(n, _)| n == name) {
+                return Err(CompileError::new(
+                    *span,
+                    CompileErrorKind::OrPatternBindingMismatch { name: name.clone() },
+                ));
+            }
+        }
+
+        for (name, _) in &bindings {
+            if !names.iter().any(|(n, _)| n == name) {
+                return Err(CompileError::new(
+                    branch,
+                    CompileErrorKind::OrPatternBindingMismatch { name: name.clone() },
+                ));
+            }
+        }
+    }
+
+    load(c, Needs::Value)?;
+    let offset = c.scopes.decl_anon(span)?;
+
+    let mut slots = Vec::with_capacity(bindings.len());
+
+    for (name, span) in &bindings {
+        c.asm.push(Inst::unit(), *span);
+        slots.push(
+            c.scopes
+                .decl_var(c.q.visitor, c.asm, name, c.source_id, *span)?,
+        );
+    }
+
+    let end_label = c.asm.new_label("pat_or_end");
+    let mut refutable = true;
+
+    for branch in branches {
+        let span = branch.span();
+        let next_label = c.asm.new_label("pat_or_next");
+        let guard = c.scopes.push_child(span)?;
+
+        let load = move |c: &mut Assembler<'_>, needs: Needs| {
+            if needs.value() {
+                c.asm.push(Inst::Copy { offset }, span);
+            }
+
+            Ok(())
+        };
+
+        refutable = pat(branch, c, next_label, &load)?;
+
+        for ((name, _), slot) in bindings.iter().zip(&slots) {
+            let var = c.scopes.get_var(c.q.visitor, name, c.source_id, span)?;
+            var.copy(c, span, format!("var `{}`", name));
+            c.asm.push(Inst::Replace { offset: *slot }, span);
+        }
+
+        let count = c.scopes.local_var_count(span)?;
+        c.locals_pop(count, span);
+        c.scopes.pop(c.asm, guard, span)?;
+        c.asm.jump(end_label, span);
+
+        if !refutable {
+            break;
+        }
+
+        c.asm.label(next_label)?;
+    }
+
+    if refutable {
+        let count = c.scopes.local_var_count(span)?;
+        c.locals_pop(count, span);
+        c.asm.jump(false_label, span);
+    }
+
+    c.asm.label(end_label)?;
+    Ok(refutable)
+}
+
+/// Collect the variables bound by a pattern in the order they are declared.
+fn pat_bindings(
+    hir: &hir::Pat<'_>,
+    c: &mut Assembler<'_>,
+    out: &mut Vec<(Box<str>, Span)>,
+) -> CompileResult<()> {
+    let span = hir.span();
+
+    match hir.kind {
+        hir::PatKind::PatPath(path) => {
+            let named = c.convert_path(path)?;
+
+            if let Some(meta) = c.try_lookup_meta(span, named.item)? {
+                if matches!(tuple_match_for(span, c, &meta), Some((0, _))) {
+                    return Ok(());
+                }
+            }
+
+            if let Some(ident) = named.as_local() {
+                out.push((ident.into(), span));
+            }
+        }
+        hir::PatKind::PatVec(hir) | hir::PatKind::PatTuple(hir) => {
+            for hir in hir.items {
+                pat_bindings(hir, c, out)?;
+            }
+        }
+        hir::PatKind::PatObject(hir) => {
+            for hir in hir.items {
+                match hir.kind {
+                    hir::PatKind::PatPath(path) => {
+                        if let Some(ident) = path.try_as_ident() {
+                            let ident = ident.resolve(resolve_context!(c.q))?;
+                            out.push((ident.into(), hir.span()));
+                        }
+                    }
+                    hir::PatKind::PatBinding(binding) => {
+                        pat_bindings(binding.pat, c, out)?;
+                    }
+                    _ => (),
+                }
+            }
+        }
+        hir::PatKind::PatOr(branches) => {
+            if let Some(hir) = branches.first() {
+                pat_bindings(hir, c, out)?;
+            }
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
 /// Assemble an [hir::Condition<'_>].
 #[instrument]
 fn condition(
@@ -424,7 +630,7 @@ fn condition(
             let false_label = c.asm.new_label("if_condition_false");
 
             let scope = c.scopes.child(span)?;
-            let expected = c.scopes.push(scope);
+            let expected = c.scopes.push(c.asm, scope);
 
             let load = |c: &mut Assembler<'_>, needs: Needs| {
                 expr(expr_let.expr, c, needs)?.apply(c)?;
@@ -438,7 +644,7 @@ fn condition(
                 c.asm.jump(then_label, span);
             };
 
-            let scope = c.scopes.pop(expected, span)?;
+            let scope = c.scopes.pop(c.asm, expected, span)?;
             Ok(scope)
         }
     }
@@ -753,6 +959,12 @@ fn pat_object(
             let mut fields = st.fields.clone();
 
             for binding in &bindings {
+                c.q.visitor.visit_field_use(
+                    Location::new(c.source_id, binding.span()),
+                    c.q.pool.item(meta.item_meta.item),
+                    binding.key(),
+                );
+
                 if !fields.remove(binding.key()) {
                     return Err(CompileError::new(
                         span,
@@ -819,7 +1031,8 @@ fn pat_object(
             }
             Binding::Ident(_, key) => {
                 c.asm.push(Inst::ObjectIndexGetAt { offset, slot }, span);
-                c.scopes.decl_var(key, span)?;
+                c.scopes
+                    .decl_var(c.q.visitor, c.asm, key, c.source_id, span)?;
             }
         }
     }
@@ -883,11 +1096,12 @@ pub(crate) fn closure_from_block(
     let guard = c.scopes.push_child(span)?;
 
     for capture in captures {
-        c.scopes.new_var(&capture.ident, span)?;
+        c.scopes.new_var(c.asm, &capture.ident, span)?;
     }
 
     return_(c, span, hir, block)?;
-    c.scopes.pop(guard, span)?;
+    c.scopes.pop(c.asm, guard, span)?;
+    c.warn_unused_vars();
     Ok(())
 }
 
@@ -935,7 +1149,7 @@ fn block(hir: &hir::Block<'_>, c: &mut Assembler<'_>, needs: Needs) -> CompileRe
         false
     };
 
-    let scope = c.scopes.pop(scopes_count, span)?;
+    let scope = c.scopes.pop(c.asm, scopes_count, span)?;
 
     if needs.value() {
         if produced {
@@ -1026,11 +1240,13 @@ fn builtin_template(
     let expected = c.scopes.push_child(span)?;
     let mut size_hint = 0;
     let mut expansions = 0;
+    let mut literal = String::new();
 
     for hir in template.exprs {
         if let hir::ExprKind::Lit(ast::Lit::Str(s)) = hir.kind {
             let s = s.resolve_template_string(resolve_context!(c.q))?;
             size_hint += s.len();
+            literal.push_str(&s);
 
             let slot = c.q.unit.new_static_string(span, &s)?;
             c.asm.push(Inst::String { slot }, span);
@@ -1045,8 +1261,13 @@ fn builtin_template(
     }
 
     if template.from_literal && expansions == 0 {
-        c.diagnostics
-            .template_without_expansions(c.source_id, span, c.context());
+        let context = c.context();
+
+        c.diagnostics.warning_with_edit(
+            c.source_id,
+            WarningDiagnosticKind::TemplateWithoutExpansions { span, context },
+            SuggestedEdit::replace("convert into a string literal", span, str_literal(&literal)),
+        );
     }
 
     c.asm.push(
@@ -1061,10 +1282,33 @@ fn builtin_template(
         c.asm.push(Inst::Pop, span);
     }
 
-    let _ = c.scopes.pop(expected, span)?;
+    let _ = c.scopes.pop(c.asm, expected, span)?;
     Ok(Asm::top(span))
 }
 
+/// Construct the source of a string literal with the given value, escaped
+/// according to the escape sequences supported by Rune.
+fn str_literal(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\0' => out.push_str("\\0"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
 /// Assemble a constant value.
 #[instrument]
 fn const_(
@@ -1389,7 +1633,7 @@ fn expr_binary(
         c.asm.push(Inst::Pop, span);
     }
 
-    c.scopes.pop(guard, span)?;
+    c.scopes.pop(c.asm, guard, span)?;
     return Ok(Asm::top(span));
 
     /// Get the need of the right-hand side operator from the type of the
@@ -1964,7 +2208,7 @@ pub(crate) fn closure_from_expr_closure(
             hir::FnArg::SelfValue(s) => {
                 return Err(CompileError::new(s, CompileErrorKind::UnsupportedSelf))
             }
-            hir::FnArg::Pat(pat) => {
+            hir::FnArg::Pat(pat) | hir::FnArg::Typed(hir::FnArgTyped { pat, .. }) => {
                 let offset = c.scopes.decl_anon(pat.span())?;
                 patterns.push((pat, offset));
             }
@@ -1975,7 +2219,7 @@ pub(crate) fn closure_from_expr_closure(
         c.asm.push(Inst::PushTuple, span);
 
         for capture in captures {
-            c.scopes.new_var(&capture.ident, span)?;
+            c.scopes.new_var(c.asm, &capture.ident, span)?;
         }
     }
 
@@ -1984,7 +2228,8 @@ pub(crate) fn closure_from_expr_closure(
     }
 
     return_(c, span, hir.body, expr)?;
-    c.scopes.pop_last(span)?;
+    c.scopes.pop_last(c.asm, span)?;
+    c.warn_unused_vars();
     Ok(())
 }
 
@@ -2412,7 +2657,7 @@ fn expr_if(
 
     c.asm.label(then_label)?;
 
-    let expected = c.scopes.push(then_scope);
+    let expected = c.scopes.push(c.asm, then_scope);
     block(hir.block, c, needs)?.apply(c)?;
     c.clean_last_scope(span, expected, needs)?;
 
@@ -2427,7 +2672,7 @@ fn expr_if(
 
         c.asm.label(label)?;
 
-        let scopes = c.scopes.push(scope);
+        let scopes = c.scopes.push(c.asm, scope);
         block(branch.block, c, needs)?.apply(c)?;
         c.clean_last_scope(span, scopes, needs)?;
 
@@ -2461,7 +2706,7 @@ fn expr_index(
         c.asm.push(Inst::Pop, span);
     }
 
-    c.scopes.pop(guard, span)?;
+    c.scopes.pop(c.asm, guard, span)?;
     Ok(Asm::top(span))
 }
 
@@ -2477,8 +2722,12 @@ fn expr_let(hir: &hir::ExprLet<'_>, c: &mut Assembler<'_>, needs: Needs) -> Comp
     };
 
     let false_label = c.asm.new_label("let_panic");
+    let offset = c.scopes.total_var_count(span)?;
 
-    if pat(hir.pat, c, false_label, &load)? {
+    let might_panic = pat(hir.pat, c, false_label, &load)?;
+    c.scopes.check_unused_since(offset, span)?;
+
+    if might_panic {
         c.diagnostics
             .let_pattern_might_panic(c.source_id, span, c.context());
 
@@ -2526,7 +2775,7 @@ fn expr_match(
         let match_false = c.asm.new_label("match_false");
 
         let scope = c.scopes.child(span)?;
-        let parent_guard = c.scopes.push(scope);
+        let parent_guard = c.scopes.push(c.asm, scope);
 
         let load = move |this: &mut Assembler, needs: Needs| {
             if needs.value() {
@@ -2542,11 +2791,11 @@ fn expr_match(
             let span = condition.span();
 
             let scope = c.scopes.child(span)?;
-            let guard = c.scopes.push(scope);
+            let guard = c.scopes.push(c.asm, scope);
 
             expr(condition, c, Needs::Value)?.apply(c)?;
             c.clean_last_scope(span, guard, Needs::Value)?;
-            let scope = c.scopes.pop(parent_guard, span)?;
+            let scope = c.scopes.pop(c.asm, parent_guard, span)?;
 
             c.asm
                 .pop_and_jump_if_not(scope.local_var_count, match_false, span);
@@ -2554,7 +2803,7 @@ fn expr_match(
             c.asm.jump(branch_label, span);
             scope
         } else {
-            c.scopes.pop(parent_guard, span)?
+            c.scopes.pop(c.asm, parent_guard, span)?
         };
 
         c.asm.jump(branch_label, span);
@@ -2578,7 +2827,7 @@ fn expr_match(
 
         c.asm.label(*label)?;
 
-        let expected = c.scopes.push(scope.clone());
+        let expected = c.scopes.push(c.asm, scope.clone());
         expr(branch.body, c, needs)?.apply(c)?;
         c.clean_last_scope(span, expected, needs)?;
 
@@ -2591,6 +2840,8 @@ fn expr_match(
 
     // pop the implicit scope where we store the anonymous match variable.
     c.clean_last_scope(span, expected_scopes, needs)?;
+
+    exhaustive::check(span, c, hir)?;
     Ok(Asm::top(span))
 }
 
@@ -2650,6 +2901,17 @@ fn expr_object(
             named.assert_not_generic()?;
 
             let meta = c.lookup_meta(path.span(), named.item)?;
+
+            for assign in hir.assignments {
+                let key = assign.key.resolve(resolve_context!(c.q))?;
+
+                c.q.visitor.visit_field_use(
+                    Location::new(c.source_id, assign.span()),
+                    c.q.pool.item(meta.item_meta.item),
+                    key.as_ref(),
+                );
+            }
+
             let item = c.q.pool.item(meta.item_meta.item);
 
             match &meta.kind {
@@ -2701,7 +2963,7 @@ fn expr_object(
         c.asm.push(Inst::Pop, span);
     }
 
-    c.scopes.pop(guard, span)?;
+    c.scopes.pop(c.asm, guard, span)?;
     return Ok(Asm::top(span));
 
     fn check_object_fields(
@@ -2862,7 +3124,7 @@ fn expr_range(
         }
     }
 
-    c.scopes.pop(guard, span)?;
+    c.scopes.pop(c.asm, guard, span)?;
     Ok(Asm::top(span))
 }
 
@@ -2966,7 +3228,8 @@ fn expr_select(
                     named.assert_not_generic()?;
 
                     if let Some(local) = named.as_local() {
-                        c.scopes.decl_var(local, path.span())?;
+                        c.scopes
+                            .decl_var(c.q.visitor, c.asm, local, c.source_id, path.span())?;
                         break;
                     }
                 }
@@ -3063,7 +3326,7 @@ fn expr_tuple(
                 $span,
             );
 
-            $c.scopes.pop(guard, $span)?;
+            $c.scopes.pop($c.asm, guard, $span)?;
         }};
     }
 
@@ -3220,7 +3483,7 @@ fn expr_loop(
 
     let expected = if let Some(hir) = hir.condition {
         let then_scope = condition(hir, c, then_label)?;
-        let expected = c.scopes.push(then_scope);
+        let expected = c.scopes.push(c.asm, then_scope);
 
         c.asm.jump(end_label, span);
         c.asm.label(then_label)?;
@@ -3278,6 +3541,10 @@ pub(crate) fn fn_from_item_fn(
 ) -> CompileResult<()> {
     let span = hir.span();
 
+    if c.options.type_check {
+        typeck::item_fn(c, hir)?;
+    }
+
     let mut patterns = Vec::new();
     let mut first = true;
 
@@ -3288,9 +3555,9 @@ pub(crate) fn fn_from_item_fn(
                     return Err(CompileError::new(*span, CompileErrorKind::UnsupportedSelf));
                 }
 
-                c.scopes.new_var(SELF, *span)?;
+                c.scopes.new_var(c.asm, SELF, *span)?;
             }
-            hir::FnArg::Pat(pat) => {
+            hir::FnArg::Pat(pat) | hir::FnArg::Typed(hir::FnArgTyped { pat, .. }) => {
                 let offset = c.scopes.decl_anon(pat.span())?;
                 patterns.push((pat, offset));
             }
@@ -3307,6 +3574,8 @@ pub(crate) fn fn_from_item_fn(
         let total_var_count = c.scopes.total_var_count(span)?;
         c.locals_pop(total_var_count, span);
         c.asm.push(Inst::ReturnUnit, span);
+        c.scopes.pop_last(c.asm, span)?;
+        c.warn_unused_vars();
         return Ok(());
     }
 
@@ -3320,7 +3589,8 @@ pub(crate) fn fn_from_item_fn(
         c.asm.push(Inst::ReturnUnit, span);
     }
 
-    c.scopes.pop_last(span)?;
+    c.scopes.pop_last(c.asm, span)?;
+    c.warn_unused_vars();
     Ok(())
 }
 
@@ -3427,8 +3697,12 @@ fn local(hir: &hir::Local<'_>, c: &mut Assembler<'_>, needs: Needs) -> CompileRe
     };
 
     let false_label = c.asm.new_label("let_panic");
+    let offset = c.scopes.total_var_count(span)?;
+
+    let might_panic = pat(hir.pat, c, false_label, &load)?;
+    c.scopes.check_unused_since(offset, span)?;
 
-    if pat(hir.pat, c, false_label, &load)? {
+    if might_panic {
         c.diagnostics
             .let_pattern_might_panic(c.source_id, span, c.context());
 
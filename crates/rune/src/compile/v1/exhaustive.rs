@@ -0,0 +1,859 @@
+//! Exhaustiveness and reachability checking of match expressions.
+//!
+//! This implements the usefulness algorithm described in "Warnings for pattern
+//! matching" by Luc Maranget. Since values are dynamically typed, the type of
+//! a value is inferred from the constructors it's matched against. Only bools,
+//! enums, structs and fixed-size tuples are considered to have a finite set of
+//! constructors, everything else is assumed to never be exhaustively matched.
+
+use std::fmt;
+
+use crate::ast::{self, Span, Spanned};
+use crate::collections::HashMap;
+use crate::compile::v1::Assembler;
+use crate::compile::{
+    CompileError, CompileErrorKind, CompileResult, ItemId, PrivMeta, PrivMetaKind, PrivVariantMeta,
+};
+use crate::hir;
+use crate::parse::Resolve;
+use crate::query::Used;
+use crate::Hash;
+
+/// The maximum number of missing patterns we collect.
+const MAX_WITNESSES: usize = 8;
+
+/// Check the arms of a match expression, warning about unreachable arms and
+/// reporting missing patterns.
+pub(crate) fn check(
+    span: Span,
+    c: &mut Assembler<'_>,
+    hir: &hir::ExprMatch<'_>,
+) -> CompileResult<()> {
+    let mut cx = Lowering {
+        c,
+        types: Types::default(),
+        opaque: 0,
+    };
+
+    let mut arms = Vec::with_capacity(hir.branches.len());
+
+    for branch in hir.branches {
+        match cx.pat(branch.pat)? {
+            Some(pat) => arms.push((branch, pat)),
+            // NB: pattern contains something we can't reason about.
+            None => return Ok(()),
+        }
+    }
+
+    let Lowering { c, types, .. } = cx;
+
+    let mut rows = Vec::new();
+
+    for (branch, pat) in arms {
+        let row = vec![pat];
+
+        if !types.is_useful(&rows, &row) {
+            c.diagnostics
+                .unreachable_pattern(c.source_id, branch.pat.span(), c.context());
+        }
+
+        if branch.condition.is_none() {
+            rows.push(row);
+        }
+    }
+
+    let witnesses = types.witnesses(&rows, 1);
+
+    // Only report missing patterns if we know that the matched value belongs
+    // to a type with a finite number of values.
+    if !witnesses.iter().flatten().any(Pat::is_enumerable) {
+        return Ok(());
+    }
+
+    let missing = witnesses
+        .iter()
+        .flatten()
+        .map(|pat| Box::from(Display { types: &types, pat }.to_string()))
+        .collect::<Box<[Box<str>]>>();
+
+    if c.options.deny_non_exhaustive {
+        return Err(CompileError::new(
+            span,
+            CompileErrorKind::MatchNotExhaustive { missing },
+        ));
+    }
+
+    c.diagnostics
+        .match_not_exhaustive(c.source_id, span, missing, c.context());
+    Ok(())
+}
+
+/// A constructor of a value.
+#[derive(Debug, Clone, PartialEq)]
+enum Ctor {
+    /// A boolean.
+    Bool(bool),
+    /// A variant of an enum.
+    Variant { enum_hash: Hash, index: usize },
+    /// A struct, which only has a single constructor.
+    Struct { hash: Hash },
+    /// An anonymous tuple with the given number of elements.
+    Tuple(usize),
+    /// A literal from a domain which can't be enumerated, like integers or
+    /// strings.
+    Lit(Lit),
+    /// A pattern which is never equal to any other constructor, like a range
+    /// or a vector.
+    Opaque(usize),
+}
+
+/// A literal which is part of a pattern.
+#[derive(Debug, Clone, PartialEq)]
+enum Lit {
+    Integer(i64),
+    Byte(u8),
+    Char(char),
+    Str(Box<str>),
+    Bytes(Box<[u8]>),
+}
+
+/// A simplified pattern.
+#[derive(Debug, Clone)]
+enum Pat {
+    /// A pattern matching anything.
+    Wild,
+    /// A constructor with the given arguments.
+    Ctor(Ctor, Vec<Pat>),
+    /// Alternative patterns.
+    Or(Vec<Pat>),
+}
+
+impl Pat {
+    /// Test if the pattern refers to a type with an enumerable set of values.
+    fn is_enumerable(&self) -> bool {
+        match self {
+            Pat::Wild => false,
+            Pat::Ctor(Ctor::Bool(..) | Ctor::Variant { .. }, _) => true,
+            Pat::Ctor(_, args) => args.iter().any(Pat::is_enumerable),
+            Pat::Or(pats) => pats.iter().any(Pat::is_enumerable),
+        }
+    }
+}
+
+/// Information on a constructor of a known type.
+#[derive(Debug)]
+struct CtorInfo {
+    /// The name of the constructor, like `Option::Some`.
+    name: Box<str>,
+    /// The number of arguments the constructor takes.
+    arity: usize,
+    /// Field names, in the order of arguments, if the constructor is
+    /// struct-like.
+    fields: Option<Box<[Box<str>]>>,
+}
+
+/// The types referenced by the patterns being checked.
+#[derive(Default)]
+struct Types {
+    /// Known variants of enums. Only present if all variants could be
+    /// resolved.
+    enums: HashMap<Hash, Vec<CtorInfo>>,
+    /// Known structs.
+    structs: HashMap<Hash, CtorInfo>,
+    /// Variants which have been seen in patterns, used for display if the full
+    /// enum isn't known.
+    variants: HashMap<(Hash, usize), CtorInfo>,
+}
+
+impl Types {
+    /// Get information on the given constructor.
+    fn info(&self, ctor: &Ctor) -> Option<&CtorInfo> {
+        match ctor {
+            Ctor::Variant { enum_hash, index } => self
+                .enums
+                .get(enum_hash)
+                .and_then(|variants| variants.get(*index))
+                .or_else(|| self.variants.get(&(*enum_hash, *index))),
+            Ctor::Struct { hash } => self.structs.get(hash),
+            _ => None,
+        }
+    }
+
+    /// The number of arguments taken by the given constructor.
+    fn arity(&self, ctor: &Ctor) -> usize {
+        match ctor {
+            Ctor::Tuple(n) => *n,
+            ctor => self.info(ctor).map(|info| info.arity).unwrap_or_default(),
+        }
+    }
+
+    /// Get all constructors of the type the given constructors belong to, if
+    /// the type has a finite number of constructors which are all known.
+    fn all_ctors(&self, seen: &[Ctor]) -> Option<Vec<Ctor>> {
+        let all = match seen.first()? {
+            Ctor::Bool(..) => vec![Ctor::Bool(true), Ctor::Bool(false)],
+            Ctor::Variant { enum_hash, .. } => {
+                let variants = self.enums.get(enum_hash)?;
+
+                (0..variants.len())
+                    .map(|index| Ctor::Variant {
+                        enum_hash: *enum_hash,
+                        index,
+                    })
+                    .collect()
+            }
+            ctor @ (Ctor::Struct { .. } | Ctor::Tuple(..)) => vec![ctor.clone()],
+            Ctor::Lit(..) | Ctor::Opaque(..) => return None,
+        };
+
+        // NB: patterns of different types are matched against the same value,
+        // so the set of constructors can't be determined.
+        if !seen.iter().all(|ctor| all.contains(ctor)) {
+            return None;
+        }
+
+        Some(all)
+    }
+
+    /// Test if the pattern vector `q` matches any value which isn't matched by
+    /// the given rows.
+    fn is_useful(&self, rows: &[Vec<Pat>], q: &[Pat]) -> bool {
+        let (head, rest) = match q.split_first() {
+            Some(split) => split,
+            None => return rows.is_empty(),
+        };
+
+        match head {
+            Pat::Or(pats) => pats.iter().any(|pat| {
+                let q = prepend(vec![pat.clone()], rest);
+                self.is_useful(rows, &q)
+            }),
+            Pat::Ctor(ctor, args) => {
+                let rows = self.specialize(rows, ctor);
+                let q = prepend(args.clone(), rest);
+                self.is_useful(&rows, &q)
+            }
+            Pat::Wild => {
+                let seen = head_ctors(rows);
+
+                match self.all_ctors(&seen) {
+                    Some(all) if all.iter().all(|ctor| seen.contains(ctor)) => {
+                        all.iter().any(|ctor| {
+                            let rows = self.specialize(rows, ctor);
+                            let q = prepend(vec![Pat::Wild; self.arity(ctor)], rest);
+                            self.is_useful(&rows, &q)
+                        })
+                    }
+                    _ => self.is_useful(&default_rows(rows), rest),
+                }
+            }
+        }
+    }
+
+    /// Collect pattern vectors of length `n` which aren't matched by any of
+    /// the given rows.
+    fn witnesses(&self, rows: &[Vec<Pat>], n: usize) -> Vec<Vec<Pat>> {
+        if n == 0 {
+            return if rows.is_empty() {
+                vec![Vec::new()]
+            } else {
+                Vec::new()
+            };
+        }
+
+        let seen = head_ctors(rows);
+        let all = self.all_ctors(&seen);
+        let mut out = Vec::new();
+
+        match all {
+            Some(all) if all.iter().all(|ctor| seen.contains(ctor)) => {
+                for ctor in all {
+                    let arity = self.arity(&ctor);
+                    let rows = self.specialize(rows, &ctor);
+
+                    for mut w in self.witnesses(&rows, arity + n - 1) {
+                        let rest = w.split_off(arity);
+                        out.push(prepend(vec![Pat::Ctor(ctor.clone(), w)], &rest));
+                    }
+
+                    if out.len() >= MAX_WITNESSES {
+                        break;
+                    }
+                }
+            }
+            all => {
+                let witnesses = self.witnesses(&default_rows(rows), n - 1);
+
+                if witnesses.is_empty() {
+                    return out;
+                }
+
+                let heads = match all {
+                    Some(all) if !seen.is_empty() => all
+                        .into_iter()
+                        .filter(|ctor| !seen.contains(ctor))
+                        .map(|ctor| Pat::Ctor(ctor.clone(), vec![Pat::Wild; self.arity(&ctor)]))
+                        .collect(),
+                    _ => vec![Pat::Wild],
+                };
+
+                for head in heads {
+                    for w in &witnesses {
+                        out.push(prepend(vec![head.clone()], w));
+                    }
+                }
+            }
+        }
+
+        out.truncate(MAX_WITNESSES);
+        out
+    }
+
+    /// Specialize the given rows for the given constructor, keeping only the
+    /// rows which match it and replacing their head with its arguments.
+    fn specialize(&self, rows: &[Vec<Pat>], ctor: &Ctor) -> Vec<Vec<Pat>> {
+        let arity = self.arity(ctor);
+        let mut out = Vec::new();
+
+        for row in rows {
+            self.specialize_row(row, ctor, arity, &mut out);
+        }
+
+        out
+    }
+
+    fn specialize_row(&self, row: &[Pat], ctor: &Ctor, arity: usize, out: &mut Vec<Vec<Pat>>) {
+        let (head, rest) = match row.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+
+        match head {
+            Pat::Wild => out.push(prepend(vec![Pat::Wild; arity], rest)),
+            Pat::Ctor(other, args) => {
+                if other == ctor {
+                    out.push(prepend(args.clone(), rest));
+                }
+            }
+            Pat::Or(pats) => {
+                for pat in pats {
+                    self.specialize_row(&prepend(vec![pat.clone()], rest), ctor, arity, out);
+                }
+            }
+        }
+    }
+}
+
+/// Construct a new pattern vector from `head` followed by `rest`.
+fn prepend(mut head: Vec<Pat>, rest: &[Pat]) -> Vec<Pat> {
+    head.extend(rest.iter().cloned());
+    head
+}
+
+/// Collect the distinct constructors at the head of the given rows.
+fn head_ctors(rows: &[Vec<Pat>]) -> Vec<Ctor> {
+    fn collect(pat: &Pat, out: &mut Vec<Ctor>) {
+        match pat {
+            Pat::Wild => (),
+            Pat::Ctor(ctor, _) => {
+                if !out.contains(ctor) {
+                    out.push(ctor.clone());
+                }
+            }
+            Pat::Or(pats) => {
+                for pat in pats {
+                    collect(pat, out);
+                }
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+
+    for pat in rows.iter().filter_map(|row| row.first()) {
+        collect(pat, &mut out);
+    }
+
+    out
+}
+
+/// The rows which match anything in their first column, with that column
+/// removed.
+fn default_rows(rows: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    fn collect(row: &[Pat], out: &mut Vec<Vec<Pat>>) {
+        let (head, rest) = match row.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+
+        match head {
+            Pat::Wild => out.push(rest.to_vec()),
+            Pat::Ctor(..) => (),
+            Pat::Or(pats) => {
+                for pat in pats {
+                    collect(&prepend(vec![pat.clone()], rest), out);
+                }
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+
+    for row in rows {
+        collect(row, &mut out);
+    }
+
+    out
+}
+
+/// Lowering of [hir::Pat] into simplified patterns.
+struct Lowering<'a, 'b> {
+    c: &'a mut Assembler<'b>,
+    types: Types,
+    opaque: usize,
+}
+
+impl Lowering<'_, '_> {
+    /// Lower the given pattern. Returns `None` if the pattern contains
+    /// something which can't be analysed.
+    fn pat(&mut self, hir: &hir::Pat<'_>) -> CompileResult<Option<Pat>> {
+        let span = hir.span();
+
+        Ok(Some(match hir.kind {
+            hir::PatKind::PatIgnore | hir::PatKind::PatRest => Pat::Wild,
+            hir::PatKind::PatPath(path) => {
+                let named = self.c.convert_path(path)?;
+
+                if let Some(meta) = self.lookup_meta(span, named.item, Used::Used)? {
+                    if let Some((ctor, 0)) = self.tuple_ctor(&meta) {
+                        return Ok(Some(Pat::Ctor(ctor, Vec::new())));
+                    }
+                }
+
+                if named.as_local().is_some() {
+                    Pat::Wild
+                } else {
+                    return Ok(None);
+                }
+            }
+            hir::PatKind::PatLit(hir) => match self.lit(span, hir)? {
+                Some(pat) => pat,
+                None => return Ok(None),
+            },
+            hir::PatKind::PatTuple(items) => {
+                let pats = match self.items(items)? {
+                    Some(pats) => pats,
+                    None => return Ok(None),
+                };
+
+                let (ctor, arity) = match items.path {
+                    Some(path) => {
+                        let named = self.c.convert_path(path)?;
+
+                        let meta = match self.lookup_meta(span, named.item, Used::Used)? {
+                            Some(meta) => meta,
+                            None => return Ok(None),
+                        };
+
+                        match self.tuple_ctor(&meta) {
+                            Some(out) => out,
+                            None => return Ok(None),
+                        }
+                    }
+                    None if items.is_open => return Ok(Some(self.opaque())),
+                    None => (Ctor::Tuple(items.count), items.count),
+                };
+
+                match pad(pats, arity, items.is_open) {
+                    Some(pats) => Pat::Ctor(ctor, pats),
+                    None => return Ok(None),
+                }
+            }
+            hir::PatKind::PatObject(items) => {
+                let path = match items.path {
+                    Some(path) => path,
+                    None => return Ok(Some(self.opaque())),
+                };
+
+                let named = self.c.convert_path(path)?;
+
+                let meta = match self.lookup_meta(span, named.item, Used::Used)? {
+                    Some(meta) => meta,
+                    None => return Ok(None),
+                };
+
+                let (ctor, fields) = match self.struct_ctor(&meta) {
+                    Some(out) => out,
+                    None => return Ok(None),
+                };
+
+                let mut pats = vec![Pat::Wild; fields.len()];
+
+                for item in items.items.iter().take(items.count) {
+                    let (key, pat) = match item.kind {
+                        hir::PatKind::PatBinding(binding) => {
+                            let key = binding
+                                .key
+                                .resolve(resolve_context!(self.c.q))?
+                                .into_owned();
+
+                            let pat = match self.pat(binding.pat)? {
+                                Some(pat) => pat,
+                                None => return Ok(None),
+                            };
+
+                            (key, pat)
+                        }
+                        hir::PatKind::PatPath(path) => match path.try_as_ident() {
+                            Some(ident) => {
+                                let key = ident.resolve(resolve_context!(self.c.q))?;
+                                (key.to_owned(), Pat::Wild)
+                            }
+                            None => return Ok(None),
+                        },
+                        _ => return Ok(None),
+                    };
+
+                    match fields.iter().position(|field| field.as_ref() == key) {
+                        Some(index) => pats[index] = pat,
+                        None => return Ok(None),
+                    }
+                }
+
+                Pat::Ctor(ctor, pats)
+            }
+            hir::PatKind::PatOr(branches) => {
+                let mut pats = Vec::with_capacity(branches.len());
+
+                for branch in branches {
+                    match self.pat(branch)? {
+                        Some(pat) => pats.push(pat),
+                        None => return Ok(None),
+                    }
+                }
+
+                Pat::Or(pats)
+            }
+            hir::PatKind::PatVec(..) | hir::PatKind::PatRange(..) => self.opaque(),
+            hir::PatKind::PatBinding(..) => return Ok(None),
+        }))
+    }
+
+    /// Lower the items of a tuple pattern.
+    fn items(&mut self, items: &hir::PatItems<'_>) -> CompileResult<Option<Vec<Pat>>> {
+        let mut pats = Vec::with_capacity(items.count);
+
+        for item in items.items.iter().take(items.count) {
+            match self.pat(item)? {
+                Some(pat) => pats.push(pat),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(pats))
+    }
+
+    /// Lower a literal pattern.
+    fn lit(&mut self, span: Span, hir: &hir::Expr<'_>) -> CompileResult<Option<Pat>> {
+        let lit = match hir.kind {
+            hir::ExprKind::Unary(hir::ExprUnary {
+                op: ast::UnOp::Neg(..),
+                expr:
+                    hir::Expr {
+                        kind: hir::ExprKind::Lit(ast::Lit::Number(lit)),
+                        ..
+                    },
+                ..
+            }) => {
+                let number = lit.resolve(resolve_context!(self.c.q))?;
+                Lit::Integer(number.as_i64(span, true)?)
+            }
+            hir::ExprKind::Lit(lit) => match lit {
+                ast::Lit::Bool(lit) => {
+                    return Ok(Some(Pat::Ctor(Ctor::Bool(lit.value), Vec::new())))
+                }
+                ast::Lit::Byte(lit) => Lit::Byte(lit.resolve(resolve_context!(self.c.q))?),
+                ast::Lit::Char(lit) => Lit::Char(lit.resolve(resolve_context!(self.c.q))?),
+                ast::Lit::Str(lit) => {
+                    Lit::Str(lit.resolve(resolve_context!(self.c.q))?.as_ref().into())
+                }
+                ast::Lit::ByteStr(lit) => {
+                    Lit::Bytes(lit.resolve(resolve_context!(self.c.q))?.as_ref().into())
+                }
+                ast::Lit::Number(lit) => {
+                    let number = lit.resolve(resolve_context!(self.c.q))?;
+                    Lit::Integer(number.as_i64(span, false)?)
+                }
+            },
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Pat::Ctor(Ctor::Lit(lit), Vec::new())))
+    }
+
+    /// Construct a pattern which is distinct from every other pattern.
+    fn opaque(&mut self) -> Pat {
+        self.opaque += 1;
+        Pat::Ctor(Ctor::Opaque(self.opaque), Vec::new())
+    }
+
+    /// Get the constructor and the number of arguments for meta which can be
+    /// used in a tuple pattern.
+    fn tuple_ctor(&mut self, meta: &PrivMeta) -> Option<(Ctor, usize)> {
+        let (ctor, info) = self.ctor(meta)?;
+
+        if info.fields.is_some() {
+            return None;
+        }
+
+        Some((ctor, info.arity))
+    }
+
+    /// Get the constructor and the fields for meta which can be used in a
+    /// struct pattern.
+    fn struct_ctor(&mut self, meta: &PrivMeta) -> Option<(Ctor, Box<[Box<str>]>)> {
+        let (ctor, info) = self.ctor(meta)?;
+        let fields = info.fields.clone()?;
+        Some((ctor, fields))
+    }
+
+    /// Get the constructor for the given meta, registering the type it belongs
+    /// to.
+    fn ctor(&mut self, meta: &PrivMeta) -> Option<(Ctor, &CtorInfo)> {
+        match &meta.kind {
+            PrivMetaKind::Struct {
+                type_hash, variant, ..
+            } => {
+                let hash = *type_hash;
+                let name = self.c.q.pool.item(meta.item_meta.item).last()?.to_string();
+
+                let info = self
+                    .types
+                    .structs
+                    .entry(hash)
+                    .or_insert_with(|| ctor_info(name.into(), variant));
+
+                Some((Ctor::Struct { hash }, info))
+            }
+            PrivMetaKind::Variant {
+                enum_item,
+                enum_hash,
+                index,
+                variant,
+                ..
+            } => {
+                let (enum_hash, index) = (*enum_hash, *index);
+                self.load_enum(meta.item_meta.location.span, *enum_item, enum_hash);
+
+                let name = variant_name(self.c, *enum_item, meta.item_meta.item)?;
+
+                let info = self
+                    .types
+                    .variants
+                    .entry((enum_hash, index))
+                    .or_insert_with(|| ctor_info(name.into(), variant));
+
+                Some((Ctor::Variant { enum_hash, index }, info))
+            }
+            _ => None,
+        }
+    }
+
+    /// Load all variants of the given enum. If this fails the enum is treated
+    /// as if it had an unknown set of variants.
+    fn load_enum(&mut self, span: Span, enum_item: ItemId, enum_hash: Hash) {
+        if self.types.enums.contains_key(&enum_hash) {
+            return;
+        }
+
+        if let Some(variants) = self.try_load_enum(span, enum_item) {
+            self.types.enums.insert(enum_hash, variants);
+        }
+    }
+
+    fn try_load_enum(&mut self, span: Span, enum_item: ItemId) -> Option<Vec<CtorInfo>> {
+        let meta = self.lookup_meta(span, enum_item, Used::Unused).ok()??;
+
+        let names = match meta.kind {
+            PrivMetaKind::Enum { variants, .. } => variants,
+            _ => return None,
+        };
+
+        let mut variants = Vec::with_capacity(names.len());
+
+        for (index, name) in names.iter().enumerate() {
+            let item = self.c.q.pool.item(enum_item).extended(name.as_ref());
+            let item = self.c.q.pool.alloc_item(&item);
+            let meta = self.lookup_meta(span, item, Used::Unused).ok()??;
+
+            match meta.kind {
+                PrivMetaKind::Variant {
+                    index: actual,
+                    ref variant,
+                    ..
+                } if actual == index => {
+                    let name = variant_name(self.c, enum_item, item)?;
+                    variants.push(ctor_info(name.into(), variant));
+                }
+                _ => return None,
+            }
+        }
+
+        Some(variants)
+    }
+
+    /// Look up meta without notifying the compile visitor.
+    fn lookup_meta(
+        &mut self,
+        span: Span,
+        item: ItemId,
+        used: Used,
+    ) -> CompileResult<Option<PrivMeta>> {
+        if let Some(meta) = self.c.q.query_meta(span, item, used)? {
+            return Ok(Some(meta));
+        }
+
+        if let Some(meta) = self.c.context.lookup_meta(self.c.q.pool.item(item)) {
+            return Ok(Some(self.c.q.insert_context_meta(span, meta)?));
+        }
+
+        Ok(None)
+    }
+}
+
+/// Pad the given patterns with wildcards to match the given arity.
+fn pad(mut pats: Vec<Pat>, arity: usize, is_open: bool) -> Option<Vec<Pat>> {
+    if pats.len() == arity || pats.len() < arity && is_open {
+        pats.resize(arity, Pat::Wild);
+        Some(pats)
+    } else {
+        None
+    }
+}
+
+/// Construct the display name of a variant, like `Option::Some`.
+fn variant_name(c: &Assembler<'_>, enum_item: ItemId, item: ItemId) -> Option<String> {
+    let enum_name = c.q.pool.item(enum_item).last()?;
+    let variant_name = c.q.pool.item(item).last()?;
+    Some(format!("{}::{}", enum_name, variant_name))
+}
+
+fn ctor_info(name: Box<str>, variant: &PrivVariantMeta) -> CtorInfo {
+    match variant {
+        PrivVariantMeta::Unit => CtorInfo {
+            name,
+            arity: 0,
+            fields: None,
+        },
+        PrivVariantMeta::Tuple(tuple) => CtorInfo {
+            name,
+            arity: tuple.args,
+            fields: None,
+        },
+        PrivVariantMeta::Struct(st) => {
+            let mut fields = st.fields.iter().cloned().collect::<Vec<_>>();
+            fields.sort();
+
+            CtorInfo {
+                name,
+                arity: fields.len(),
+                fields: Some(fields.into()),
+            }
+        }
+    }
+}
+
+/// Helper to display a missing pattern.
+struct Display<'a> {
+    types: &'a Types,
+    pat: &'a Pat,
+}
+
+impl Display<'_> {
+    fn with<'a>(&'a self, pat: &'a Pat) -> Display<'a> {
+        Display {
+            types: self.types,
+            pat,
+        }
+    }
+}
+
+impl fmt::Display for Display<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (ctor, args) = match self.pat {
+            Pat::Wild => return write!(f, "_"),
+            Pat::Ctor(ctor, args) => (ctor, args),
+            Pat::Or(pats) => {
+                let mut it = pats.iter().peekable();
+
+                while let Some(pat) = it.next() {
+                    write!(f, "{}", self.with(pat))?;
+
+                    if it.peek().is_some() {
+                        write!(f, " | ")?;
+                    }
+                }
+
+                return Ok(());
+            }
+        };
+
+        match ctor {
+            Ctor::Bool(value) => write!(f, "{}", value),
+            Ctor::Tuple(..) => {
+                write!(f, "(")?;
+
+                for (index, arg) in args.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{}", self.with(arg))?;
+                }
+
+                if args.len() == 1 {
+                    write!(f, ",")?;
+                }
+
+                write!(f, ")")
+            }
+            Ctor::Lit(..) | Ctor::Opaque(..) => write!(f, "_"),
+            ctor => {
+                let info = match self.types.info(ctor) {
+                    Some(info) => info,
+                    None => return write!(f, "_"),
+                };
+
+                write!(f, "{}", info.name)?;
+
+                match &info.fields {
+                    Some(fields) => {
+                        write!(f, " {{ ")?;
+
+                        for (field, arg) in fields.iter().zip(args) {
+                            if !matches!(arg, Pat::Wild) {
+                                write!(f, "{}: {}, ", field, self.with(arg))?;
+                            }
+                        }
+
+                        write!(f, ".. }}")
+                    }
+                    None if args.is_empty() => Ok(()),
+                    None => {
+                        write!(f, "(")?;
+
+                        for (index, arg) in args.iter().enumerate() {
+                            if index > 0 {
+                                write!(f, ", ")?;
+                            }
+
+                            write!(f, "{}", self.with(arg))?;
+                        }
+
+                        write!(f, ")")
+                    }
+                }
+            }
+        }
+    }
+}
@@ -3,14 +3,17 @@ use crate::compile::{
     ir, Assembly, CompileError, CompileErrorKind, CompileResult, IrBudget, IrCompiler,
     IrInterpreter, ItemId, ItemMeta, Location, Options, PrivMeta,
 };
+use crate::diagnostics::{SuggestedEdit, WarningDiagnosticKind};
 use crate::hir;
 use crate::query::{Named, Query, QueryConstFn, Used};
 use crate::runtime::{ConstValue, Inst};
 use crate::{Context, Diagnostics, SourceId};
 
 pub(crate) mod assemble;
+mod exhaustive;
 mod loops;
 mod scopes;
+mod typeck;
 
 pub(crate) use self::loops::{Loop, Loops};
 pub(crate) use self::scopes::{Scope, ScopeGuard, Scopes, Var};
@@ -136,7 +139,7 @@ impl<'a> Assembler<'a> {
         expected: ScopeGuard,
         needs: Needs,
     ) -> CompileResult<()> {
-        let scope = self.scopes.pop(expected, span)?;
+        let scope = self.scopes.pop(self.asm, expected, span)?;
 
         if needs.value() {
             self.locals_clean(scope.local_var_count, span);
@@ -147,6 +150,21 @@ impl<'a> Assembler<'a> {
         Ok(())
     }
 
+    /// Warn about variables bound by `let` which went out of scope without
+    /// being used.
+    pub(crate) fn warn_unused_vars(&mut self) {
+        for span in self.scopes.take_unused() {
+            self.diagnostics.warning_with_edit(
+                self.source_id,
+                WarningDiagnosticKind::NotUsed {
+                    span,
+                    context: None,
+                },
+                SuggestedEdit::replace("prefix with an underscore", span.head(), "_"),
+            );
+        }
+    }
+
     /// Get the latest relevant warning context.
     pub(crate) fn context(&self) -> Option<Span> {
         self.contexts.last().copied()
@@ -2,7 +2,7 @@ use crate::ast::Span;
 use crate::collections::HashMap;
 use crate::compile::v1::Assembler;
 use crate::compile::{Assembly, CompileError, CompileErrorKind, CompileResult, CompileVisitor};
-use crate::runtime::Inst;
+use crate::runtime::{DebugLocal, Inst};
 use crate::SourceId;
 
 /// A locally declared variable, its calculated stack offset and where it was
@@ -15,6 +15,12 @@ pub struct Var {
     span: Span,
     /// Variable has been taken at the given position.
     moved_at: Option<Span>,
+    /// The instruction from which the variable is live.
+    start: usize,
+    /// If the variable has been used.
+    used: bool,
+    /// If the variable should be reported if it's never used.
+    check_unused: bool,
 }
 
 impl Var {
@@ -45,6 +51,16 @@ impl Var {
             comment,
         );
     }
+
+    /// Record the variable as live up until the current instruction.
+    fn close(&self, asm: &mut Assembly, name: &str) {
+        let end = asm.instructions.len();
+
+        if self.start < end {
+            asm.locals
+                .push(DebugLocal::new(name.into(), self.offset, self.start..end));
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -77,13 +93,16 @@ impl Scope {
     }
 
     /// Insert a new local, and return the old one if there's a conflict.
-    fn new_var(&mut self, name: &str, span: Span) -> CompileResult<usize> {
+    fn new_var(&mut self, name: &str, span: Span, start: usize) -> CompileResult<usize> {
         let offset = self.total_var_count;
 
         let local = Var {
             offset,
             span,
             moved_at: None,
+            start,
+            used: false,
+            check_unused: false,
         };
 
         self.total_var_count += 1;
@@ -102,24 +121,27 @@ impl Scope {
         Ok(offset)
     }
 
-    /// Insert a new local, and return the old one if there's a conflict.
-    fn decl_var(&mut self, name: &str, span: Span) -> usize {
+    /// Insert a new local, and return the old one if it was shadowed.
+    fn decl_var(&mut self, name: &str, span: Span, start: usize) -> (usize, Option<Var>) {
         let offset = self.total_var_count;
 
         tracing::trace!("decl {} => {}", name, offset);
 
-        self.locals.insert(
+        let old = self.locals.insert(
             name.to_owned(),
             Var {
                 offset,
                 span,
                 moved_at: None,
+                start,
+                used: false,
+                check_unused: false,
             },
         );
 
         self.total_var_count += 1;
         self.local_var_count += 1;
-        offset
+        (offset, old)
     }
 
     /// Declare an anonymous variable.
@@ -148,8 +170,8 @@ impl Scope {
     }
 
     /// Access the variable with the given name.
-    fn get(&self, name: &str, span: Span) -> CompileResult<Option<Var>> {
-        if let Some(var) = self.locals.get(name) {
+    fn get(&mut self, name: &str, span: Span) -> CompileResult<Option<Var>> {
+        if let Some(var) = self.locals.get_mut(name) {
             if let Some(moved_at) = var.moved_at {
                 return Err(CompileError::new(
                     span,
@@ -157,6 +179,7 @@ impl Scope {
                 ));
             }
 
+            var.used = true;
             return Ok(Some(*var));
         }
 
@@ -174,6 +197,7 @@ impl Scope {
             }
 
             var.moved_at = Some(span);
+            var.used = true;
             return Ok(Some(var));
         }
 
@@ -190,6 +214,8 @@ pub(crate) struct ScopeGuard(usize);
 
 pub(crate) struct Scopes {
     scopes: Vec<Scope>,
+    /// Spans of variables which went out of scope without being used.
+    unused: Vec<Span>,
 }
 
 impl Scopes {
@@ -197,13 +223,14 @@ impl Scopes {
     pub(crate) fn new() -> Self {
         Self {
             scopes: vec![Scope::new()],
+            unused: Vec::new(),
         }
     }
 
     /// Try to get the local with the given name. Returns `None` if it's
     /// missing.
     pub(crate) fn try_get_var(
-        &self,
+        &mut self,
         visitor: &mut dyn CompileVisitor,
         name: &str,
         source_id: SourceId,
@@ -211,7 +238,7 @@ impl Scopes {
     ) -> CompileResult<Option<Var>> {
         tracing::trace!("get var: {}", name);
 
-        for scope in self.scopes.iter().rev() {
+        for scope in self.scopes.iter_mut().rev() {
             if let Some(var) = scope.get(name, span)? {
                 tracing::trace!("found var: {} => {:?}", name, var);
                 visitor.visit_variable_use(source_id, var.span, span);
@@ -246,7 +273,7 @@ impl Scopes {
 
     /// Get the local with the given name.
     pub(crate) fn get_var(
-        &self,
+        &mut self,
         visitor: &mut dyn CompileVisitor,
         name: &str,
         source_id: SourceId,
@@ -283,13 +310,62 @@ impl Scopes {
     }
 
     /// Construct a new variable.
-    pub(crate) fn new_var(&mut self, name: &str, span: Span) -> CompileResult<usize> {
-        self.last_mut(span)?.new_var(name, span)
+    pub(crate) fn new_var(
+        &mut self,
+        asm: &Assembly,
+        name: &str,
+        span: Span,
+    ) -> CompileResult<usize> {
+        let start = asm.instructions.len();
+        self.last_mut(span)?.new_var(name, span, start)
     }
 
     /// Declare the given variable.
-    pub(crate) fn decl_var(&mut self, name: &str, span: Span) -> CompileResult<usize> {
-        Ok(self.last_mut(span)?.decl_var(name, span))
+    pub(crate) fn decl_var(
+        &mut self,
+        visitor: &mut dyn CompileVisitor,
+        asm: &mut Assembly,
+        name: &str,
+        source_id: SourceId,
+        span: Span,
+    ) -> CompileResult<usize> {
+        let start = asm.instructions.len();
+        let (offset, old) = self.last_mut(span)?.decl_var(name, span, start);
+        visitor.visit_variable_decl(source_id, span);
+
+        if let Some(old) = old {
+            old.close(asm, name);
+            self.check_unused(old);
+        }
+
+        Ok(offset)
+    }
+
+    /// Mark the named variables declared in the last scope since the given
+    /// offset, like the ones bound by a `let` pattern, to be reported if
+    /// they're never used. Variables prefixed with `_` are exempt.
+    pub(crate) fn check_unused_since(&mut self, offset: usize, span: Span) -> CompileResult<()> {
+        for (name, var) in &mut self.last_mut(span)?.locals {
+            if var.offset >= offset && !name.starts_with('_') {
+                var.check_unused = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Take the spans of variables which went out of scope without being
+    /// used.
+    pub(crate) fn take_unused(&mut self) -> Vec<Span> {
+        let mut unused = std::mem::take(&mut self.unused);
+        unused.sort_by_key(|span| span.start);
+        unused
+    }
+
+    fn check_unused(&mut self, var: Var) {
+        if var.check_unused && !var.used {
+            self.unused.push(var.span);
+        }
     }
 
     /// Declare an anonymous variable.
@@ -303,13 +379,26 @@ impl Scopes {
     }
 
     /// Push a scope and return an index.
-    pub(crate) fn push(&mut self, scope: Scope) -> ScopeGuard {
+    ///
+    /// The variables in the scope become live at the current instruction.
+    pub(crate) fn push(&mut self, asm: &Assembly, mut scope: Scope) -> ScopeGuard {
+        let start = asm.instructions.len();
+
+        for var in scope.locals.values_mut() {
+            var.start = start;
+        }
+
         self.scopes.push(scope);
         ScopeGuard(self.scopes.len())
     }
 
     /// Pop the last scope and compare with the expected length.
-    pub(crate) fn pop(&mut self, expected: ScopeGuard, span: Span) -> CompileResult<Scope> {
+    pub(crate) fn pop(
+        &mut self,
+        asm: &mut Assembly,
+        expected: ScopeGuard,
+        span: Span,
+    ) -> CompileResult<Scope> {
         let ScopeGuard(expected) = expected;
 
         if self.scopes.len() != expected {
@@ -319,28 +408,36 @@ impl Scopes {
             ));
         }
 
-        self.pop_unchecked(span)
+        self.pop_unchecked(asm, span)
     }
 
     /// Pop the last of the scope.
-    pub(crate) fn pop_last(&mut self, span: Span) -> CompileResult<Scope> {
-        self.pop(ScopeGuard(1), span)
+    pub(crate) fn pop_last(&mut self, asm: &mut Assembly, span: Span) -> CompileResult<Scope> {
+        self.pop(asm, ScopeGuard(1), span)
     }
 
     /// Pop the last scope and compare with the expected length.
-    pub(crate) fn pop_unchecked(&mut self, span: Span) -> CompileResult<Scope> {
+    ///
+    /// The variables in the scope stop being live at the current instruction.
+    pub(crate) fn pop_unchecked(&mut self, asm: &mut Assembly, span: Span) -> CompileResult<Scope> {
         let scope = self
             .scopes
             .pop()
             .ok_or_else(|| CompileError::msg(&span, "missing parent scope"))?;
 
+        for (name, var) in &scope.locals {
+            var.close(asm, name);
+            self.check_unused(*var);
+        }
+
         Ok(scope)
     }
 
     /// Construct a new child scope and return its guard.
     pub(crate) fn push_child(&mut self, span: Span) -> CompileResult<ScopeGuard> {
         let scope = self.last(span)?.child();
-        Ok(self.push(scope))
+        self.scopes.push(scope);
+        Ok(ScopeGuard(self.scopes.len()))
     }
 
     /// Construct a new child scope.
@@ -0,0 +1,982 @@
+//! Checking of optional type annotations.
+//!
+//! Types are inferred from literals, constructors, calls to annotated
+//! functions and annotated variables. Everything else is considered to be of
+//! an unknown type which is compatible with any annotation, so that only
+//! mismatches we can be sure about are reported. The check never affects the
+//! code being generated.
+//!
+//! Native functions registered in the context don't record the types of their
+//! arguments or their return value, so for calls to them only the number of
+//! arguments is checked and the result is of an unknown type.
+
+use std::fmt;
+use std::rc::Rc;
+
+use crate::ast::{self, Span, Spanned};
+use crate::collections::HashMap;
+use crate::compile::context::ContextSignature;
+use crate::compile::v1::Assembler;
+use crate::compile::{
+    CompileError, CompileErrorKind, CompileResult, ItemId, PrivMeta, PrivMetaKind, PrivVariantMeta,
+};
+use crate::hir;
+use crate::parse::Resolve;
+use crate::query::Used;
+use crate::runtime::{
+    StaticType, BOOL_TYPE, BYTES_TYPE, BYTE_TYPE, CHAR_TYPE, FLOAT_TYPE, INTEGER_TYPE, OBJECT_TYPE,
+    STRING_TYPE, TUPLE_TYPE, VEC_TYPE,
+};
+use crate::Hash;
+
+/// Check the type annotations of a function item.
+pub(crate) fn item_fn(c: &mut Assembler<'_>, hir: &hir::ItemFn<'_>) -> CompileResult<()> {
+    let mut cx = Checker::new(c);
+
+    if let Some(output) = hir.output {
+        cx.output = Some(cx.ty(output, true)?);
+    }
+
+    for arg in hir.args {
+        match arg {
+            hir::FnArg::SelfValue(..) => (),
+            hir::FnArg::Pat(pat) => cx.bind(pat, Ty::Unknown, false)?,
+            hir::FnArg::Typed(typed) => {
+                let ty = cx.ty(typed.ty, true)?;
+                cx.bind(typed.pat, ty, true)?;
+            }
+        }
+    }
+
+    let actual = cx.block(hir.body)?;
+
+    if let Some(span) = hir.body.statements.last().map(Spanned::span) {
+        cx.check_output(span, &actual);
+    }
+
+    cx.finish();
+    Ok(())
+}
+
+/// A type which has been inferred or resolved from an annotation.
+#[derive(Debug, Clone)]
+enum Ty {
+    /// A type which could not be determined.
+    Unknown,
+    /// A named type.
+    Type {
+        hash: Hash,
+        item: Option<ItemId>,
+        name: Box<str>,
+    },
+    /// An anonymous tuple, the empty tuple being the unit type.
+    Tuple(Vec<Ty>),
+}
+
+impl Ty {
+    /// Test if a value of type `actual` can be used where `self` is expected.
+    fn accepts(&self, actual: &Ty) -> bool {
+        match (self, actual) {
+            (Ty::Unknown, _) | (_, Ty::Unknown) => true,
+            (Ty::Type { hash: a, .. }, Ty::Type { hash: b, .. }) => a == b,
+            (Ty::Tuple(a), Ty::Tuple(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.accepts(b))
+            }
+            (Ty::Type { hash, .. }, Ty::Tuple(..)) | (Ty::Tuple(..), Ty::Type { hash, .. }) => {
+                *hash == TUPLE_TYPE.hash
+            }
+        }
+    }
+
+    fn is_known(&self) -> bool {
+        !matches!(self, Ty::Unknown)
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Unknown => write!(f, "_"),
+            Ty::Type { name, .. } => write!(f, "{}", name),
+            Ty::Tuple(items) => {
+                write!(f, "(")?;
+
+                let mut it = items.iter();
+
+                if let Some(item) = it.next() {
+                    write!(f, "{}", item)?;
+                }
+
+                for item in it {
+                    write!(f, ", {}", item)?;
+                }
+
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// The resolved signature of an annotated function.
+struct Signature {
+    /// Types of the arguments, `self` is always unknown.
+    args: Vec<Ty>,
+    /// The type being returned.
+    output: Ty,
+}
+
+/// A variable in scope.
+struct Var {
+    name: Box<str>,
+    ty: Ty,
+    /// If the variable was annotated, in which case assignments to it are
+    /// checked against its type.
+    annotated: bool,
+}
+
+struct Checker<'a, 'b> {
+    c: &'a mut Assembler<'b>,
+    /// Variables in scope, from outermost to innermost.
+    vars: Vec<Var>,
+    /// The expected return type of the function.
+    output: Option<Ty>,
+    /// Mismatches against the return type. These are only reported once we
+    /// know that the function is not a generator.
+    output_errors: Vec<CompileError>,
+    /// If the function yields, in which case it's a generator.
+    yields: bool,
+    /// Resolved signatures of called functions.
+    signatures: HashMap<ItemId, Option<Rc<Signature>>>,
+}
+
+impl<'a, 'b> Checker<'a, 'b> {
+    fn new(c: &'a mut Assembler<'b>) -> Self {
+        Self {
+            c,
+            vars: Vec::new(),
+            output: None,
+            output_errors: Vec::new(),
+            yields: false,
+            signatures: HashMap::new(),
+        }
+    }
+
+    /// Report any deferred errors.
+    fn finish(self) {
+        if self.yields {
+            return;
+        }
+
+        for error in self.output_errors {
+            self.c.diagnostics.error(self.c.source_id, error);
+        }
+    }
+
+    /// Report a type mismatch unless `actual` is compatible with `expected`.
+    fn check(&mut self, span: Span, expected: &Ty, actual: &Ty) {
+        if let Some(error) = mismatch(span, expected, actual) {
+            self.c.diagnostics.error(self.c.source_id, error);
+        }
+    }
+
+    /// Check a value being returned from the function.
+    fn check_output(&mut self, span: Span, actual: &Ty) {
+        if let Some(expected) = &self.output {
+            if let Some(error) = mismatch(span, expected, actual) {
+                self.output_errors.push(error);
+            }
+        }
+    }
+
+    /// Resolve a type annotation. If `report` is set, annotations which can't
+    /// be resolved are reported as errors.
+    fn ty(&mut self, hir: &hir::Type<'_>, report: bool) -> CompileResult<Ty> {
+        let span = hir.span();
+
+        let path = match hir.kind {
+            hir::TypeKind::Path(path) => path,
+            hir::TypeKind::Tuple(items) => {
+                let mut tys = Vec::with_capacity(items.len());
+
+                for item in items {
+                    tys.push(self.ty(item, report)?);
+                }
+
+                return Ok(Ty::Tuple(tys));
+            }
+        };
+
+        let named = self.c.convert_path(path)?;
+
+        if named.generics.is_some() {
+            return Ok(Ty::Unknown);
+        }
+
+        let meta = match self.lookup_meta(span, named.item, Used::Used)? {
+            Some(meta) => meta,
+            None => {
+                if report {
+                    let item = self.c.q.pool.item(named.item).to_owned();
+                    self.c.diagnostics.error(
+                        self.c.source_id,
+                        CompileError::new(span, CompileErrorKind::MissingItem { item }),
+                    );
+                }
+
+                return Ok(Ty::Unknown);
+            }
+        };
+
+        match meta.kind {
+            PrivMetaKind::Struct { type_hash, .. }
+            | PrivMetaKind::Enum { type_hash, .. }
+            | PrivMetaKind::Unknown { type_hash } => Ok(self.named(type_hash, meta.item_meta.item)),
+            _ => {
+                if report {
+                    let error =
+                        CompileError::expected_meta(span, meta.info(self.c.q.pool), "a type");
+                    self.c.diagnostics.error(self.c.source_id, error);
+                }
+
+                Ok(Ty::Unknown)
+            }
+        }
+    }
+
+    /// Construct the type declared by the given item.
+    fn named(&self, hash: Hash, item: ItemId) -> Ty {
+        let name = match self.c.q.pool.item(item).last() {
+            Some(name) => name.to_string(),
+            None => String::from("_"),
+        };
+
+        Ty::Type {
+            hash,
+            item: Some(item),
+            name: name.into(),
+        }
+    }
+
+    /// Construct a builtin type, named after how it's declared in the
+    /// context.
+    fn builtin(&self, ty: &StaticType) -> Ty {
+        let name = match self
+            .c
+            .context
+            .lookup_type_by_type_hash(ty.hash)
+            .and_then(|info| info.item.last())
+        {
+            Some(name) => name.to_string(),
+            None => ty.name.to_string(),
+        };
+
+        Ty::Type {
+            hash: ty.hash,
+            item: None,
+            name: name.into(),
+        }
+    }
+
+    /// The type of values constructed through the given meta, like a struct
+    /// or an enum variant.
+    fn constructed(&mut self, span: Span, meta: &PrivMeta) -> CompileResult<Ty> {
+        match &meta.kind {
+            PrivMetaKind::Struct { type_hash, .. } => {
+                Ok(self.named(*type_hash, meta.item_meta.item))
+            }
+            PrivMetaKind::Variant { enum_item, .. } => {
+                let enum_item = *enum_item;
+
+                match self.lookup_meta(span, enum_item, Used::Unused)? {
+                    Some(PrivMeta {
+                        kind: PrivMetaKind::Enum { type_hash, .. },
+                        ..
+                    }) => Ok(self.named(type_hash, enum_item)),
+                    _ => Ok(Ty::Unknown),
+                }
+            }
+            _ => Ok(Ty::Unknown),
+        }
+    }
+
+    /// Look up the resolved signature of an annotated script function.
+    fn signature(&mut self, item: ItemId) -> CompileResult<Option<Rc<Signature>>> {
+        if let Some(signature) = self.signatures.get(&item) {
+            return Ok(signature.clone());
+        }
+
+        let signature = match self.c.q.fn_signature_for(item) {
+            Some(signature) => signature,
+            None => {
+                self.signatures.insert(item, None);
+                return Ok(None);
+            }
+        };
+
+        let arena = hir::Arena::new();
+
+        let (args, output) = {
+            let ctx = hir::lowering::Ctx::new(&arena, self.c.q.borrow());
+
+            let mut args = Vec::with_capacity(signature.args.len());
+
+            for arg in signature.args.iter() {
+                args.push(match arg {
+                    Some(ty) => Some(hir::lowering::ty(&ctx, ty)?),
+                    None => None,
+                });
+            }
+
+            let output = match &signature.output {
+                Some(ty) => Some(hir::lowering::ty(&ctx, ty)?),
+                None => None,
+            };
+
+            (args, output)
+        };
+
+        let mut resolved = Signature {
+            args: Vec::with_capacity(args.len()),
+            output: Ty::Unknown,
+        };
+
+        // NB: errors in the annotations are reported when the function itself
+        // is checked.
+        for arg in &args {
+            resolved.args.push(match arg {
+                Some(ty) => self.ty(ty, false)?,
+                None => Ty::Unknown,
+            });
+        }
+
+        // Calling an async function produces a future, which we don't track
+        // the type of.
+        if !signature.is_async {
+            if let Some(output) = &output {
+                resolved.output = self.ty(output, false)?;
+            }
+        }
+
+        let resolved = Some(Rc::new(resolved));
+        self.signatures.insert(item, resolved.clone());
+        Ok(resolved)
+    }
+
+    /// Look up meta without notifying the compile visitor.
+    fn lookup_meta(
+        &mut self,
+        span: Span,
+        item: ItemId,
+        used: Used,
+    ) -> CompileResult<Option<PrivMeta>> {
+        if let Some(meta) = self.c.q.query_meta(span, item, used)? {
+            return Ok(Some(meta));
+        }
+
+        if let Some(meta) = self.c.context.lookup_meta(self.c.q.pool.item(item)) {
+            return Ok(Some(self.c.q.insert_context_meta(span, meta)?));
+        }
+
+        Ok(None)
+    }
+
+    /// Look up the variable with the given name.
+    fn var(&self, name: &str) -> Option<&Var> {
+        self.vars.iter().rev().find(|var| &*var.name == name)
+    }
+
+    fn declare(&mut self, name: &str, ty: Ty, annotated: bool) {
+        self.vars.push(Var {
+            name: name.into(),
+            ty,
+            annotated,
+        });
+    }
+
+    /// Bind the variables declared in the given pattern, destructuring the
+    /// known type of the value being matched where possible.
+    fn bind(&mut self, pat: &hir::Pat<'_>, ty: Ty, annotated: bool) -> CompileResult<()> {
+        let span = pat.span();
+
+        match pat.kind {
+            hir::PatKind::PatPath(path) => {
+                let named = self.c.convert_path(path)?;
+
+                if let Some(meta) = self.lookup_meta(span, named.item, Used::Used)? {
+                    if matches!(
+                        meta.kind,
+                        PrivMetaKind::Struct {
+                            variant: PrivVariantMeta::Unit,
+                            ..
+                        } | PrivMetaKind::Variant {
+                            variant: PrivVariantMeta::Unit,
+                            ..
+                        }
+                    ) {
+                        return Ok(());
+                    }
+                }
+
+                if let Some(name) = named.as_local() {
+                    let annotated = annotated && ty.is_known();
+                    self.declare(name, ty, annotated);
+                }
+            }
+            hir::PatKind::PatTuple(items) if items.path.is_none() && !items.is_open => {
+                let tys = match ty {
+                    Ty::Tuple(tys) if tys.len() == items.count => tys,
+                    _ => vec![Ty::Unknown; items.count],
+                };
+
+                for (pat, ty) in items.items.iter().zip(tys) {
+                    self.bind(pat, ty, annotated)?;
+                }
+            }
+            hir::PatKind::PatTuple(items)
+            | hir::PatKind::PatVec(items)
+            | hir::PatKind::PatObject(items) => {
+                for pat in items.items {
+                    self.bind(pat, Ty::Unknown, false)?;
+                }
+            }
+            hir::PatKind::PatBinding(binding) => {
+                self.bind(binding.pat, Ty::Unknown, false)?;
+            }
+            hir::PatKind::PatOr(pats) => {
+                // NB: all alternatives bind the same variables.
+                if let Some(pat) = pats.first() {
+                    self.bind(pat, Ty::Unknown, false)?;
+                }
+            }
+            hir::PatKind::PatIgnore
+            | hir::PatKind::PatRest
+            | hir::PatKind::PatLit(..)
+            | hir::PatKind::PatRange(..) => (),
+        }
+
+        Ok(())
+    }
+
+    /// Check a block and return the type of its value.
+    fn block(&mut self, hir: &hir::Block<'_>) -> CompileResult<Ty> {
+        let scope = self.vars.len();
+        let mut ty = Ty::Unknown;
+
+        for (n, stmt) in hir.statements.iter().enumerate() {
+            let is_last = n + 1 == hir.statements.len();
+
+            match stmt {
+                hir::Stmt::Local(local) => {
+                    let actual = self.expr(local.expr)?;
+
+                    match local.ty {
+                        Some(ty) => {
+                            let expected = self.ty(ty, true)?;
+                            self.check(local.expr.span(), &expected, &actual);
+                            self.bind(local.pat, expected, true)?;
+                        }
+                        None => {
+                            self.bind(local.pat, actual, false)?;
+                        }
+                    }
+                }
+                hir::Stmt::Expr(hir) => {
+                    let actual = self.expr(hir)?;
+
+                    if is_last {
+                        ty = actual;
+                    }
+                }
+                hir::Stmt::Semi(hir) => {
+                    self.expr(hir)?;
+                }
+                hir::Stmt::Item(..) => (),
+            }
+        }
+
+        self.vars.truncate(scope);
+        Ok(ty)
+    }
+
+    /// Check a condition, binding any variables it declares in the current
+    /// scope.
+    fn condition(&mut self, hir: &hir::Condition<'_>) -> CompileResult<()> {
+        match hir {
+            hir::Condition::Expr(hir) => {
+                self.expr(hir)?;
+            }
+            hir::Condition::ExprLet(hir) => {
+                self.expr(hir.expr)?;
+                self.bind(hir.pat, Ty::Unknown, false)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check an expression and return its type.
+    fn expr(&mut self, hir: &hir::Expr<'_>) -> CompileResult<Ty> {
+        let span = hir.span();
+
+        Ok(match hir.kind {
+            hir::ExprKind::Path(path) => self.expr_path(span, path)?,
+            hir::ExprKind::Assign(hir) => {
+                let actual = self.expr(hir.rhs)?;
+
+                if let hir::ExprKind::Path(path) = hir.lhs.kind {
+                    if let Some(ident) = path.try_as_ident() {
+                        let name = ident.resolve(resolve_context!(self.c.q))?.to_owned();
+                        self.assign(hir.rhs.span(), &name, actual);
+                    }
+                } else {
+                    self.expr(hir.lhs)?;
+                }
+
+                Ty::Tuple(Vec::new())
+            }
+            hir::ExprKind::Loop(hir) => {
+                let scope = self.vars.len();
+
+                if let Some(condition) = hir.condition {
+                    self.condition(condition)?;
+                }
+
+                self.block(hir.body)?;
+                self.vars.truncate(scope);
+                Ty::Unknown
+            }
+            hir::ExprKind::For(hir) => {
+                self.expr(hir.iter)?;
+
+                let scope = self.vars.len();
+                self.bind(hir.binding, Ty::Unknown, false)?;
+                self.block(hir.body)?;
+                self.vars.truncate(scope);
+                Ty::Unknown
+            }
+            hir::ExprKind::Let(hir) => {
+                self.expr(hir.expr)?;
+                self.bind(hir.pat, Ty::Unknown, false)?;
+                Ty::Unknown
+            }
+            hir::ExprKind::If(hir) => self.expr_if(hir)?,
+            hir::ExprKind::Match(hir) => {
+                self.expr(hir.expr)?;
+
+                let mut tys = Vec::with_capacity(hir.branches.len());
+
+                for branch in hir.branches {
+                    let scope = self.vars.len();
+                    self.bind(branch.pat, Ty::Unknown, false)?;
+
+                    if let Some(condition) = branch.condition {
+                        self.expr(condition)?;
+                    }
+
+                    tys.push(self.expr(branch.body)?);
+                    self.vars.truncate(scope);
+                }
+
+                join(tys)
+            }
+            hir::ExprKind::Call(hir) => self.expr_call(span, hir)?,
+            hir::ExprKind::FieldAccess(hir) => {
+                self.expr(hir.expr)?;
+                Ty::Unknown
+            }
+            hir::ExprKind::Binary(hir) => {
+                self.expr(hir.lhs)?;
+                self.expr(hir.rhs)?;
+
+                match hir.op {
+                    ast::BinOp::Eq(..)
+                    | ast::BinOp::Neq(..)
+                    | ast::BinOp::Gt(..)
+                    | ast::BinOp::Lt(..)
+                    | ast::BinOp::Gte(..)
+                    | ast::BinOp::Lte(..)
+                    | ast::BinOp::Is(..)
+                    | ast::BinOp::IsNot(..)
+                    | ast::BinOp::And(..)
+                    | ast::BinOp::Or(..) => self.builtin(BOOL_TYPE),
+                    _ => Ty::Unknown,
+                }
+            }
+            hir::ExprKind::Unary(hir) => {
+                self.expr(hir.expr)?;
+                Ty::Unknown
+            }
+            hir::ExprKind::Index(hir) => {
+                self.expr(hir.target)?;
+                self.expr(hir.index)?;
+                Ty::Unknown
+            }
+            hir::ExprKind::Block(hir) => match hir.kind {
+                hir::ExprBlockKind::Default => self.block(hir.block)?,
+                // NB: async blocks are compiled and checked separately, and
+                // const blocks are evaluated at compile time.
+                hir::ExprBlockKind::Async | hir::ExprBlockKind::Const => Ty::Unknown,
+            },
+            hir::ExprKind::Break(value) => {
+                if let Some(hir::ExprBreakValue::Expr(hir)) = value {
+                    self.expr(hir)?;
+                }
+
+                Ty::Unknown
+            }
+            hir::ExprKind::Continue(..) => Ty::Unknown,
+            hir::ExprKind::Yield(value) => {
+                self.yields = true;
+
+                if let Some(hir) = value {
+                    self.expr(hir)?;
+                }
+
+                Ty::Unknown
+            }
+            hir::ExprKind::Return(value) => {
+                let actual = match value {
+                    Some(hir) => self.expr(hir)?,
+                    None => Ty::Tuple(Vec::new()),
+                };
+
+                self.check_output(value.map(Spanned::span).unwrap_or(span), &actual);
+                Ty::Unknown
+            }
+            hir::ExprKind::Await(hir) | hir::ExprKind::Try(hir) => {
+                self.expr(hir)?;
+                Ty::Unknown
+            }
+            hir::ExprKind::Select(hir) => {
+                for branch in hir.branches {
+                    match branch {
+                        hir::ExprSelectBranch::Pat(branch) => {
+                            self.expr(branch.expr)?;
+
+                            let scope = self.vars.len();
+                            self.bind(branch.pat, Ty::Unknown, false)?;
+                            self.expr(branch.body)?;
+                            self.vars.truncate(scope);
+                        }
+                        hir::ExprSelectBranch::Default(hir) => {
+                            self.expr(hir)?;
+                        }
+                    }
+                }
+
+                Ty::Unknown
+            }
+            // NB: closures are compiled and checked separately.
+            hir::ExprKind::Closure(..) => Ty::Unknown,
+            hir::ExprKind::Lit(lit) => self.lit(lit)?,
+            hir::ExprKind::Object(hir) => {
+                for assign in hir.assignments {
+                    if let Some(hir) = assign.assign {
+                        self.expr(hir)?;
+                    }
+                }
+
+                match hir.path {
+                    Some(path) => {
+                        let named = self.c.convert_path(path)?;
+
+                        match self.lookup_meta(path.span(), named.item, Used::Used)? {
+                            Some(meta) => self.constructed(span, &meta)?,
+                            None => Ty::Unknown,
+                        }
+                    }
+                    None => self.builtin(OBJECT_TYPE),
+                }
+            }
+            hir::ExprKind::Tuple(hir) => {
+                let mut tys = Vec::with_capacity(hir.items.len());
+
+                for hir in hir.items {
+                    tys.push(self.expr(hir)?);
+                }
+
+                Ty::Tuple(tys)
+            }
+            hir::ExprKind::Vec(hir) => {
+                for hir in hir.items {
+                    self.expr(hir)?;
+                }
+
+                self.builtin(VEC_TYPE)
+            }
+            hir::ExprKind::Range(hir) => {
+                if let Some(from) = hir.from {
+                    self.expr(from)?;
+                }
+
+                if let Some(to) = hir.to {
+                    self.expr(to)?;
+                }
+
+                Ty::Unknown
+            }
+            hir::ExprKind::Group(hir) => self.expr(hir)?,
+            hir::ExprKind::MacroCall(hir) => match hir {
+                hir::MacroCall::Template(hir) => {
+                    for hir in hir.exprs {
+                        self.expr(hir)?;
+                    }
+
+                    self.builtin(STRING_TYPE)
+                }
+                hir::MacroCall::Format(hir) => {
+                    self.expr(hir.value)?;
+                    Ty::Unknown
+                }
+                hir::MacroCall::File(..) => self.builtin(STRING_TYPE),
+                hir::MacroCall::Line(..) => self.builtin(INTEGER_TYPE),
+            },
+        })
+    }
+
+    /// Assign to the variable with the given name.
+    fn assign(&mut self, span: Span, name: &str, actual: Ty) {
+        let index = match self.vars.iter().rposition(|var| &*var.name == name) {
+            Some(index) => index,
+            None => return,
+        };
+
+        if self.vars[index].annotated {
+            let expected = self.vars[index].ty.clone();
+            self.check(span, &expected, &actual);
+        } else {
+            // NB: the variable might now hold a value of a different type.
+            self.vars[index].ty = Ty::Unknown;
+        }
+    }
+
+    fn expr_path(&mut self, span: Span, path: &hir::Path<'_>) -> CompileResult<Ty> {
+        let named = self.c.convert_path(path)?;
+
+        if let Some(name) = named.as_local() {
+            if let Some(var) = self.var(name) {
+                return Ok(var.ty.clone());
+            }
+        }
+
+        let meta = match self.lookup_meta(span, named.item, Used::Used)? {
+            Some(meta) => meta,
+            None => return Ok(Ty::Unknown),
+        };
+
+        match meta.kind {
+            PrivMetaKind::Struct {
+                variant: PrivVariantMeta::Unit,
+                ..
+            }
+            | PrivMetaKind::Variant {
+                variant: PrivVariantMeta::Unit,
+                ..
+            } => self.constructed(span, &meta),
+            _ => Ok(Ty::Unknown),
+        }
+    }
+
+    fn expr_if(&mut self, hir: &hir::ExprIf<'_>) -> CompileResult<Ty> {
+        let mut tys = Vec::new();
+
+        let scope = self.vars.len();
+        self.condition(hir.condition)?;
+        tys.push(self.block(hir.block)?);
+        self.vars.truncate(scope);
+
+        for branch in hir.expr_else_ifs {
+            self.condition(branch.condition)?;
+            tys.push(self.block(branch.block)?);
+            self.vars.truncate(scope);
+        }
+
+        match hir.expr_else {
+            Some(branch) => tys.push(self.block(branch.block)?),
+            None => return Ok(Ty::Unknown),
+        }
+
+        Ok(join(tys))
+    }
+
+    fn expr_call(&mut self, span: Span, hir: &hir::ExprCall<'_>) -> CompileResult<Ty> {
+        match hir.expr.kind {
+            hir::ExprKind::Path(path) => {
+                let named = self.c.convert_path(path)?;
+
+                if named.generics.is_some()
+                    || named.as_local().and_then(|name| self.var(name)).is_some()
+                {
+                    self.args(hir.args, &[])?;
+                    return Ok(Ty::Unknown);
+                }
+
+                let meta = match self.lookup_meta(path.span(), named.item, Used::Used)? {
+                    Some(meta) => meta,
+                    None => {
+                        self.args(hir.args, &[])?;
+                        return Ok(Ty::Unknown);
+                    }
+                };
+
+                match &meta.kind {
+                    PrivMetaKind::Struct { .. } | PrivMetaKind::Variant { .. } => {
+                        self.args(hir.args, &[])?;
+                        self.constructed(span, &meta)
+                    }
+                    PrivMetaKind::Function { .. } => {
+                        let item = meta.item_meta.item;
+
+                        if let Some(signature) = self.signature(item)? {
+                            self.arg_count(span, &meta, signature.args.len(), hir.args.len());
+                            self.args(hir.args, &signature.args)?;
+                            return Ok(signature.output.clone());
+                        }
+
+                        // NB: native signatures only record the number of
+                        // arguments, so that is all we can check.
+                        let hash = self.c.q.pool.item_type_hash(item);
+
+                        if let Some(ContextSignature::Function {
+                            args: Some(expected),
+                            ..
+                        }) = self.c.context.lookup_signature(hash)
+                        {
+                            let expected = *expected;
+                            self.arg_count(span, &meta, expected, hir.args.len());
+                        }
+
+                        self.args(hir.args, &[])?;
+                        Ok(Ty::Unknown)
+                    }
+                    _ => {
+                        self.args(hir.args, &[])?;
+                        Ok(Ty::Unknown)
+                    }
+                }
+            }
+            hir::ExprKind::FieldAccess(access) => {
+                let target = self.expr(access.expr)?;
+
+                let signature = match (target, access.expr_field) {
+                    (
+                        Ty::Type {
+                            item: Some(item), ..
+                        },
+                        hir::ExprField::Path(path),
+                    ) => match path.try_as_ident() {
+                        Some(ident) => {
+                            let name = ident.resolve(resolve_context!(self.c.q))?;
+                            let item = self.c.q.pool.item(item).extended(name);
+                            let item = self.c.q.pool.alloc_item(item);
+                            self.signature(item)?
+                        }
+                        None => None,
+                    },
+                    _ => None,
+                };
+
+                match signature {
+                    // NB: the first argument is the instance itself.
+                    Some(signature) if signature.args.len() == hir.args.len() + 1 => {
+                        self.args(hir.args, &signature.args[1..])?;
+                        Ok(signature.output.clone())
+                    }
+                    _ => {
+                        self.args(hir.args, &[])?;
+                        Ok(Ty::Unknown)
+                    }
+                }
+            }
+            _ => {
+                self.expr(hir.expr)?;
+                self.args(hir.args, &[])?;
+                Ok(Ty::Unknown)
+            }
+        }
+    }
+
+    /// Check the arguments of a call against the expected types, if any.
+    fn args(&mut self, args: &[hir::Expr<'_>], expected: &[Ty]) -> CompileResult<()> {
+        for (n, hir) in args.iter().enumerate() {
+            let actual = self.expr(hir)?;
+
+            if let Some(expected) = expected.get(n) {
+                self.check(hir.span(), expected, &actual);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Report a call with the wrong number of arguments.
+    fn arg_count(&mut self, span: Span, meta: &PrivMeta, expected: usize, actual: usize) {
+        if expected == actual {
+            return;
+        }
+
+        let error = CompileError::new(
+            span,
+            CompileErrorKind::UnsupportedArgumentCount {
+                meta: meta.info(self.c.q.pool),
+                expected,
+                actual,
+            },
+        );
+
+        self.c.diagnostics.error(self.c.source_id, error);
+    }
+
+    fn lit(&mut self, lit: &ast::Lit) -> CompileResult<Ty> {
+        Ok(match lit {
+            ast::Lit::Bool(..) => self.builtin(BOOL_TYPE),
+            ast::Lit::Byte(..) => self.builtin(BYTE_TYPE),
+            ast::Lit::Char(..) => self.builtin(CHAR_TYPE),
+            ast::Lit::Str(..) => self.builtin(STRING_TYPE),
+            ast::Lit::ByteStr(..) => self.builtin(BYTES_TYPE),
+            ast::Lit::Number(lit) => match lit.resolve(resolve_context!(self.c.q))? {
+                ast::Number::Integer(..) => self.builtin(INTEGER_TYPE),
+                ast::Number::Float(..) => self.builtin(FLOAT_TYPE),
+            },
+        })
+    }
+}
+
+/// Construct a mismatch error unless `actual` is compatible with `expected`.
+fn mismatch(span: Span, expected: &Ty, actual: &Ty) -> Option<CompileError> {
+    if expected.accepts(actual) {
+        return None;
+    }
+
+    Some(CompileError::new(
+        span,
+        CompileErrorKind::TypeMismatch {
+            expected: expected.to_string().into(),
+            actual: actual.to_string().into(),
+        },
+    ))
+}
+
+/// Join the types of multiple branches, which is only known if they all
+/// agree.
+fn join(tys: Vec<Ty>) -> Ty {
+    let mut it = tys.into_iter();
+
+    let first = match it.next() {
+        Some(first) if first.is_known() => first,
+        _ => return Ty::Unknown,
+    };
+
+    for ty in it {
+        let same = match (&first, &ty) {
+            (Ty::Type { hash: a, .. }, Ty::Type { hash: b, .. }) => a == b,
+            _ => false,
+        };
+
+        if !same {
+            return Ty::Unknown;
+        }
+    }
+
+    first
+}
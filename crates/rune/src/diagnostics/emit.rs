@@ -6,7 +6,7 @@ use crate::diagnostics::{
 };
 use crate::parse::ResolveErrorKind;
 use crate::query::QueryErrorKind;
-use crate::runtime::{Unit, VmError, VmErrorKind};
+use crate::runtime::{BacktraceFrame, Unit, VmError, VmErrorKind};
 use crate::{Source, Diagnostics, SourceId, Sources};
 use crate::ast::{Span, Spanned};
 use std::convert::TryInto;
@@ -20,11 +20,6 @@ use codespan_reporting::term;
 use codespan_reporting::term::termcolor::WriteColor;
 pub use codespan_reporting::term::termcolor;
 
-struct StackFrame {
-    source_id: SourceId,
-    span: Span,
-}
-
 /// Errors that can be raised when formatting diagnostics.
 #[derive(Debug, Error)]
 pub enum EmitError {
@@ -88,8 +83,8 @@ impl VmError {
     {
         let (error, unwound) = self.as_unwound();
 
-        let (unit, ip, frames) = match unwound {
-            Some((unit, ip, frames)) => (unit, ip, frames),
+        let (unit, ip) = match unwound {
+            Some((unit, ip, _)) => (unit, ip),
             None => {
                 writeln!(
                     out,
@@ -171,28 +166,6 @@ impl VmError {
             }
         };
 
-        let mut backtrace = vec![StackFrame { source_id, span }];
-
-        for ip in frames.iter().map(|v| v.ip()) {
-            let debug_inst = match debug_info.instruction_at(ip) {
-                Some(debug_inst) => debug_inst,
-                None => {
-                    writeln!(
-                        out,
-                        "virtual machine error: {} (no debug instruction)",
-                        error
-                    )?;
-
-                    return Ok(());
-                }
-            };
-
-            let source_id = debug_inst.source_id;
-            let span = debug_inst.span;
-
-            backtrace.push(StackFrame { source_id, span });
-        }
-
         let diagnostic = d::Diagnostic::error()
             .with_message(reason)
             .with_labels(labels)
@@ -200,25 +173,13 @@ impl VmError {
 
         term::emit(out, &config, sources, &diagnostic)?;
 
-        if !backtrace.is_empty() {
-            writeln!(out, "backtrace:")?;
+        let backtrace = self.backtrace();
 
-            for frame in &backtrace {
-                let source = match sources.get(frame.source_id) {
-                    Some(source) => source,
-                    None => continue,
-                };
-
-                let (line, line_count, text) = match source.line(frame.span) {
-                    Some((line, line_count, text)) => (
-                        line.saturating_add(1),
-                        line_count.saturating_add(1),
-                        text.trim_end(),
-                    ),
-                    None => continue,
-                };
+        if !backtrace.is_empty() {
+            writeln!(out, "stack backtrace:")?;
 
-                writeln!(out, "{}:{}:{}: {}", source.name(), line, line_count, text)?;
+            for (n, frame) in backtrace.iter().enumerate() {
+                backtrace_frame_emit(out, sources, n, frame)?;
             }
         }
 
@@ -226,6 +187,47 @@ impl VmError {
     }
 }
 
+/// Emit a single frame of a backtrace, in the style of a Rust backtrace.
+fn backtrace_frame_emit<O>(
+    out: &mut O,
+    sources: &Sources,
+    n: usize,
+    frame: &BacktraceFrame,
+) -> Result<(), EmitError>
+where
+    O: WriteColor,
+{
+    match frame {
+        BacktraceFrame::Native { hash, name } => match name {
+            Some(name) => writeln!(out, "{:>4}: {}", n, name)?,
+            None => writeln!(out, "{:>4}: <native function {}>", n, hash)?,
+        },
+        BacktraceFrame::Script {
+            ip,
+            function,
+            location,
+        } => {
+            match function {
+                Some(function) => writeln!(out, "{:>4}: {}", n, function)?,
+                None => writeln!(out, "{:>4}: <unknown function at inst {}>", n, ip)?,
+            }
+
+            let (source_id, span) = match location {
+                Some(location) => *location,
+                None => return Ok(()),
+            };
+
+            if let Some(source) = sources.get(source_id) {
+                let (line, column) = source.pos_to_utf16cu_linecol(span.start.into_usize());
+                let (line, column) = (line + 1, column + 1);
+                writeln!(out, "             at {}:{}:{}", source.name(), line, column)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 impl FatalDiagnostic {
     /// Generate formatted diagnostics capable of referencing source lines and
     /// hints.
@@ -435,6 +437,38 @@ where
 
             None
         }
+        WarningDiagnosticKind::MatchNotExhaustive {
+            span,
+            missing,
+            context,
+        } => {
+            labels.push(
+                d::Label::primary(this.source_id(), span.range())
+                    .with_message("match is not exhaustive"),
+            );
+
+            let mut note = String::new();
+            write!(note, "Missing patterns: ")?;
+
+            for (index, pattern) in missing.iter().enumerate() {
+                if index > 0 {
+                    write!(note, ", ")?;
+                }
+
+                write!(note, "`{}`", pattern)?;
+            }
+
+            notes.push(note);
+            *context
+        }
+        WarningDiagnosticKind::UnreachablePattern { span, context } => {
+            labels.push(
+                d::Label::primary(this.source_id(), span.range())
+                    .with_message("unreachable pattern"),
+            );
+
+            *context
+        }
     };
 
     if let Some(context) = context {
@@ -612,6 +646,12 @@ where
 
                 notes.push("You can also make the pattern non-exhaustive by adding `..`".to_string());
             }
+            CompileErrorKind::TraitFnConflict { existing, .. } => {
+                labels.push(
+                    d::Label::secondary(this.source_id(), existing.range())
+                        .with_message("previously declared here"),
+                );
+            }
             _ => (),
         }
 
@@ -38,7 +38,7 @@ mod fatal;
 pub use self::fatal::{FatalDiagnostic, FatalDiagnosticKind};
 
 mod warning;
-pub use self::warning::{WarningDiagnostic, WarningDiagnosticKind};
+pub use self::warning::{SuggestedEdit, WarningDiagnostic, WarningDiagnosticKind};
 
 cfg_emit! {
     mod emit;
@@ -242,21 +242,51 @@ impl Diagnostics {
         variant: Span,
         context: Option<Span>,
     ) {
-        self.warning(
+        let parens = Span::new(variant.end, span.end);
+
+        self.warning_with_edit(
             source_id,
             WarningDiagnosticKind::RemoveTupleCallParams {
                 span,
                 variant,
                 context,
             },
+            SuggestedEdit::remove("remove call parentheses", parens),
         );
     }
 
     /// Add a warning about an unecessary semi-colon.
     pub fn uneccessary_semi_colon(&mut self, source_id: SourceId, span: Span) {
-        self.warning(
+        self.warning_with_edit(
             source_id,
             WarningDiagnosticKind::UnecessarySemiColon { span },
+            SuggestedEdit::remove("remove unnecessary semicolon", span),
+        );
+    }
+
+    /// Indicate that a match expression does not cover all possible values.
+    pub fn match_not_exhaustive(
+        &mut self,
+        source_id: SourceId,
+        span: Span,
+        missing: Box<[Box<str>]>,
+        context: Option<Span>,
+    ) {
+        self.warning(
+            source_id,
+            WarningDiagnosticKind::MatchNotExhaustive {
+                span,
+                missing,
+                context,
+            },
+        );
+    }
+
+    /// Indicate that a match arm can never be reached.
+    pub fn unreachable_pattern(&mut self, source_id: SourceId, span: Span, context: Option<Span>) {
+        self.warning(
+            source_id,
+            WarningDiagnosticKind::UnreachablePattern { span, context },
         );
     }
 
@@ -265,6 +295,24 @@ impl Diagnostics {
     where
         WarningDiagnosticKind: From<T>,
     {
+        self.push_warning(source_id, kind.into(), None);
+    }
+
+    /// Push a warning to the collection of diagnostics, together with an
+    /// edit which would resolve it.
+    pub fn warning_with_edit<T>(&mut self, source_id: SourceId, kind: T, edit: SuggestedEdit)
+    where
+        WarningDiagnosticKind: From<T>,
+    {
+        self.push_warning(source_id, kind.into(), Some(edit));
+    }
+
+    fn push_warning(
+        &mut self,
+        source_id: SourceId,
+        kind: WarningDiagnosticKind,
+        edit: Option<SuggestedEdit>,
+    ) {
         if !self.mode.warnings() {
             return;
         }
@@ -272,7 +320,8 @@ impl Diagnostics {
         self.diagnostics
             .push(Diagnostic::Warning(WarningDiagnostic {
                 source_id,
-                kind: kind.into(),
+                kind,
+                edit,
             }));
 
         self.has_warning = true;
@@ -6,12 +6,14 @@ use thiserror::Error;
 
 /// Warning diagnostic emitted during compilation. Warning diagnostics indicates
 /// an recoverable issues.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct WarningDiagnostic {
     /// The id of the source where the warning happened.
     pub(crate) source_id: SourceId,
     /// The kind of the warning.
     pub(crate) kind: WarningDiagnosticKind,
+    /// An edit which would resolve the warning.
+    pub(crate) edit: Option<SuggestedEdit>,
 }
 
 impl WarningDiagnostic {
@@ -30,6 +32,12 @@ impl WarningDiagnostic {
         self.kind
     }
 
+    /// An edit to the source the warning originates from which would resolve
+    /// it, if one is available.
+    pub fn suggested_edit(&self) -> Option<&SuggestedEdit> {
+        self.edit.as_ref()
+    }
+
     /// Get the span of the warning.
     pub fn span(&self) -> Span {
         match &self.kind {
@@ -38,6 +46,8 @@ impl WarningDiagnostic {
             WarningDiagnosticKind::TemplateWithoutExpansions { span, .. } => *span,
             WarningDiagnosticKind::RemoveTupleCallParams { span, .. } => *span,
             WarningDiagnosticKind::UnecessarySemiColon { span, .. } => *span,
+            WarningDiagnosticKind::MatchNotExhaustive { span, .. } => *span,
+            WarningDiagnosticKind::UnreachablePattern { span, .. } => *span,
         }
     }
 }
@@ -54,8 +64,58 @@ impl error::Error for WarningDiagnostic {
     }
 }
 
+/// A machine-applicable edit which is suggested to resolve a
+/// [WarningDiagnostic].
+///
+/// The edit replaces the text covered by a span in the same source as the
+/// warning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuggestedEdit {
+    /// A short description of the edit.
+    pub(crate) description: &'static str,
+    /// The span of the text being replaced.
+    pub(crate) span: Span,
+    /// The text to replace the span with.
+    pub(crate) replacement: Box<str>,
+}
+
+impl SuggestedEdit {
+    /// Construct a suggested edit replacing the given span.
+    pub(crate) fn replace<R>(description: &'static str, span: Span, replacement: R) -> Self
+    where
+        Box<str>: From<R>,
+    {
+        Self {
+            description,
+            span,
+            replacement: replacement.into(),
+        }
+    }
+
+    /// Construct a suggested edit removing the given span.
+    pub(crate) fn remove(description: &'static str, span: Span) -> Self {
+        Self::replace(description, span, "")
+    }
+
+    /// A short description of the edit, like `remove unnecessary semicolon`.
+    pub fn description(&self) -> &str {
+        self.description
+    }
+
+    /// The span of the text being replaced.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The text to replace the span with, which is empty if the text should
+    /// be removed.
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+}
+
 /// The kind of a [WarningDiagnostic].
-#[derive(Debug, Clone, Copy, Error)]
+#[derive(Debug, Clone, Error)]
 #[allow(missing_docs)]
 #[non_exhaustive]
 pub enum WarningDiagnosticKind {
@@ -100,4 +160,23 @@ pub enum WarningDiagnosticKind {
         /// Span where the semi-colon is.
         span: Span,
     },
+    /// A match expression does not cover all possible values.
+    #[error("match is not exhaustive")]
+    MatchNotExhaustive {
+        /// The span of the match expression.
+        span: Span,
+        /// Patterns which are not covered by the match.
+        missing: Box<[Box<str>]>,
+        /// The context in which it is used.
+        context: Option<Span>,
+    },
+    /// A match arm which can never be reached since earlier arms cover all
+    /// the values it matches.
+    #[error("unreachable pattern")]
+    UnreachablePattern {
+        /// The span of the pattern.
+        span: Span,
+        /// The context in which it is used.
+        context: Option<Span>,
+    },
 }
@@ -0,0 +1,23 @@
+use crate::parse::ParseError;
+use thiserror::Error;
+
+/// An error raised when formatting a source.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum FormattingError {
+    /// The source could not be parsed.
+    #[error("{error}")]
+    ParseError {
+        /// The source error.
+        #[from]
+        #[source]
+        error: ParseError,
+    },
+    /// The formatted output doesn't consist of the same tokens as the
+    /// source. This indicates a bug in the formatter.
+    #[error("formatting would change the meaning of the source at byte {offset}")]
+    Mismatch {
+        /// The byte offset in the source where the output diverges.
+        offset: usize,
+    },
+}
@@ -0,0 +1,81 @@
+//! Source code formatting for Rune.
+//!
+//! The formatter pretty-prints an [ast::File] while preserving the comments
+//! in the source it was parsed from.
+//!
+//! # Examples
+//!
+//! ```
+//! let source = "pub fn main(){let a=[1,2,3];a}";
+//! let output = rune::fmt::layout_source(source)?;
+//!
+//! assert_eq!(output, "pub fn main() {\n    let a = [1, 2, 3];\n    a\n}\n");
+//! # Ok::<_, rune::fmt::FormattingError>(())
+//! ```
+
+mod error;
+mod printer;
+
+pub use self::error::FormattingError;
+
+use crate::ast;
+use crate::parse::{self, Lexer};
+use crate::SourceId;
+
+/// Parse and format the given source.
+pub fn layout_source(source: &str) -> Result<String, FormattingError> {
+    let file = parse::parse_all::<ast::File>(source, SourceId::empty(), true)?;
+    layout_file(&file, source)
+}
+
+/// Format the given file, which must have been parsed from `source`.
+///
+/// Fails if the formatted output would consist of different tokens than the
+/// original source, disregarding whitespace and commas.
+pub fn layout_file(file: &ast::File, source: &str) -> Result<String, FormattingError> {
+    let output = printer::Printer::new(source)?.file(file);
+    verify(source, &output)?;
+    Ok(output)
+}
+
+/// Verify that the formatted output consists of the same tokens and comments
+/// as the source.
+fn verify(source: &str, output: &str) -> Result<(), FormattingError> {
+    let mut a = Lexer::new(source, SourceId::empty(), true);
+    let mut b = Lexer::new(output, SourceId::empty(), true);
+
+    loop {
+        let (a, b) = match (significant(&mut a, source)?, significant(&mut b, output)?) {
+            (None, None) => return Ok(()),
+            (a, b) => (a, b),
+        };
+
+        match (a, b) {
+            (Some((_, a)), Some((_, b))) if a == b => (),
+            (Some((offset, _)), _) => return Err(FormattingError::Mismatch { offset }),
+            (None, _) => {
+                return Err(FormattingError::Mismatch {
+                    offset: source.len(),
+                })
+            }
+        }
+    }
+}
+
+/// Get the offset and text of the next token which isn't whitespace or a
+/// comma.
+fn significant<'a>(
+    lexer: &mut Lexer<'_>,
+    source: &'a str,
+) -> Result<Option<(usize, &'a str)>, FormattingError> {
+    while let Some(token) = lexer.next()? {
+        if matches!(token.kind, ast::Kind::Whitespace | ast::Kind::Comma) {
+            continue;
+        }
+
+        let text = source.get(token.span.range()).unwrap_or_default();
+        return Ok(Some((token.span.start.into_usize(), text.trim_end())));
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,1615 @@
+//! The printer which lays out an [ast::File].
+
+use crate::ast::{self, OptionSpanned, Span, Spanned};
+use crate::fmt::FormattingError;
+use crate::parse::Lexer;
+use crate::SourceId;
+
+/// The maximum width of a line before groups are broken up over multiple
+/// lines.
+const MAX_WIDTH: usize = 100;
+
+/// A single level of indentation.
+const INDENT: &str = "    ";
+
+/// A comment in the source.
+#[derive(Debug, Clone, Copy)]
+struct Comment {
+    /// The span of the comment.
+    span: Span,
+    /// If the comment runs until the end of the line.
+    line: bool,
+}
+
+/// How a delimited group is laid out.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    /// Pad the inside of the delimiters with spaces when on a single line.
+    padding: bool,
+    /// Keep the trailing separator for groups with a single element, since
+    /// it's what distinguishes a tuple from a parenthesized expression.
+    single_trailing: bool,
+}
+
+impl Layout {
+    const TIGHT: Self = Self {
+        padding: false,
+        single_trailing: false,
+    };
+
+    const PADDED: Self = Self {
+        padding: true,
+        single_trailing: false,
+    };
+
+    const TUPLE: Self = Self {
+        padding: false,
+        single_trailing: true,
+    };
+}
+
+/// A snapshot of the printer which can be restored.
+#[derive(Debug, Clone, Copy)]
+struct Snapshot {
+    len: usize,
+    column: usize,
+    last: usize,
+    comment: usize,
+    line_break: bool,
+    forced: usize,
+    indent: usize,
+}
+
+/// Lays out an [ast::File] with the comments of the source it was parsed
+/// from.
+pub(super) struct Printer<'a> {
+    /// The source being formatted.
+    source: &'a str,
+    /// Comments in the source, in order.
+    comments: Vec<Comment>,
+    /// The index of the next comment to emit.
+    comment: usize,
+    /// The output being written.
+    out: String,
+    /// The current level of indentation.
+    indent: usize,
+    /// The column on the current line, where `0` means that nothing has been
+    /// written to the line.
+    column: usize,
+    /// The offset in the source after the last token written.
+    last: usize,
+    /// A line break has to be written before anything else.
+    line_break: bool,
+    /// The number of line breaks forced by comments.
+    forced: usize,
+}
+
+impl<'a> Printer<'a> {
+    /// Construct a new printer, collecting comments from the given source.
+    pub(super) fn new(source: &'a str) -> Result<Self, FormattingError> {
+        let mut lexer = Lexer::new(source, SourceId::empty(), true);
+        let mut comments = Vec::new();
+
+        while let Some(token) = lexer.next()? {
+            let line = match token.kind {
+                ast::Kind::Comment => true,
+                ast::Kind::MultilineComment(..) => false,
+                _ => continue,
+            };
+
+            comments.push(Comment {
+                span: token.span,
+                line,
+            });
+        }
+
+        Ok(Self {
+            source,
+            comments,
+            comment: 0,
+            out: String::new(),
+            indent: 0,
+            column: 0,
+            last: 0,
+            line_break: false,
+            forced: 0,
+        })
+    }
+
+    /// Lay out the given file and return the output.
+    pub(super) fn file(mut self, file: &ast::File) -> String {
+        if let Some(shebang) = &file.shebang {
+            self.token(shebang.span);
+            self.force_line_break();
+        }
+
+        for attribute in &file.attributes {
+            self.separator(attribute.span());
+            self.attribute(attribute);
+            self.force_line_break();
+        }
+
+        self.items(&file.items);
+
+        self.comments_before(self.source.len());
+        self.line();
+
+        while self.out.ends_with("\n\n") {
+            self.out.pop();
+        }
+
+        if self.out.trim().is_empty() {
+            self.out.clear();
+        }
+
+        self.out
+    }
+
+    /// Lay out a sequence of items, each on their own line.
+    fn items(&mut self, items: &[(ast::Item, Option<T![;]>)]) {
+        for (item, semi) in items {
+            self.separator(item.span());
+            self.item(item);
+
+            if let Some(semi) = semi {
+                self.token(semi.span());
+            }
+        }
+    }
+
+    fn item(&mut self, item: &ast::Item) {
+        match item {
+            ast::Item::Use(item) => {
+                self.item_attributes(&item.attributes);
+                self.visibility(&item.visibility);
+                self.token(item.use_token.span());
+                self.space();
+                self.use_path(&item.path);
+            }
+            ast::Item::Fn(item) => {
+                self.item_fn(item);
+            }
+            ast::Item::Enum(item) => {
+                self.item_attributes(&item.attributes);
+                self.visibility(&item.visibility);
+                self.token(item.enum_token.span());
+                self.space();
+                self.token(item.name.span());
+                self.space();
+
+                let variants = &item.variants;
+
+                self.group(
+                    variants.open.span(),
+                    &variants.braced,
+                    variants.close.span(),
+                    Layout::PADDED,
+                    |p, variant| {
+                        p.item_attributes(&variant.attributes);
+                        p.token(variant.name.span());
+                        p.variant_body(&variant.body);
+                    },
+                );
+            }
+            ast::Item::Struct(item) => {
+                self.item_attributes(&item.attributes);
+                self.visibility(&item.visibility);
+                self.token(item.struct_token.span());
+                self.space();
+                self.token(item.ident.span());
+                self.struct_body(&item.body);
+            }
+            ast::Item::Impl(item) => {
+                self.item_attributes(&item.attributes);
+                self.token(item.impl_.span());
+                self.space();
+
+                if let Some((path, for_)) = &item.trait_ {
+                    self.path(path);
+                    self.space();
+                    self.token(for_.span());
+                    self.space();
+                }
+
+                self.path(&item.path);
+                self.space();
+                self.token(item.open.span());
+
+                self.indent += 1;
+
+                for item_fn in &item.functions {
+                    self.separator(item_fn.span());
+                    self.item_fn(item_fn);
+                }
+
+                self.close(item.close.span());
+            }
+            ast::Item::Trait(item) => {
+                self.item_attributes(&item.attributes);
+                self.visibility(&item.visibility);
+                self.token(item.trait_token.span());
+                self.space();
+                self.token(item.ident.span());
+                self.space();
+                self.token(item.open.span());
+
+                self.indent += 1;
+
+                for function in &item.functions {
+                    self.separator(function.span());
+
+                    match function {
+                        ast::TraitFn::Required(decl) => {
+                            self.item_attributes(&decl.attributes);
+                            self.token(decl.fn_token.span());
+                            self.space();
+                            self.token(decl.name.span());
+                            self.fn_args(&decl.args);
+                            self.fn_output(&decl.output);
+                            self.token(decl.semi.span());
+                        }
+                        ast::TraitFn::Provided(item_fn) => {
+                            self.item_fn(item_fn);
+                        }
+                    }
+                }
+
+                self.close(item.close.span());
+            }
+            ast::Item::Mod(item) => {
+                self.item_attributes(&item.attributes);
+                self.visibility(&item.visibility);
+                self.token(item.mod_token.span());
+                self.space();
+                self.token(item.name.span());
+
+                match &item.body {
+                    ast::ItemModBody::EmptyBody(semi) => {
+                        self.token(semi.span());
+                    }
+                    ast::ItemModBody::InlineBody(body) => {
+                        self.space();
+                        self.token(body.open.span());
+
+                        self.indent += 1;
+
+                        for attribute in &body.file.attributes {
+                            self.separator(attribute.span());
+                            self.attribute(attribute);
+                            self.force_line_break();
+                        }
+
+                        self.items(&body.file.items);
+                        self.close(body.close.span());
+                    }
+                }
+            }
+            ast::Item::Const(item) => {
+                self.item_attributes(&item.attributes);
+                self.visibility(&item.visibility);
+                self.token(item.const_token.span());
+                self.space();
+                self.token(item.name.span());
+                self.space();
+                self.token(item.eq.span());
+                self.space();
+                self.expr(&item.expr);
+            }
+            ast::Item::MacroCall(macro_call) => {
+                self.macro_call(macro_call);
+            }
+        }
+    }
+
+    fn item_fn(&mut self, item: &ast::ItemFn) {
+        self.item_attributes(&item.attributes);
+        self.visibility(&item.visibility);
+
+        if let Some(const_token) = &item.const_token {
+            self.token(const_token.span());
+            self.space();
+        }
+
+        if let Some(async_token) = &item.async_token {
+            self.token(async_token.span());
+            self.space();
+        }
+
+        self.token(item.fn_token.span());
+        self.space();
+        self.token(item.name.span());
+        self.fn_args(&item.args);
+        self.fn_output(&item.output);
+        self.space();
+        self.block(&item.body);
+    }
+
+    fn fn_args(&mut self, args: &ast::Parenthesized<ast::FnArg, T![,]>) {
+        self.group(
+            args.open.span(),
+            &args.parenthesized,
+            args.close.span(),
+            Layout::TIGHT,
+            Self::fn_arg,
+        );
+    }
+
+    fn struct_body(&mut self, body: &ast::ItemStructBody) {
+        match body {
+            ast::ItemStructBody::UnitBody => {}
+            ast::ItemStructBody::TupleBody(fields) => self.tuple_fields(fields),
+            ast::ItemStructBody::StructBody(fields) => self.struct_fields(fields),
+        }
+    }
+
+    fn variant_body(&mut self, body: &ast::ItemVariantBody) {
+        match body {
+            ast::ItemVariantBody::UnitBody => {}
+            ast::ItemVariantBody::TupleBody(fields) => self.tuple_fields(fields),
+            ast::ItemVariantBody::StructBody(fields) => self.struct_fields(fields),
+        }
+    }
+
+    fn tuple_fields(&mut self, fields: &ast::Parenthesized<ast::Field, T![,]>) {
+        self.group(
+            fields.open.span(),
+            &fields.parenthesized,
+            fields.close.span(),
+            Layout::TIGHT,
+            Self::field,
+        );
+    }
+
+    fn struct_fields(&mut self, fields: &ast::Braced<ast::Field, T![,]>) {
+        self.space();
+
+        self.group(
+            fields.open.span(),
+            &fields.braced,
+            fields.close.span(),
+            Layout::PADDED,
+            Self::field,
+        );
+    }
+
+    fn field(&mut self, field: &ast::Field) {
+        self.item_attributes(&field.attributes);
+        self.visibility(&field.visibility);
+        self.token(field.name.span());
+    }
+
+    fn fn_arg(&mut self, arg: &ast::FnArg) {
+        match arg {
+            ast::FnArg::SelfValue(token) => self.token(token.span()),
+            ast::FnArg::Pat(pat) => self.pat(pat),
+            ast::FnArg::Typed(typed) => {
+                self.pat(&typed.pat);
+                self.token(typed.colon.span());
+                self.space();
+                self.ty(&typed.ty);
+            }
+        }
+    }
+
+    fn fn_output(&mut self, output: &Option<(T![->], ast::Type)>) {
+        if let Some((arrow, ty)) = output {
+            self.space();
+            self.token(arrow.span());
+            self.space();
+            self.ty(ty);
+        }
+    }
+
+    fn ty(&mut self, ty: &ast::Type) {
+        match ty {
+            ast::Type::Path(path) => self.path(path),
+            ast::Type::Tuple(tuple) => {
+                self.group(
+                    tuple.open.span(),
+                    &tuple.parenthesized,
+                    tuple.close.span(),
+                    Layout::TIGHT,
+                    Self::ty,
+                );
+            }
+        }
+    }
+
+    fn use_path(&mut self, path: &ast::ItemUsePath) {
+        if let Some(global) = &path.global {
+            self.token(global.span());
+        }
+
+        self.use_segment(&path.first);
+
+        for (colon, segment) in &path.segments {
+            self.token(colon.span());
+            self.use_segment(segment);
+        }
+
+        if let Some((as_token, ident)) = &path.alias {
+            self.space();
+            self.token(as_token.span());
+            self.space();
+            self.token(ident.span());
+        }
+    }
+
+    fn use_segment(&mut self, segment: &ast::ItemUseSegment) {
+        match segment {
+            ast::ItemUseSegment::PathSegment(segment) => self.path_segment(segment),
+            ast::ItemUseSegment::Wildcard(star) => self.token(star.span()),
+            ast::ItemUseSegment::Group(group) => {
+                self.group(
+                    group.open.span(),
+                    &group.braced,
+                    group.close.span(),
+                    Layout::TIGHT,
+                    Self::use_path,
+                );
+            }
+        }
+    }
+
+    fn visibility(&mut self, visibility: &ast::Visibility) {
+        if let Some(span) = visibility.option_span() {
+            self.token(span);
+            self.space();
+        }
+    }
+
+    /// Attributes of items, which are each put on their own line.
+    fn item_attributes(&mut self, attributes: &[ast::Attribute]) {
+        for attribute in attributes {
+            self.attribute(attribute);
+            self.force_line_break();
+        }
+    }
+
+    /// Attributes of expressions and patterns, which are kept on the same line
+    /// unless they are doc comments.
+    fn inline_attributes(&mut self, attributes: &[ast::Attribute]) {
+        for attribute in attributes {
+            self.attribute(attribute);
+            self.space();
+        }
+    }
+
+    /// Attributes are written as they appear in the source, which also covers
+    /// doc comments since they're lexed into attributes.
+    fn attribute(&mut self, attribute: &ast::Attribute) {
+        let span = attribute.span();
+        self.token(span);
+
+        if self.source[span.range()].starts_with("//") {
+            self.force_line_break();
+        }
+    }
+
+    fn block(&mut self, block: &ast::Block) {
+        self.token(block.open.span());
+
+        if block.statements.is_empty() && !self.has_comments_before(block.close.span()) {
+            self.token(block.close.span());
+            return;
+        }
+
+        self.indent += 1;
+
+        for stmt in &block.statements {
+            self.separator(stmt.span());
+            self.stmt(stmt);
+        }
+
+        self.close(block.close.span());
+    }
+
+    fn stmt(&mut self, stmt: &ast::Stmt) {
+        match stmt {
+            ast::Stmt::Local(local) => {
+                self.inline_attributes(&local.attributes);
+                self.token(local.let_token.span());
+                self.space();
+                self.pat(&local.pat);
+
+                if let Some((colon, ty)) = &local.ty {
+                    self.token(colon.span());
+                    self.space();
+                    self.ty(ty);
+                }
+
+                self.space();
+                self.token(local.eq.span());
+                self.space();
+                self.expr(&local.expr);
+                self.token(local.semi.span());
+            }
+            ast::Stmt::Item(item, semi) => {
+                self.item(item);
+
+                if let Some(semi) = semi {
+                    self.token(semi.span());
+                }
+            }
+            ast::Stmt::Expr(expr) => {
+                self.expr(expr);
+            }
+            ast::Stmt::Semi(semi) => {
+                self.expr(&semi.expr);
+                self.token(semi.semi_token.span());
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &ast::Expr) {
+        if !matches!(expr, ast::Expr::Empty(..) | ast::Expr::MacroCall(..)) {
+            self.inline_attributes(expr.attributes());
+        }
+
+        if self.chain(expr) {
+            return;
+        }
+
+        match expr {
+            ast::Expr::Path(path) => {
+                self.path(path);
+            }
+            ast::Expr::Assign(expr) => {
+                self.expr(&expr.lhs);
+                self.space();
+                self.token(expr.eq.span());
+                self.space();
+                self.expr(&expr.rhs);
+            }
+            ast::Expr::While(expr) => {
+                self.label(&expr.label);
+                self.token(expr.while_token.span());
+                self.space();
+                self.condition(&expr.condition);
+                self.space();
+                self.block(&expr.body);
+            }
+            ast::Expr::Loop(expr) => {
+                self.label(&expr.label);
+                self.token(expr.loop_token.span());
+                self.space();
+                self.block(&expr.body);
+            }
+            ast::Expr::For(expr) => {
+                self.label(&expr.label);
+                self.token(expr.for_token.span());
+                self.space();
+                self.pat(&expr.binding);
+                self.space();
+                self.token(expr.in_.span());
+                self.space();
+                self.expr(&expr.iter);
+                self.space();
+                self.block(&expr.body);
+            }
+            ast::Expr::Let(expr) => {
+                self.expr_let(expr);
+            }
+            ast::Expr::If(expr) => {
+                self.token(expr.if_.span());
+                self.space();
+                self.condition(&expr.condition);
+                self.space();
+                self.block(&expr.block);
+
+                for else_if in &expr.expr_else_ifs {
+                    self.space();
+                    self.token(else_if.else_.span());
+                    self.space();
+                    self.token(else_if.if_.span());
+                    self.space();
+                    self.condition(&else_if.condition);
+                    self.space();
+                    self.block(&else_if.block);
+                }
+
+                if let Some(expr_else) = &expr.expr_else {
+                    self.space();
+                    self.token(expr_else.else_.span());
+                    self.space();
+                    self.block(&expr_else.block);
+                }
+            }
+            ast::Expr::Match(expr) => {
+                self.token(expr.match_.span());
+                self.space();
+                self.expr(&expr.expr);
+                self.space();
+
+                self.branches(
+                    expr.open.span(),
+                    &expr.branches,
+                    expr.close.span(),
+                    |p, branch| {
+                        p.pat(&branch.pat);
+
+                        if let Some((if_, condition)) = &branch.condition {
+                            p.space();
+                            p.token(if_.span());
+                            p.space();
+                            p.expr(condition);
+                        }
+
+                        p.space();
+                        p.token(branch.rocket.span());
+                        p.space();
+                        p.expr(&branch.body);
+                        &branch.body
+                    },
+                );
+            }
+            ast::Expr::Call(expr) => {
+                self.expr(&expr.expr);
+
+                let args = &expr.args;
+
+                self.group(
+                    args.open.span(),
+                    &args.parenthesized,
+                    args.close.span(),
+                    Layout::TIGHT,
+                    Self::expr,
+                );
+            }
+            ast::Expr::FieldAccess(expr) => {
+                self.expr(&expr.expr);
+                self.token(expr.dot.span());
+
+                match &expr.expr_field {
+                    ast::ExprField::Path(path) => self.path(path),
+                    ast::ExprField::LitNumber(number) => self.token(number.span()),
+                }
+            }
+            ast::Expr::Binary(expr) => {
+                self.expr(&expr.lhs);
+
+                let tight = matches!(expr.op, ast::BinOp::DotDot(..) | ast::BinOp::DotDotEq(..));
+
+                if !tight {
+                    self.space();
+                }
+
+                self.token(expr.op.span());
+
+                if !tight {
+                    self.space();
+                }
+
+                self.expr(&expr.rhs);
+            }
+            ast::Expr::Unary(expr) => {
+                self.token(expr.op.span());
+                self.expr(&expr.expr);
+            }
+            ast::Expr::Index(expr) => {
+                self.expr(&expr.target);
+                self.token(expr.open.span());
+                self.expr(&expr.index);
+                self.token(expr.close.span());
+            }
+            ast::Expr::Break(expr) => {
+                self.token(expr.break_token.span());
+
+                match expr.expr.as_deref() {
+                    Some(ast::ExprBreakValue::Expr(value)) => {
+                        self.space();
+                        self.expr(value);
+                    }
+                    Some(ast::ExprBreakValue::Label(label)) => {
+                        self.space();
+                        self.token(label.span());
+                    }
+                    None => {}
+                }
+            }
+            ast::Expr::Continue(expr) => {
+                self.token(expr.continue_token.span());
+
+                if let Some(label) = &expr.label {
+                    self.space();
+                    self.token(label.span());
+                }
+            }
+            ast::Expr::Yield(expr) => {
+                self.token(expr.yield_token.span());
+
+                if let Some(value) = &expr.expr {
+                    self.space();
+                    self.expr(value);
+                }
+            }
+            ast::Expr::Block(expr) => {
+                for token in [
+                    expr.async_token.option_span(),
+                    expr.const_token.option_span(),
+                    expr.move_token.option_span(),
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    self.token(token);
+                    self.space();
+                }
+
+                self.block(&expr.block);
+            }
+            ast::Expr::Return(expr) => {
+                self.token(expr.return_token.span());
+
+                if let Some(value) = &expr.expr {
+                    self.space();
+                    self.expr(value);
+                }
+            }
+            ast::Expr::Await(expr) => {
+                self.expr(&expr.expr);
+                self.token(expr.dot.span());
+                self.token(expr.await_token.span());
+            }
+            ast::Expr::Try(expr) => {
+                self.expr(&expr.expr);
+                self.token(expr.try_token.span());
+            }
+            ast::Expr::Select(expr) => {
+                self.token(expr.select.span());
+                self.space();
+
+                self.branches(
+                    expr.open.span(),
+                    &expr.branches,
+                    expr.close.span(),
+                    |p, branch| match branch {
+                        ast::ExprSelectBranch::Pat(branch) => {
+                            p.pat(&branch.pat);
+                            p.space();
+                            p.token(branch.eq.span());
+                            p.space();
+                            p.expr(&branch.expr);
+                            p.space();
+                            p.token(branch.rocket.span());
+                            p.space();
+                            p.expr(&branch.body);
+                            &branch.body
+                        }
+                        ast::ExprSelectBranch::Default(branch) => {
+                            p.token(branch.default.span());
+                            p.space();
+                            p.token(branch.rocket.span());
+                            p.space();
+                            p.expr(&branch.body);
+                            &branch.body
+                        }
+                    },
+                );
+            }
+            ast::Expr::Closure(expr) => {
+                if let Some(async_token) = &expr.async_token {
+                    self.token(async_token.span());
+                    self.space();
+                }
+
+                if let Some(move_token) = &expr.move_token {
+                    self.token(move_token.span());
+                    self.space();
+                }
+
+                match &expr.args {
+                    ast::ExprClosureArgs::Empty { token } => {
+                        self.token(token.span());
+                    }
+                    ast::ExprClosureArgs::List { open, args, close } => {
+                        self.token(open.span());
+
+                        for (n, (arg, comma)) in args.iter().enumerate() {
+                            self.fn_arg(arg);
+
+                            if n + 1 < args.len() {
+                                match comma {
+                                    Some(comma) => self.token(comma.span()),
+                                    None => self.write(","),
+                                }
+
+                                self.space();
+                            }
+                        }
+
+                        self.token(close.span());
+                    }
+                }
+
+                self.space();
+                self.expr(&expr.body);
+            }
+            ast::Expr::Lit(expr) => {
+                self.token(expr.lit.span());
+            }
+            ast::Expr::Object(expr) => {
+                self.object(&expr.ident, &expr.assignments, |p, assign| {
+                    match &assign.key {
+                        ast::ObjectKey::LitStr(key) => p.token(key.span()),
+                        ast::ObjectKey::Path(path) => p.path(path),
+                    }
+
+                    if let Some((colon, value)) = &assign.assign {
+                        p.token(colon.span());
+                        p.space();
+                        p.expr(value);
+                    }
+                });
+            }
+            ast::Expr::Tuple(expr) => {
+                let items = &expr.items;
+
+                self.group(
+                    items.open.span(),
+                    &items.parenthesized,
+                    items.close.span(),
+                    Layout::TUPLE,
+                    Self::expr,
+                );
+            }
+            ast::Expr::Vec(expr) => {
+                let items = &expr.items;
+
+                self.group(
+                    items.open.span(),
+                    &items.bracketed,
+                    items.close.span(),
+                    Layout::TIGHT,
+                    Self::expr,
+                );
+            }
+            ast::Expr::Range(expr) => {
+                if let Some(from) = &expr.from {
+                    self.expr(from);
+                }
+
+                self.token(expr.limits.span());
+
+                if let Some(to) = &expr.to {
+                    self.expr(to);
+                }
+            }
+            ast::Expr::Empty(expr) => {
+                // Empty groups are produced by template strings, which are
+                // kept as they are.
+                self.token(expr.span());
+            }
+            ast::Expr::Group(expr) => {
+                self.token(expr.open.span());
+                self.expr(&expr.expr);
+                self.token(expr.close.span());
+            }
+            ast::Expr::MacroCall(macro_call) => {
+                self.macro_call(macro_call);
+            }
+        }
+    }
+
+    /// Lay out a chain of method calls and field accesses, which is broken up
+    /// with one call per line if it doesn't fit on a single line or if it was
+    /// broken up in the source.
+    ///
+    /// Returns `false` if the expression isn't a chain long enough to be laid
+    /// out this way.
+    fn chain(&mut self, expr: &ast::Expr) -> bool {
+        let mut links = Vec::new();
+        let mut root = expr;
+
+        loop {
+            let (link, inner) = match root {
+                ast::Expr::FieldAccess(expr) => (Link::Field(expr), &*expr.expr),
+                ast::Expr::Await(expr) => (Link::Await(expr), &*expr.expr),
+                ast::Expr::Try(expr) => (Link::Try(expr), &*expr.expr),
+                ast::Expr::Call(expr) if matches!(&*expr.expr, ast::Expr::FieldAccess(..)) => {
+                    (Link::Call(expr), &*expr.expr)
+                }
+                _ => break,
+            };
+
+            // Attributes on an inner expression would apply to the chain up
+            // until that point, so it's left as-is.
+            if !links.is_empty() && !root.attributes().is_empty() {
+                return false;
+            }
+
+            links.push(link);
+            root = inner;
+        }
+
+        links.reverse();
+
+        let dots = links.iter().filter(|link| link.dot().is_some()).count();
+
+        if dots < 2 {
+            return false;
+        }
+
+        let broken_in_source = links.iter().filter_map(Link::dot).any(|dot| {
+            let before = &self.source[..dot.start.into_usize()];
+            before[before.trim_end().len()..].contains('\n')
+        });
+
+        if !broken_in_source {
+            let snapshot = self.snapshot();
+            let line_start = self.out.rfind('\n').map_or(0, |n| n + 1);
+
+            self.expr(root);
+            let mut last_start = self.out.len();
+
+            for link in &links {
+                last_start = self.out.len();
+                self.link(link);
+            }
+
+            // Like groups, only the last link is permitted to span multiple
+            // lines.
+            let multiline = self.out[snapshot.len..last_start].contains('\n');
+            let line = self.out[line_start..]
+                .split('\n')
+                .next()
+                .unwrap_or_default();
+
+            if !multiline && self.forced == snapshot.forced && line.chars().count() <= MAX_WIDTH {
+                return true;
+            }
+
+            self.restore(snapshot);
+        }
+
+        self.expr(root);
+
+        let mut links = links.iter().peekable();
+
+        // A chain on a plain path keeps the first call on the same line, like
+        // `client.post(url)`.
+        if let ast::Expr::Path(..) = root {
+            if let Some(link) = links.next() {
+                self.link(link);
+            }
+
+            while let Some(link) = links.next_if(|link| link.dot().is_none()) {
+                self.link(link);
+            }
+        }
+
+        self.indent += 1;
+
+        for link in links {
+            if link.dot().is_some() {
+                self.line();
+            }
+
+            self.link(link);
+        }
+
+        self.indent -= 1;
+        true
+    }
+
+    fn link(&mut self, link: &Link<'_>) {
+        match link {
+            Link::Field(expr) => {
+                self.token(expr.dot.span());
+
+                match &expr.expr_field {
+                    ast::ExprField::Path(path) => self.path(path),
+                    ast::ExprField::LitNumber(number) => self.token(number.span()),
+                }
+            }
+            Link::Await(expr) => {
+                self.token(expr.dot.span());
+                self.token(expr.await_token.span());
+            }
+            Link::Try(expr) => {
+                self.token(expr.try_token.span());
+            }
+            Link::Call(expr) => {
+                let args = &expr.args;
+
+                self.group(
+                    args.open.span(),
+                    &args.parenthesized,
+                    args.close.span(),
+                    Layout::TIGHT,
+                    Self::expr,
+                );
+            }
+        }
+    }
+
+    fn expr_let(&mut self, expr: &ast::ExprLet) {
+        self.token(expr.let_token.span());
+        self.space();
+        self.pat(&expr.pat);
+        self.space();
+        self.token(expr.eq.span());
+        self.space();
+        self.expr(&expr.expr);
+    }
+
+    fn condition(&mut self, condition: &ast::Condition) {
+        match condition {
+            ast::Condition::Expr(expr) => self.expr(expr),
+            ast::Condition::ExprLet(expr) => self.expr_let(expr),
+        }
+    }
+
+    fn label(&mut self, label: &Option<(ast::Label, T![:])>) {
+        if let Some((label, colon)) = label {
+            self.token(label.span());
+            self.token(colon.span());
+            self.space();
+        }
+    }
+
+    /// Macro calls are written as they appear in the source, since their input
+    /// is an arbitrary stream of tokens.
+    fn macro_call(&mut self, macro_call: &ast::MacroCall) {
+        self.item_attributes(&macro_call.attributes);
+        self.token(macro_call.path.span().join(macro_call.close.span()));
+    }
+
+    fn object<T>(
+        &mut self,
+        ident: &ast::ObjectIdent,
+        items: &ast::Braced<T, T![,]>,
+        item: impl FnMut(&mut Self, &T),
+    ) where
+        T: Spanned,
+    {
+        let layout = match ident {
+            ast::ObjectIdent::Anonymous(hash) => {
+                self.token(hash.span());
+                Layout::TIGHT
+            }
+            ast::ObjectIdent::Named(path) => {
+                self.path(path);
+                self.space();
+                Layout::PADDED
+            }
+        };
+
+        self.group(
+            items.open.span(),
+            &items.braced,
+            items.close.span(),
+            layout,
+            item,
+        );
+    }
+
+    fn pat(&mut self, pat: &ast::Pat) {
+        match pat {
+            ast::Pat::PatIgnore(pat) => {
+                self.inline_attributes(&pat.attributes);
+                self.token(pat.underscore.span());
+            }
+            ast::Pat::PatPath(pat) => {
+                self.inline_attributes(&pat.attributes);
+                self.path(&pat.path);
+            }
+            ast::Pat::PatLit(pat) => {
+                self.inline_attributes(&pat.attributes);
+                self.expr(&pat.expr);
+            }
+            ast::Pat::PatVec(pat) => {
+                self.inline_attributes(&pat.attributes);
+
+                let items = &pat.items;
+
+                self.group(
+                    items.open.span(),
+                    &items.bracketed,
+                    items.close.span(),
+                    Layout::TIGHT,
+                    Self::pat,
+                );
+            }
+            ast::Pat::PatTuple(pat) => {
+                self.inline_attributes(&pat.attributes);
+
+                let layout = match &pat.path {
+                    Some(path) => {
+                        self.path(path);
+                        Layout::TIGHT
+                    }
+                    None => Layout::TUPLE,
+                };
+
+                let items = &pat.items;
+
+                self.group(
+                    items.open.span(),
+                    &items.parenthesized,
+                    items.close.span(),
+                    layout,
+                    Self::pat,
+                );
+            }
+            ast::Pat::PatObject(pat) => {
+                self.inline_attributes(&pat.attributes);
+                self.object(&pat.ident, &pat.items, Self::pat);
+            }
+            ast::Pat::PatBinding(pat) => {
+                self.inline_attributes(&pat.attributes);
+
+                match &pat.key {
+                    ast::ObjectKey::LitStr(key) => self.token(key.span()),
+                    ast::ObjectKey::Path(path) => self.path(path),
+                }
+
+                self.token(pat.colon.span());
+                self.space();
+                self.pat(&pat.pat);
+            }
+            ast::Pat::PatRest(pat) => {
+                self.inline_attributes(&pat.attributes);
+                self.token(pat.dot_dot.span());
+            }
+            ast::Pat::PatRange(pat) => {
+                self.inline_attributes(&pat.attributes);
+                self.expr(&pat.start);
+                self.token(pat.limits.span());
+                self.expr(&pat.end);
+            }
+            ast::Pat::PatOr(pat) => {
+                self.pat(&pat.first);
+
+                for (pipe, pat) in &pat.rest {
+                    self.space();
+                    self.token(pipe.span());
+                    self.space();
+                    self.pat(pat);
+                }
+            }
+        }
+    }
+
+    fn path(&mut self, path: &ast::Path) {
+        if let Some(global) = &path.global {
+            self.token(global.span());
+        }
+
+        self.path_segment(&path.first);
+
+        for (colon, segment) in &path.rest {
+            self.token(colon.span());
+            self.path_segment(segment);
+        }
+
+        if let Some(trailing) = &path.trailing {
+            self.token(trailing.span());
+        }
+    }
+
+    fn path_segment(&mut self, segment: &ast::PathSegment) {
+        match segment {
+            ast::PathSegment::Generics(generics) => {
+                self.token(generics.open.span());
+
+                for (n, (arg, comma)) in generics.angle_bracketed.iter().enumerate() {
+                    self.expr(&arg.expr);
+
+                    if n + 1 < generics.angle_bracketed.len() {
+                        match comma {
+                            Some(comma) => self.token(comma.span()),
+                            None => self.write(","),
+                        }
+
+                        self.space();
+                    }
+                }
+
+                self.token(generics.close.span());
+            }
+            segment => {
+                self.token(segment.span());
+            }
+        }
+    }
+
+    /// Lay out the branches of a `match` or `select`, each on their own line.
+    ///
+    /// Branches with block-like bodies keep their separator as written, all
+    /// other branches are terminated with a comma.
+    fn branches<'b, T>(
+        &mut self,
+        open: Span,
+        branches: &'b [(T, Option<T![,]>)],
+        close: Span,
+        mut branch: impl FnMut(&mut Self, &'b T) -> &'b ast::Expr,
+    ) where
+        T: Spanned,
+    {
+        self.token(open);
+
+        if branches.is_empty() && !self.has_comments_before(close) {
+            self.token(close);
+            return;
+        }
+
+        self.indent += 1;
+
+        for (value, comma) in branches {
+            self.separator(value.span());
+            let body = branch(self, value);
+
+            match comma {
+                Some(comma) => self.token(comma.span()),
+                None if !is_block_like(body) => self.write(","),
+                None => {}
+            }
+        }
+
+        self.close(close);
+    }
+
+    /// Lay out a delimited group of values separated by commas.
+    ///
+    /// The group is kept on a single line if it fits and the source didn't
+    /// start the values on a new line, otherwise each value is put on its own
+    /// line with a trailing comma.
+    fn group<T, S>(
+        &mut self,
+        open: Span,
+        items: &[(T, Option<S>)],
+        close: Span,
+        layout: Layout,
+        mut item: impl FnMut(&mut Self, &T),
+    ) where
+        T: Spanned,
+        S: Spanned,
+    {
+        self.token(open);
+
+        if items.is_empty() {
+            if self.has_comments_before(close) {
+                self.indent += 1;
+                self.close(close);
+            } else {
+                self.token(close);
+            }
+
+            return;
+        }
+
+        let open_end = open.end.into_usize();
+        let leading = &self.source[open_end..];
+        let leading = &leading[..leading.len() - leading.trim_start().len()];
+
+        if !leading.contains('\n') {
+            let snapshot = self.snapshot();
+
+            if self.group_line(items, close, layout, &mut item, snapshot) {
+                return;
+            }
+
+            self.restore(snapshot);
+        }
+
+        self.indent += 1;
+
+        for (value, sep) in items {
+            self.separator(value.span());
+            item(self, value);
+
+            match sep {
+                Some(sep) => self.token(sep.span()),
+                None => self.write(","),
+            }
+        }
+
+        self.close(close);
+    }
+
+    /// Try to lay out a group on a single line, returning `false` if it didn't
+    /// fit.
+    fn group_line<T, S>(
+        &mut self,
+        items: &[(T, Option<S>)],
+        close: Span,
+        layout: Layout,
+        item: &mut impl FnMut(&mut Self, &T),
+        snapshot: Snapshot,
+    ) -> bool
+    where
+        S: Spanned,
+    {
+        if layout.padding {
+            self.space();
+        }
+
+        let mut last_start = self.out.len();
+
+        for (n, (value, sep)) in items.iter().enumerate() {
+            let is_last = n + 1 == items.len();
+
+            if is_last {
+                last_start = self.out.len();
+            }
+
+            item(self, value);
+
+            if !is_last {
+                match sep {
+                    Some(sep) => self.token(sep.span()),
+                    None => self.write(","),
+                }
+
+                self.space();
+            } else if let (Some(sep), true) = (sep, layout.single_trailing && items.len() == 1) {
+                self.token(sep.span());
+            }
+        }
+
+        if layout.padding {
+            self.space();
+        }
+
+        if self.forced != snapshot.forced || self.has_comments_before(close) {
+            return false;
+        }
+
+        self.token(close);
+
+        // Only the last value is permitted to span multiple lines, like a
+        // closure with a block body.
+        if self.out[snapshot.len..last_start].contains('\n') {
+            return false;
+        }
+
+        let line_start = self.out[..last_start].rfind('\n').map_or(0, |n| n + 1);
+        let line = &self.out[line_start..];
+        let line = line.split('\n').next().unwrap_or_default();
+        line.chars().count() <= MAX_WIDTH
+    }
+
+    /// Close a block which has been laid out over multiple lines, with an
+    /// indentation which has already been increased.
+    fn close(&mut self, close: Span) {
+        self.trailing_comments();
+        self.comments_before(close.start.into_usize());
+        self.indent -= 1;
+        self.line();
+        self.token(close);
+    }
+
+    /// Start a new line for the next element in a sequence which starts at the
+    /// given span, preserving a single blank line from the source.
+    fn separator(&mut self, next: Span) {
+        self.trailing_comments();
+        self.line();
+
+        let start = next.start.into_usize();
+        self.comments_before(start);
+
+        if self.last < start && self.has_blank_line(start) {
+            self.blank_line();
+        }
+    }
+
+    /// Write the given span from the source.
+    fn token(&mut self, span: Span) {
+        self.comments_before(span.start.into_usize());
+
+        if let Some(text) = self.source.get(span.range()) {
+            self.write(text);
+        }
+
+        self.last = self.last.max(span.end.into_usize());
+    }
+
+    /// Write text, starting a new line and indenting it as necessary.
+    fn write(&mut self, text: &str) {
+        if self.line_break {
+            self.line();
+        }
+
+        if text.is_empty() {
+            return;
+        }
+
+        if self.column == 0 {
+            for _ in 0..self.indent {
+                self.out.push_str(INDENT);
+            }
+
+            self.column = self.indent * INDENT.len();
+        }
+
+        self.out.push_str(text);
+
+        self.column = match text.rfind('\n') {
+            Some(n) => text[n + 1..].chars().count(),
+            None => self.column + text.chars().count(),
+        };
+    }
+
+    /// Write a single space, unless at the start of a line.
+    fn space(&mut self) {
+        if self.column != 0 && !self.line_break && !self.out.ends_with(' ') {
+            self.out.push(' ');
+            self.column += 1;
+        }
+    }
+
+    /// Make sure that the next thing written starts on a new line.
+    fn line(&mut self) {
+        self.line_break = false;
+
+        if self.column != 0 {
+            let len = self.out.trim_end_matches(' ').len();
+            self.out.truncate(len);
+            self.out.push('\n');
+            self.column = 0;
+        }
+    }
+
+    /// Make sure that there's a blank line before the next thing written,
+    /// unless it directly follows an opening delimiter.
+    fn blank_line(&mut self) {
+        self.line();
+
+        let trimmed = self.out.trim_end();
+
+        if trimmed.is_empty() || trimmed.ends_with(['{', '(', '[']) {
+            return;
+        }
+
+        if !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Force a line break before the next thing written.
+    fn force_line_break(&mut self) {
+        if !self.line_break {
+            self.line_break = true;
+            self.forced += 1;
+        }
+    }
+
+    /// Write comments which are on the same line as the last token.
+    fn trailing_comments(&mut self) {
+        while let Some(comment) = self.comments.get(self.comment) {
+            let start = comment.span.start.into_usize();
+
+            if start < self.last {
+                break;
+            }
+
+            let gap = &self.source[self.last..start];
+
+            if !gap.trim().is_empty() || gap.contains('\n') {
+                break;
+            }
+
+            self.comments_before(start + 1);
+        }
+    }
+
+    /// Test if there are comments which haven't been written before the given
+    /// span.
+    fn has_comments_before(&self, span: Span) -> bool {
+        matches!(self.comments.get(self.comment), Some(c) if c.span.start < span.start)
+    }
+
+    /// Write all comments before the given offset.
+    fn comments_before(&mut self, offset: usize) {
+        while let Some(comment) = self.comments.get(self.comment).copied() {
+            let start = comment.span.start.into_usize();
+            let end = comment.span.end.into_usize();
+
+            if start >= offset {
+                break;
+            }
+
+            self.comment += 1;
+
+            // The comment has already been written as part of a token which
+            // is written as-is.
+            if start < self.last {
+                continue;
+            }
+
+            let gap = &self.source[self.last..start];
+
+            if self.column != 0 && !self.line_break && !gap.contains('\n') {
+                self.space();
+            } else {
+                self.line();
+
+                if self.has_blank_line(start) {
+                    self.blank_line();
+                }
+            }
+
+            self.write(self.source[comment.span.range()].trim_end());
+            self.last = end;
+
+            let rest = &self.source[end..];
+            let rest = &rest[..rest.len() - rest.trim_start().len()];
+
+            if comment.line || rest.contains('\n') {
+                self.force_line_break();
+            } else {
+                self.space();
+            }
+        }
+    }
+
+    /// Test if there's a blank line between the last token written and the
+    /// given offset.
+    ///
+    /// Line comments include the line break which terminates them, so it has
+    /// to be accounted for separately.
+    fn has_blank_line(&self, end: usize) -> bool {
+        let mut count = self.source[self.last..end].matches('\n').count();
+
+        if self.source[..self.last].ends_with('\n') {
+            count += 1;
+        }
+
+        count >= 2
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            len: self.out.len(),
+            column: self.column,
+            last: self.last,
+            comment: self.comment,
+            line_break: self.line_break,
+            forced: self.forced,
+            indent: self.indent,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.out.truncate(snapshot.len);
+        self.column = snapshot.column;
+        self.last = snapshot.last;
+        self.comment = snapshot.comment;
+        self.line_break = snapshot.line_break;
+        self.forced = snapshot.forced;
+        self.indent = snapshot.indent;
+    }
+}
+
+/// A single link in a chain of calls.
+enum Link<'a> {
+    Field(&'a ast::ExprFieldAccess),
+    Await(&'a ast::ExprAwait),
+    Try(&'a ast::ExprTry),
+    Call(&'a ast::ExprCall),
+}
+
+impl Link<'_> {
+    /// The dot that the link starts with, if any.
+    fn dot(&self) -> Option<Span> {
+        match self {
+            Link::Field(expr) => Some(expr.dot.span()),
+            Link::Await(expr) => Some(expr.dot.span()),
+            Link::Try(..) | Link::Call(..) => None,
+        }
+    }
+}
+
+/// Test if the expression is block-like, which means that it doesn't need to
+/// be followed by a comma in a match branch.
+fn is_block_like(expr: &ast::Expr) -> bool {
+    matches!(
+        expr,
+        ast::Expr::Block(..)
+            | ast::Expr::For(..)
+            | ast::Expr::While(..)
+            | ast::Expr::If(..)
+            | ast::Expr::Match(..)
+    )
+}
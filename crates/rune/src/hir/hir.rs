@@ -55,6 +55,22 @@ pub enum PatKind<'hir> {
     PatObject(&'hir PatItems<'hir>),
     /// A binding `a: pattern` or `"foo": pattern`.
     PatBinding(&'hir PatBinding<'hir>),
+    /// A range pattern `1..=9`.
+    PatRange(&'hir PatRange<'hir>),
+    /// Alternative patterns `A | B`.
+    PatOr(&'hir [Pat<'hir>]),
+}
+
+/// A range pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PatRange<'hir> {
+    /// The start of the range.
+    pub start: &'hir Expr<'hir>,
+    /// The range limits.
+    pub limits: ExprRangeLimits,
+    /// The end of the range.
+    pub end: &'hir Expr<'hir>,
 }
 
 /// A tuple pattern.
@@ -684,6 +700,8 @@ pub struct ItemFn<'hir> {
     pub name: &'hir ast::Ident,
     /// The arguments of the function.
     pub args: &'hir [FnArg<'hir>],
+    /// The annotated return type of the function.
+    pub output: Option<&'hir Type<'hir>>,
     /// The body of the function.
     pub body: &'hir Block<'hir>,
 }
@@ -696,6 +714,42 @@ pub enum FnArg<'hir> {
     SelfValue(Span),
     /// Function argument is a pattern binding.
     Pat(&'hir Pat<'hir>),
+    /// Function argument is a pattern binding with a type annotation.
+    Typed(&'hir FnArgTyped<'hir>),
+}
+
+/// A function argument with a type annotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Spanned)]
+#[non_exhaustive]
+pub struct FnArgTyped<'hir> {
+    /// The span of the argument.
+    #[rune(span)]
+    pub span: Span,
+    /// The pattern being bound.
+    pub pat: &'hir Pat<'hir>,
+    /// The type of the argument.
+    pub ty: &'hir Type<'hir>,
+}
+
+/// A type annotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Spanned)]
+#[non_exhaustive]
+pub struct Type<'hir> {
+    /// The span of the type.
+    #[rune(span)]
+    pub span: Span,
+    /// The kind of the type.
+    pub kind: TypeKind<'hir>,
+}
+
+/// The kind of a [Type].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TypeKind<'hir> {
+    /// A type referenced by path.
+    Path(&'hir Path<'hir>),
+    /// A tuple type.
+    Tuple(&'hir [Type<'hir>]),
 }
 
 /// A block of statements.
@@ -744,6 +798,8 @@ pub struct Local<'hir> {
     pub span: Span,
     /// The name of the binding.
     pub pat: &'hir Pat<'hir>,
+    /// The annotated type of the binding.
+    pub ty: Option<&'hir Type<'hir>>,
     /// The expression the binding is assigned to.
     pub expr: &'hir Expr<'hir>,
 }
@@ -85,6 +85,7 @@ pub fn item_fn<'hir>(
         visibility: alloc!(ctx, ast; visibility(ctx, &ast.visibility)?),
         name: alloc!(ctx, ast; ast.name),
         args: iter!(ctx, ast; &ast.args, |(ast, _)| fn_arg(ctx, ast)?),
+        output: option!(ctx, ast; &ast.output, |(_, ast)| ty(ctx, ast)?),
         body: alloc!(ctx, ast; block(ctx, &ast.body)?),
     })
 }
@@ -327,6 +328,24 @@ fn fn_arg<'hir>(ctx: &Ctx<'hir, '_>, ast: &ast::FnArg) -> Result<hir::FnArg<'hir
     Ok(match ast {
         ast::FnArg::SelfValue(ast) => hir::FnArg::SelfValue(ast.span()),
         ast::FnArg::Pat(ast) => hir::FnArg::Pat(alloc!(ctx, ast; pat(ctx, ast)?)),
+        ast::FnArg::Typed(ast) => hir::FnArg::Typed(alloc!(ctx, ast; hir::FnArgTyped {
+            span: ast.span(),
+            pat: alloc!(ctx, ast; pat(ctx, &ast.pat)?),
+            ty: alloc!(ctx, ast; ty(ctx, &ast.ty)?),
+        })),
+    })
+}
+
+/// Lower a type annotation.
+pub fn ty<'hir>(ctx: &Ctx<'hir, '_>, ast: &ast::Type) -> Result<hir::Type<'hir>, HirError> {
+    Ok(hir::Type {
+        span: ast.span(),
+        kind: match ast {
+            ast::Type::Path(ast) => hir::TypeKind::Path(alloc!(ctx, ast; path(ctx, ast)?)),
+            ast::Type::Tuple(ast) => {
+                hir::TypeKind::Tuple(iter!(ctx, ast; ast, |(ast, _)| ty(ctx, ast)?))
+            }
+        },
     })
 }
 
@@ -335,6 +354,7 @@ fn local<'hir>(ctx: &Ctx<'hir, '_>, ast: &ast::Local) -> Result<hir::Local<'hir>
     Ok(hir::Local {
         span: ast.span(),
         pat: alloc!(ctx, ast; pat(ctx, &ast.pat)?),
+        ty: option!(ctx, ast; &ast.ty, |(_, ast)| ty(ctx, ast)?),
         expr: alloc!(ctx, ast; expr(ctx, &ast.expr)?),
     })
 }
@@ -398,6 +418,21 @@ fn pat<'hir>(ctx: &Ctx<'hir, '_>, ast: &ast::Pat) -> Result<hir::Pat<'hir>, HirE
                     pat: alloc!(ctx, ast; pat(ctx, &ast.pat)?),
                 }))
             }
+            ast::Pat::PatRange(ast) => hir::PatKind::PatRange(alloc!(ctx, ast; hir::PatRange {
+                start: alloc!(ctx, ast; expr(ctx, &ast.start)?),
+                limits: match ast.limits {
+                    ast::ExprRangeLimits::HalfOpen(_) => hir::ExprRangeLimits::HalfOpen,
+                    ast::ExprRangeLimits::Closed(_) => hir::ExprRangeLimits::Closed,
+                },
+                end: alloc!(ctx, ast; expr(ctx, &ast.end)?),
+            })),
+            ast::Pat::PatOr(ast) => {
+                let branches = std::iter::once(&*ast.first)
+                    .chain(ast.rest.iter().map(|(_, ast)| ast))
+                    .collect::<Vec<_>>();
+
+                hir::PatKind::PatOr(iter!(ctx, ast; branches, |ast| pat(ctx, ast)?))
+            }
         },
     })
 }
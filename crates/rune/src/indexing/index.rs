@@ -3,19 +3,19 @@ use crate::ast::{OptionSpanned, Span, Spanned};
 use crate::collections::HashMap;
 use crate::compile::attrs::Attributes;
 use crate::compile::{
-    attrs, ir, CompileError, CompileErrorKind, CompileResult, Doc, ItemId, Location, ModId,
-    Options, SourceLoader, Visibility,
+    attrs, ir, CompileError, CompileErrorKind, CompileResult, Doc, ItemId, ItemMeta, Location,
+    ModId, Options, SourceLoader, Visibility,
 };
 use crate::indexing::locals;
 use crate::indexing::{IndexFnKind, IndexScopes};
 use crate::macros::MacroCompiler;
 use crate::parse::{Parse, ParseError, ParseErrorKind, Parser, Resolve};
 use crate::query::{
-    BuiltInFile, BuiltInFormat, BuiltInLine, BuiltInMacro, BuiltInTemplate, Function, Indexed,
-    IndexedEntry, IndexedFunction, InstanceFunction, Query,
+    BuiltInFile, BuiltInFormat, BuiltInLine, BuiltInMacro, BuiltInTemplate, FnSignature, Function,
+    Indexed, IndexedEntry, IndexedFunction, InstanceFunction, Query, Trait, TraitFn, TraitImpl,
 };
 use crate::runtime::format;
-use crate::runtime::Call;
+use crate::runtime::{Call, Protocol};
 use crate::shared::{Items, MissingLastId};
 use crate::worker::{Import, ImportKind, LoadFileKind, Task};
 use crate::{Context, Diagnostics, SourceId};
@@ -641,66 +641,10 @@ fn item_fn(ast: &mut ast::ItemFn, idx: &mut Indexer<'_>) -> CompileResult<()> {
         &docs,
     )?;
 
-    let kind = match (ast.const_token, ast.async_token) {
-        (Some(const_token), Some(async_token)) => {
-            return Err(CompileError::new(
-                const_token.span().join(async_token.span()),
-                CompileErrorKind::FnConstAsyncConflict,
-            ));
-        }
-        (Some(..), _) => IndexFnKind::Const,
-        (_, Some(..)) => IndexFnKind::Async,
-        _ => IndexFnKind::None,
-    };
-
-    if let (Some(const_token), Some(async_token)) = (ast.const_token, ast.async_token) {
-        return Err(CompileError::new(
-            const_token.span().join(async_token.span()),
-            CompileErrorKind::FnConstAsyncConflict,
-        ));
-    }
-
-    let guard = idx.scopes.push_function(kind);
-
-    for (arg, _) in &mut ast.args {
-        match arg {
-            ast::FnArg::SelfValue(s) => {
-                let span = s.span();
-                idx.scopes.declare(SELF, span)?;
-            }
-            ast::FnArg::Pat(p) => {
-                locals::pat(p, idx)?;
-            }
-        }
-    }
-
-    // Take and restore item nesting.
-    let last = idx.nested_item.replace(ast.descriptive_span());
-    block(&mut ast.body, idx)?;
-    idx.nested_item = last;
-
-    let f = guard.into_function(span)?;
-    ast.id = item_meta.id;
-
-    let call = match Indexer::call(f.generator, f.kind) {
-        Some(call) => call,
-        // const function.
-        None => {
-            if f.generator {
-                return Err(CompileError::new(
-                    span,
-                    CompileErrorKind::FnConstNotGenerator,
-                ));
-            }
-
-            idx.q.index_const_fn(item_meta, Box::new(ast.clone()))?;
-            return Ok(());
-        }
-    };
-
-    let function = Function {
-        ast: Box::new(ast.clone()),
-        call,
+    let function = match fn_body(ast, idx, item_meta)? {
+        Some(function) => function,
+        // const function, which has been indexed separately.
+        None => return Ok(()),
     };
 
     // NB: it's only a public item in the sense of exporting it if it's not
@@ -739,6 +683,22 @@ fn item_fn(ast: &mut ast::ItemFn, idx: &mut Indexer<'_>) -> CompileResult<()> {
         _ => false,
     };
 
+    let protocol = match attributes.try_parse::<attrs::Protocol>(resolve_context!(idx.q))? {
+        Some((span, attr)) => {
+            let name = attr.name.resolve(resolve_context!(idx.q))?;
+
+            let protocol = Protocol::from_name(name).ok_or_else(|| {
+                CompileError::new(
+                    attr.name.span(),
+                    CompileErrorKind::MissingProtocol { name: name.into() },
+                )
+            })?;
+
+            Some((span, protocol))
+        }
+        None => None,
+    };
+
     if let Some(attrs) = attributes.remaining() {
         return Err(CompileError::msg(attrs, "unrecognized function attribute"));
     }
@@ -768,9 +728,17 @@ fn item_fn(ast: &mut ast::ItemFn, idx: &mut Indexer<'_>) -> CompileResult<()> {
                 function,
                 impl_item,
                 instance_span: span,
+                protocol: protocol.map(|(_, protocol)| protocol),
             }),
         });
     } else {
+        if let Some((span, _)) = protocol {
+            return Err(CompileError::msg(
+                span,
+                "#[protocol] is only supported on member functions",
+            ));
+        }
+
         let entry = IndexedEntry {
             item_meta,
             indexed: Indexed::Function(IndexedFunction {
@@ -790,6 +758,107 @@ fn item_fn(ast: &mut ast::ItemFn, idx: &mut Indexer<'_>) -> CompileResult<()> {
     Ok(())
 }
 
+/// Index the arguments and body of a function, returning how it should be
+/// built. Constant functions are indexed immediately, in which case `None` is
+/// returned.
+fn fn_body(
+    ast: &mut ast::ItemFn,
+    idx: &mut Indexer<'_>,
+    item_meta: ItemMeta,
+) -> CompileResult<Option<Function>> {
+    let span = ast.span();
+
+    let kind = match (ast.const_token, ast.async_token) {
+        (Some(const_token), Some(async_token)) => {
+            return Err(CompileError::new(
+                const_token.span().join(async_token.span()),
+                CompileErrorKind::FnConstAsyncConflict,
+            ));
+        }
+        (Some(..), _) => IndexFnKind::Const,
+        (_, Some(..)) => IndexFnKind::Async,
+        _ => IndexFnKind::None,
+    };
+
+    if let (Some(const_token), Some(async_token)) = (ast.const_token, ast.async_token) {
+        return Err(CompileError::new(
+            const_token.span().join(async_token.span()),
+            CompileErrorKind::FnConstAsyncConflict,
+        ));
+    }
+
+    let guard = idx.scopes.push_function(kind);
+
+    for (arg, _) in &mut ast.args {
+        match arg {
+            ast::FnArg::SelfValue(s) => {
+                let span = s.span();
+                idx.scopes.declare(SELF, span)?;
+            }
+            ast::FnArg::Pat(p) => {
+                locals::pat(p, idx)?;
+            }
+            ast::FnArg::Typed(typed) => {
+                ty(&mut typed.ty, idx)?;
+                locals::pat(&mut typed.pat, idx)?;
+            }
+        }
+    }
+
+    if let Some((_, output)) = &mut ast.output {
+        ty(output, idx)?;
+    }
+
+    let args = ast
+        .args
+        .iter()
+        .map(|(arg, _)| match arg {
+            ast::FnArg::Typed(typed) => Some(typed.ty.clone()),
+            _ => None,
+        })
+        .collect::<Box<[_]>>();
+
+    if ast.output.is_some() || args.iter().any(Option::is_some) {
+        idx.q.insert_fn_signature(
+            item_meta.item,
+            FnSignature {
+                args,
+                output: ast.output.as_ref().map(|(_, ty)| ty.clone()),
+                is_async: ast.async_token.is_some(),
+            },
+        );
+    }
+
+    // Take and restore item nesting.
+    let last = idx.nested_item.replace(ast.descriptive_span());
+    block(&mut ast.body, idx)?;
+    idx.nested_item = last;
+
+    let f = guard.into_function(span)?;
+    ast.id = item_meta.id;
+
+    let call = match Indexer::call(f.generator, f.kind) {
+        Some(call) => call,
+        // const function.
+        None => {
+            if f.generator {
+                return Err(CompileError::new(
+                    span,
+                    CompileErrorKind::FnConstNotGenerator,
+                ));
+            }
+
+            idx.q.index_const_fn(item_meta, Box::new(ast.clone()))?;
+            return Ok(None);
+        }
+    };
+
+    Ok(Some(Function {
+        ast: Box::new(ast.clone()),
+        call,
+    }))
+}
+
 #[instrument]
 fn expr_block(ast: &mut ast::ExprBlock, idx: &mut Indexer<'_>) -> CompileResult<()> {
     let span = ast.span();
@@ -910,7 +979,7 @@ fn block(ast: &mut ast::Block, idx: &mut Indexer<'_>) -> CompileResult<()> {
             ast::Stmt::Semi(semi) => {
                 if !semi.needs_semi() {
                     idx.diagnostics
-                        .uneccessary_semi_colon(idx.source_id, semi.span());
+                        .uneccessary_semi_colon(idx.source_id, semi.semi_token.span());
                 }
 
                 expr(&mut semi.expr, idx, IS_USED)?;
@@ -939,6 +1008,28 @@ fn local(ast: &mut ast::Local, idx: &mut Indexer<'_>) -> CompileResult<()> {
     // declaration and use that instead of capturing from the outside.
     expr(&mut ast.expr, idx, IS_USED)?;
     pat(&mut ast.pat, idx, NOT_USED)?;
+
+    if let Some((_, ast)) = &mut ast.ty {
+        ty(ast, idx)?;
+    }
+
+    Ok(())
+}
+
+/// Index a type annotation.
+#[instrument]
+fn ty(ast: &mut ast::Type, idx: &mut Indexer<'_>) -> CompileResult<()> {
+    match ast {
+        ast::Type::Path(ast) => {
+            path(ast, idx, NOT_USED)?;
+        }
+        ast::Type::Tuple(ast) => {
+            for (ast, _) in &mut ast.parenthesized {
+                ty(ast, idx)?;
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -981,8 +1072,16 @@ fn pat(ast: &mut ast::Pat, idx: &mut Indexer<'_>, is_used: IsUsed) -> CompileRes
         ast::Pat::PatBinding(pat) => {
             pat_binding(pat, idx)?;
         }
+        ast::Pat::PatOr(pat) => {
+            self::pat(&mut pat.first, idx, is_used)?;
+
+            for (_, p) in &mut pat.rest {
+                self::pat(p, idx, is_used)?;
+            }
+        }
         ast::Pat::PatIgnore(..) => (),
         ast::Pat::PatLit(..) => (),
+        ast::Pat::PatRange(..) => (),
         ast::Pat::PatRest(..) => (),
     }
 
@@ -1245,7 +1344,13 @@ fn item_enum(ast: &mut ast::ItemEnum, idx: &mut Indexer<'_>) -> CompileResult<()
         &docs,
     )?;
 
-    idx.q.index_enum(enum_item)?;
+    let variants = ast
+        .variants
+        .iter()
+        .map(|(variant, _)| Ok(variant.name.resolve(resolve_context!(idx.q))?.into()))
+        .collect::<CompileResult<_>>()?;
+
+    idx.q.index_enum(enum_item, variants)?;
 
     for (index, (variant, _)) in ast.variants.iter_mut().enumerate() {
         let mut attrs = Attributes::new(variant.attributes.to_vec());
@@ -1371,6 +1476,10 @@ fn item_impl(ast: &mut ast::ItemImpl, idx: &mut Indexer<'_>) -> CompileResult<()
         ));
     }
 
+    if let Some((trait_path, _)) = &mut ast.trait_ {
+        path(trait_path, idx, NOT_USED)?;
+    }
+
     let mut guards = Vec::new();
 
     if let Some(global) = &ast.path.global {
@@ -1396,6 +1505,153 @@ fn item_impl(ast: &mut ast::ItemImpl, idx: &mut Indexer<'_>) -> CompileResult<()
     }
 
     idx.impl_item = old;
+
+    if let Some((trait_path, _)) = &ast.trait_ {
+        let functions = ast
+            .functions
+            .iter()
+            .map(|f| {
+                let name = f.name.resolve(resolve_context!(idx.q))?;
+                Ok((f.descriptive_span(), name.into()))
+            })
+            .collect::<CompileResult<Vec<_>>>()?;
+
+        let _guard = idx.items.push_id();
+
+        let item_meta = idx.q.insert_new_item(
+            &idx.items,
+            Location::new(idx.source_id, ast.span()),
+            idx.mod_item,
+            Visibility::Inherited,
+            &[],
+        )?;
+
+        idx.q.index_trait_impl(
+            item_meta,
+            TraitImpl {
+                path: Box::new(trait_path.clone()),
+                impl_item: new,
+                impl_span: ast.path.span(),
+                functions,
+            },
+        );
+    }
+
+    Ok(())
+}
+
+#[instrument]
+fn item_trait(ast: &mut ast::ItemTrait, idx: &mut Indexer<'_>) -> CompileResult<()> {
+    let span = ast.span();
+    let mut attrs = Attributes::new(ast.attributes.to_vec());
+    let docs = Doc::collect_from(resolve_context!(idx.q), &mut attrs)?;
+
+    if let Some(first) = attrs.remaining() {
+        return Err(CompileError::msg(
+            first,
+            "trait attributes are not supported",
+        ));
+    }
+
+    let name = ast.ident.resolve(resolve_context!(idx.q))?;
+    let _guard = idx.items.push_name(name.as_ref());
+
+    let item_meta = idx.q.insert_new_item(
+        &idx.items,
+        Location::new(idx.source_id, span),
+        idx.mod_item,
+        ast_to_visibility(&ast.visibility)?,
+        &docs,
+    )?;
+
+    ast.id = item_meta.id;
+
+    let old = idx.impl_item.replace(item_meta.item);
+
+    let mut seen = HashMap::<Box<str>, Span>::new();
+    let mut required = Vec::new();
+    let mut provided = Vec::new();
+
+    for f in &mut ast.functions {
+        let span = f.name().span();
+        let name: Box<str> = f.name().resolve(resolve_context!(idx.q))?.into();
+
+        if let Some(existing) = seen.insert(name.clone(), span) {
+            return Err(CompileError::new(
+                span,
+                CompileErrorKind::TraitFnConflict { name, existing },
+            ));
+        }
+
+        match f {
+            ast::TraitFn::Required(decl) => {
+                if !decl.is_instance() {
+                    return Err(CompileError::new(
+                        span,
+                        CompileErrorKind::TraitFnNotInstance { name },
+                    ));
+                }
+
+                let mut attrs = Attributes::new(decl.attributes.to_vec());
+                Doc::collect_from(resolve_context!(idx.q), &mut attrs)?;
+
+                if let Some(first) = attrs.remaining() {
+                    return Err(CompileError::msg(first, "unrecognized function attribute"));
+                }
+
+                required.push(name);
+            }
+            ast::TraitFn::Provided(item_fn) => {
+                if !item_fn.is_instance() {
+                    return Err(CompileError::new(
+                        span,
+                        CompileErrorKind::TraitFnNotInstance { name },
+                    ));
+                }
+
+                if let Some(const_token) = &item_fn.const_token {
+                    return Err(CompileError::msg(
+                        const_token,
+                        "const functions are not supported in traits",
+                    ));
+                }
+
+                let _guard = idx.items.push_name(name.as_ref());
+
+                let mut attrs = Attributes::new(item_fn.attributes.to_vec());
+                let docs = Doc::collect_from(resolve_context!(idx.q), &mut attrs)?;
+
+                if let Some(first) = attrs.remaining() {
+                    return Err(CompileError::msg(first, "unrecognized function attribute"));
+                }
+
+                let fn_meta = idx.q.insert_new_item(
+                    &idx.items,
+                    Location::new(idx.source_id, item_fn.span()),
+                    idx.mod_item,
+                    Visibility::Inherited,
+                    &docs,
+                )?;
+
+                // NB: const functions are rejected above.
+                if let Some(function) = fn_body(item_fn, idx, fn_meta)? {
+                    provided.push(TraitFn {
+                        name,
+                        location: fn_meta.location,
+                        function,
+                    });
+                }
+            }
+        }
+    }
+
+    idx.impl_item = old;
+
+    idx.q.index(IndexedEntry {
+        item_meta,
+        indexed: Indexed::Trait(Trait { required, provided }),
+    });
+
     Ok(())
 }
 
@@ -1501,6 +1757,9 @@ fn item(ast: &mut ast::Item, idx: &mut Indexer<'_>) -> CompileResult<()> {
         ast::Item::Impl(item) => {
             item_impl(item, idx)?;
         }
+        ast::Item::Trait(item) => {
+            item_trait(item, idx)?;
+        }
         ast::Item::Mod(item) => {
             item_mod(item, idx)?;
         }
@@ -1632,6 +1891,10 @@ fn expr_closure(ast: &mut ast::ExprClosure, idx: &mut Indexer<'_>) -> CompileRes
             ast::FnArg::Pat(p) => {
                 locals::pat(p, idx)?;
             }
+            ast::FnArg::Typed(typed) => {
+                ty(&mut typed.ty, idx)?;
+                locals::pat(&mut typed.pat, idx)?;
+            }
         }
     }
 
@@ -24,8 +24,16 @@ pub(crate) fn pat(ast: &mut ast::Pat, idx: &mut Indexer<'_>) -> CompileResult<()
         ast::Pat::PatBinding(p) => {
             pat_binding(p, idx)?;
         }
+        ast::Pat::PatOr(p) => {
+            pat(&mut p.first, idx)?;
+
+            for (_, p) in &mut p.rest {
+                pat(p, idx)?;
+            }
+        }
         ast::Pat::PatIgnore(..) => (),
         ast::Pat::PatLit(..) => (),
+        ast::Pat::PatRange(..) => (),
         ast::Pat::PatRest(..) => (),
     }
 
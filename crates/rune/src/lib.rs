@@ -224,6 +224,8 @@ pub mod diagnostics;
 #[doc(inline)]
 pub use self::diagnostics::Diagnostics;
 
+pub mod fmt;
+
 mod hash;
 pub use self::hash::{Hash, InstFnInfo, InstFnKind, InstFnName, IntoTypeHash, Params};
 
@@ -1,6 +1,7 @@
 //! `std::bytes` module.
 
-use crate::runtime::Bytes;
+use crate::runtime::memory;
+use crate::runtime::{Bytes, Mut, VmError};
 use crate::{ContextError, Module};
 
 /// Construct the `std::bytes` module.
@@ -9,21 +10,50 @@ pub fn module() -> Result<Module, ContextError> {
 
     module.ty::<Bytes>()?;
     module.function(&["Bytes", "new"], Bytes::new)?;
-    module.function(&["Bytes", "with_capacity"], Bytes::with_capacity)?;
+    module.function(&["Bytes", "with_capacity"], with_capacity)?;
     module.function(&["Bytes", "from_vec"], Bytes::from_vec)?;
 
     module.inst_fn("into_vec", Bytes::into_vec)?;
-    module.inst_fn("extend", Bytes::extend)?;
-    module.inst_fn("extend_str", Bytes::extend_str)?;
+    module.inst_fn("extend", extend)?;
+    module.inst_fn("extend_str", extend_str)?;
     module.inst_fn("pop", Bytes::pop)?;
     module.inst_fn("last", Bytes::last)?;
 
     module.inst_fn("len", Bytes::len)?;
     module.inst_fn("capacity", Bytes::capacity)?;
     module.inst_fn("clear", Bytes::clear)?;
-    module.inst_fn("reserve", Bytes::reserve)?;
-    module.inst_fn("reserve_exact", Bytes::reserve_exact)?;
+    module.inst_fn("reserve", reserve)?;
+    module.inst_fn("reserve_exact", reserve_exact)?;
     module.inst_fn("clone", Bytes::clone)?;
     module.inst_fn("shrink_to_fit", Bytes::shrink_to_fit)?;
     Ok(module)
 }
+
+fn with_capacity(capacity: usize) -> Result<Bytes, VmError> {
+    memory::check(capacity)?;
+    Ok(Bytes::with_capacity(capacity))
+}
+
+fn extend(mut bytes: Mut<Bytes>, other: &Bytes) -> Result<(), VmError> {
+    Ok(memory::resize(&mut bytes, other.len(), |bytes| {
+        bytes.extend(other)
+    })?)
+}
+
+fn extend_str(mut bytes: Mut<Bytes>, s: &str) -> Result<(), VmError> {
+    Ok(memory::resize(&mut bytes, s.len(), |bytes| {
+        bytes.extend_str(s)
+    })?)
+}
+
+fn reserve(mut bytes: Mut<Bytes>, additional: usize) -> Result<(), VmError> {
+    Ok(memory::resize(&mut bytes, additional, |bytes| {
+        bytes.reserve(additional)
+    })?)
+}
+
+fn reserve_exact(mut bytes: Mut<Bytes>, additional: usize) -> Result<(), VmError> {
+    Ok(memory::resize(&mut bytes, additional, |bytes| {
+        bytes.reserve_exact(additional)
+    })?)
+}
@@ -1,6 +1,7 @@
 //! The `std::object` module.
 
-use crate::runtime::{Iterator, Object, Protocol, Value};
+use crate::runtime::memory;
+use crate::runtime::{Iterator, Mut, Object, Protocol, Value, VmError};
 use crate::{ContextError, Module};
 
 /// Construct the `std::object` module.
@@ -10,7 +11,7 @@ pub fn module() -> Result<Module, ContextError> {
     module.ty::<Object>()?;
 
     module.inst_fn("len", Object::len)?;
-    module.inst_fn("insert", Object::insert)?;
+    module.inst_fn("insert", insert)?;
     module.inst_fn("remove", remove)?;
     module.inst_fn("clear", Object::clear)?;
     module.inst_fn("contains_key", contains_key)?;
@@ -27,6 +28,10 @@ fn contains_key(object: &Object, key: &str) -> bool {
     object.contains_key(key)
 }
 
+fn insert(mut object: Mut<Object>, key: String, value: Value) -> Result<Option<Value>, VmError> {
+    Ok(memory::insert_entry(&mut object, key, value)?)
+}
+
 fn remove(object: &mut Object, key: &str) -> Option<Value> {
     object.remove(key)
 }
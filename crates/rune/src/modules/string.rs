@@ -1,6 +1,7 @@
 //! The `std::string` module.
 
-use crate::runtime::{Bytes, Iterator, Protocol, Value, VmError, VmErrorKind};
+use crate::runtime::memory;
+use crate::runtime::{Bytes, Iterator, Mut, Protocol, Value, VmError, VmErrorKind};
 use crate::{Any, ContextError, Module};
 
 /// Construct the `std::string` module.
@@ -11,7 +12,7 @@ pub fn module() -> Result<Module, ContextError> {
 
     module.function(&["String", "from_str"], <String as From<&str>>::from)?;
     module.function(&["String", "new"], String::new)?;
-    module.function(&["String", "with_capacity"], String::with_capacity)?;
+    module.function(&["String", "with_capacity"], with_capacity)?;
 
     module.inst_fn("cmp", str::cmp)?;
     module.inst_fn("len", String::len)?;
@@ -19,10 +20,10 @@ pub fn module() -> Result<Module, ContextError> {
     module.inst_fn("ends_with", str::ends_with::<&str>)?;
     module.inst_fn("capacity", String::capacity)?;
     module.inst_fn("clear", String::clear)?;
-    module.inst_fn("push", String::push)?;
-    module.inst_fn("push_str", String::push_str)?;
-    module.inst_fn("reserve", String::reserve)?;
-    module.inst_fn("reserve_exact", String::reserve_exact)?;
+    module.inst_fn("push", push)?;
+    module.inst_fn("push_str", push_str)?;
+    module.inst_fn("reserve", reserve)?;
+    module.inst_fn("reserve_exact", reserve_exact)?;
     module.inst_fn("into_bytes", into_bytes)?;
     module.inst_fn("clone", String::clone)?;
     module.inst_fn("shrink_to_fit", String::shrink_to_fit)?;
@@ -36,7 +37,7 @@ pub fn module() -> Result<Module, ContextError> {
     module.inst_fn("is_empty", str::is_empty)?;
     module.inst_fn("chars", string_chars)?;
     module.inst_fn(Protocol::ADD, add)?;
-    module.inst_fn(Protocol::ADD_ASSIGN, String::push_str)?;
+    module.inst_fn(Protocol::ADD_ASSIGN, push_str)?;
     module.inst_fn(Protocol::INDEX_GET, string_index_get)?;
     module.inst_fn("get", string_get)?;
 
@@ -63,6 +64,31 @@ impl NotCharBoundary {
     }
 }
 
+fn with_capacity(capacity: usize) -> Result<String, VmError> {
+    memory::check(capacity)?;
+    Ok(String::with_capacity(capacity))
+}
+
+fn push(mut s: Mut<String>, c: char) -> Result<(), VmError> {
+    Ok(memory::resize(&mut s, c.len_utf8(), |s| s.push(c))?)
+}
+
+fn push_str(mut s: Mut<String>, other: &str) -> Result<(), VmError> {
+    Ok(memory::resize(&mut s, other.len(), |s| s.push_str(other))?)
+}
+
+fn reserve(mut s: Mut<String>, additional: usize) -> Result<(), VmError> {
+    Ok(memory::resize(&mut s, additional, |s| {
+        s.reserve(additional)
+    })?)
+}
+
+fn reserve_exact(mut s: Mut<String>, additional: usize) -> Result<(), VmError> {
+    Ok(memory::resize(&mut s, additional, |s| {
+        s.reserve_exact(additional)
+    })?)
+}
+
 /// into_bytes shim for strings.
 fn into_bytes(s: String) -> Bytes {
     Bytes::from_vec(s.into_bytes())
@@ -1,6 +1,7 @@
 //! The `std::vec` module.
 
-use crate::runtime::{Function, Protocol, TypeOf, Value, Vec};
+use crate::runtime::memory;
+use crate::runtime::{Function, Mut, Protocol, TypeOf, Value, Vec, VmError};
 use crate::{ContextError, Module, Params};
 
 /// Construct the `std::vec` module.
@@ -12,15 +13,15 @@ pub fn module() -> Result<Module, ContextError> {
     module.function(&["Vec", "new"], Vec::new)?;
     module.inst_fn("clear", Vec::clear)?;
     module.inst_fn("clone", Vec::clone)?;
-    module.inst_fn("extend", Vec::extend)?;
+    module.inst_fn("extend", extend)?;
     module.inst_fn("get", vec_get)?;
     module.inst_fn("iter", Vec::into_iterator)?;
     module.inst_fn("len", Vec::len)?;
     module.inst_fn("pop", Vec::pop)?;
-    module.inst_fn("push", Vec::push)?;
+    module.inst_fn("push", push)?;
     module.inst_fn("remove", Vec::remove)?;
     module.inst_fn("sort_by", sort_by)?;
-    module.inst_fn("insert", Vec::insert)?;
+    module.inst_fn("insert", insert)?;
     module.inst_fn(Protocol::INTO_ITER, Vec::into_iterator)?;
     module.inst_fn(Protocol::INDEX_SET, Vec::set)?;
 
@@ -38,6 +39,26 @@ fn sort_int(vec: &mut Vec) {
     });
 }
 
+fn push(mut vec: Mut<Vec>, value: Value) -> Result<(), VmError> {
+    Ok(memory::resize(&mut vec, 1, |vec| vec.push(value))?)
+}
+
+fn insert(mut vec: Mut<Vec>, index: usize, value: Value) -> Result<(), VmError> {
+    Ok(memory::resize(&mut vec, 1, |vec| vec.insert(index, value))?)
+}
+
+fn extend(mut vec: Mut<Vec>, value: Value) -> Result<(), VmError> {
+    let mut it = value.into_iter()?;
+
+    // NB: the length of the iterator isn't known up front, so each value is
+    // checked against the limit as it's pushed.
+    while let Some(value) = it.next()? {
+        memory::resize(&mut vec, 1, |vec| vec.push(value))?;
+    }
+
+    Ok(())
+}
+
 fn vec_get(vec: &Vec, index: usize) -> Option<Value> {
     vec.get(index).cloned()
 }
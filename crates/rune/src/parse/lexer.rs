@@ -6,6 +6,31 @@ use std::collections::VecDeque;
 use std::fmt;
 
 /// Lexer for the rune language.
+///
+/// Note that the lexer also produces whitespace and comment tokens, which are
+/// ignored by the parser.
+///
+/// # Examples
+///
+/// ```
+/// use rune::ast;
+/// use rune::parse::Lexer;
+/// use rune::SourceId;
+///
+/// let mut lexer = Lexer::new("let a = 42;", SourceId::empty(), false);
+/// let mut kinds = Vec::new();
+///
+/// while let Some(token) = lexer.next()? {
+///     if !matches!(token.kind, ast::Kind::Whitespace) {
+///         kinds.push(token.kind);
+///     }
+/// }
+///
+/// assert!(matches!(kinds[0], ast::Kind::Let));
+/// assert!(matches!(kinds[1], ast::Kind::Ident(..)));
+/// assert_eq!(kinds.len(), 5);
+/// # Ok::<_, rune::parse::ParseError>(())
+/// ```
 #[derive(Debug)]
 pub struct Lexer<'a> {
     /// The source identifier of the lexed data.
@@ -22,7 +47,9 @@ pub struct Lexer<'a> {
 
 impl<'a> Lexer<'a> {
     /// Construct a new lexer over the given source.
-    pub(crate) fn new(source: &'a str, source_id: SourceId, shebang: bool) -> Self {
+    ///
+    /// If `shebang` is set, a leading `#!` line is lexed as a shebang token.
+    pub fn new(source: &'a str, source_id: SourceId, shebang: bool) -> Self {
         Self {
             iter: SourceIter::new(source),
             source_id,
@@ -539,7 +566,7 @@ impl<'a> Lexer<'a> {
 
     /// Consume the next token from the lexer.
     #[allow(clippy::should_implement_trait)]
-    pub(crate) fn next(&mut self) -> Result<Option<ast::Token>, ParseError> {
+    pub fn next(&mut self) -> Result<Option<ast::Token>, ParseError> {
         'outer: loop {
             if let Some(token) = self.buffer.pop_front() {
                 return Ok(Some(token));
@@ -726,6 +753,12 @@ impl<'a> Lexer<'a> {
                             self.iter.next();
                             break ast::Kind::Arrow;
                         }
+                        // NB: identifiers may start with an underscore, like
+                        // `_unused`, while a single `_` is the underscore
+                        // token.
+                        ('_', 'a'..='z' | 'A'..='Z' | '_' | '0'..='9') => {
+                            return self.next_ident(start);
+                        }
                         ('b', '\'') => {
                             self.iter.next();
                             self.iter.next();
@@ -1231,6 +1264,49 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_underscore_idents() {
+        test_lexer! {
+            "[_,_foo,_0,__]",
+            ast::Token {
+                span: span!(0, 1),
+                kind: ast::Kind::Open(ast::Delimiter::Bracket),
+            },
+            ast::Token {
+                span: span!(1, 2),
+                kind: ast::Kind::Underscore,
+            },
+            ast::Token {
+                span: span!(2, 3),
+                kind: ast::Kind::Comma,
+            },
+            ast::Token {
+                span: span!(3, 7),
+                kind: ast::Kind::Ident(ast::LitSource::Text(SourceId::EMPTY)),
+            },
+            ast::Token {
+                span: span!(7, 8),
+                kind: ast::Kind::Comma,
+            },
+            ast::Token {
+                span: span!(8, 10),
+                kind: ast::Kind::Ident(ast::LitSource::Text(SourceId::EMPTY)),
+            },
+            ast::Token {
+                span: span!(10, 11),
+                kind: ast::Kind::Comma,
+            },
+            ast::Token {
+                span: span!(11, 13),
+                kind: ast::Kind::Ident(ast::LitSource::Text(SourceId::EMPTY)),
+            },
+            ast::Token {
+                span: span!(13, 14),
+                kind: ast::Kind::Close(ast::Delimiter::Bracket),
+            },
+        };
+    }
+
     #[test]
     fn test_doc_strings() {
         test_lexer! {
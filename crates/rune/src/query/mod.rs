@@ -21,7 +21,7 @@ use crate::hir;
 use crate::macros::Storage;
 use crate::parse::{Id, NonZeroId, Opaque, Resolve, ResolveContext};
 use crate::runtime::format;
-use crate::runtime::Call;
+use crate::runtime::{Call, Protocol};
 use crate::shared::{Consts, Gen, Items};
 use crate::{Context, Hash, SourceId, Sources};
 
@@ -105,6 +105,10 @@ pub(crate) struct QueryInner {
     indexed: LinkedHashMap<ItemId, Vec<IndexedEntry>>,
     /// Compiled constant functions.
     const_fns: HashMap<NonZeroId, Arc<QueryConstFn>>,
+    /// Traits which have been built.
+    traits: HashMap<ItemId, Arc<Trait>>,
+    /// Type annotations of functions.
+    signatures: HashMap<ItemId, Arc<FnSignature>>,
     /// Query paths.
     query_paths: HashMap<NonZeroId, QueryPath>,
     /// The result of internally resolved macros.
@@ -488,14 +492,45 @@ impl<'a> Query<'a> {
         Ok(())
     }
 
+    /// Queue up an implementation of a trait to be built.
+    #[tracing::instrument(skip_all)]
+    pub(crate) fn index_trait_impl(&mut self, item_meta: ItemMeta, trait_impl: TraitImpl) {
+        tracing::trace!(item = ?self.pool.item(item_meta.item));
+
+        self.inner.queue.push_back(BuildEntry {
+            item_meta,
+            build: Build::TraitImpl(trait_impl),
+            used: Used::Used,
+        });
+    }
+
+    /// Get the trait which has been built for the given item.
+    pub(crate) fn trait_for(&self, item: ItemId) -> Option<Arc<Trait>> {
+        self.inner.traits.get(&item).cloned()
+    }
+
+    /// Insert the type annotations of the function with the given item.
+    pub(crate) fn insert_fn_signature(&mut self, item: ItemId, signature: FnSignature) {
+        self.inner.signatures.insert(item, Arc::new(signature));
+    }
+
+    /// Get the type annotations of the function with the given item.
+    pub(crate) fn fn_signature_for(&self, item: ItemId) -> Option<Arc<FnSignature>> {
+        self.inner.signatures.get(&item).cloned()
+    }
+
     /// Add a new enum item.
     #[tracing::instrument(skip_all)]
-    pub(crate) fn index_enum(&mut self, item_meta: ItemMeta) -> Result<(), QueryError> {
+    pub(crate) fn index_enum(
+        &mut self,
+        item_meta: ItemMeta,
+        variants: Box<[Box<str>]>,
+    ) -> Result<(), QueryError> {
         tracing::trace!(item = ?self.pool.item(item_meta.item));
 
         self.index(IndexedEntry {
             item_meta,
-            indexed: Indexed::Enum,
+            indexed: Indexed::Enum(Enum { variants }),
         });
 
         Ok(())
@@ -643,7 +678,13 @@ impl<'a> Query<'a> {
                 index,
                 variant: variant.clone(),
             },
-            ContextMetaKind::Enum { type_hash } => PrivMetaKind::Enum { type_hash },
+            ContextMetaKind::Enum {
+                type_hash,
+                ref variants,
+            } => PrivMetaKind::Enum {
+                type_hash,
+                variants: variants.clone(),
+            },
             ContextMetaKind::Function { type_hash } => PrivMetaKind::Function {
                 type_hash,
                 is_test: true,
@@ -1015,21 +1056,34 @@ impl<'a> Query<'a> {
         )?;
 
         let import = match entry.indexed {
-            Indexed::Import(import) => import.entry,
+            Indexed::Import(import) => import,
             indexed => {
                 self.import_indexed(span, entry.item_meta, indexed, used)?;
                 return Ok(None);
             }
         };
 
+        let import_entry = import.entry;
+
+        // Build the import so that it's visited like any other import.
+        if !import.wildcard && self.visitor.visit_imports() {
+            self.inner.queue.push_back(BuildEntry {
+                item_meta: entry.item_meta,
+                build: Build::Import(import),
+                used,
+            });
+        }
+
         let meta = PrivMeta {
             item_meta: entry.item_meta,
-            kind: PrivMetaKind::Import { import },
+            kind: PrivMetaKind::Import {
+                import: import_entry,
+            },
             source: None,
         };
 
         self.insert_meta(span, meta)?;
-        Ok(Some(import))
+        Ok(Some(import_entry))
     }
 
     /// Build a single, indexed entry and return its metadata.
@@ -1042,8 +1096,9 @@ impl<'a> Query<'a> {
         let IndexedEntry { item_meta, indexed } = entry;
 
         let kind = match indexed {
-            Indexed::Enum => PrivMetaKind::Enum {
+            Indexed::Enum(en) => PrivMetaKind::Enum {
                 type_hash: self.pool.item_type_hash(item_meta.item),
+                variants: en.variants,
             },
             Indexed::Variant(variant) => {
                 let enum_item = self.item_for((item_meta.location.span, variant.enum_id))?;
@@ -1182,6 +1237,21 @@ impl<'a> Query<'a> {
                     import: import.entry,
                 }
             }
+            Indexed::Trait(t) => {
+                self.inner.traits.insert(item_meta.item, Arc::new(t));
+
+                if used.is_unused() {
+                    self.inner.queue.push_back(BuildEntry {
+                        item_meta,
+                        build: Build::Unused,
+                        used,
+                    });
+                }
+
+                PrivMetaKind::Trait {
+                    type_hash: self.pool.item_type_hash(item_meta.item),
+                }
+            }
             Indexed::Module => PrivMetaKind::Module,
         };
 
@@ -1424,7 +1494,7 @@ impl Default for Used {
 #[derive(Debug, Clone)]
 pub(crate) enum Indexed {
     /// An enum.
-    Enum,
+    Enum(Enum),
     /// A struct.
     Struct(Struct),
     /// A variant.
@@ -1443,6 +1513,8 @@ pub(crate) enum Indexed {
     ConstFn(ConstFn),
     /// An import.
     Import(Import),
+    /// A trait.
+    Trait(Trait),
     /// An indexed module.
     Module,
 }
@@ -1499,6 +1571,17 @@ pub(crate) struct Function {
     pub(crate) call: Call,
 }
 
+/// The type annotations of a function.
+#[derive(Debug)]
+pub(crate) struct FnSignature {
+    /// The annotated types of the arguments, `self` is never annotated.
+    pub(crate) args: Box<[Option<ast::Type>]>,
+    /// The annotated return type.
+    pub(crate) output: Option<ast::Type>,
+    /// If the function is async.
+    pub(crate) is_async: bool,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct IndexedFunction {
     /// The underlying indexed function.
@@ -1517,6 +1600,8 @@ pub(crate) struct InstanceFunction {
     pub(crate) impl_item: ItemId,
     /// The span of the instance function.
     pub(crate) instance_span: Span,
+    /// The protocol the function implements, if any.
+    pub(crate) protocol: Option<Protocol>,
 }
 
 #[derive(Debug, Clone)]
@@ -1559,6 +1644,49 @@ pub(crate) struct ConstFn {
     pub(crate) item_fn: Box<ast::ItemFn>,
 }
 
+#[derive(Debug, Clone)]
+pub(crate) struct Enum {
+    /// The names of the variants of the enum, in declaration order.
+    pub(crate) variants: Box<[Box<str>]>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Trait {
+    /// The names of functions which implementors are required to provide.
+    pub(crate) required: Vec<Box<str>>,
+    /// Functions with a default implementation.
+    pub(crate) provided: Vec<TraitFn>,
+}
+
+impl Trait {
+    /// Test if the trait declares a function with the given name.
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.required.iter().any(|n| &**n == name) || self.provided.iter().any(|f| &*f.name == name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TraitFn {
+    /// The name of the function.
+    pub(crate) name: Box<str>,
+    /// The location of the default implementation.
+    pub(crate) location: Location,
+    /// The default implementation.
+    pub(crate) function: Function,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TraitImpl {
+    /// The path to the trait being implemented.
+    pub(crate) path: Box<ast::Path>,
+    /// The item of the type the trait is implemented for.
+    pub(crate) impl_item: ItemId,
+    /// The span of the path to the type the trait is implemented for.
+    pub(crate) impl_span: Span,
+    /// The functions defined in the implementation, and their spans.
+    pub(crate) functions: Vec<(Span, Box<str>)>,
+}
+
 /// An entry in the build queue.
 #[derive(Debug, Clone)]
 pub(crate) enum Build {
@@ -1568,6 +1696,8 @@ pub(crate) enum Build {
     AsyncBlock(AsyncBlock),
     Unused,
     Import(Import),
+    /// An implementation of a trait.
+    TraitImpl(TraitImpl),
     /// A public re-export.
     ReExport,
     /// A build which simply queries for the item.
@@ -1,3 +1,5 @@
+use crate::runtime::memory::Accounted;
+use crate::runtime::shared::Charge;
 use crate::runtime::{AnyObjError, RawStr};
 use std::cell::Cell;
 use std::fmt;
@@ -435,6 +437,7 @@ impl Drop for RawTakeGuard {
 /// access depending on what we do. Releasing the guard releases the access.
 pub struct BorrowMut<'a, T: ?Sized> {
     data: &'a mut T,
+    charge: Option<Charge>,
     guard: AccessGuard<'a>,
 }
 
@@ -448,8 +451,23 @@ impl<'a, T: ?Sized> BorrowMut<'a, T> {
     /// [Access::exclusive]. Otherwise access can be release incorrectly once
     /// this guard is dropped.
     pub(crate) unsafe fn new(data: &'a mut T, access: &'a Access) -> Self {
+        Self::with_charge(data, access, None)
+    }
+
+    /// Construct a new exclusive guard, which charges for changes to the heap
+    /// allocations of the data once it's dropped.
+    ///
+    /// # Safety
+    ///
+    /// Same as [BorrowMut::new].
+    pub(crate) unsafe fn with_charge(
+        data: &'a mut T,
+        access: &'a Access,
+        charge: Option<Charge>,
+    ) -> Self {
         Self {
             data,
+            charge,
             guard: AccessGuard(access),
         }
     }
@@ -475,6 +493,7 @@ impl<'a, T: ?Sized> BorrowMut<'a, T> {
     {
         BorrowMut {
             data: m(this.data),
+            charge: this.charge,
             guard: this.guard,
         }
     }
@@ -500,6 +519,7 @@ impl<'a, T: ?Sized> BorrowMut<'a, T> {
     {
         Some(BorrowMut {
             data: m(this.data)?,
+            charge: this.charge,
             guard: this.guard,
         })
     }
@@ -519,6 +539,12 @@ impl<T: ?Sized> ops::DerefMut for BorrowMut<'_, T> {
     }
 }
 
+impl<T: ?Sized> Accounted for BorrowMut<'_, T> {
+    fn accounted(&mut self) -> (&mut Self::Target, Option<&Charge>) {
+        (self.data, self.charge.as_ref())
+    }
+}
+
 impl<T: ?Sized> fmt::Debug for BorrowMut<'_, T>
 where
     T: fmt::Debug,
@@ -132,6 +132,12 @@ impl FromValue for Bytes {
     }
 }
 
+impl FromValue for Mut<Bytes> {
+    fn from_value(value: Value) -> Result<Self, VmError> {
+        Ok(value.into_bytes()?.into_mut()?)
+    }
+}
+
 impl<'a> UnsafeFromValue for &'a Bytes {
     type Output = *const Bytes;
     type Guard = RawRef;
@@ -52,9 +52,9 @@ impl ConstValue {
             Self::Bool(b) => Value::Bool(b),
             Self::Integer(n) => Value::Integer(n),
             Self::Float(n) => Value::Float(n),
-            Self::String(s) => Value::String(Shared::new(s)),
+            Self::String(s) => Value::String(Shared::new_measured(s)),
             Self::StaticString(s) => Value::StaticString(s),
-            Self::Bytes(b) => Value::Bytes(Shared::new(b)),
+            Self::Bytes(b) => Value::Bytes(Shared::new_measured(b)),
             Self::Option(option) => {
                 Value::Option(Shared::new(option.map(|some| some.into_value())))
             }
@@ -65,7 +65,7 @@ impl ConstValue {
                     v.push(value.into_value());
                 }
 
-                Value::Vec(Shared::new(v))
+                Value::Vec(Shared::new_measured(v))
             }
             Self::Tuple(tuple) => {
                 let mut t = vec::Vec::with_capacity(tuple.len());
@@ -74,7 +74,7 @@ impl ConstValue {
                     t.push(value.into_value());
                 }
 
-                Value::Tuple(Shared::new(Tuple::from(t)))
+                Value::Tuple(Shared::new_measured(Tuple::from(t)))
             }
             Self::Object(object) => {
                 let mut o = Object::with_capacity(object.len());
@@ -83,7 +83,7 @@ impl ConstValue {
                     o.insert(key, value.into_value());
                 }
 
-                Value::Object(Shared::new(o))
+                Value::Object(Shared::new_measured(o))
             }
         }
     }
@@ -0,0 +1,254 @@
+//! Code coverage for the virtual machine.
+//!
+//! A [Coverage] is attached to a [Vm][crate::Vm] through
+//! [Vm::set_coverage][crate::Vm::set_coverage]. While the virtual machine is
+//! running, every instruction executed in the unit being covered is counted,
+//! as are the jumps taken.
+
+use crate::collections::BTreeMap;
+use crate::runtime::{Inst, Unit};
+use crate::{SourceId, Sources};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug)]
+struct Inner {
+    /// The unit being covered.
+    unit: Arc<Unit>,
+    /// The number of times each instruction has been executed.
+    hits: Vec<AtomicU64>,
+    /// The number of times each jump instruction has jumped.
+    taken: Vec<AtomicU64>,
+}
+
+/// The coverage of a single line, as reported by [Coverage::files].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LineCoverage {
+    /// The line, starting at 1.
+    pub line: usize,
+    /// The number of times the line was executed.
+    pub hits: u64,
+}
+
+/// The coverage of a single branch, as reported by [Coverage::files].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BranchCoverage {
+    /// The line of the branch, starting at 1.
+    pub line: usize,
+    /// The instruction pointer of the conditional jump.
+    pub ip: usize,
+    /// The number of times the jump was taken.
+    pub taken: u64,
+    /// The number of times execution continued without jumping.
+    pub not_taken: u64,
+}
+
+/// The coverage of a single source file, as reported by [Coverage::files].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct FileCoverage {
+    /// The source the coverage is for.
+    pub source_id: SourceId,
+    /// The path of the source, or its name if it doesn't have a path.
+    pub name: String,
+    /// Every line which has instructions associated with it, in order.
+    pub lines: Vec<LineCoverage>,
+    /// Every conditional jump, ordered by line.
+    pub branches: Vec<BranchCoverage>,
+}
+
+impl FileCoverage {
+    /// The number of lines which were executed at least once.
+    pub fn lines_hit(&self) -> usize {
+        self.lines.iter().filter(|line| line.hits > 0).count()
+    }
+
+    /// The number of branch directions, where each conditional jump has two.
+    pub fn branches_found(&self) -> usize {
+        self.branches.len() * 2
+    }
+
+    /// The number of branch directions which were taken at least once.
+    pub fn branches_hit(&self) -> usize {
+        self.branches
+            .iter()
+            .map(|branch| usize::from(branch.taken > 0) + usize::from(branch.not_taken > 0))
+            .sum()
+    }
+}
+
+/// A recorder of which instructions a [Vm][crate::Vm] executes in a unit,
+/// which can be attached through [Vm::set_coverage][crate::Vm::set_coverage].
+///
+/// Cloning the recorder produces a handle to the same coverage, so one handle
+/// can be kept to report on it after the virtual machine has run. Instructions
+/// are mapped to source lines using the debug info of the unit.
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    inner: Arc<Inner>,
+}
+
+impl Coverage {
+    /// Construct a new recorder for the given unit.
+    pub fn new(unit: Arc<Unit>) -> Self {
+        let len = unit.iter_instructions().count();
+
+        Self {
+            inner: Arc::new(Inner {
+                unit,
+                hits: (0..len).map(|_| AtomicU64::new(0)).collect(),
+                taken: (0..len).map(|_| AtomicU64::new(0)).collect(),
+            }),
+        }
+    }
+
+    /// The number of times the instruction at the given instruction pointer
+    /// has been executed.
+    pub fn hits(&self, ip: usize) -> u64 {
+        self.inner
+            .hits
+            .get(ip)
+            .map(|hits| hits.load(Ordering::Relaxed))
+            .unwrap_or_default()
+    }
+
+    /// Report the coverage of every source file with instructions in the unit.
+    pub fn files(&self, sources: &Sources) -> Vec<FileCoverage> {
+        let inner = &*self.inner;
+
+        let debug = match inner.unit.debug_info() {
+            Some(debug) => debug,
+            None => return Vec::new(),
+        };
+
+        let mut files = BTreeMap::<SourceId, (BTreeMap<usize, u64>, Vec<BranchCoverage>)>::new();
+
+        for (ip, inst) in inner.unit.iter_instructions().enumerate() {
+            let (source_id, span) = match debug.instruction_at(ip) {
+                Some(inst) => (inst.source_id, inst.span),
+                None => continue,
+            };
+
+            let source = match sources.get(source_id) {
+                Some(source) => source,
+                None => continue,
+            };
+
+            let line = source.line_index(span.start.into_usize()) + 1;
+            let hits = inner.hits[ip].load(Ordering::Relaxed);
+
+            let (lines, branches) = files.entry(source_id).or_default();
+            let line_hits = lines.entry(line).or_default();
+            *line_hits = (*line_hits).max(hits);
+
+            if is_branch(&inst) {
+                let taken = inner.taken[ip].load(Ordering::Relaxed);
+
+                branches.push(BranchCoverage {
+                    line,
+                    ip,
+                    taken,
+                    not_taken: hits.saturating_sub(taken),
+                });
+            }
+        }
+
+        files
+            .into_iter()
+            .map(|(source_id, (lines, mut branches))| {
+                branches.sort_by_key(|branch| (branch.line, branch.ip));
+
+                let name = match sources.path(source_id) {
+                    Some(path) => path.display().to_string(),
+                    None => sources.name(source_id).unwrap_or_default().to_owned(),
+                };
+
+                FileCoverage {
+                    source_id,
+                    name,
+                    lines: lines
+                        .into_iter()
+                        .map(|(line, hits)| LineCoverage { line, hits })
+                        .collect(),
+                    branches,
+                }
+            })
+            .collect()
+    }
+
+    /// Write the coverage in the [LCOV] tracefile format.
+    ///
+    /// [LCOV]: https://github.com/linux-test-project/lcov
+    pub fn write_lcov<W>(&self, sources: &Sources, mut out: W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        for file in self.files(sources) {
+            writeln!(out, "SF:{}", file.name)?;
+
+            for branch in &file.branches {
+                let executed = branch.taken + branch.not_taken > 0;
+
+                for (n, count) in [branch.taken, branch.not_taken].into_iter().enumerate() {
+                    if executed {
+                        writeln!(out, "BRDA:{},{},{},{}", branch.line, branch.ip, n, count)?;
+                    } else {
+                        writeln!(out, "BRDA:{},{},{},-", branch.line, branch.ip, n)?;
+                    }
+                }
+            }
+
+            writeln!(out, "BRF:{}", file.branches_found())?;
+            writeln!(out, "BRH:{}", file.branches_hit())?;
+
+            for line in &file.lines {
+                writeln!(out, "DA:{},{}", line.line, line.hits)?;
+            }
+
+            writeln!(out, "LF:{}", file.lines.len())?;
+            writeln!(out, "LH:{}", file.lines_hit())?;
+            writeln!(out, "end_of_record")?;
+        }
+
+        Ok(())
+    }
+
+    /// Record that the instruction at the given instruction pointer is being
+    /// executed.
+    #[inline]
+    pub(crate) fn hit(&self, unit: &Arc<Unit>, ip: usize) {
+        if Arc::ptr_eq(&self.inner.unit, unit) {
+            if let Some(hits) = self.inner.hits.get(ip) {
+                hits.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Record that the instruction at the given instruction pointer jumped.
+    #[inline]
+    pub(crate) fn jump(&self, unit: &Arc<Unit>, ip: usize) {
+        if Arc::ptr_eq(&self.inner.unit, unit) {
+            if let Some(taken) = self.inner.taken.get(ip) {
+                taken.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+static_assertions::assert_impl_all!(Coverage: Send, Sync);
+
+/// Test if the instruction is a conditional jump.
+fn is_branch(inst: &Inst) -> bool {
+    matches!(
+        inst,
+        Inst::JumpIf { .. }
+            | Inst::JumpIfOrPop { .. }
+            | Inst::JumpIfNotOrPop { .. }
+            | Inst::JumpIfBranch { .. }
+            | Inst::PopAndJumpIfNot { .. }
+    )
+}
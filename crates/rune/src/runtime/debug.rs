@@ -1,12 +1,13 @@
 //! Debug information for units.
 
 use crate::ast::Span;
-use crate::collections::HashMap;
+use crate::collections::{BTreeMap, HashMap};
 use crate::compile::ItemBuf;
 use crate::runtime::DebugLabel;
 use crate::{Hash, SourceId};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::ops::Range;
 
 /// Debug information about a unit.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -16,8 +17,11 @@ pub struct DebugInfo {
     pub instructions: Vec<DebugInst>,
     /// Function signatures.
     pub functions: HashMap<Hash, DebugSignature>,
-    /// Reverse lookup of a function.
-    pub functions_rev: HashMap<usize, Hash>,
+    /// Reverse lookup of a function, ordered by the offset it starts at.
+    pub functions_rev: BTreeMap<usize, Hash>,
+    /// Named local variables of each function, ordered by where they become
+    /// live.
+    pub locals: HashMap<Hash, Box<[DebugLocal]>>,
 }
 
 impl DebugInfo {
@@ -32,6 +36,34 @@ impl DebugInfo {
         let signature = self.functions.get(&hash)?;
         Some((hash, signature))
     }
+
+    /// Get the function which the given instruction pointer belongs to.
+    pub fn function_containing(&self, ip: usize) -> Option<(Hash, &DebugSignature)> {
+        let hash = *self.function_hash_containing(ip)?;
+        let signature = self.functions.get(&hash)?;
+        Some((hash, signature))
+    }
+
+    /// Get the named local variables which are live at the given instruction
+    /// pointer.
+    ///
+    /// A variable which is shadowed in a nested scope is still live, so the
+    /// same name might be returned more than once. In that case the last one
+    /// returned is the one that the name refers to.
+    pub fn locals_at(&self, ip: usize) -> impl Iterator<Item = &DebugLocal> + '_ {
+        self.function_hash_containing(ip)
+            .and_then(|hash| self.locals.get(hash))
+            .into_iter()
+            .flat_map(|locals| locals.iter())
+            .filter(move |local| local.range.contains(&ip))
+    }
+
+    /// Get the hash of the function with the closest entry at or before the
+    /// given instruction pointer.
+    fn function_hash_containing(&self, ip: usize) -> Option<&Hash> {
+        let (_, hash) = self.functions_rev.range(..=ip).next_back()?;
+        Some(hash)
+    }
 }
 
 /// Debug information for every instruction.
@@ -65,6 +97,30 @@ impl DebugInst {
     }
 }
 
+/// Debug information on a named local variable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DebugLocal {
+    /// The name of the variable.
+    pub name: Box<str>,
+    /// The offset of the variable from the bottom of the stack frame of the
+    /// function it belongs to.
+    pub offset: usize,
+    /// The range of instructions in which the variable is live.
+    pub range: Range<usize>,
+}
+
+impl DebugLocal {
+    /// Construct information on a new local variable.
+    pub fn new(name: Box<str>, offset: usize, range: Range<usize>) -> Self {
+        Self {
+            name,
+            offset,
+            range,
+        }
+    }
+}
+
 /// Debug information on function arguments.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DebugArgs {
@@ -527,7 +527,7 @@ where
     pub(crate) fn call_with_vm(&self, vm: &mut Vm, args: usize) -> Result<Option<VmHalt>, VmError> {
         let reason = match &self.inner {
             Inner::FnHandler(handler) => {
-                (handler.handler)(vm.stack_mut(), args)?;
+                vm.call_native(handler.hash, |stack| (handler.handler)(stack, args))?;
                 None
             }
             Inner::FnOffset(fn_offset) => {
@@ -32,6 +32,14 @@ where
         }
     }
 
+    /// Get a mutable reference to the virtual machine currently running the
+    /// generator, or `None` if it has completed.
+    ///
+    /// This can be used to provide more fuel to a generator which ran out of it.
+    pub fn vm_mut(&mut self) -> Option<&mut Vm> {
+        Some(self.execution.as_mut()?.vm_mut())
+    }
+
     /// Get the next value produced by this stream.
     #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Result<Option<Value>, VmError> {
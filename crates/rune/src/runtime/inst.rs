@@ -823,6 +823,19 @@ pub enum Inst {
         /// The slot to test against.
         slot: usize,
     },
+    /// Test if the top of the stack is contained in the given range. Values of
+    /// a different type than the range never match.
+    ///
+    /// # Operation
+    ///
+    /// ```text
+    /// <value>
+    /// => <boolean>
+    /// ```
+    MatchRange {
+        /// The range to test against.
+        range: InstRange,
+    },
     /// Test that the top of the stack has the given type.
     ///
     /// # Operation
@@ -1226,6 +1239,9 @@ impl fmt::Display for Inst {
             Self::EqInteger { integer } => {
                 write!(fmt, "eq-integer integer={}", integer)?;
             }
+            Self::MatchRange { range } => {
+                write!(fmt, "match-range range={}", range)?;
+            }
             Self::EqBool { boolean } => {
                 write!(fmt, "eq-integer boolean={}", boolean)?;
             }
@@ -1347,6 +1363,48 @@ impl fmt::Display for InstRangeLimits {
     }
 }
 
+/// A range of constant values matched by [Inst::MatchRange].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum InstRange {
+    /// A range of bytes.
+    Byte {
+        /// The start of the range.
+        start: u8,
+        /// The end of the range.
+        end: u8,
+        /// The limits of the range.
+        limits: InstRangeLimits,
+    },
+    /// A range of characters.
+    Char {
+        /// The start of the range.
+        start: char,
+        /// The end of the range.
+        end: char,
+        /// The limits of the range.
+        limits: InstRangeLimits,
+    },
+    /// A range of integers.
+    Integer {
+        /// The start of the range.
+        start: i64,
+        /// The end of the range.
+        end: i64,
+        /// The limits of the range.
+        limits: InstRangeLimits,
+    },
+}
+
+impl fmt::Display for InstRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Byte { start, end, limits } => write!(f, "{:?}{}{:?}", start, limits, end),
+            Self::Char { start, end, limits } => write!(f, "{:?}{}{:?}", start, limits, end),
+            Self::Integer { start, end, limits } => write!(f, "{}{}{}", start, limits, end),
+        }
+    }
+}
+
 /// The target of an operation.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum InstTarget {
@@ -0,0 +1,95 @@
+//! Interrupting a running virtual machine.
+//!
+//! A [Vm][crate::Vm] hands out an [InterruptHandle] through
+//! [Vm::interrupt_handle][crate::Vm::interrupt_handle], which is observed by
+//! the virtual machine through its limits.
+
+use crate::runtime::VmErrorKind;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Waker;
+
+/// A handle which can be used to interrupt a running virtual machine, usually
+/// from another thread.
+///
+/// See [Vm::interrupt_handle][crate::Vm::interrupt_handle].
+#[derive(Debug, Clone)]
+pub struct InterruptHandle {
+    inner: Arc<Interrupt>,
+}
+
+impl InterruptHandle {
+    /// Interrupt the virtual machine.
+    ///
+    /// The virtual machine stops with [VmErrorKind::Interrupted] the next time
+    /// it jumps backwards or calls a function, and any future it's currently
+    /// waiting on is cancelled. The interrupt stays pending until it's
+    /// [reset][InterruptHandle::reset], so that every virtual machine started
+    /// from the interrupted one observes it as well.
+    pub fn interrupt(&self) {
+        self.inner.interrupted.store(true, Ordering::Release);
+
+        let wakers = mem::take(&mut *self.inner.wakers());
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Test if an interrupt is pending.
+    pub fn is_interrupted(&self) -> bool {
+        self.inner.interrupted.load(Ordering::Acquire)
+    }
+
+    /// Reset a pending interrupt, so that the virtual machine can be used
+    /// again.
+    pub fn reset(&self) {
+        self.inner.interrupted.store(false, Ordering::Release);
+    }
+}
+
+/// The interrupt state shared between a virtual machine and its handles.
+#[derive(Debug, Default)]
+pub(crate) struct Interrupt {
+    /// Indicates that an interrupt is pending.
+    interrupted: AtomicBool,
+    /// Tasks to wake up when interrupted.
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl Interrupt {
+    /// Construct a handle to the interrupt.
+    pub(crate) fn handle(self: &Arc<Self>) -> InterruptHandle {
+        InterruptHandle {
+            inner: self.clone(),
+        }
+    }
+
+    /// Check if an interrupt is pending.
+    #[inline]
+    pub(crate) fn check(&self) -> Result<(), VmErrorKind> {
+        if self.interrupted.load(Ordering::Acquire) {
+            return Err(VmErrorKind::Interrupted);
+        }
+
+        Ok(())
+    }
+
+    /// Register a task to wake up when interrupted.
+    pub(crate) fn register(&self, waker: &Waker) {
+        let mut wakers = self.wakers();
+
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    fn wakers(&self) -> std::sync::MutexGuard<'_, Vec<Waker>> {
+        // NB: the lock is never held while calling out, so it can't be
+        // poisoned in a way that leaves the wakers inconsistent.
+        self.wakers
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
@@ -129,10 +129,10 @@ impl Key {
             Self::Bool(b) => Value::Bool(b),
             Self::Integer(n) => Value::Integer(n),
             Self::String(s) => match s {
-                StringKey::String(s) => Value::String(Shared::new(String::from(s))),
+                StringKey::String(s) => Value::String(Shared::new_measured(String::from(s))),
                 StringKey::StaticString(s) => Value::StaticString(s),
             },
-            Self::Bytes(b) => Value::Bytes(Shared::new(b)),
+            Self::Bytes(b) => Value::Bytes(Shared::new_measured(b)),
             Self::Option(option) => {
                 Value::Option(Shared::new(option.map(|some| some.into_value())))
             }
@@ -143,9 +143,9 @@ impl Key {
                     v.push(value.into_value());
                 }
 
-                Value::Vec(Shared::new(v))
+                Value::Vec(Shared::new_measured(v))
             }
-            Self::Tuple(tuple) => Value::Tuple(Shared::new(tuple_into_value(tuple))),
+            Self::Tuple(tuple) => Value::Tuple(Shared::new_measured(tuple_into_value(tuple))),
             Self::Variant(variant) => {
                 let data = match variant.data {
                     VariantKeyData::Unit => VariantData::Unit,
@@ -0,0 +1,283 @@
+//! Limits and hooks of the virtual machine which is currently running.
+//!
+//! Every [Vm][crate::Vm] carries its own [Limits]. While it's running they're
+//! installed here with a [Guard], so that any virtual machine started from
+//! inside of it inherits the ones it hasn't configured itself. See the
+//! [Vm][crate::Vm] documentation for how each of them is inherited.
+
+use crate::runtime::interrupt::Interrupt;
+use crate::runtime::memory::Memory;
+use crate::runtime::{Coverage, Profiler, VmErrorKind};
+use pin_project::pin_project;
+use std::cell::RefCell;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+thread_local!(static CURRENT: RefCell<Installed> = const { RefCell::new(Installed::new()) });
+
+/// The limits and hooks of a virtual machine.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Limits {
+    /// The number of instructions the virtual machine is allowed to execute,
+    /// if limited.
+    pub(crate) fuel: Option<u64>,
+    /// Memory accounting for values allocated by the virtual machine, if
+    /// limited.
+    pub(crate) memory: Option<Arc<Memory>>,
+    /// The interrupt shared with handles to the virtual machine, if any have
+    /// been constructed.
+    pub(crate) interrupt: Option<Arc<Interrupt>>,
+    /// The maximum number of script functions which are allowed to be called
+    /// at the same time, if limited.
+    pub(crate) max_call_depth: Option<usize>,
+    /// The profiler recording calls made by the virtual machine, if any.
+    pub(crate) profiler: Option<Profiler>,
+    /// The coverage recording instructions executed by the virtual machine, if
+    /// any.
+    pub(crate) coverage: Option<Coverage>,
+}
+
+impl Limits {
+    /// Construct limits which don't limit anything.
+    pub(crate) const fn new() -> Self {
+        Self {
+            fuel: None,
+            memory: None,
+            interrupt: None,
+            max_call_depth: None,
+            profiler: None,
+            coverage: None,
+        }
+    }
+
+    /// Get the limits observed by a virtual machine with these limits, which
+    /// are either its own or the ones currently installed.
+    ///
+    /// Fuel is left out, since it can only be drawn from by one virtual
+    /// machine at a time.
+    pub(crate) fn inherited(&self) -> Self {
+        CURRENT.with(|tls| self.inherited_from(&tls.borrow().limits))
+    }
+
+    /// Combine these limits with the given ones, preferring these.
+    fn inherited_from(&self, current: &Limits) -> Self {
+        Self {
+            fuel: None,
+            memory: self.memory.clone().or_else(|| current.memory.clone()),
+            interrupt: self.interrupt.clone().or_else(|| current.interrupt.clone()),
+            max_call_depth: self.max_call_depth.or(current.max_call_depth),
+            profiler: self.profiler.clone().or_else(|| current.profiler.clone()),
+            coverage: self.coverage.clone().or_else(|| current.coverage.clone()),
+        }
+    }
+}
+
+/// The limits installed on the current thread.
+struct Installed {
+    /// The installed limits.
+    limits: Limits,
+    /// The number of script functions currently being called.
+    depth: usize,
+}
+
+impl Installed {
+    const fn new() -> Self {
+        Self {
+            limits: Limits::new(),
+            depth: 0,
+        }
+    }
+
+    /// Add the given number of calls, checking that the limit isn't exceeded.
+    fn add_calls(&mut self, calls: usize) -> Result<(), VmErrorKind> {
+        let depth = self.depth.saturating_add(calls);
+
+        if let Some(limit) = self.limits.max_call_depth {
+            if depth > limit {
+                return Err(VmErrorKind::StackOverflow { limit });
+            }
+        }
+
+        self.depth = depth;
+        Ok(())
+    }
+}
+
+/// Take the installed fuel, so that the running virtual machine can draw from
+/// it without going through the installed limits for every instruction.
+#[inline]
+pub(crate) fn take_fuel() -> Option<u64> {
+    CURRENT.with(|tls| tls.borrow_mut().limits.fuel.take())
+}
+
+/// Install fuel which was previously taken with [take_fuel].
+#[inline]
+pub(crate) fn put_fuel(fuel: Option<u64>) {
+    CURRENT.with(|tls| tls.borrow_mut().limits.fuel = fuel);
+}
+
+/// Lend the given fuel to any virtual machine started while `f` is running,
+/// taking back whatever is left once it returns.
+#[inline]
+pub(crate) fn lend_fuel<T>(fuel: &mut Option<u64>, f: impl FnOnce() -> T) -> T {
+    if fuel.is_none() {
+        return f();
+    }
+
+    put_fuel(fuel.take());
+    let output = f();
+    *fuel = take_fuel();
+    output
+}
+
+/// Get the memory accounting currently installed, if any.
+#[inline]
+pub(crate) fn memory() -> Option<Arc<Memory>> {
+    CURRENT.with(|tls| tls.borrow().limits.memory.clone())
+}
+
+/// Record that a script function is being called.
+#[inline]
+pub(crate) fn push_call() -> Result<(), VmErrorKind> {
+    CURRENT.with(|tls| tls.borrow_mut().add_calls(1))
+}
+
+/// Record that a script function has returned.
+#[inline]
+pub(crate) fn pop_call() {
+    CURRENT.with(|tls| {
+        let mut current = tls.borrow_mut();
+        current.depth = current.depth.saturating_sub(1);
+    });
+}
+
+/// A guard for limits which have been installed with [Guard::new].
+pub(crate) struct Guard {
+    /// The limits which were installed before.
+    old: Option<Installed>,
+    /// Indicates if fuel was installed by the guard, as opposed to being drawn
+    /// from the fuel which was already installed.
+    fuel: bool,
+}
+
+impl Guard {
+    /// Install the given limits for a virtual machine which is currently
+    /// calling the given number of script functions. Limits which aren't set
+    /// continue to use the ones already installed.
+    pub(crate) fn new(limits: &Limits, calls: usize) -> Result<Self, VmErrorKind> {
+        let guard = Self::install(limits);
+        CURRENT.with(|tls| tls.borrow_mut().add_calls(calls))?;
+        Ok(guard)
+    }
+
+    fn install(limits: &Limits) -> Self {
+        CURRENT.with(|tls| {
+            let mut current = tls.borrow_mut();
+
+            let installed = Installed {
+                limits: Limits {
+                    fuel: limits.fuel.or(current.limits.fuel),
+                    ..limits.inherited_from(&current.limits)
+                },
+                depth: current.depth,
+            };
+
+            Self {
+                old: Some(mem::replace(&mut *current, installed)),
+                fuel: limits.fuel.is_some(),
+            }
+        })
+    }
+
+    /// Uninstall the limits, returning what's left of the fuel if it was
+    /// installed by the guard.
+    pub(crate) fn finish(self) -> Option<u64> {
+        if !self.fuel {
+            return None;
+        }
+
+        Some(
+            CURRENT
+                .with(|tls| tls.borrow().limits.fuel)
+                .unwrap_or_default(),
+        )
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let mut old = match self.old.take() {
+            Some(old) => old,
+            None => return,
+        };
+
+        let replaced = CURRENT.with(|tls| {
+            let mut current = tls.borrow_mut();
+
+            // NB: fuel which was drawn from the surrounding supply stays
+            // spent.
+            if !self.fuel {
+                old.limits.fuel = current.limits.fuel;
+            }
+
+            mem::replace(&mut *current, old)
+        });
+
+        // NB: dropped outside of the borrow, in case it releases anything
+        // accounted for.
+        drop(replaced);
+    }
+}
+
+/// A future which has limits installed every time it's polled, and which is
+/// cancelled if they're interrupted.
+#[pin_project]
+pub(crate) struct Limited<T> {
+    /// The limits to install.
+    limits: Limits,
+    /// The future being limited.
+    #[pin]
+    value: T,
+}
+
+/// Install the given limits while the future is being polled. The future
+/// produces `None` if it was interrupted, together with the fuel that's left.
+pub(crate) fn with<T>(limits: Limits, value: T) -> Limited<T> {
+    Limited { limits, value }
+}
+
+impl<T> Future for Limited<T>
+where
+    T: Future,
+{
+    type Output = (Option<T::Output>, Option<u64>);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Some(interrupt) = &this.limits.interrupt {
+            // NB: register before checking, so that an interrupt arriving in
+            // between is guaranteed to wake us up.
+            interrupt.register(cx.waker());
+
+            if interrupt.check().is_err() {
+                return Poll::Ready((None, this.limits.fuel));
+            }
+        }
+
+        let guard = Guard::install(this.limits);
+        let poll = this.value.poll(cx);
+
+        if let Some(fuel) = guard.finish() {
+            this.limits.fuel = Some(fuel);
+        }
+
+        match poll {
+            Poll::Ready(output) => Poll::Ready((Some(output), this.limits.fuel)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! Memory accounting for the virtual machine.
+//!
+//! Accounting is enabled for a [Vm][crate::Vm] by setting a limit through
+//! [Vm::set_memory_limit][crate::Vm::set_memory_limit]. While the virtual
+//! machine is running, every [Shared] value allocated charges the [Memory]
+//! installed with its limits. Whenever exclusive access to the value is
+//! released, that same [Memory] is charged for what its heap allocations have
+//! changed by. Exactly what was charged is released once the value is freed,
+//! regardless of which virtual machine happens to be running.
+//!
+//! [Shared]: crate::runtime::Shared
+
+use crate::runtime::limits;
+use crate::runtime::shared::Charge;
+use crate::runtime::{Bytes, Object, Tuple, Value, Vec, VmErrorKind};
+use std::mem;
+use std::ops;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Memory usage shared by a virtual machine and every value allocated while
+/// it's running.
+#[derive(Debug)]
+pub(crate) struct Memory {
+    /// The number of bytes currently in use.
+    usage: AtomicUsize,
+    /// The maximum number of bytes allowed to be in use.
+    limit: AtomicUsize,
+}
+
+impl Memory {
+    /// Construct new memory accounting with the given limit.
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            usage: AtomicUsize::new(0),
+            limit: AtomicUsize::new(limit),
+        }
+    }
+
+    /// The number of bytes currently in use.
+    pub(crate) fn usage(&self) -> usize {
+        self.usage.load(Ordering::Relaxed)
+    }
+
+    /// The maximum number of bytes allowed to be in use.
+    pub(crate) fn limit(&self) -> usize {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    /// Change the limit.
+    pub(crate) fn set_limit(&self, limit: usize) {
+        self.limit.store(limit, Ordering::Relaxed);
+    }
+
+    /// Charge the given number of bytes.
+    pub(crate) fn allocate(&self, bytes: usize) {
+        self.usage.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Release the given number of bytes.
+    pub(crate) fn release(&self, bytes: usize) {
+        let _ = self
+            .usage
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |usage| {
+                Some(usage.saturating_sub(bytes))
+            });
+    }
+
+    /// Check that usage is within the limit.
+    #[inline]
+    pub(crate) fn check(&self) -> Result<(), VmErrorKind> {
+        self.check_additional(0)
+    }
+
+    /// Check that the given number of bytes can be allocated without
+    /// exceeding the limit.
+    pub(crate) fn check_additional(&self, bytes: usize) -> Result<(), VmErrorKind> {
+        let usage = self.usage().saturating_add(bytes);
+        let limit = self.limit();
+
+        if usage > limit {
+            return Err(VmErrorKind::MemoryLimitExceeded { limit, usage });
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that the given number of bytes can be allocated without exceeding
+/// the limit of the installed memory accounting.
+pub(crate) fn check(bytes: usize) -> Result<(), VmErrorKind> {
+    match limits::memory() {
+        Some(memory) => memory.check_additional(bytes),
+        None => Ok(()),
+    }
+}
+
+/// Exclusive access to a value, whose heap allocations might be charged to
+/// the memory accounting of the box it's stored in.
+pub(crate) trait Accounted: ops::DerefMut {
+    /// Access the value together with the charge for the box it's stored in,
+    /// if it's accounted for.
+    fn accounted(&mut self) -> (&mut Self::Target, Option<&Charge>);
+}
+
+/// Account for an operation on a value which might cause it to reallocate to
+/// make room for `additional` more elements.
+///
+/// The growth is checked against the limit before the operation is performed,
+/// so that it errors instead of allocating. It's charged to the box the value
+/// is stored in, or checked against the installed limit if it isn't
+/// accounted for.
+pub(crate) fn resize<G, O>(
+    value: &mut G,
+    additional: usize,
+    f: impl FnOnce(&mut G::Target) -> O,
+) -> Result<O, VmErrorKind>
+where
+    G: ?Sized + Accounted,
+    G::Target: Measure,
+{
+    let growth = value.growth(additional);
+    grow(value, growth, f)
+}
+
+/// Insert an entry into an object, accounting for it if it's new.
+pub(crate) fn insert_entry<G>(
+    object: &mut G,
+    key: String,
+    value: Value,
+) -> Result<Option<Value>, VmErrorKind>
+where
+    G: ?Sized + Accounted<Target = Object>,
+{
+    let bytes = if object.contains_key(&key) {
+        0
+    } else {
+        key.capacity() + mem::size_of::<(String, Value)>()
+    };
+
+    grow(object, bytes, |object| object.insert(key, value))
+}
+
+/// Perform an operation which grows the heap allocations of a value by the
+/// given number of bytes.
+fn grow<G, O>(
+    value: &mut G,
+    bytes: usize,
+    f: impl FnOnce(&mut G::Target) -> O,
+) -> Result<O, VmErrorKind>
+where
+    G: ?Sized + Accounted,
+{
+    let (value, charge) = value.accounted();
+
+    match charge {
+        Some(charge) => charge.memory().check_additional(bytes)?,
+        None => check(bytes)?,
+    }
+
+    let output = f(value);
+
+    // NB: charged right away rather than once access is released, so that
+    // repeated growth is checked against the limit.
+    if let Some(charge) = charge {
+        charge.reconcile();
+    }
+
+    Ok(output)
+}
+
+/// A value whose heap allocations are accounted for.
+pub(crate) trait Measure {
+    /// The number of bytes allocated on the heap by the value.
+    fn measure(&self) -> usize;
+
+    /// The number of bytes the value has to allocate to make room for
+    /// `additional` more elements.
+    fn growth(&self, additional: usize) -> usize {
+        let _ = additional;
+        0
+    }
+}
+
+/// The number of elements which have to be allocated to make room for
+/// `additional` more.
+fn growth(len: usize, capacity: usize, additional: usize) -> usize {
+    len.saturating_add(additional).saturating_sub(capacity)
+}
+
+impl Measure for String {
+    fn measure(&self) -> usize {
+        self.capacity()
+    }
+
+    fn growth(&self, additional: usize) -> usize {
+        growth(self.len(), self.capacity(), additional)
+    }
+}
+
+impl Measure for Bytes {
+    fn measure(&self) -> usize {
+        self.capacity()
+    }
+
+    fn growth(&self, additional: usize) -> usize {
+        growth(self.len(), self.capacity(), additional)
+    }
+}
+
+impl Measure for Vec {
+    fn measure(&self) -> usize {
+        self.capacity() * mem::size_of::<Value>()
+    }
+
+    fn growth(&self, additional: usize) -> usize {
+        growth(self.len(), self.capacity(), additional).saturating_mul(mem::size_of::<Value>())
+    }
+}
+
+impl Measure for Tuple {
+    fn measure(&self) -> usize {
+        self.len() * mem::size_of::<Value>()
+    }
+}
+
+impl Measure for Object {
+    fn measure(&self) -> usize {
+        let entries = self.len() * mem::size_of::<(String, Value)>();
+        self.keys().map(String::capacity).sum::<usize>() + entries
+    }
+}
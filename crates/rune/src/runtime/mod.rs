@@ -8,6 +8,7 @@ pub mod budget;
 mod bytes;
 mod call;
 mod const_value;
+mod coverage;
 pub mod debug;
 mod env;
 pub mod format;
@@ -18,11 +19,15 @@ mod generator;
 mod generator_state;
 mod guarded_args;
 mod inst;
+pub(crate) mod interrupt;
 mod iterator;
 mod key;
 mod label;
+mod limits;
+pub(crate) mod memory;
 mod object;
 mod panic;
+mod profiler;
 mod protocol;
 mod protocol_caller;
 mod range;
@@ -39,6 +44,8 @@ mod tuple;
 mod type_info;
 mod type_of;
 mod unit;
+#[cfg(feature = "unit-file")]
+mod unit_file;
 mod value;
 mod variant;
 mod vec;
@@ -59,7 +66,8 @@ pub(crate) use self::awaited::Awaited;
 pub use self::bytes::Bytes;
 pub use self::call::Call;
 pub use self::const_value::ConstValue;
-pub use self::debug::{DebugInfo, DebugInst};
+pub use self::coverage::{BranchCoverage, Coverage, FileCoverage, LineCoverage};
+pub use self::debug::{DebugInfo, DebugInst, DebugLocal};
 pub use self::format::{Format, FormatSpec};
 pub use self::from_value::{FromValue, UnsafeFromValue};
 pub use self::function::{Function, SyncFunction};
@@ -68,14 +76,16 @@ pub use self::generator::Generator;
 pub use self::generator_state::GeneratorState;
 pub use self::guarded_args::GuardedArgs;
 pub use self::inst::{
-    Inst, InstAddress, InstAssignOp, InstOp, InstRangeLimits, InstTarget, InstValue, InstVariant,
-    PanicReason, TypeCheck,
+    Inst, InstAddress, InstAssignOp, InstOp, InstRange, InstRangeLimits, InstTarget, InstValue,
+    InstVariant, PanicReason, TypeCheck,
 };
+pub use self::interrupt::InterruptHandle;
 pub use self::iterator::{Iterator, IteratorTrait};
 pub use self::key::Key;
 pub use self::label::{DebugLabel, Label};
 pub use self::object::Object;
 pub use self::panic::Panic;
+pub use self::profiler::{FunctionProfile, Profiler};
 pub use self::protocol::Protocol;
 pub(crate) use self::protocol_caller::{EnvProtocolCaller, ProtocolCaller};
 pub use self::range::{Range, RangeLimits};
@@ -98,13 +108,16 @@ pub use self::tuple::Tuple;
 pub use self::type_info::TypeInfo;
 pub use self::type_of::TypeOf;
 pub use self::unit::{Unit, UnitFn};
+#[cfg(feature = "unit-file")]
+#[cfg_attr(docsrs, doc(cfg(feature = "unit-file")))]
+pub use self::unit_file::UnitFileError;
 pub use self::value::{Rtti, Struct, TupleStruct, UnitStruct, Value, VariantRtti};
 pub use self::variant::{Variant, VariantData};
 pub use self::vec::Vec;
 pub use self::vec_tuple::VecTuple;
 pub use self::vm::{CallFrame, Vm};
 pub(crate) use self::vm_call::VmCall;
-pub use self::vm_error::{VmError, VmErrorKind, VmIntegerRepr};
+pub use self::vm_error::{BacktraceFrame, VmError, VmErrorKind, VmIntegerRepr};
 pub use self::vm_execution::{ExecutionState, VmExecution, VmSendExecution};
 pub(crate) use self::vm_halt::VmHalt;
 pub use self::vm_halt::VmHaltInfo;
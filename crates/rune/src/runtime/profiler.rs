@@ -0,0 +1,393 @@
+//! An instrumenting profiler for the virtual machine.
+//!
+//! A [Profiler] is attached to a [Vm] through [Vm::set_profiler]. While the
+//! virtual machine is running, every call to a script or native function is
+//! recorded in a call tree, which is used to aggregate time and instruction
+//! counts per function and to produce flamegraphs.
+
+use crate::collections::HashMap;
+use crate::runtime::{RuntimeContext, Unit, Vm, VmError};
+use crate::Hash;
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// A function as identified by the profiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+    /// The root of the call tree.
+    Root,
+    /// A script function, identified by the hash in its debug info.
+    Script(Hash),
+    /// A native function.
+    Native(Hash),
+}
+
+/// A node in the call tree, which is a function called through a unique path
+/// of other functions.
+#[derive(Debug)]
+struct Node {
+    key: Key,
+    parent: usize,
+    children: HashMap<Key, usize>,
+    /// Time spent in the function itself, excluding its callees.
+    exclusive: Duration,
+}
+
+/// A function which is currently being called.
+#[derive(Debug)]
+struct Frame {
+    node: usize,
+    start: Instant,
+    /// Time spent in callees so far.
+    children: Duration,
+    /// Instructions executed in the function so far.
+    instructions: u64,
+}
+
+/// Statistics for a single function.
+#[derive(Debug, Default)]
+struct Stats {
+    calls: u64,
+    inclusive: Duration,
+    exclusive: Duration,
+    instructions: u64,
+}
+
+#[derive(Debug)]
+struct Inner {
+    nodes: Vec<Node>,
+    stack: Vec<Frame>,
+    stats: HashMap<Key, Stats>,
+    names: HashMap<Key, String>,
+}
+
+impl Inner {
+    /// The call tree node currently being executed.
+    fn current(&self) -> usize {
+        self.stack
+            .last()
+            .map(|frame| frame.node)
+            .unwrap_or_default()
+    }
+
+    /// Start calling the given function.
+    fn push(&mut self, key: Key, name: impl FnOnce() -> String) {
+        self.names.entry(key).or_insert_with(name);
+
+        let parent = self.current();
+        let next = self.nodes.len();
+
+        let node = *self.nodes[parent].children.entry(key).or_insert(next);
+
+        if node == next {
+            self.nodes.push(Node {
+                key,
+                parent,
+                children: HashMap::new(),
+                exclusive: Duration::ZERO,
+            });
+        }
+
+        self.stack.push(Frame {
+            node,
+            start: Instant::now(),
+            children: Duration::ZERO,
+            instructions: 0,
+        });
+    }
+
+    /// Stop calling the function at the top of the stack, counting it as a
+    /// call if it's `completed`.
+    fn pop(&mut self, completed: bool) {
+        let frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let inclusive = frame.start.elapsed();
+        let exclusive = inclusive.saturating_sub(frame.children);
+
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += inclusive;
+        }
+
+        let node = &mut self.nodes[frame.node];
+        node.exclusive += exclusive;
+        let key = node.key;
+
+        // NB: only the outermost call of a recursive function contributes to
+        // its inclusive time, so that it isn't counted more than once.
+        let recursive = self
+            .stack
+            .iter()
+            .any(|frame| self.nodes[frame.node].key == key);
+
+        let stats = self.stats.entry(key).or_default();
+        stats.calls += u64::from(completed);
+        stats.exclusive += exclusive;
+        stats.instructions += frame.instructions;
+
+        if !recursive {
+            stats.inclusive += inclusive;
+        }
+    }
+
+    /// Stop calling every function from the given depth and up. The function
+    /// at `depth` is counted as a call if it's `completed`.
+    fn truncate(&mut self, depth: usize, completed: bool) {
+        while self.stack.len() > depth {
+            self.pop(completed && self.stack.len() == depth + 1);
+        }
+    }
+
+    /// The path of functions leading up to the given node, separated by `;`.
+    fn path(&self, mut node: usize) -> String {
+        let mut keys = Vec::new();
+
+        while node != 0 {
+            keys.push(self.nodes[node].key);
+            node = self.nodes[node].parent;
+        }
+
+        let names = keys
+            .iter()
+            .rev()
+            .map(|key| self.names.get(key).map(String::as_str).unwrap_or("?"))
+            .collect::<Vec<_>>();
+
+        names.join(";")
+    }
+}
+
+/// The profile collected for a single function by a [Profiler].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct FunctionProfile {
+    /// The name of the function.
+    pub name: String,
+    /// The hash of the function. For script functions this is only available
+    /// if the unit has debug info.
+    pub hash: Hash,
+    /// Indicates if the function is a native function.
+    pub native: bool,
+    /// The number of calls to the function which completed.
+    pub calls: u64,
+    /// Time spent in the function, including the functions it called.
+    pub inclusive: Duration,
+    /// Time spent in the function itself, excluding the functions it called.
+    pub exclusive: Duration,
+    /// The number of instructions executed in the function itself. This is
+    /// always zero for native functions.
+    pub instructions: u64,
+}
+
+/// An instrumenting profiler which can be attached to a [Vm] through
+/// [Vm::set_profiler].
+///
+/// Cloning a profiler produces a handle to the same profile, so one handle can
+/// be kept to inspect the profile after the virtual machine has run.
+///
+/// Script functions are named using the debug info of the unit they belong
+/// to, so units should be compiled with debug info for a useful profile.
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Profiler {
+    /// Construct a new empty profiler.
+    pub fn new() -> Self {
+        let root = Node {
+            key: Key::Root,
+            parent: 0,
+            children: HashMap::new(),
+            exclusive: Duration::ZERO,
+        };
+
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                nodes: vec![root],
+                stack: Vec::new(),
+                stats: HashMap::new(),
+                names: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Get the profile of every function called, sorted with the function
+    /// which the most time was spent in first.
+    pub fn functions(&self) -> Vec<FunctionProfile> {
+        let inner = self.inner();
+        let mut output = Vec::new();
+
+        for (key, stats) in &inner.stats {
+            let (hash, native) = match *key {
+                Key::Root => continue,
+                Key::Script(hash) => (hash, false),
+                Key::Native(hash) => (hash, true),
+            };
+
+            output.push(FunctionProfile {
+                name: inner.names.get(key).cloned().unwrap_or_default(),
+                hash,
+                native,
+                calls: stats.calls,
+                inclusive: stats.inclusive,
+                exclusive: stats.exclusive,
+                instructions: stats.instructions,
+            });
+        }
+
+        output.sort_by(|a, b| b.exclusive.cmp(&a.exclusive).then(a.name.cmp(&b.name)));
+        output
+    }
+
+    /// Write the profile in the collapsed stack format used by flamegraph
+    /// tools like [inferno], where each line is a `;` separated path of
+    /// functions followed by the number of nanoseconds spent in the last one.
+    ///
+    /// [inferno]: https://github.com/jonhoo/inferno
+    pub fn write_folded<W>(&self, mut out: W) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let inner = self.inner();
+
+        let mut lines = inner
+            .nodes
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, node)| !node.exclusive.is_zero())
+            .map(|(index, node)| (inner.path(index), node.exclusive.as_nanos()))
+            .collect::<Vec<_>>();
+
+        lines.sort();
+
+        for (path, nanos) in lines {
+            writeln!(out, "{} {}", path, nanos)?;
+        }
+
+        Ok(())
+    }
+
+    /// Enter the given virtual machine, recording the functions it's
+    /// currently calling until the returned guard is dropped.
+    pub(crate) fn enter(&self, vm: &Vm) -> Entered {
+        let mut inner = self.inner();
+        let depth = inner.stack.len();
+
+        let frames = vm.call_frames().iter().map(|frame| frame.ip());
+
+        for ip in frames.chain(std::iter::once(vm.ip())) {
+            push_script(&mut inner, vm.unit(), ip);
+        }
+
+        Entered {
+            profiler: self.clone(),
+            depth,
+        }
+    }
+
+    /// Record a call to the script function at the given instruction pointer.
+    pub(crate) fn call(&self, unit: &Unit, ip: usize) {
+        push_script(&mut self.inner(), unit, ip);
+    }
+
+    /// Record a return from the script function currently being called.
+    pub(crate) fn ret(&self) {
+        self.inner().pop(true);
+    }
+
+    /// Record an instruction executed in the function currently being called.
+    #[inline]
+    pub(crate) fn instruction(&self) {
+        if let Some(frame) = self.inner().stack.last_mut() {
+            frame.instructions += 1;
+        }
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        // NB: the profile is kept consistent between calls, so it can still be
+        // used if a thread panicked while holding the lock.
+        self.inner.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+static_assertions::assert_impl_all!(Profiler: Send, Sync);
+
+/// Start calling the script function at the given instruction pointer.
+fn push_script(inner: &mut Inner, unit: &Unit, ip: usize) {
+    let function = unit
+        .debug_info()
+        .and_then(|debug| debug.function_containing(ip));
+
+    match function {
+        Some((hash, signature)) => {
+            inner.push(Key::Script(hash), || signature.path.to_string());
+        }
+        None => {
+            inner.push(Key::Script(Hash::EMPTY), || String::from("<unknown>"));
+        }
+    }
+}
+
+/// A virtual machine which has been entered with [Profiler::enter].
+pub(crate) struct Entered {
+    profiler: Profiler,
+    depth: usize,
+}
+
+impl Entered {
+    /// Leave the virtual machine, counting the function it was called with as
+    /// a call if it `exited`.
+    pub(crate) fn finish(self, exited: bool) {
+        self.profiler.inner().truncate(self.depth, exited);
+    }
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        // NB: does nothing if already finished.
+        self.profiler.inner().truncate(self.depth, false);
+    }
+}
+
+/// Call the native function with the given hash through `f`, recording it in
+/// the profiler if there is one.
+#[inline]
+pub(crate) fn native<T>(
+    profiler: Option<&Profiler>,
+    context: &RuntimeContext,
+    hash: Hash,
+    f: impl FnOnce() -> Result<T, VmError>,
+) -> Result<T, VmError> {
+    let profiler = match profiler {
+        Some(profiler) => profiler,
+        None => return f(),
+    };
+
+    let depth = {
+        let mut inner = profiler.inner();
+        let depth = inner.stack.len();
+
+        inner.push(Key::Native(hash), || match context.function_name(hash) {
+            Some(name) => name.to_owned(),
+            None => hash.to_string(),
+        });
+
+        depth
+    };
+
+    let result = f();
+    profiler.inner().truncate(depth, result.is_ok());
+    result
+}
@@ -75,6 +75,48 @@ impl hash::Hash for Protocol {
 }
 
 impl Protocol {
+    /// Look up a protocol which can be implemented by an instance function by
+    /// the name of its constant, like `"ADD"` for [Protocol::ADD].
+    ///
+    /// Field functions like [Protocol::GET] are not included, since they are
+    /// implemented separately for each field.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "EQ" => Self::EQ,
+            "FALLBACK_GET" => Self::FALLBACK_GET,
+            "FALLBACK_SET" => Self::FALLBACK_SET,
+            "INDEX_GET" => Self::INDEX_GET,
+            "INDEX_SET" => Self::INDEX_SET,
+            "ADD" => Self::ADD,
+            "ADD_ASSIGN" => Self::ADD_ASSIGN,
+            "SUB" => Self::SUB,
+            "SUB_ASSIGN" => Self::SUB_ASSIGN,
+            "MUL" => Self::MUL,
+            "MUL_ASSIGN" => Self::MUL_ASSIGN,
+            "DIV" => Self::DIV,
+            "DIV_ASSIGN" => Self::DIV_ASSIGN,
+            "REM" => Self::REM,
+            "REM_ASSIGN" => Self::REM_ASSIGN,
+            "BIT_AND" => Self::BIT_AND,
+            "BIT_AND_ASSIGN" => Self::BIT_AND_ASSIGN,
+            "BIT_XOR" => Self::BIT_XOR,
+            "BIT_XOR_ASSIGN" => Self::BIT_XOR_ASSIGN,
+            "BIT_OR" => Self::BIT_OR,
+            "BIT_OR_ASSIGN" => Self::BIT_OR_ASSIGN,
+            "SHL" => Self::SHL,
+            "SHL_ASSIGN" => Self::SHL_ASSIGN,
+            "SHR" => Self::SHR,
+            "SHR_ASSIGN" => Self::SHR_ASSIGN,
+            "STRING_DISPLAY" => Self::STRING_DISPLAY,
+            "STRING_DEBUG" => Self::STRING_DEBUG,
+            "INTO_ITER" => Self::INTO_ITER,
+            "NEXT" => Self::NEXT,
+            "INTO_FUTURE" => Self::INTO_FUTURE,
+            "IS_VARIANT" => Self::IS_VARIANT,
+            _ => return None,
+        })
+    }
+
     /// Check two types for equality.
     pub const EQ: Protocol = Protocol {
         name: "eq",
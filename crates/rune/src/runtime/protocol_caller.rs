@@ -92,7 +92,9 @@ impl ProtocolCaller for &mut Vm {
     where
         A: GuardedArgs,
     {
-        if let CallResult::Unsupported(..) = self.call_instance_fn(target, protocol, args)? {
+        if let CallResult::Unsupported(..) =
+            self.call_instance_fn_immediate(target, protocol, args)?
+        {
             return Err(VmError::from(VmErrorKind::MissingFunction {
                 hash: protocol.hash,
             }));
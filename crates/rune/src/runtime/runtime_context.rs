@@ -24,16 +24,20 @@ pub struct RuntimeContext {
     functions: HashMap<Hash, Arc<FunctionHandler>>,
     /// Named constant values
     constants: HashMap<Hash, ConstValue>,
+    /// Names of native functions, used in backtraces.
+    function_names: HashMap<Hash, Box<str>>,
 }
 
 impl RuntimeContext {
     pub(crate) fn new(
         functions: HashMap<Hash, Arc<FunctionHandler>>,
         constants: HashMap<Hash, ConstValue>,
+        function_names: HashMap<Hash, Box<str>>,
     ) -> Self {
         Self {
             functions,
             constants,
+            function_names,
         }
     }
 
@@ -42,6 +46,11 @@ impl RuntimeContext {
         self.functions.get(&hash)
     }
 
+    /// Lookup the name of the given native function.
+    pub fn function_name(&self, hash: Hash) -> Option<&str> {
+        self.function_names.get(&hash).map(AsRef::as_ref)
+    }
+
     /// Read a constant value from the unit.
     pub fn constant(&self, hash: Hash) -> Option<&ConstValue> {
         self.constants.get(&hash)
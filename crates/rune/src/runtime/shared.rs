@@ -1,7 +1,10 @@
+use crate::runtime::limits;
+use crate::runtime::memory::{Accounted, Measure, Memory};
 use crate::runtime::{
     Access, AccessError, AccessKind, AnyObj, AnyObjError, BorrowMut, BorrowRef, RawAccessGuard,
 };
 use crate::{Any, Hash};
+use std::alloc::{self, Layout};
 use std::any;
 use std::cell::{Cell, UnsafeCell};
 use std::fmt;
@@ -12,6 +15,7 @@ use std::ops;
 use std::pin::Pin;
 use std::process;
 use std::ptr;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 /// A shared value.
@@ -22,15 +26,32 @@ pub struct Shared<T: ?Sized> {
 impl<T> Shared<T> {
     /// Construct a new shared value.
     pub fn new(data: T) -> Self {
-        let inner = Box::leak(Box::new(SharedBox {
+        Self::with_measure(data, None)
+    }
+
+    /// Construct a new shared value whose heap allocations are accounted for.
+    pub(crate) fn new_measured(data: T) -> Self
+    where
+        T: Measure,
+    {
+        Self::with_measure(data, Some(measure_erased::<T>))
+    }
+
+    fn with_measure(data: T, measure: Option<MeasureFn>) -> Self {
+        let inner = SharedBox {
             access: Access::new(false),
             count: Cell::new(1),
             data: data.into(),
-        }));
+        };
 
-        Self {
-            inner: inner.into(),
-        }
+        // NB: only values allocated while memory accounting is installed pay
+        // for it, by having it allocated in front of the box.
+        let inner = match limits::memory() {
+            Some(memory) => unsafe { Accounting::alloc(memory, measure, inner) },
+            None => ptr::NonNull::from(Box::leak(Box::new(inner))),
+        };
+
+        Self { inner }
     }
 
     /// Return a debug formatter, that when printed will display detailed
@@ -150,6 +171,7 @@ impl<T> Shared<T> {
             //
             // Future access is forever prevented since we never release
             // the access (see above).
+            SharedBox::release_data(inner);
             Ok(ptr::read(inner.data.get()))
         }
     }
@@ -265,6 +287,7 @@ impl<T> Shared<T> {
 
             Ok(Mut {
                 data: ptr::NonNull::new_unchecked(this.inner.as_ref().data.get()),
+                charge: Charge::new(this.inner.as_ptr()),
                 guard,
                 inner: RawDrop::decrement_shared_box(this.inner),
             })
@@ -352,7 +375,12 @@ impl<T: ?Sized> Shared<T> {
             let inner = self.inner.as_ref();
             let guard = inner.access.exclusive(AccessKind::Any)?;
             mem::forget(guard);
-            Ok(BorrowMut::new(&mut *inner.data.get(), &inner.access))
+
+            Ok(BorrowMut::with_charge(
+                &mut *inner.data.get(),
+                &inner.access,
+                Charge::new(inner),
+            ))
         }
     }
 }
@@ -665,6 +693,7 @@ impl Shared<AnyObj> {
 
             Ok(Mut {
                 data: ptr::NonNull::new_unchecked(data as *mut T),
+                charge: None,
                 guard,
                 inner: RawDrop::decrement_shared_box(this.inner),
             })
@@ -728,7 +757,7 @@ where
             let mut debug = fmt.debug_struct("Shared");
 
             debug.field("access", &inner.access);
-            debug.field("count", &inner.count.get());
+            debug.field("count", &(inner.count.get() & !ACCOUNTED));
 
             if !inner.access.is_shared() {
                 debug.field("data", &any::type_name::<T>());
@@ -746,7 +775,8 @@ where
 struct SharedBox<T: ?Sized> {
     /// The access of the shared data.
     access: Access,
-    /// The number of strong references to the shared data.
+    /// The number of strong references to the shared data. The
+    /// [ACCOUNTED] bit is set if the box is preceded by [Accounting].
     count: Cell<usize>,
     /// The value being held. Guarded by the `access` field to determine if it
     /// can be access shared or exclusively.
@@ -754,11 +784,23 @@ struct SharedBox<T: ?Sized> {
 }
 
 impl<T: ?Sized> SharedBox<T> {
+    /// Release the memory accounted for by the heap allocations of the data,
+    /// since it's about to be moved out of the box.
+    ///
+    /// # Safety
+    ///
+    /// The data must not have been taken.
+    unsafe fn release_data(this: *const Self) {
+        if let Some(accounting) = Accounting::get(this) {
+            accounting.memory.release(accounting.heap.replace(0));
+        }
+    }
+
     /// Increment the reference count of the inner value.
     unsafe fn inc(this: *const Self) {
         let count = (*this).count.get();
 
-        if count == 0 || count == usize::max_value() {
+        if count & !ACCOUNTED == 0 || count & !ACCOUNTED == !ACCOUNTED {
             process::abort();
         }
 
@@ -774,17 +816,22 @@ impl<T: ?Sized> SharedBox<T> {
     unsafe fn dec(this: *mut Self) -> bool {
         let count = (*this).count.get();
 
-        if count == 0 {
+        if count & !ACCOUNTED == 0 {
             process::abort();
         }
 
         let count = count - 1;
         (*this).count.set(count);
 
-        if count != 0 {
+        if count & !ACCOUNTED != 0 {
             return false;
         }
 
+        if count & ACCOUNTED != 0 {
+            Accounting::free(this);
+            return true;
+        }
+
         let this = Box::from_raw(this);
 
         if this.access.is_taken() {
@@ -809,6 +856,203 @@ impl<T: ?Sized> SharedBox<T> {
     }
 }
 
+/// Bit set in the reference count of a [SharedBox] which is preceded by
+/// [Accounting].
+const ACCOUNTED: usize = !(usize::MAX >> 1);
+
+/// Measures the heap allocations of type-erased data.
+type MeasureFn = unsafe fn(*const ()) -> usize;
+
+unsafe fn measure_erased<T>(data: *const ()) -> usize
+where
+    T: Measure,
+{
+    (*(data as *const T)).measure()
+}
+
+/// Memory accounting for a [SharedBox], which is allocated in front of it if
+/// it was constructed while accounting was installed.
+struct Accounting {
+    /// The memory accounting charged for the box.
+    memory: Arc<Memory>,
+    /// Measures the heap allocations of the data, if they're accounted for.
+    measure: Option<MeasureFn>,
+    /// The number of bytes currently charged for the heap allocations of the
+    /// data.
+    heap: Cell<usize>,
+}
+
+impl Accounting {
+    /// The layout of a box preceded by accounting, and the offset of the box.
+    fn layout(inner: Layout) -> (Layout, usize) {
+        // NB: this can only fail for layouts which are already close to
+        // `isize::MAX` in size, which a box can't be.
+        let (layout, offset) = Layout::new::<Self>()
+            .extend(inner)
+            .expect("layout of accounted box overflowed");
+
+        (layout.pad_to_align(), offset)
+    }
+
+    /// Allocate the given box preceded by accounting, charging `memory` for
+    /// it.
+    ///
+    /// # Safety
+    ///
+    /// If `measure` is specified, it must be able to measure `T`.
+    unsafe fn alloc<T>(
+        memory: Arc<Memory>,
+        measure: Option<MeasureFn>,
+        inner: SharedBox<T>,
+    ) -> ptr::NonNull<SharedBox<T>> {
+        let (layout, offset) = Self::layout(Layout::new::<SharedBox<T>>());
+        let ptr = alloc::alloc(layout);
+
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+
+        let inner_ptr = ptr.add(offset) as *mut SharedBox<T>;
+        ptr::write(inner_ptr, inner);
+        (*inner_ptr).count.set((*inner_ptr).count.get() | ACCOUNTED);
+
+        let heap = match measure {
+            Some(measure) => measure((*inner_ptr).data.get() as *const ()),
+            None => 0,
+        };
+
+        memory.allocate(layout.size() + heap);
+
+        ptr::write(
+            ptr as *mut Self,
+            Self {
+                memory,
+                measure,
+                heap: Cell::new(heap),
+            },
+        );
+
+        ptr::NonNull::new_unchecked(inner_ptr)
+    }
+
+    /// Get the accounting in front of the given box, if it has any.
+    ///
+    /// # Safety
+    ///
+    /// The box must be alive.
+    unsafe fn get<'a, T: ?Sized>(this: *const SharedBox<T>) -> Option<&'a Self> {
+        if (*this).count.get() & ACCOUNTED == 0 {
+            return None;
+        }
+
+        let (_, offset) = Self::layout(Layout::for_value(&*this));
+        Some(&*((this as *const u8).sub(offset) as *const Self))
+    }
+
+    /// Charge or release whatever the heap allocations of the data have
+    /// changed by since they were last charged.
+    ///
+    /// # Safety
+    ///
+    /// The data must belong to the box this accounting is in front of, and
+    /// must not have been taken.
+    unsafe fn reconcile(&self, data: *const ()) {
+        let measure = match self.measure {
+            Some(measure) => measure,
+            None => return,
+        };
+
+        let heap = measure(data);
+        let charged = self.heap.replace(heap);
+
+        if heap > charged {
+            self.memory.allocate(heap - charged);
+        } else {
+            self.memory.release(charged - heap);
+        }
+    }
+
+    /// Drop and free a box preceded by accounting, releasing what it was
+    /// charged.
+    ///
+    /// # Safety
+    ///
+    /// The box must have been allocated with [Accounting::alloc], and must
+    /// not be used again.
+    unsafe fn free<T: ?Sized>(this: *mut SharedBox<T>) {
+        let (layout, offset) = Self::layout(Layout::for_value(&*this));
+        let ptr = (this as *mut u8).sub(offset);
+        let accounting = ptr::read(ptr as *const Self);
+        let taken = (*this).access.is_taken();
+
+        // NB: exactly what was charged is released, which is kept up to date
+        // whenever exclusive access to the data is released.
+        accounting
+            .memory
+            .release(layout.size().saturating_add(accounting.heap.get()));
+
+        // NB: the data has already been moved out if the box is taken.
+        if !taken {
+            debug_assert!(
+                (*this).access.is_exclusive(),
+                "expected exclusive, but was: {:?}",
+                (*this).access
+            );
+
+            ptr::drop_in_place((*this).data.get());
+        }
+
+        alloc::dealloc(ptr, layout);
+    }
+}
+
+/// Charges the memory accounting of a [SharedBox] for what the heap
+/// allocations of its data have changed by, once exclusive access to it is
+/// released.
+pub(crate) struct Charge {
+    accounting: ptr::NonNull<Accounting>,
+    data: *const (),
+}
+
+impl Charge {
+    /// Construct a charge for the given box, if the heap allocations of its
+    /// data are accounted for.
+    ///
+    /// # Safety
+    ///
+    /// The box must outlive the charge, and the charge must only be
+    /// constructed while the data is exclusively accessed.
+    unsafe fn new<T: ?Sized>(this: *const SharedBox<T>) -> Option<Self> {
+        let accounting = Accounting::get(this)?;
+        accounting.measure?;
+
+        Some(Self {
+            accounting: accounting.into(),
+            data: (*this).data.get() as *const (),
+        })
+    }
+
+    /// The memory accounting the box is charged to.
+    pub(crate) fn memory(&self) -> &Memory {
+        // Safety: the box outlives the charge.
+        unsafe { &self.accounting.as_ref().memory }
+    }
+
+    /// Charge or release whatever the heap allocations of the data have
+    /// changed by.
+    pub(crate) fn reconcile(&self) {
+        // Safety: the box outlives the charge, and its data can't be taken
+        // while it's exclusively accessed.
+        unsafe { self.accounting.as_ref().reconcile(self.data) }
+    }
+}
+
+impl Drop for Charge {
+    fn drop(&mut self) {
+        self.reconcile();
+    }
+}
+
 type DropFn = unsafe fn(*const ());
 
 struct RawDrop {
@@ -1000,6 +1244,7 @@ pub struct RawRef {
 /// A strong mutable reference to the given type.
 pub struct Mut<T: ?Sized> {
     data: ptr::NonNull<T>,
+    charge: Option<Charge>,
     // Safety: it is important that the guard is dropped before `RawDrop`, since
     // `RawDrop` might deallocate the `Access` instance the guard is referring
     // to. This is guaranteed by: https://github.com/rust-lang/rfcs/pull/1857
@@ -1029,9 +1274,9 @@ impl<T: ?Sized> Mut<T> {
     {
         let Self {
             mut data,
+            charge,
             guard,
             inner,
-            ..
         } = this;
 
         // Safety: this follows the same safety guarantees as when the managed
@@ -1041,6 +1286,7 @@ impl<T: ?Sized> Mut<T> {
 
         Mut {
             data: data.into(),
+            charge,
             guard,
             inner,
         }
@@ -1067,9 +1313,9 @@ impl<T: ?Sized> Mut<T> {
     {
         let Self {
             mut data,
+            charge,
             guard,
             inner,
-            ..
         } = this;
 
         // Safety: this follows the same safety guarantees as when the managed
@@ -1077,6 +1323,7 @@ impl<T: ?Sized> Mut<T> {
         // permitted to do any sort of projection to `U`.
         f(unsafe { data.as_mut() }).map(|data| Mut {
             data: data.into(),
+            charge,
             guard,
             inner,
         })
@@ -1091,6 +1338,7 @@ impl<T: ?Sized> Mut<T> {
     /// the current.
     pub fn into_raw(this: Self) -> (*mut T, RawMut) {
         let guard = RawMut {
+            _charge: this.charge,
             _guard: this.guard,
             _inner: this.inner,
         };
@@ -1117,6 +1365,14 @@ impl<T: ?Sized> ops::DerefMut for Mut<T> {
     }
 }
 
+impl<T: ?Sized> Accounted for Mut<T> {
+    fn accounted(&mut self) -> (&mut Self::Target, Option<&Charge>) {
+        // Safety: An owned mut holds onto a hard pointer to the data,
+        // preventing it from being dropped for the duration of the owned mut.
+        (unsafe { self.data.as_mut() }, self.charge.as_ref())
+    }
+}
+
 impl<T: ?Sized> fmt::Debug for Mut<T>
 where
     T: fmt::Debug,
@@ -1141,6 +1397,7 @@ where
 
 /// A raw guard to a [Ref].
 pub struct RawMut {
+    _charge: Option<Charge>,
     _guard: RawAccessGuard,
     _inner: RawDrop,
 }
@@ -31,6 +31,14 @@ where
         }
     }
 
+    /// Get a mutable reference to the virtual machine currently running the
+    /// stream, or `None` if it has completed.
+    ///
+    /// This can be used to provide more fuel to a stream which ran out of it.
+    pub fn vm_mut(&mut self) -> Option<&mut Vm> {
+        Some(self.execution.as_mut()?.vm_mut())
+    }
+
     /// Get the next value produced by this stream.
     pub async fn next(&mut self) -> Result<Option<Value>, VmError> {
         Ok(match self.resume(Value::Unit).await? {
@@ -101,13 +101,13 @@ where
 
 impl ToValue for Box<str> {
     fn to_value(self) -> Result<Value, VmError> {
-        Ok(Value::from(Shared::new(self.to_string())))
+        Ok(Value::from(Shared::new_measured(self.to_string())))
     }
 }
 
 impl ToValue for &str {
     fn to_value(self) -> Result<Value, VmError> {
-        Ok(Value::from(Shared::new(self.to_string())))
+        Ok(Value::from(Shared::new_measured(self.to_string())))
     }
 }
 
@@ -208,7 +208,7 @@ macro_rules! impl_map {
                     output.insert(key, value.to_value()?);
                 }
 
-                Ok(Value::from(Shared::new(output)))
+                Ok(Value::from(Shared::new_measured(output)))
             }
         }
     };
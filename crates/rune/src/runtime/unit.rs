@@ -3,7 +3,7 @@
 //! A unit consists of a sequence of instructions, and lookaside tables for
 //! metadata like function locations.
 
-use crate::collections::HashMap;
+use crate::collections::{HashMap, HashSet};
 use crate::runtime::{
     Call, ConstValue, DebugInfo, Inst, Rtti, StaticString, VariantRtti, VmError, VmErrorKind,
 };
@@ -38,6 +38,8 @@ pub struct Unit {
     debug: Option<Box<DebugInfo>>,
     /// Named constants
     constants: HashMap<Hash, ConstValue>,
+    /// Traits implemented by types, as pairs of type and trait hashes.
+    impls: HashSet<(Hash, Hash)>,
 }
 
 impl Unit {
@@ -53,6 +55,7 @@ impl Unit {
         variant_rtti: HashMap<Hash, Arc<VariantRtti>>,
         debug: Option<Box<DebugInfo>>,
         constants: HashMap<Hash, ConstValue>,
+        impls: HashSet<(Hash, Hash)>,
     ) -> Self {
         Self {
             instructions,
@@ -64,6 +67,7 @@ impl Unit {
             variant_rtti,
             debug,
             constants,
+            impls,
         }
     }
 
@@ -149,6 +153,11 @@ impl Unit {
     pub fn constant(&self, hash: Hash) -> Option<&ConstValue> {
         self.constants.get(&hash)
     }
+
+    /// Test if the type with the given hash implements the given trait.
+    pub fn implements(&self, type_hash: Hash, trait_hash: Hash) -> bool {
+        self.impls.contains(&(type_hash, trait_hash))
+    }
 }
 
 /// The kind and necessary information on registered functions.
@@ -0,0 +1,440 @@
+//! The file format used to store compiled units.
+//!
+//! A unit file starts with [MAGIC] and the version of the format as a little
+//! endian `u32`, which is followed by a header and the unit itself, both
+//! encoded with [bincode]. The header records the version of Rune which
+//! produced the unit, and the native functions and types in the
+//! [Context] it was linked against, so that loading it with an incompatible
+//! context can be refused up front instead of failing at runtime.
+//!
+//! Decoding is limited to [MAX_SIZE] bytes, so that a corrupt or malicious
+//! file can't make us allocate unbounded amounts of memory.
+
+use crate::collections::{BTreeMap, HashSet};
+use crate::compile::{Context, ContextSignature};
+use crate::runtime::{Inst, InstAssignOp, InstOp, InstTarget, InstValue, Protocol, Unit};
+use crate::Hash;
+use bincode::Options as _;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use thiserror::Error;
+
+/// The magic bytes which every unit file starts with.
+pub const MAGIC: [u8; 4] = *b"RNC\0";
+
+/// The version of the unit file format.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The maximum number of bytes the header or the unit of a unit file may
+/// decode from.
+pub const MAX_SIZE: u64 = 256 * 1024 * 1024;
+
+/// The version of Rune which unit files are written by.
+const RUNE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// An error raised when reading or writing a unit file.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+#[non_exhaustive]
+pub enum UnitFileError {
+    #[error("i/o error: {error}")]
+    Io {
+        #[from]
+        error: io::Error,
+    },
+    #[error("failed to encode or decode unit: {error}")]
+    Encoding {
+        #[from]
+        error: bincode::Error,
+    },
+    #[error("not a unit file")]
+    BadMagic,
+    #[error("unsupported unit file format version {actual}, expected {expected}")]
+    UnsupportedFormat { expected: u32, actual: u32 },
+    #[error("unit was built by rune {actual}, but this is rune {expected}")]
+    RuneVersionMismatch { expected: String, actual: String },
+    #[error(
+        "unit was linked against native items missing from the context: {}",
+        DisplayMissing(missing)
+    )]
+    MissingNatives { missing: Vec<(Hash, Box<str>)> },
+}
+
+/// Display a list of missing native items.
+struct DisplayMissing<'a>(&'a [(Hash, Box<str>)]);
+
+impl fmt::Display for DisplayMissing<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut it = self.0.iter().peekable();
+
+        while let Some((hash, name)) = it.next() {
+            write!(f, "`{}` ({})", name, hash)?;
+
+            if it.peek().is_some() {
+                write!(f, ", ")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The header of a unit file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    /// The version of Rune which wrote the unit.
+    rune_version: String,
+    /// A hash of every native function and type in the context the unit was
+    /// linked against.
+    context: Hash,
+    /// The native functions referenced by the unit, and their names.
+    functions: Vec<(Hash, Box<str>)>,
+    /// The native types referenced by the unit, and their names.
+    types: Vec<(Hash, Box<str>)>,
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "unit-file")))]
+impl Unit {
+    /// Write the unit to the given writer in the unit file format, recording
+    /// the native functions and types it uses from `context`, which should be
+    /// the one it was compiled with.
+    ///
+    /// ```
+    /// use rune::{Context, Unit};
+    ///
+    /// # fn main() -> rune::Result<()> {
+    /// let context = Context::with_default_modules()?;
+    ///
+    /// let mut sources = rune::sources! {
+    ///     entry => {
+    ///         pub fn main() {
+    ///             String::from_str("hello")
+    ///         }
+    ///     }
+    /// };
+    ///
+    /// let unit = rune::prepare(&mut sources).with_context(&context).build()?;
+    ///
+    /// let mut file = Vec::new();
+    /// unit.to_writer(&mut file, &context)?;
+    ///
+    /// let unit = Unit::from_reader(&file[..], &context)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn to_writer<W>(&self, mut writer: W, context: &Context) -> Result<(), UnitFileError>
+    where
+        W: io::Write,
+    {
+        let mut natives = Natives::new(context);
+
+        for inst in self.iter_instructions() {
+            natives.inst(self, inst);
+        }
+
+        let (functions, types) = natives.finish();
+
+        let header = Header {
+            rune_version: RUNE_VERSION.to_owned(),
+            context: context_hash(context),
+            functions,
+            types,
+        };
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        options().serialize_into(&mut writer, &header)?;
+        options().serialize_into(&mut writer, self)?;
+        Ok(())
+    }
+
+    /// Read a unit in the unit file format from the given reader.
+    ///
+    /// This refuses to load a unit which was written by a different version
+    /// of Rune, or which uses native functions or types which are missing from
+    /// `context`.
+    pub fn from_reader<R>(mut reader: R, context: &Context) -> Result<Self, UnitFileError>
+    where
+        R: io::Read,
+    {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+
+        if magic != MAGIC {
+            return Err(UnitFileError::BadMagic);
+        }
+
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+
+        if version != FORMAT_VERSION {
+            return Err(UnitFileError::UnsupportedFormat {
+                expected: FORMAT_VERSION,
+                actual: version,
+            });
+        }
+
+        let header: Header = options()
+            .with_limit(MAX_SIZE)
+            .deserialize_from(&mut reader)?;
+
+        if header.rune_version != RUNE_VERSION {
+            return Err(UnitFileError::RuneVersionMismatch {
+                expected: RUNE_VERSION.to_owned(),
+                actual: header.rune_version,
+            });
+        }
+
+        // NB: only if the context differs from the one the unit was linked
+        // against do we need to check each native item individually.
+        if header.context != context_hash(context) {
+            let functions = header
+                .functions
+                .into_iter()
+                .filter(|(hash, _)| context.lookup_function(*hash).is_none());
+
+            let types = header
+                .types
+                .into_iter()
+                .filter(|(hash, _)| context.lookup_type(*hash).is_none());
+
+            let missing = functions.chain(types).collect::<Vec<_>>();
+
+            if !missing.is_empty() {
+                return Err(UnitFileError::MissingNatives { missing });
+            }
+        }
+
+        Ok(options().with_limit(MAX_SIZE).deserialize_from(reader)?)
+    }
+}
+
+/// Collects the native functions and types referenced by the instructions of
+/// a unit.
+///
+/// Instance functions and protocols are resolved against the type of a value
+/// at runtime, so for those every native implementation which could be called
+/// is recorded.
+struct Natives<'a> {
+    context: &'a Context,
+    functions: BTreeMap<Hash, Box<str>>,
+    types: BTreeMap<Hash, Box<str>>,
+    /// Name hashes of instance functions, including protocols.
+    instance_fns: HashSet<Hash>,
+    /// Protocols and field names of field functions.
+    field_fns: HashSet<(Protocol, Hash)>,
+    /// Protocols and indexes of index functions.
+    index_fns: HashSet<(Protocol, Hash)>,
+}
+
+impl<'a> Natives<'a> {
+    fn new(context: &'a Context) -> Self {
+        Self {
+            context,
+            functions: BTreeMap::new(),
+            types: BTreeMap::new(),
+            instance_fns: HashSet::new(),
+            field_fns: HashSet::new(),
+            index_fns: HashSet::new(),
+        }
+    }
+
+    /// Record the natives referenced by a single instruction.
+    fn inst(&mut self, unit: &Unit, inst: Inst) {
+        match inst {
+            Inst::Call { hash, .. } | Inst::LoadFn { hash } if unit.function(hash).is_none() => {
+                self.function(hash);
+            }
+            Inst::CallInstance { hash, .. } | Inst::LoadInstanceFn { hash } => {
+                self.instance_fns.insert(hash);
+            }
+            Inst::IndexGet { .. } => {
+                self.protocol(Protocol::INDEX_GET);
+            }
+            Inst::IndexSet => {
+                self.protocol(Protocol::INDEX_SET);
+            }
+            Inst::TupleIndexGet { index } | Inst::TupleIndexGetAt { index, .. } => {
+                self.index_fns.insert((Protocol::GET, Hash::index(index)));
+            }
+            Inst::TupleIndexSet { index } => {
+                self.index_fns.insert((Protocol::SET, Hash::index(index)));
+            }
+            Inst::ObjectIndexGet { slot } | Inst::ObjectIndexGetAt { slot, .. } => {
+                self.field(unit, Protocol::GET, slot);
+                self.protocol(Protocol::FALLBACK_GET);
+            }
+            Inst::ObjectIndexSet { slot } => {
+                self.field(unit, Protocol::SET, slot);
+                self.protocol(Protocol::FALLBACK_SET);
+            }
+            Inst::Await | Inst::Select { .. } => {
+                self.protocol(Protocol::INTO_FUTURE);
+            }
+            Inst::StringConcat { .. } | Inst::Format { .. } => {
+                self.protocol(Protocol::STRING_DISPLAY);
+                self.protocol(Protocol::STRING_DEBUG);
+            }
+            Inst::Op { op, .. } => {
+                if let Some(protocol) = op_protocol(op) {
+                    self.protocol(protocol);
+                }
+            }
+            Inst::Assign { target, op } => {
+                let protocol = assign_protocol(op);
+
+                match target {
+                    InstTarget::Offset(..) => self.protocol(protocol),
+                    InstTarget::Field(slot) => self.field(unit, protocol, slot),
+                    InstTarget::TupleField(index) => {
+                        self.index_fns.insert((protocol, Hash::index(index)));
+                    }
+                }
+            }
+            Inst::Push {
+                value: InstValue::Type(hash),
+            }
+            | Inst::MatchType { hash }
+            | Inst::UnitStruct { hash }
+            | Inst::Struct { hash, .. }
+            | Inst::UnitVariant { hash }
+            | Inst::StructVariant { hash, .. } => {
+                self.ty(hash);
+            }
+            Inst::MatchVariant {
+                enum_hash,
+                variant_hash,
+                ..
+            } => {
+                self.ty(enum_hash);
+                self.ty(variant_hash);
+                self.protocol(Protocol::IS_VARIANT);
+            }
+            _ => {}
+        }
+    }
+
+    /// Record a native function called directly by hash.
+    fn function(&mut self, hash: Hash) {
+        if self.context.lookup_function(hash).is_none() {
+            return;
+        }
+
+        let name = match self.context.lookup_signature(hash) {
+            Some(signature) => signature.to_string(),
+            None => hash.to_string(),
+        };
+
+        self.functions.insert(hash, name.into());
+    }
+
+    /// Record a native type.
+    fn ty(&mut self, hash: Hash) {
+        if let Some(ty) = self.context.lookup_type(hash) {
+            self.types.insert(hash, ty.item.to_string().into());
+        }
+    }
+
+    /// Record a protocol which is called as an instance function.
+    fn protocol(&mut self, protocol: Protocol) {
+        self.instance_fns.insert(protocol.hash);
+    }
+
+    /// Record a field function for the field name stored in the given slot.
+    fn field(&mut self, unit: &Unit, protocol: Protocol, slot: usize) {
+        if let Ok(field) = unit.lookup_string(slot) {
+            self.field_fns.insert((protocol, field.hash()));
+        }
+    }
+
+    /// Resolve the instance, field and index functions recorded against every
+    /// native type they could be called on, and return all natives used.
+    #[allow(clippy::type_complexity)]
+    fn finish(mut self) -> (Vec<(Hash, Box<str>)>, Vec<(Hash, Box<str>)>) {
+        for (hash, signature) in self.context.iter_functions() {
+            let type_hash = match signature {
+                ContextSignature::Instance { type_hash, .. } => *type_hash,
+                ContextSignature::Function { .. } => continue,
+            };
+
+            let used =
+                self.instance_fns
+                    .iter()
+                    .any(|name| Hash::instance_function(type_hash, *name) == hash)
+                    || self.field_fns.iter().any(|(protocol, name)| {
+                        Hash::field_fn(*protocol, type_hash, *name) == hash
+                    })
+                    || self.index_fns.iter().any(|(protocol, index)| {
+                        Hash::index_fn(*protocol, type_hash, *index) == hash
+                    });
+
+            if used {
+                self.functions.insert(hash, signature.to_string().into());
+            }
+        }
+
+        (
+            self.functions.into_iter().collect(),
+            self.types.into_iter().collect(),
+        )
+    }
+}
+
+/// The protocol an operation falls back to for values which aren't built in.
+fn op_protocol(op: InstOp) -> Option<Protocol> {
+    Some(match op {
+        InstOp::Add => Protocol::ADD,
+        InstOp::Sub => Protocol::SUB,
+        InstOp::Mul => Protocol::MUL,
+        InstOp::Div => Protocol::DIV,
+        InstOp::Rem => Protocol::REM,
+        InstOp::BitAnd => Protocol::BIT_AND,
+        InstOp::BitXor => Protocol::BIT_XOR,
+        InstOp::BitOr => Protocol::BIT_OR,
+        InstOp::Shl => Protocol::SHL,
+        InstOp::Shr => Protocol::SHR,
+        InstOp::Eq | InstOp::Neq => Protocol::EQ,
+        _ => return None,
+    })
+}
+
+/// The protocol an assign operation falls back to for values which aren't
+/// built in.
+fn assign_protocol(op: InstAssignOp) -> Protocol {
+    match op {
+        InstAssignOp::Add => Protocol::ADD_ASSIGN,
+        InstAssignOp::Sub => Protocol::SUB_ASSIGN,
+        InstAssignOp::Mul => Protocol::MUL_ASSIGN,
+        InstAssignOp::Div => Protocol::DIV_ASSIGN,
+        InstAssignOp::Rem => Protocol::REM_ASSIGN,
+        InstAssignOp::BitAnd => Protocol::BIT_AND_ASSIGN,
+        InstAssignOp::BitXor => Protocol::BIT_XOR_ASSIGN,
+        InstAssignOp::BitOr => Protocol::BIT_OR_ASSIGN,
+        InstAssignOp::Shl => Protocol::SHL_ASSIGN,
+        InstAssignOp::Shr => Protocol::SHR_ASSIGN,
+    }
+}
+
+/// The bincode options used for unit files, which match the encoding of
+/// [bincode::serialize].
+fn options() -> impl bincode::Options {
+    bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+}
+
+/// Hash every native function and type in the context.
+fn context_hash(context: &Context) -> Hash {
+    let mut functions = context
+        .iter_functions()
+        .map(|(hash, _)| hash)
+        .collect::<Vec<_>>();
+    let mut types = context
+        .iter_types()
+        .map(|(hash, _)| hash)
+        .collect::<Vec<_>>();
+    functions.sort();
+    types.sort();
+    Hash::of((functions, types))
+}
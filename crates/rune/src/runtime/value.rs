@@ -569,12 +569,12 @@ impl Value {
 
     /// Construct a vector.
     pub fn vec(vec: vec::Vec<Value>) -> Self {
-        Self::Vec(Shared::new(Vec::from(vec)))
+        Self::Vec(Shared::new_measured(Vec::from(vec)))
     }
 
     /// Construct a tuple.
     pub fn tuple(vec: vec::Vec<Value>) -> Self {
-        Self::Tuple(Shared::new(Tuple::from(vec)))
+        Self::Tuple(Shared::new_measured(Tuple::from(vec)))
     }
 
     /// Construct an empty.
@@ -611,11 +611,11 @@ impl Value {
             Self::Float(value) => Self::Float(value),
             Self::Type(value) => Self::Type(value),
             Self::StaticString(value) => Self::StaticString(value),
-            Self::String(value) => Self::String(Shared::new(value.take()?)),
-            Self::Bytes(value) => Self::Bytes(Shared::new(value.take()?)),
-            Self::Vec(value) => Self::Vec(Shared::new(value.take()?)),
-            Self::Tuple(value) => Self::Tuple(Shared::new(value.take()?)),
-            Self::Object(value) => Self::Object(Shared::new(value.take()?)),
+            Self::String(value) => Self::String(Shared::new_measured(value.take()?)),
+            Self::Bytes(value) => Self::Bytes(Shared::new_measured(value.take()?)),
+            Self::Vec(value) => Self::Vec(Shared::new_measured(value.take()?)),
+            Self::Tuple(value) => Self::Tuple(Shared::new_measured(value.take()?)),
+            Self::Object(value) => Self::Object(Shared::new_measured(value.take()?)),
             Self::Range(value) => Self::Range(Shared::new(value.take()?)),
             Self::Future(value) => Self::Future(Shared::new(value.take()?)),
             Self::Stream(value) => Self::Stream(Shared::new(value.take()?)),
@@ -958,6 +958,22 @@ impl Value {
     ///
     /// This is the basis for the eq operation (`==`).
     pub(crate) fn value_ptr_eq(vm: &mut Vm, a: &Value, b: &Value) -> Result<bool, VmError> {
+        // NB: types declared in scripts which implement the EQ protocol take
+        // precedence over structural equality.
+        if let Self::UnitStruct(..) | Self::TupleStruct(..) | Self::Struct(..) | Self::Variant(..) =
+            a
+        {
+            let hash = Hash::instance_function(a.type_hash()?, Protocol::EQ);
+
+            if vm.unit().function(hash).is_some() {
+                if let CallResult::Ok(()) =
+                    vm.call_instance_fn_immediate(a.clone(), Protocol::EQ, (b.clone(),))?
+                {
+                    return bool::from_value(vm.stack_mut().pop()?);
+                }
+            }
+        }
+
         match (a, b) {
             (Self::Unit, Self::Unit) => return Ok(true),
             (Self::Bool(a), Self::Bool(b)) => return Ok(a == b),
@@ -1044,7 +1060,7 @@ impl Value {
                 (Err(a), Err(b)) => return Self::value_ptr_eq(vm, a, b),
                 _ => return Ok(false),
             },
-            (a, b) => match vm.call_instance_fn(a.clone(), Protocol::EQ, (b.clone(),))? {
+            (a, b) => match vm.call_instance_fn_immediate(a.clone(), Protocol::EQ, (b.clone(),))? {
                 CallResult::Ok(()) => return bool::from_value(vm.stack_mut().pop()?),
                 CallResult::Unsupported(..) => {}
             },
@@ -1237,6 +1253,26 @@ macro_rules! impl_from_wrapper {
     };
 }
 
+macro_rules! impl_from_measured {
+    ($($variant:ident => $ty:ty),* $(,)?) => {
+        impl_from!($($variant => Shared<$ty>),*);
+
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(Shared::new_measured(value))
+                }
+            }
+
+            impl ToValue for $ty {
+                fn to_value(self) -> Result<Value, VmError> {
+                    Ok(Value::from(self))
+                }
+            }
+        )*
+    };
+}
+
 impl_from! {
     Byte => u8,
     Bool => bool,
@@ -1251,11 +1287,6 @@ impl_from_wrapper! {
     StaticString => Arc<StaticString>,
     Format => Box<Format>,
     Iterator => Shared<Iterator>,
-    Bytes => Shared<Bytes>,
-    String => Shared<String>,
-    Vec => Shared<Vec>,
-    Tuple => Shared<Tuple>,
-    Object => Shared<Object>,
     Range => Shared<Range>,
     Future => Shared<Future>,
     Stream => Shared<Stream<Vm>>,
@@ -1269,6 +1300,14 @@ impl_from_wrapper! {
     Any => Shared<AnyObj>,
 }
 
+impl_from_measured! {
+    Bytes => Bytes,
+    String => String,
+    Vec => Vec,
+    Tuple => Tuple,
+    Object => Object,
+}
+
 /// Deserialize implementation for value pointers.
 impl<'de> de::Deserialize<'de> for Value {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -1373,7 +1412,7 @@ impl<'de> de::Visitor<'de> for VmVisitor {
     where
         E: de::Error,
     {
-        Ok(Value::String(Shared::new(value.to_owned())))
+        Ok(Value::String(Shared::new_measured(value.to_owned())))
     }
 
     #[inline]
@@ -1381,7 +1420,7 @@ impl<'de> de::Visitor<'de> for VmVisitor {
     where
         E: de::Error,
     {
-        Ok(Value::String(Shared::new(value)))
+        Ok(Value::String(Shared::new_measured(value)))
     }
 
     #[inline]
@@ -1389,7 +1428,9 @@ impl<'de> de::Visitor<'de> for VmVisitor {
     where
         E: de::Error,
     {
-        Ok(Value::Bytes(Shared::new(Bytes::from_vec(v.to_vec()))))
+        Ok(Value::Bytes(Shared::new_measured(Bytes::from_vec(
+            v.to_vec(),
+        ))))
     }
 
     #[inline]
@@ -1397,7 +1438,7 @@ impl<'de> de::Visitor<'de> for VmVisitor {
     where
         E: de::Error,
     {
-        Ok(Value::Bytes(Shared::new(Bytes::from_vec(v))))
+        Ok(Value::Bytes(Shared::new_measured(Bytes::from_vec(v))))
     }
 
     #[inline]
@@ -1535,7 +1576,7 @@ impl<'de> de::Visitor<'de> for VmVisitor {
             vec.push(elem);
         }
 
-        Ok(Value::Vec(Shared::new(Vec::from(vec))))
+        Ok(Value::Vec(Shared::new_measured(Vec::from(vec))))
     }
 
     #[inline]
@@ -1549,7 +1590,7 @@ impl<'de> de::Visitor<'de> for VmVisitor {
             object.insert(key, value);
         }
 
-        Ok(Value::Object(Shared::new(object)))
+        Ok(Value::Object(Shared::new_measured(object)))
     }
 }
 
@@ -86,6 +86,12 @@ impl Vec {
         }
     }
 
+    /// Returns the number of elements the vector can hold without
+    /// reallocating.
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
     /// Appends an element to the back of a dynamic vector.
     pub fn push(&mut self, value: Value) {
         self.inner.push(value);
@@ -341,6 +347,6 @@ where
             vec.push(value.to_value()?);
         }
 
-        Ok(Value::from(Shared::new(Vec::from(vec))))
+        Ok(Value::from(Shared::new_measured(Vec::from(vec))))
     }
 }
@@ -1,12 +1,16 @@
 use crate::runtime::budget;
 use crate::runtime::future::SelectFuture;
+use crate::runtime::limits::{self, Limits};
+use crate::runtime::memory::{self, Memory};
+use crate::runtime::profiler;
 use crate::runtime::unit::UnitFn;
 use crate::runtime::{
-    Args, Awaited, BorrowMut, Bytes, Call, Format, FormatSpec, FromValue, Function, Future,
-    Generator, GuardedArgs, Inst, InstAddress, InstAssignOp, InstOp, InstRangeLimits, InstTarget,
-    InstValue, InstVariant, Object, Panic, Protocol, Range, RangeLimits, RuntimeContext, Select,
-    Shared, Stack, Stream, Struct, Tuple, TypeCheck, Unit, UnitStruct, Value, Variant, VariantData,
-    Vec, VmError, VmErrorKind, VmExecution, VmHalt, VmIntegerRepr, VmSendExecution,
+    Args, Awaited, BorrowMut, Bytes, Call, Coverage, Format, FormatSpec, FromValue, Function,
+    Future, Generator, GuardedArgs, Inst, InstAddress, InstAssignOp, InstOp, InstRange,
+    InstRangeLimits, InstTarget, InstValue, InstVariant, InterruptHandle, Object, Panic, Profiler,
+    Protocol, Range, RangeLimits, RuntimeContext, Select, Shared, Stack, Stream, Struct, Tuple,
+    TypeCheck, Unit, UnitStruct, Value, Variant, VariantData, Vec, VmError, VmErrorKind,
+    VmExecution, VmHalt, VmIntegerRepr, VmSendExecution,
 };
 use crate::{Hash, IntoTypeHash};
 use std::fmt;
@@ -70,6 +74,26 @@ macro_rules! target_value {
 }
 
 /// A stack which references variables indirectly from a slab.
+///
+/// # Limits and hooks
+///
+/// A virtual machine can be limited through [Vm::set_fuel],
+/// [Vm::set_memory_limit], [Vm::set_max_call_depth] and
+/// [Vm::interrupt_handle], and observed through [Vm::set_profiler] and
+/// [Vm::set_coverage].
+///
+/// Any virtual machine started while one is running, like for closures called
+/// from native functions, generators and streams being resumed or async
+/// functions being awaited, inherits whatever it hasn't configured itself:
+///
+/// * It draws from the same fuel. If the fuel runs out inside of it, the error
+///   is propagated like any other and the execution can't be resumed.
+/// * Values it allocates are charged to the same memory limit.
+/// * The functions it calls count towards the same maximum call depth. Since
+///   it recurses on the native stack, this protects the host from running out
+///   of stack space.
+/// * It's interrupted along with the virtual machine it was started from.
+/// * It's profiled as part of the same call tree, and covered as well.
 #[derive(Debug, Clone)]
 pub struct Vm {
     /// Context associated with virtual machine.
@@ -82,6 +106,8 @@ pub struct Vm {
     stack: Stack,
     /// Frames relative to the stack.
     call_frames: vec::Vec<CallFrame>,
+    /// The limits and hooks of the virtual machine.
+    limits: Limits,
 }
 
 impl Vm {
@@ -98,6 +124,7 @@ impl Vm {
             ip: 0,
             stack,
             call_frames: vec::Vec::new(),
+            limits: Limits::new(),
         }
     }
 
@@ -119,6 +146,375 @@ impl Vm {
         self.ip = ip;
     }
 
+    /// Set the number of instructions the virtual machine is allowed to
+    /// execute before it halts.
+    ///
+    /// Once the fuel runs out, the execution errors with
+    /// [VmErrorKind::Halted] and [VmHaltInfo::OutOfFuel][crate::runtime::VmHaltInfo::OutOfFuel].
+    /// It can then be resumed where it left off once more fuel has been
+    /// provided. See [limits and hooks](#limits-and-hooks).
+    ///
+    /// ```
+    /// use rune::runtime::{VmErrorKind, VmHaltInfo};
+    /// use rune::{FromValue, Vm};
+    /// use std::sync::Arc;
+    ///
+    /// # fn main() -> rune::Result<()> {
+    /// let mut sources = rune::sources! {
+    ///     entry => {
+    ///         pub fn main() {
+    ///             let n = 0;
+    ///             let i = 0;
+    ///
+    ///             while i < 100 {
+    ///                 n += i;
+    ///                 i += 1;
+    ///             }
+    ///
+    ///             n
+    ///         }
+    ///     }
+    /// };
+    ///
+    /// let unit = rune::prepare(&mut sources).build()?;
+    ///
+    /// let mut vm = Vm::without_runtime(Arc::new(unit));
+    /// vm.set_fuel(10);
+    ///
+    /// let mut execution = vm.execute(["main"], ())?;
+    ///
+    /// let output = loop {
+    ///     match execution.complete() {
+    ///         Ok(output) => break output,
+    ///         Err(error) => {
+    ///             assert!(matches!(
+    ///                 error.kind(),
+    ///                 VmErrorKind::Halted { halt: VmHaltInfo::OutOfFuel }
+    ///             ));
+    ///
+    ///             execution.vm_mut().set_fuel(10);
+    ///         }
+    ///     }
+    /// };
+    ///
+    /// assert_eq!(i64::from_value(output)?, 4950);
+    /// # Ok(()) }
+    /// ```
+    #[inline]
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.limits.fuel = Some(fuel);
+    }
+
+    /// Get the remaining fuel of the virtual machine, or `None` if its
+    /// execution isn't limited.
+    #[inline]
+    pub fn fuel(&self) -> Option<u64> {
+        self.limits.fuel
+    }
+
+    /// Remove any limit on the number of instructions the virtual machine is
+    /// allowed to execute.
+    #[inline]
+    pub fn clear_fuel(&mut self) {
+        self.limits.fuel = None;
+    }
+
+    /// Limit the number of bytes which values allocated by the virtual machine
+    /// are allowed to use.
+    ///
+    /// Allocations are accounted for strings, bytes, vectors, objects, tuples
+    /// and any other shared value, and are released once the values are
+    /// freed. Exceeding the limit errors with
+    /// [VmErrorKind::MemoryLimitExceeded]. See [limits and
+    /// hooks](#limits-and-hooks).
+    ///
+    /// To only track usage, set the limit to [usize::MAX].
+    ///
+    /// ```
+    /// use rune::runtime::VmErrorKind;
+    /// use rune::{Context, Vm};
+    /// use std::sync::Arc;
+    ///
+    /// # fn main() -> rune::Result<()> {
+    /// let context = Context::with_default_modules()?;
+    ///
+    /// let mut sources = rune::sources! {
+    ///     entry => {
+    ///         pub fn main() {
+    ///             let v = [1, 2, 3];
+    ///
+    ///             loop {
+    ///                 v.push(v.clone());
+    ///             }
+    ///         }
+    ///     }
+    /// };
+    ///
+    /// let unit = rune::prepare(&mut sources).with_context(&context).build()?;
+    ///
+    /// let mut vm = Vm::new(Arc::new(context.runtime()), Arc::new(unit));
+    /// vm.set_memory_limit(1 << 20);
+    ///
+    /// let error = vm.call(["main"], ()).unwrap_err();
+    ///
+    /// assert!(matches!(
+    ///     error.as_unwound().0,
+    ///     VmErrorKind::MemoryLimitExceeded { limit, .. } if *limit == 1 << 20
+    /// ));
+    /// # Ok(()) }
+    /// ```
+    pub fn set_memory_limit(&mut self, limit: usize) {
+        match &self.limits.memory {
+            Some(memory) => memory.set_limit(limit),
+            None => self.limits.memory = Some(Arc::new(Memory::new(limit))),
+        }
+    }
+
+    /// Get the memory limit of the virtual machine, or `None` if it isn't
+    /// limited.
+    pub fn memory_limit(&self) -> Option<usize> {
+        Some(self.limits.memory.as_ref()?.limit())
+    }
+
+    /// Get the number of bytes currently used by values allocated by the
+    /// virtual machine.
+    ///
+    /// This is always zero unless a limit has been set with
+    /// [Vm::set_memory_limit].
+    pub fn memory_usage(&self) -> usize {
+        self.limits
+            .memory
+            .as_ref()
+            .map(|memory| memory.usage())
+            .unwrap_or_default()
+    }
+
+    /// Limit the number of script functions which are allowed to be called at
+    /// the same time.
+    ///
+    /// Calling a function beyond the limit errors with
+    /// [VmErrorKind::StackOverflow], which reports the calls leading up to it
+    /// in its backtrace. See [limits and hooks](#limits-and-hooks).
+    ///
+    /// ```
+    /// use rune::runtime::VmErrorKind;
+    /// use rune::{Context, Vm};
+    /// use std::sync::Arc;
+    ///
+    /// # fn main() -> rune::Result<()> {
+    /// let context = Context::with_default_modules()?;
+    ///
+    /// let mut sources = rune::sources! {
+    ///     entry => {
+    ///         fn recurse(n) {
+    ///             recurse(n + 1)
+    ///         }
+    ///
+    ///         pub fn main() {
+    ///             recurse(0)
+    ///         }
+    ///     }
+    /// };
+    ///
+    /// let unit = rune::prepare(&mut sources).with_context(&context).build()?;
+    ///
+    /// let mut vm = Vm::new(Arc::new(context.runtime()), Arc::new(unit));
+    /// vm.set_max_call_depth(100);
+    ///
+    /// let error = vm.call(["main"], ()).unwrap_err();
+    ///
+    /// assert!(matches!(
+    ///     error.as_unwound().0,
+    ///     VmErrorKind::StackOverflow { limit: 100 }
+    /// ));
+    /// # Ok(()) }
+    /// ```
+    pub fn set_max_call_depth(&mut self, limit: usize) {
+        self.limits.max_call_depth = Some(limit);
+    }
+
+    /// Get the maximum call depth of the virtual machine, or `None` if it
+    /// isn't limited.
+    pub fn max_call_depth(&self) -> Option<usize> {
+        self.limits.max_call_depth
+    }
+
+    /// Remove the maximum call depth of the virtual machine, allowing it to
+    /// call functions as deeply as the rest of the environment permits.
+    pub fn clear_max_call_depth(&mut self) {
+        self.limits.max_call_depth = None;
+    }
+
+    /// Record calls made by the virtual machine in the given profiler. See
+    /// [limits and hooks](#limits-and-hooks).
+    ///
+    /// ```
+    /// use rune::runtime::Profiler;
+    /// use rune::{Context, Vm};
+    /// use std::sync::Arc;
+    ///
+    /// # fn main() -> rune::Result<()> {
+    /// let context = Context::with_default_modules()?;
+    ///
+    /// let mut sources = rune::sources! {
+    ///     entry => {
+    ///         fn add(a, b) {
+    ///             a + b
+    ///         }
+    ///
+    ///         pub fn main() {
+    ///             add(1, 2)
+    ///         }
+    ///     }
+    /// };
+    ///
+    /// let unit = rune::prepare(&mut sources).with_context(&context).build()?;
+    ///
+    /// let profiler = Profiler::new();
+    ///
+    /// let mut vm = Vm::new(Arc::new(context.runtime()), Arc::new(unit));
+    /// vm.set_profiler(profiler.clone());
+    /// vm.call(["main"], ())?;
+    ///
+    /// let functions = profiler.functions();
+    /// assert!(functions.iter().any(|f| f.name == "add" && f.calls == 1));
+    ///
+    /// let mut folded = Vec::new();
+    /// profiler.write_folded(&mut folded)?;
+    /// # Ok(()) }
+    /// ```
+    pub fn set_profiler(&mut self, profiler: Profiler) {
+        self.limits.profiler = Some(profiler);
+    }
+
+    /// Get the profiler of the virtual machine, if one has been set.
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.limits.profiler.as_ref()
+    }
+
+    /// Record the instructions executed by the virtual machine in the given
+    /// coverage. See [limits and hooks](#limits-and-hooks).
+    ///
+    /// ```
+    /// use rune::runtime::Coverage;
+    /// use rune::{Context, Vm};
+    /// use std::sync::Arc;
+    ///
+    /// # fn main() -> rune::Result<()> {
+    /// let context = Context::with_default_modules()?;
+    ///
+    /// let mut sources = rune::sources! {
+    ///     entry => {
+    ///         pub fn main(n) {
+    ///             if n > 0 {
+    ///                 1
+    ///             } else {
+    ///                 2
+    ///             }
+    ///         }
+    ///     }
+    /// };
+    ///
+    /// let unit = Arc::new(rune::prepare(&mut sources).with_context(&context).build()?);
+    /// let coverage = Coverage::new(unit.clone());
+    ///
+    /// let mut vm = Vm::new(Arc::new(context.runtime()), unit);
+    /// vm.set_coverage(coverage.clone());
+    /// vm.call(["main"], (1i64,))?;
+    ///
+    /// let files = coverage.files(&sources);
+    /// assert_eq!(files[0].branches_found(), 2);
+    /// assert_eq!(files[0].branches_hit(), 1);
+    /// # Ok(()) }
+    /// ```
+    pub fn set_coverage(&mut self, coverage: Coverage) {
+        self.limits.coverage = Some(coverage);
+    }
+
+    /// Get the coverage of the virtual machine, if it has been set.
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.limits.coverage.as_ref()
+    }
+
+    /// Get a handle which can be used to interrupt the virtual machine from
+    /// another thread.
+    ///
+    /// Once interrupted, the virtual machine errors with
+    /// [VmErrorKind::Interrupted] the next time it jumps backwards or calls a
+    /// function, which reports the frame where it stopped in its backtrace.
+    /// Futures awaited by an asynchronous execution are cancelled as well. It
+    /// keeps erroring until the interrupt is reset with
+    /// [InterruptHandle::reset]. See [limits and hooks](#limits-and-hooks).
+    ///
+    /// ```
+    /// use rune::runtime::VmErrorKind;
+    /// use rune::{Context, Vm};
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// # fn main() -> rune::Result<()> {
+    /// let context = Context::with_default_modules()?;
+    ///
+    /// let mut sources = rune::sources! {
+    ///     entry => {
+    ///         pub fn main() {
+    ///             loop {}
+    ///         }
+    ///     }
+    /// };
+    ///
+    /// let unit = rune::prepare(&mut sources).with_context(&context).build()?;
+    ///
+    /// let mut vm = Vm::new(Arc::new(context.runtime()), Arc::new(unit));
+    /// let handle = vm.interrupt_handle();
+    ///
+    /// let interrupter = thread::spawn(move || {
+    ///     thread::sleep(Duration::from_millis(10));
+    ///     handle.interrupt();
+    /// });
+    ///
+    /// let error = vm.call(["main"], ()).unwrap_err();
+    /// assert!(matches!(error.as_unwound().0, VmErrorKind::Interrupted));
+    /// interrupter.join().expect("interrupter panicked");
+    /// # Ok(()) }
+    /// ```
+    pub fn interrupt_handle(&mut self) -> InterruptHandle {
+        self.limits
+            .interrupt
+            .get_or_insert_with(Default::default)
+            .handle()
+    }
+
+    /// Check if the virtual machine has been interrupted.
+    #[inline]
+    fn check_interrupt(&self) -> Result<(), VmErrorKind> {
+        match &self.limits.interrupt {
+            Some(interrupt) => interrupt.check(),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the limits observed by the virtual machine, which are either its
+    /// own or the ones of the virtual machine it was started from. Fuel is left
+    /// out.
+    #[inline]
+    pub(crate) fn inherited_limits(&self) -> Limits {
+        self.limits.inherited()
+    }
+
+    /// Set the limits of the virtual machine.
+    #[inline]
+    pub(crate) fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Take the fuel of the virtual machine, leaving it unlimited.
+    #[inline]
+    pub(crate) fn take_fuel(&mut self) -> Option<u64> {
+        self.limits.fuel.take()
+    }
+
     /// Get the stack.
     #[inline]
     pub fn call_frames(&self) -> &[CallFrame] {
@@ -170,6 +566,16 @@ impl Vm {
 
     /// Modify the current instruction pointer.
     pub fn modify_ip(&mut self, offset: isize) -> Result<(), VmError> {
+        // NB: every loop involves a backwards jump, which makes it a good
+        // place to check for interrupts.
+        if offset < 0 {
+            self.check_interrupt()?;
+        }
+
+        if let Some(coverage) = &self.limits.coverage {
+            coverage.jump(&self.unit, self.ip);
+        }
+
         self.ip = if offset < 0 {
             self.ip.wrapping_sub(-offset as usize)
         } else {
@@ -492,7 +898,10 @@ impl Vm {
         }
 
         if let Some(handler) = self.context.function(hash) {
-            handler(&mut self.stack, full_count)?;
+            native(&mut self.limits, &self.context, hash, || {
+                handler(&mut self.stack, full_count)
+            })
+            .map_err(|error| error.with_native(&self.context, hash))?;
             return Ok(CallResult::Ok(()));
         }
 
@@ -502,6 +911,50 @@ impl Vm {
         Ok(CallResult::Unsupported(target))
     }
 
+    /// Helper to call an instance function, waiting for it to complete so that
+    /// its result is on top of the stack when this returns.
+    ///
+    /// Unlike [Vm::call_instance_fn], functions defined in the unit are run to
+    /// completion in a nested virtual machine instead of in a new call frame.
+    pub(crate) fn call_instance_fn_immediate<H, A>(
+        &mut self,
+        target: Value,
+        hash: H,
+        args: A,
+    ) -> Result<CallResult<()>, VmError>
+    where
+        H: IntoTypeHash,
+        A: GuardedArgs,
+    {
+        let hash = hash.into_type_hash();
+
+        if let Some(UnitFn::Offset {
+            offset,
+            call,
+            args: expected,
+        }) = self
+            .unit
+            .function(Hash::instance_function(target.type_hash()?, hash))
+        {
+            let count = args.count() + 1;
+            Self::check_args(count, expected)?;
+
+            let mut stack = Stack::with_capacity(count);
+            stack.push(target);
+
+            // Safety: We hold onto the guard until the vm has completed.
+            let _guard = unsafe { args.unsafe_into_stack(&mut stack)? };
+
+            let mut vm = Vm::with_stack(self.context.clone(), self.unit.clone(), stack);
+            vm.set_ip(offset);
+            let value = limits::lend_fuel(&mut self.limits.fuel, || call.call_with_vm(vm))?;
+            self.stack.push(value);
+            return Ok(CallResult::Ok(()));
+        }
+
+        self.call_instance_fn(target, hash, args)
+    }
+
     /// Helper to call a field function.
     #[inline(always)]
     fn call_field_fn<H, A>(
@@ -523,7 +976,10 @@ impl Vm {
         args.into_stack(&mut self.stack)?;
 
         if let Some(handler) = self.context.function(hash) {
-            handler(&mut self.stack, full_count)?;
+            native(&mut self.limits, &self.context, hash, || {
+                handler(&mut self.stack, full_count)
+            })
+            .map_err(|error| error.with_native(&self.context, hash))?;
             return Ok(CallResult::Ok(()));
         }
 
@@ -553,7 +1009,10 @@ impl Vm {
         args.into_stack(&mut self.stack)?;
 
         if let Some(handler) = self.context.function(hash) {
-            handler(&mut self.stack, full_count)?;
+            native(&mut self.limits, &self.context, hash, || {
+                handler(&mut self.stack, full_count)
+            })
+            .map_err(|error| error.with_native(&self.context, hash))?;
             return Ok(CallResult::Ok(()));
         }
 
@@ -595,6 +1054,13 @@ impl Vm {
     /// This will cause the `args` number of elements on the stack to be
     /// associated and accessible to the new call frame.
     pub(crate) fn push_call_frame(&mut self, ip: usize, args: usize) -> Result<(), VmError> {
+        self.check_interrupt()?;
+        limits::push_call()?;
+
+        if let Some(profiler) = &self.limits.profiler {
+            profiler.call(&self.unit, ip);
+        }
+
         let stack_top = self.stack.swap_stack_bottom(args)?;
 
         self.call_frames.push(CallFrame {
@@ -606,6 +1072,16 @@ impl Vm {
         Ok(())
     }
 
+    /// Call a native function with the given hash through `f`, recording it
+    /// in the profiler and in the backtrace of any error it raises.
+    pub(crate) fn call_native<F>(&mut self, hash: Hash, f: F) -> Result<(), VmError>
+    where
+        F: FnOnce(&mut Stack) -> Result<(), VmError>,
+    {
+        native(&mut self.limits, &self.context, hash, || f(&mut self.stack))
+            .map_err(|error| error.with_native(&self.context, hash))
+    }
+
     /// Pop a call frame and return it.
     fn pop_call_frame(&mut self) -> Result<bool, VmError> {
         let frame = match self.call_frames.pop() {
@@ -616,6 +1092,12 @@ impl Vm {
             }
         };
 
+        limits::pop_call();
+
+        if let Some(profiler) = &self.limits.profiler {
+            profiler.ret();
+        }
+
         self.stack.pop_stack_top(frame.stack_bottom)?;
         self.ip = frame.ip;
         Ok(false)
@@ -931,7 +1413,11 @@ impl Vm {
                     match self.call_field_fn(Protocol::GET, target, index.hash(), ())? {
                         CallResult::Ok(()) => return Ok(CallResult::Ok(self.stack.pop()?)),
                         CallResult::Unsupported(target) => {
-                            match self.call_instance_fn(target, Protocol::FALLBACK_GET, (index,))? {
+                            match self.call_instance_fn_immediate(
+                                target,
+                                Protocol::FALLBACK_GET,
+                                (index,),
+                            )? {
                                 CallResult::Ok(()) => CallResult::Ok(self.stack.pop()?),
                                 CallResult::Unsupported(target) => CallResult::Unsupported(target),
                             }
@@ -957,7 +1443,7 @@ impl Vm {
         Ok(match target {
             Value::Object(object) => {
                 let mut object = object.borrow_mut()?;
-                object.insert(field.as_str().to_owned(), value);
+                memory::insert_entry(&mut object, field.as_str().to_owned(), value)?;
                 return Ok(CallResult::Ok(()));
             }
             Value::Struct(typed_object) => {
@@ -1002,7 +1488,7 @@ impl Vm {
                             CallResult::Ok(())
                         }
                         CallResult::Unsupported(target) => {
-                            match self.call_instance_fn(
+                            match self.call_instance_fn_immediate(
                                 target,
                                 Protocol::FALLBACK_SET,
                                 (index, value),
@@ -1077,7 +1563,8 @@ impl Vm {
             }
         };
 
-        Ok(a.type_hash()? == hash)
+        let type_hash = a.type_hash()?;
+        Ok(type_hash == hash || self.unit.implements(type_hash, hash))
     }
 
     fn internal_boolean_op(
@@ -1196,7 +1683,7 @@ impl Vm {
     ) -> Result<(), VmError> {
         match fallback {
             TargetFallback::Value(lhs, rhs) => {
-                match self.call_instance_fn(lhs, protocol, (&rhs,))? {
+                match self.call_instance_fn_immediate(lhs, protocol, (&rhs,))? {
                     CallResult::Ok(()) => <()>::from_value(self.stack.pop()?)?,
                     CallResult::Unsupported(lhs) => {
                         return Err(VmError::from(VmErrorKind::UnsupportedBinaryOperation {
@@ -1591,7 +2078,7 @@ impl Vm {
     #[cfg_attr(feature = "bench", inline(never))]
     fn op_vec(&mut self, count: usize) -> Result<(), VmError> {
         let vec = Vec::from(self.stack.pop_sequence(count)?);
-        self.stack.push(Shared::new(vec));
+        self.stack.push(vec);
         Ok(())
     }
 
@@ -1920,7 +2407,7 @@ impl Vm {
             match &target {
                 Value::Object(object) => {
                     let mut object = object.borrow_mut()?;
-                    object.insert(field.to_owned(), value);
+                    memory::insert_entry(&mut object, field.to_owned(), value)?;
                     return Ok(());
                 }
                 Value::Struct(typed_object) => {
@@ -2203,7 +2690,7 @@ impl Vm {
             object.insert(key.clone(), value);
         }
 
-        self.stack.push(Shared::new(object));
+        self.stack.push(object);
         Ok(())
     }
 
@@ -2406,6 +2893,36 @@ impl Vm {
         Ok(())
     }
 
+    #[cfg_attr(feature = "bench", inline(never))]
+    fn op_match_range(&mut self, range: InstRange) -> Result<(), VmError> {
+        fn contains<T>(start: T, end: T, limits: InstRangeLimits, value: T) -> bool
+        where
+            T: PartialOrd,
+        {
+            match limits {
+                InstRangeLimits::HalfOpen => start <= value && value < end,
+                InstRangeLimits::Closed => start <= value && value <= end,
+            }
+        }
+
+        let value = self.stack.pop()?;
+
+        self.stack.push(match (range, value) {
+            (InstRange::Byte { start, end, limits }, Value::Byte(value)) => {
+                contains(start, end, limits, value)
+            }
+            (InstRange::Char { start, end, limits }, Value::Char(value)) => {
+                contains(start, end, limits, value)
+            }
+            (InstRange::Integer { start, end, limits }, Value::Integer(value)) => {
+                contains(start, end, limits, value)
+            }
+            _ => false,
+        });
+
+        Ok(())
+    }
+
     #[cfg_attr(feature = "bench", inline(never))]
     fn op_eq_integer(&mut self, integer: i64) -> Result<(), VmError> {
         let value = self.stack.pop()?;
@@ -2780,7 +3297,10 @@ impl Vm {
                     .function(hash)
                     .ok_or(VmErrorKind::MissingFunction { hash })?;
 
-                handler(&mut self.stack, args)?;
+                native(&mut self.limits, &self.context, hash, || {
+                    handler(&mut self.stack, args)
+                })
+                .map_err(|error| error.with_native(&self.context, hash))?;
             }
         }
 
@@ -2807,7 +3327,10 @@ impl Vm {
         }
 
         if let Some(handler) = self.context.function(hash) {
-            handler(&mut self.stack, args)?;
+            native(&mut self.limits, &self.context, hash, || {
+                handler(&mut self.stack, args)
+            })
+            .map_err(|error| error.with_native(&self.context, hash))?;
             return Ok(());
         }
 
@@ -2912,11 +3435,86 @@ impl Vm {
         // unit.
         let _guard = crate::runtime::env::Guard::new(&self.context, &self.unit);
 
+        // NB: install the limits of this virtual machine, so that virtual
+        // machines started from native functions observe them as well. The
+        // functions it's currently calling count towards their depth.
+        let guard = limits::Guard::new(&self.limits, self.call_frames.len() + 1)?;
+
+        // NB: while running, the virtual machine observes the limits it
+        // inherited. The installed fuel is taken so that it can be drawn from
+        // directly, and is lent to virtual machines started from native
+        // functions.
+        let inherited = self.limits.inherited();
+        let mut own = mem::replace(&mut self.limits, inherited);
+        self.limits.fuel = limits::take_fuel();
+
+        let profile = self
+            .limits
+            .profiler
+            .as_ref()
+            .map(|profiler| profiler.enter(self));
+
+        let result = self.run_with_limits();
+        limits::put_fuel(self.limits.fuel.take());
+
+        if let Some(profile) = profile {
+            profile.finish(matches!(result, Ok(VmHalt::Exited)));
+        }
+
+        if let Some(remaining) = guard.finish() {
+            own.fuel = Some(remaining);
+        }
+
+        self.limits = own;
+        result
+    }
+
+    /// Observe the limits and hooks of the virtual machine before executing an
+    /// instruction, indicating with `false` if the fuel has run out.
+    #[inline(always)]
+    fn observe_limits(&mut self) -> Result<bool, VmError> {
+        if let Some(fuel) = &mut self.limits.fuel {
+            if *fuel == 0 {
+                return Ok(false);
+            }
+
+            *fuel -= 1;
+        }
+
+        if let Some(memory) = &self.limits.memory {
+            memory.check()?;
+        }
+
+        if let Some(profiler) = &self.limits.profiler {
+            profiler.instruction();
+        }
+
+        if let Some(coverage) = &self.limits.coverage {
+            coverage.hit(&self.unit, self.ip);
+        }
+
+        Ok(true)
+    }
+
+    fn run_with_limits(&mut self) -> Result<VmHalt, VmError> {
+        // NB: limits and hooks aren't added or removed while running, so
+        // whether there are any to observe is only determined once.
+        let limited = self.limits.fuel.is_some()
+            || self.limits.memory.is_some()
+            || self.limits.profiler.is_some()
+            || self.limits.coverage.is_some();
+
         loop {
+            // NB: the budget is checked first, since yielding because of it
+            // shouldn't consume any fuel.
             if !budget::take() {
                 return Ok(VmHalt::Limited);
             }
 
+            if limited && !self.observe_limits()? {
+                return Ok(VmHalt::OutOfFuel);
+            }
+
             let inst = *self
                 .unit
                 .instruction_at(self.ip)
@@ -3116,6 +3714,9 @@ impl Vm {
                 Inst::EqInteger { integer } => {
                     self.op_eq_integer(integer)?;
                 }
+                Inst::MatchRange { range } => {
+                    self.op_match_range(range)?;
+                }
                 Inst::EqBool { boolean } => {
                     self.op_eq_bool(boolean)?;
                 }
@@ -3228,3 +3829,18 @@ impl Drop for ClearStack<'_> {
         self.0.stack.clear();
     }
 }
+
+/// Call a native function through `f`, recording it in the profiler and
+/// lending the fuel of the virtual machine to any virtual machine it starts.
+#[inline(always)]
+fn native<T>(
+    limits: &mut Limits,
+    context: &RuntimeContext,
+    hash: Hash,
+    f: impl FnOnce() -> Result<T, VmError>,
+) -> Result<T, VmError> {
+    let profiler = limits.profiler.as_ref();
+    limits::lend_fuel(&mut limits.fuel, || {
+        profiler::native(profiler, context, hash, f)
+    })
+}
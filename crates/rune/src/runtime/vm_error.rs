@@ -1,19 +1,86 @@
+use crate::ast::Span;
 use crate::compile::ItemBuf;
 use crate::runtime::panic::BoxedPanic;
 use crate::runtime::{
-    AccessError, CallFrame, ExecutionState, Key, Panic, Protocol, StackError, TypeInfo, TypeOf,
-    Unit, Value, VmHaltInfo,
+    AccessError, CallFrame, ExecutionState, Key, Panic, Protocol, RuntimeContext, StackError,
+    TypeInfo, TypeOf, Unit, Value, VmHaltInfo,
 };
-use crate::Hash;
+use crate::{Hash, SourceId};
+use std::error;
 use std::fmt;
 use std::sync::Arc;
 use thiserror::Error;
 
 /// Errors raised by the execution of the virtual machine.
-#[derive(Error, Debug)]
-#[error(transparent)]
+#[derive(Debug)]
 pub struct VmError {
-    kind: Box<VmErrorKind>,
+    inner: Box<VmErrorInner>,
+}
+
+#[derive(Debug)]
+struct VmErrorInner {
+    kind: VmErrorKind,
+    /// Native functions and script executions that the error has been
+    /// propagated through, innermost first.
+    unwound: Vec<Unwound>,
+}
+
+/// Something that an error has been propagated through.
+#[derive(Debug)]
+enum Unwound {
+    /// A native function.
+    Native { hash: Hash, name: Option<Box<str>> },
+    /// The call frames of a script execution.
+    Script {
+        unit: Arc<Unit>,
+        ip: usize,
+        frames: Vec<CallFrame>,
+    },
+}
+
+/// A single frame in the backtrace of a [VmError], as returned by
+/// [VmError::backtrace].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum BacktraceFrame {
+    /// A native function.
+    Native {
+        /// The hash of the function.
+        hash: Hash,
+        /// The name of the function, if it's known by the context.
+        name: Option<Box<str>>,
+    },
+    /// A script function.
+    Script {
+        /// The instruction pointer the frame was at.
+        ip: usize,
+        /// The signature of the function, if debug info is available.
+        function: Option<String>,
+        /// The source and span of the instruction, if debug info is
+        /// available.
+        location: Option<(SourceId, Span)>,
+    },
+}
+
+impl BacktraceFrame {
+    /// Resolve a script frame using the debug info in the given unit.
+    fn script(unit: &Unit, ip: usize) -> Self {
+        let debug = unit.debug_info();
+
+        let function = debug
+            .and_then(|debug| debug.function_containing(ip))
+            .map(|(_, signature)| signature.path.to_string());
+
+        let location = debug
+            .and_then(|debug| debug.instruction_at(ip))
+            .map(|inst| (inst.source_id, inst.span));
+
+        Self::Script {
+            ip,
+            function,
+            location,
+        }
+    }
 }
 
 impl VmError {
@@ -57,31 +124,95 @@ impl VmError {
 
     /// Access the underlying error kind.
     pub fn kind(&self) -> &VmErrorKind {
-        &*self.kind
+        &self.inner.kind
     }
 
     /// Access the underlying error kind while consuming the error.
     pub fn into_kind(self) -> VmErrorKind {
-        *self.kind
+        self.inner.kind
+    }
+
+    /// Resolve the backtrace of the error, innermost frame first.
+    ///
+    /// Frames in script functions are resolved using the debug info of the
+    /// unit they belong to, if it's available. Native functions which the
+    /// error was raised in or propagated through appear as their own frames.
+    pub fn backtrace(&self) -> Vec<BacktraceFrame> {
+        let mut backtrace = Vec::new();
+
+        for unwound in &self.inner.unwound {
+            match unwound {
+                Unwound::Native { hash, name } => {
+                    backtrace.push(BacktraceFrame::Native {
+                        hash: *hash,
+                        name: name.clone(),
+                    });
+                }
+                Unwound::Script { unit, ip, frames } => {
+                    backtrace.push(BacktraceFrame::script(unit, *ip));
+
+                    for frame in frames.iter().rev() {
+                        backtrace.push(BacktraceFrame::script(unit, frame.ip()));
+                    }
+                }
+            }
+        }
+
+        backtrace
+    }
+
+    /// Record that the error was raised in or propagated through the native
+    /// function with the given hash.
+    pub(crate) fn with_native(mut self, context: &RuntimeContext, hash: Hash) -> Self {
+        let name = context.function_name(hash).map(Box::from);
+        self.inner.unwound.push(Unwound::Native { hash, name });
+        self
     }
 
     /// Convert into an unwinded vm error.
-    pub(crate) fn into_unwinded(self, unit: &Arc<Unit>, ip: usize, frames: Vec<CallFrame>) -> Self {
-        if let VmErrorKind::Unwound { .. } = &*self.kind {
+    ///
+    /// An error which has already been unwound keeps its original location,
+    /// but the given frames are still recorded in its backtrace.
+    pub(crate) fn into_unwinded(
+        mut self,
+        unit: &Arc<Unit>,
+        ip: usize,
+        frames: Vec<CallFrame>,
+    ) -> Self {
+        if let VmErrorKind::Unwound { .. } = &self.inner.kind {
+            self.inner.unwound.push(Unwound::Script {
+                unit: unit.clone(),
+                ip,
+                frames,
+            });
+
             return self;
         }
 
-        Self::from(VmErrorKind::Unwound {
-            kind: self.kind,
+        self.inner.unwound.push(Unwound::Script {
             unit: unit.clone(),
             ip,
-            frames,
-        })
+            frames: frames.clone(),
+        });
+
+        let VmErrorInner { kind, unwound } = *self.inner;
+
+        Self {
+            inner: Box::new(VmErrorInner {
+                kind: VmErrorKind::Unwound {
+                    kind: Box::new(kind),
+                    unit: unit.clone(),
+                    ip,
+                    frames,
+                },
+                unwound,
+            }),
+        }
     }
 
     /// Unpack an unwinded error, if it is present.
     pub fn as_unwound(&self) -> (&VmErrorKind, Option<(&Arc<Unit>, usize, &[CallFrame])>) {
-        match &*self.kind {
+        match &self.inner.kind {
             VmErrorKind::Unwound {
                 kind,
                 unit,
@@ -94,17 +225,30 @@ impl VmError {
 
     /// Unpack an unwinded error, if it is present.
     pub fn into_unwound(self) -> (Self, Option<(Arc<Unit>, usize, Vec<CallFrame>)>) {
-        match *self.kind {
+        let VmErrorInner { kind, unwound } = *self.inner;
+
+        match kind {
             VmErrorKind::Unwound {
                 kind,
                 unit,
                 ip,
                 frames,
             } => {
-                let error = Self { kind };
+                let error = Self {
+                    inner: Box::new(VmErrorInner {
+                        kind: *kind,
+                        unwound,
+                    }),
+                };
+
                 (error, Some((unit, ip, frames)))
             }
-            kind => (Self::from(kind), None),
+            kind => (
+                Self {
+                    inner: Box::new(VmErrorInner { kind, unwound }),
+                },
+                None,
+            ),
         }
     }
 
@@ -122,7 +266,7 @@ impl VmError {
     ///
     /// Returns `true` if the error should be propagated.
     fn is_critical(&self) -> bool {
-        match &*self.kind {
+        match &self.inner.kind {
             VmErrorKind::Panic { .. } => true,
             VmErrorKind::Unwound { .. } => true,
             _ => false,
@@ -130,13 +274,28 @@ impl VmError {
     }
 }
 
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.kind.fmt(f)
+    }
+}
+
+impl error::Error for VmError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.inner.kind.source()
+    }
+}
+
 impl<E> From<E> for VmError
 where
     VmErrorKind: From<E>,
 {
     fn from(err: E) -> Self {
         Self {
-            kind: Box::new(VmErrorKind::from(err)),
+            inner: Box::new(VmErrorInner {
+                kind: VmErrorKind::from(err),
+                unwound: Vec::new(),
+            }),
         }
     }
 }
@@ -171,6 +330,12 @@ pub enum VmErrorKind {
     NoRunningVm,
     #[error("halted for unexpected reason `{halt}`")]
     Halted { halt: VmHaltInfo },
+    #[error("maximum call depth of {limit} exceeded")]
+    StackOverflow { limit: usize },
+    #[error("virtual machine was interrupted")]
+    Interrupted,
+    #[error("memory limit of {limit} bytes exceeded with {usage} bytes in use")]
+    MemoryLimitExceeded { limit: usize, usage: usize },
     #[error("failed to format argument")]
     FormatError,
     #[error("stack error: {error}")]
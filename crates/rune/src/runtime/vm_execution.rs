@@ -1,4 +1,5 @@
 use crate::runtime::budget;
+use crate::runtime::limits;
 use crate::runtime::{
     Generator, GeneratorState, Stream, Value, Vm, VmError, VmErrorKind, VmHalt, VmHaltInfo,
 };
@@ -19,6 +20,9 @@ pub enum ExecutionState {
     /// The resumed state of an execution. This expects a value to be pushed
     /// onto the virtual machine before it is continued.
     Resumed,
+    /// The execution was halted before it finished, like when it ran out of
+    /// fuel. It continues where it left off when resumed.
+    Halted,
 }
 
 impl fmt::Display for ExecutionState {
@@ -26,6 +30,7 @@ impl fmt::Display for ExecutionState {
         match self {
             ExecutionState::Initial => write!(f, "initial"),
             ExecutionState::Resumed => write!(f, "resumed"),
+            ExecutionState::Halted => write!(f, "halted"),
         }
     }
 }
@@ -79,6 +84,9 @@ where
     }
 
     /// Test if the current execution state is resumed.
+    ///
+    /// A halted execution is not, since it can't accept a value to resume
+    /// with.
     pub(crate) fn is_resumed(&self) -> bool {
         matches!(self.state, ExecutionState::Resumed)
     }
@@ -226,7 +234,24 @@ where
             match Self::run(vm)? {
                 VmHalt::Exited => (),
                 VmHalt::Awaited(awaited) => {
-                    awaited.into_vm(vm).await?;
+                    // NB: the awaited future might run other virtual machines
+                    // which should observe the limits of this one. It's
+                    // cancelled if the virtual machine is interrupted.
+                    let mut limits = vm.inherited_limits();
+                    limits.fuel = vm.take_fuel();
+                    let profile = limits.profiler.as_ref().map(|profiler| profiler.enter(vm));
+                    let (result, fuel) = limits::with(limits, awaited.into_vm(vm)).await;
+                    drop(profile);
+
+                    if let Some(fuel) = fuel {
+                        vm.set_fuel(fuel);
+                    }
+
+                    match result {
+                        Some(result) => result?,
+                        None => return Err(Self::interrupted(vm)),
+                    }
+
                     continue;
                 }
                 VmHalt::VmCall(vm_call) => {
@@ -237,6 +262,12 @@ where
                     let value = vm.stack_mut().pop()?;
                     return Ok(GeneratorState::Yielded(value));
                 }
+                VmHalt::OutOfFuel => {
+                    self.state = ExecutionState::Halted;
+                    return Err(VmError::from(VmErrorKind::Halted {
+                        halt: VmHaltInfo::OutOfFuel,
+                    }));
+                }
                 halt => {
                     return Err(VmError::from(VmErrorKind::Halted {
                         halt: halt.into_info(),
@@ -300,6 +331,12 @@ where
                     let value = vm.stack_mut().pop()?;
                     return Ok(GeneratorState::Yielded(value));
                 }
+                VmHalt::OutOfFuel => {
+                    self.state = ExecutionState::Halted;
+                    return Err(VmError::from(VmErrorKind::Halted {
+                        halt: VmHaltInfo::OutOfFuel,
+                    }));
+                }
                 halt => {
                     return Err(VmError::from(VmErrorKind::Halted {
                         halt: halt.into_info(),
@@ -389,7 +426,16 @@ where
     }
 
     /// Push a virtual machine state onto the execution.
-    pub(crate) fn push_vm(&mut self, vm: Vm) {
+    ///
+    /// The pushed virtual machine takes over the fuel of the current one, and
+    /// shares the rest of its limits.
+    pub(crate) fn push_vm(&mut self, mut vm: Vm) {
+        let current = vm_mut!(self);
+
+        let mut limits = current.inherited_limits();
+        limits.fuel = current.take_fuel();
+        vm.set_limits(limits);
+
         self.vms.push((vm, self.state));
         self.state = ExecutionState::Initial;
     }
@@ -403,13 +449,30 @@ where
         let value = stack.pop()?;
         debug_assert!(stack.is_empty(), "vm stack not clean");
 
+        let fuel = from.take_fuel();
+
         let onto = vm_mut!(self);
+
+        if let Some(fuel) = fuel {
+            onto.set_fuel(fuel);
+        }
+
         onto.stack_mut().push(value);
         onto.advance();
         self.state = state;
         Ok(())
     }
 
+    /// Construct an error for an interrupted virtual machine, reporting where
+    /// it stopped.
+    fn interrupted(vm: &Vm) -> VmError {
+        VmError::from(VmErrorKind::Interrupted).into_unwinded(
+            vm.unit(),
+            vm.ip(),
+            vm.call_frames().to_vec(),
+        )
+    }
+
     #[inline]
     fn run(vm: &mut Vm) -> Result<VmHalt, VmError> {
         match vm.run() {
@@ -423,7 +486,11 @@ impl VmExecution<&mut Vm> {
     /// Convert the current execution into one which owns its virtual machine.
     pub fn into_owned(self) -> VmExecution<Vm> {
         let stack = take(self.head.stack_mut());
-        let head = Vm::with_stack(self.head.context().clone(), self.head.unit().clone(), stack);
+        let mut head = Vm::with_stack(self.head.context().clone(), self.head.unit().clone(), stack);
+
+        let mut limits = self.head.inherited_limits();
+        limits.fuel = self.head.fuel();
+        head.set_limits(limits);
 
         VmExecution {
             head,
@@ -8,6 +8,8 @@ pub(crate) enum VmHalt {
     Exited,
     /// The virtual machine exited because it ran out of execution quota.
     Limited,
+    /// The virtual machine ran out of fuel.
+    OutOfFuel,
     /// The virtual machine yielded.
     Yielded,
     /// The virtual machine awaited on the given future.
@@ -22,6 +24,7 @@ impl VmHalt {
         match self {
             Self::Exited => VmHaltInfo::Exited,
             Self::Limited => VmHaltInfo::Limited,
+            Self::OutOfFuel => VmHaltInfo::OutOfFuel,
             Self::Yielded => VmHaltInfo::Yielded,
             Self::Awaited(..) => VmHaltInfo::Awaited,
             Self::VmCall(..) => VmHaltInfo::VmCall,
@@ -36,6 +39,9 @@ pub enum VmHaltInfo {
     Exited,
     /// The virtual machine exited because it ran out of execution quota.
     Limited,
+    /// The virtual machine ran out of fuel. The execution can be resumed once
+    /// more fuel has been provided through [Vm::set_fuel][crate::Vm::set_fuel].
+    OutOfFuel,
     /// The virtual machine yielded.
     Yielded,
     /// The virtual machine awaited on the given future.
@@ -49,6 +55,7 @@ impl fmt::Display for VmHaltInfo {
         match self {
             Self::Exited => write!(f, "exited"),
             Self::Limited => write!(f, "limited"),
+            Self::OutOfFuel => write!(f, "out of fuel"),
             Self::Yielded => write!(f, "yielded"),
             Self::Awaited => write!(f, "awaited"),
             Self::VmCall => write!(f, "calling into other vm"),
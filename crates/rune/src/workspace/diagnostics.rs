@@ -2,13 +2,28 @@ use crate::{SourceId};
 use crate::workspace::WorkspaceError;
 
 /// A reported diagnostic error.
-pub(crate) struct FatalDiagnostic {
+#[derive(Debug)]
+pub struct FatalDiagnostic {
     pub(crate) source_id: SourceId,
     pub(crate) error: WorkspaceError,
 }
 
+impl FatalDiagnostic {
+    /// The source id of the manifest the error originates from.
+    pub fn source_id(&self) -> SourceId {
+        self.source_id
+    }
+
+    /// The error reported.
+    pub fn error(&self) -> &WorkspaceError {
+        &self.error
+    }
+}
+
 /// A single workspace diagnostic.
-pub(crate) enum Diagnostic {
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Diagnostic {
     /// An error in a workspace.
     Fatal(FatalDiagnostic),
 }
@@ -33,6 +48,11 @@ impl Diagnostics {
         self.diagnostics.iter().any(|e| matches!(e, Diagnostic::Fatal(..)))
     }
 
+    /// Access underlying diagnostics.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
     /// Test if diagnostics is empty.
     pub fn is_empty(&self) -> bool {
         self.diagnostics.is_empty()
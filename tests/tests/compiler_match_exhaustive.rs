@@ -0,0 +1,146 @@
+use rune::compile::CompileErrorKind;
+use rune::diagnostics::{Diagnostic, FatalDiagnosticKind, WarningDiagnosticKind::*};
+use rune::{span, Diagnostics, Options};
+use rune_tests::*;
+
+/// Collect the missing patterns reported for the given source.
+fn missing(source: &str) -> Vec<Box<str>> {
+    let mut diagnostics = Default::default();
+    let _ = compile_helper(source, &mut diagnostics).expect("source should compile");
+
+    let mut out = Vec::new();
+
+    for diagnostic in diagnostics.into_diagnostics() {
+        if let Diagnostic::Warning(warning) = diagnostic {
+            if let MatchNotExhaustive { missing, .. } = warning.into_kind() {
+                out.extend(missing.into_vec());
+            }
+        }
+    }
+
+    out
+}
+
+#[test]
+fn test_non_exhaustive_script_enum() {
+    assert_warnings! {
+        r#"enum Shape { Circle(r), Square(s), Point } pub fn main() { match Shape::Point { Shape::Circle(r) => r, Shape::Point => 0 } }"#,
+        MatchNotExhaustive { span, missing, .. } => {
+            assert_eq!(span, span!(59, 122));
+            assert_eq!(&*missing, &["Shape::Square(_)".into()]);
+        }
+    };
+
+    assert_eq!(
+        missing(r#"enum Shape { Rect { w, h }, Point } pub fn main() { match Shape::Point { Shape::Point => 0 } }"#),
+        vec![Box::from("Shape::Rect { .. }")]
+    );
+
+    assert_eq!(
+        missing(r#"enum A { X, Y } pub fn main() { match (A::X, A::Y) { (A::X, _) => 0, (_, A::X) => 1 } }"#),
+        vec![Box::from("(A::Y, A::Y)")]
+    );
+}
+
+#[test]
+fn test_non_exhaustive_builtins() {
+    assert_eq!(
+        missing(r#"pub fn main() { match Some(1) { Some(n) => n } }"#),
+        vec![Box::from("Option::None")]
+    );
+
+    assert_eq!(
+        missing(r#"pub fn main() { match Ok(1) { Ok(n) => n, Err(true) => 0 } }"#),
+        vec![Box::from("Result::Err(false)")]
+    );
+
+    assert_eq!(
+        missing(r#"pub fn main() { match (true, false) { (true, _) => 0, (false, true) => 1 } }"#),
+        vec![Box::from("(false, false)")]
+    );
+}
+
+#[test]
+fn test_exhaustive_matches() {
+    let sources = [
+        r#"pub fn main() { match Some(1) { Some(n) if n > 0 => n, Some(n) => n, None => 0 } }"#,
+        r#"pub fn main() { match Some(true) { Some(true) | None => 0, Some(false) => 1 } }"#,
+        r#"enum E { A, B } pub fn main() { match E::A { E::A | E::B => 0 } }"#,
+        r#"pub fn main() { match 10 { 1 => 0, n => n } }"#,
+        r#"pub fn main() { match 10 { 1 => 0, 2..=4 => 1 } }"#,
+        r#"pub fn main() { match [1] { [] => 0, [n] => n } }"#,
+    ];
+
+    for source in sources {
+        let mut diagnostics = Default::default();
+        let _ = compile_helper(source, &mut diagnostics).expect("source should compile");
+        assert!(!diagnostics.has_warning(), "unexpected warnings for {}", source);
+    }
+}
+
+#[test]
+fn test_guarded_arm_is_not_exhaustive() {
+    assert_eq!(
+        missing(r#"pub fn main() { match true { true => 0, false if true => 1 } }"#),
+        vec![Box::from("false")]
+    );
+}
+
+#[test]
+fn test_unreachable_patterns() {
+    assert_warnings! {
+        r#"pub fn main() { match Some(1) { _ => 0, Some(n) => n } }"#,
+        UnreachablePattern { span, .. } => {
+            assert_eq!(span, span!(40, 47));
+        }
+    };
+
+    assert_warnings! {
+        r#"pub fn main() { match 1 { 1 | 2 => 0, 2 => 1, _ => 2 } }"#,
+        UnreachablePattern { span, .. } => {
+            assert_eq!(span, span!(38, 39));
+        }
+    };
+
+    assert_warnings! {
+        r#"pub fn main() { match true { true => 0, false => 1, _ => 2 } }"#,
+        UnreachablePattern { span, .. } => {
+            assert_eq!(span, span!(52, 53));
+        }
+    };
+}
+
+#[test]
+fn test_deny_non_exhaustive() {
+    let mut options = Options::default();
+    options.deny_non_exhaustive(true);
+
+    let context = modules::default_context().expect("setting up default modules");
+    let mut sources = sources(r#"pub fn main() { match None { Some(n) => n } }"#);
+    let mut diagnostics = Diagnostics::new();
+
+    let result = rune::prepare(&mut sources)
+        .with_context(&context)
+        .with_diagnostics(&mut diagnostics)
+        .with_options(&options)
+        .build();
+
+    assert!(result.is_err());
+
+    let e = match diagnostics.into_diagnostics().into_iter().next() {
+        Some(Diagnostic::Fatal(e)) => e,
+        other => panic!("expected fatal diagnostic but was {:?}", other),
+    };
+
+    let e = match e.into_kind() {
+        FatalDiagnosticKind::CompileError(e) => e,
+        kind => panic!("expected compile error but was {:?}", kind),
+    };
+
+    match e.into_kind() {
+        CompileErrorKind::MatchNotExhaustive { missing } => {
+            assert_eq!(&*missing, &["Option::None".into()]);
+        }
+        kind => panic!("expected non-exhaustive match but was {:?}", kind),
+    }
+}
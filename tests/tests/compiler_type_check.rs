@@ -0,0 +1,166 @@
+use rune::compile::CompileErrorKind;
+use rune::diagnostics::{Diagnostic, FatalDiagnosticKind};
+use rune::{Diagnostics, Options};
+use rune_tests::*;
+
+/// Compile the given source with type checking enabled, and collect the
+/// messages of all reported compile errors.
+fn check(source: &str) -> Vec<String> {
+    let mut options = Options::default();
+    options.type_check(true);
+
+    let context = modules::default_context().expect("setting up default modules");
+    let mut sources = sources(source);
+    let mut diagnostics = Diagnostics::new();
+
+    let _ = rune::prepare(&mut sources)
+        .with_context(&context)
+        .with_diagnostics(&mut diagnostics)
+        .with_options(&options)
+        .build();
+
+    let mut out = Vec::new();
+
+    for diagnostic in diagnostics.into_diagnostics() {
+        let e = match diagnostic {
+            Diagnostic::Fatal(e) => e,
+            _ => continue,
+        };
+
+        match e.into_kind() {
+            FatalDiagnosticKind::CompileError(e) => match e.into_kind() {
+                kind @ (CompileErrorKind::TypeMismatch { .. }
+                | CompileErrorKind::UnsupportedArgumentCount { .. }
+                | CompileErrorKind::MissingItem { .. }
+                | CompileErrorKind::ExpectedMeta { .. }) => out.push(kind.to_string()),
+                kind => panic!("unexpected compile error {:?}", kind),
+            },
+            kind => panic!("expected compile error but was {:?}", kind),
+        }
+    }
+
+    out
+}
+
+#[test]
+fn test_let_annotations() {
+    assert_eq!(
+        check(r#"pub fn main() { let a: int = "hello"; a }"#),
+        vec!["expected type `int` but found `String`"]
+    );
+
+    assert_eq!(
+        check(r#"pub fn main() { let (a, b): (int, bool) = (1, 2.0); a }"#),
+        vec!["expected type `(int, bool)` but found `(int, float)`"]
+    );
+
+    assert_eq!(
+        check(r#"pub fn main() { let a: String = "a"; a = 42; a }"#),
+        vec!["expected type `String` but found `int`"]
+    );
+}
+
+#[test]
+fn test_fn_annotations() {
+    assert_eq!(
+        check(r#"fn add(a: int, b: int) -> int { a + b } pub fn main() { add(1, "two") }"#),
+        vec!["expected type `int` but found `String`"]
+    );
+
+    assert_eq!(
+        check(r#"pub fn main(a: int) -> String { if a > 0 { return a; } "a" }"#),
+        vec!["expected type `String` but found `int`"]
+    );
+
+    assert_eq!(
+        check(r#"fn name() -> String { "a" } pub fn main() { let a: int = name(); a }"#),
+        vec!["expected type `int` but found `String`"]
+    );
+
+    assert_eq!(
+        check(r#"fn name(a: String) -> String { a } pub fn main() { name() }"#),
+        vec!["wrong number of arguments, expected `1` but got `0`"]
+    );
+}
+
+#[test]
+fn test_script_types() {
+    assert_eq!(
+        check(
+            r#"
+            struct Point { x, y }
+            enum Shape { Circle(r), Square(s) }
+            fn area(shape: Shape) -> float { 0.0 }
+            pub fn main() { area(Shape::Circle(1.0)) + area(Point { x: 1, y: 2 }) }
+            "#
+        ),
+        vec!["expected type `Shape` but found `Point`"]
+    );
+
+    assert_eq!(
+        check(
+            r#"
+            struct Counter { value }
+            impl Counter { fn add(self, n: int) { self.value += n; } }
+            pub fn main() { let c = Counter { value: 0 }; c.add(true); }
+            "#
+        ),
+        vec!["expected type `int` but found `bool`"]
+    );
+
+    assert_eq!(
+        check(r#"pub fn main() { let a: Option = Some(1); let b: Result = a; }"#),
+        vec!["expected type `Result` but found `Option`"]
+    );
+}
+
+#[test]
+fn test_native_functions() {
+    assert_eq!(
+        check(r#"pub fn main() { std::string::String::from_str() }"#),
+        vec!["wrong number of arguments, expected `1` but got `0`"]
+    );
+}
+
+#[test]
+fn test_bad_annotations() {
+    assert_eq!(
+        check(r#"pub fn main(a: Missing) { a }"#),
+        vec!["missing item `Missing`"]
+    );
+
+    assert_eq!(
+        check(r#"fn foo() {} pub fn main(a: foo) { foo(); a }"#),
+        vec!["expected a type but got `fn foo`"]
+    );
+}
+
+#[test]
+fn test_unknown_types_are_accepted() {
+    let sources = [
+        r#"fn f(a: int) -> int { a } pub fn main(b) { let a = b; f(a) }"#,
+        r#"pub fn main() -> int { let a = 1; a = "s"; let b: int = 2; b }"#,
+        r#"pub fn main(a: int) -> String { if a > 0 { "a" } else { "b" } }"#,
+        r#"pub fn main() -> int { yield "a"; 1 }"#,
+        r#"async fn f() -> int { 1 } pub async fn main() { let a = f(); a.await }"#,
+        r#"pub fn main() { let f = |a: int| a; f("s") }"#,
+    ];
+
+    for source in sources {
+        assert_eq!(check(source), Vec::<String>::new(), "for {}", source);
+    }
+}
+
+#[test]
+fn test_annotations_without_type_check() {
+    let mut diagnostics = Default::default();
+    let _ = compile_helper(r#"pub fn main() { let a: int = "hello"; a }"#, &mut diagnostics)
+        .expect("annotations should not be checked by default");
+
+    let value: (i64, String) = rune! {
+        fn add(a: int, b: int) -> int { a + b }
+        pub fn main() -> (int, String) { let s: String = "hi"; (add(1, 2), s) }
+    };
+
+    assert_eq!(value, (3, String::from("hi")));
+}
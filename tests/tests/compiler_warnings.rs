@@ -31,3 +31,50 @@ fn test_remove_variant_parens() {
         }
     };
 }
+
+#[test]
+fn test_suggested_edits() {
+    let source = r#"fn unused() {} pub fn main() { let b = 1; let _c = 2; let a = `"Hello" \\ Wórld`; if a { 1 }; None() }"#;
+
+    let mut diagnostics = Default::default();
+    let _ = compile_helper(source, &mut diagnostics).expect("source should compile");
+
+    let mut edited = source.to_owned();
+    let mut edits = Vec::new();
+
+    for diagnostic in diagnostics.diagnostics() {
+        if let rune::diagnostics::Diagnostic::Warning(warning) = diagnostic {
+            if let Some(edit) = warning.suggested_edit() {
+                edits.push(edit.clone());
+            }
+        }
+    }
+
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.span().start));
+
+    for edit in edits {
+        edited.replace_range(edit.span().range(), edit.replacement());
+    }
+
+    assert_eq!(
+        edited,
+        r#"fn _unused() {} pub fn main() { let _b = 1; let _c = 2; let a = "\"Hello\" \\ Wórld"; if a { 1 } None }"#
+    );
+}
+
+#[test]
+fn test_unused_bindings() {
+    assert_warnings! {
+        r#"pub fn main() { let a = 1; let _b = 2; let a = 3; a }"#,
+        NotUsed { span, .. } => {
+            assert_eq!(span, span!(20, 21));
+        }
+    };
+
+    assert_warnings! {
+        r#"fn _unused() {} pub fn main() { }"#,
+        NotUsed { span, .. } => {
+            assert_eq!(span, span!(0, 15));
+        }
+    };
+}
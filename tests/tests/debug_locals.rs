@@ -0,0 +1,87 @@
+use rune::runtime::{Inst, InstOp};
+use rune::Unit;
+
+/// Get the names and offsets of the locals which are live at the first
+/// instruction matching the given predicate.
+fn locals_at(unit: &Unit, predicate: impl Fn(&Inst) -> bool) -> Vec<(String, usize)> {
+    let debug = unit.debug_info().expect("debug info");
+
+    let ip = unit
+        .iter_instructions()
+        .position(|inst| predicate(&inst))
+        .expect("matching instruction");
+
+    debug
+        .locals_at(ip)
+        .map(|local| (local.name.to_string(), local.offset))
+        .collect()
+}
+
+fn names(locals: &[(String, usize)]) -> Vec<&str> {
+    locals.iter().map(|(name, _)| name.as_str()).collect()
+}
+
+#[test]
+fn test_locals_at() -> rune::Result<()> {
+    let context = rune_modules::default_context()?;
+
+    let mut sources = rune::sources! {
+        entry => {
+            pub fn main(a) {
+                let b = a * 2;
+                let b = b - 1;
+
+                match b {
+                    Some(c) if c > 1 => c,
+                    d => d / 3,
+                }
+            }
+        }
+    };
+
+    let unit = rune::prepare(&mut sources).with_context(&context).build()?;
+
+    let first = locals_at(&unit, |inst| matches!(inst, Inst::Op { op: InstOp::Sub, .. }));
+    assert_eq!(names(&first), ["a", "b"]);
+
+    let guard = locals_at(&unit, |inst| matches!(inst, Inst::Op { op: InstOp::Gt, .. }));
+    assert_eq!(names(&guard), ["a", "b", "c"]);
+
+    // The second `b` shadows the first one in its own stack slot.
+    assert_ne!(first[1].1, guard[1].1);
+
+    let fallback = locals_at(&unit, |inst| matches!(inst, Inst::Op { op: InstOp::Div, .. }));
+    assert_eq!(names(&fallback), ["a", "b", "d"]);
+    Ok(())
+}
+
+#[test]
+fn test_locals_in_nested_scopes() -> rune::Result<()> {
+    let context = rune_modules::default_context()?;
+
+    let mut sources = rune::sources! {
+        entry => {
+            pub fn main() {
+                let a = 1;
+
+                let b = {
+                    let a = a + 1;
+                    a * 2
+                };
+
+                a - b
+            }
+        }
+    };
+
+    let unit = rune::prepare(&mut sources).with_context(&context).build()?;
+
+    // The outer `a` is still live while it's shadowed, and is returned first.
+    let inner = locals_at(&unit, |inst| matches!(inst, Inst::Op { op: InstOp::Mul, .. }));
+    assert_eq!(names(&inner), ["a", "a"]);
+
+    let outer = locals_at(&unit, |inst| matches!(inst, Inst::Op { op: InstOp::Sub, .. }));
+    assert_eq!(names(&outer), ["a", "b"]);
+    assert_eq!(outer[0].1, inner[0].1);
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use rune::fmt::layout_source;
+
+/// Assert that the given source is formatted into the expected output, and
+/// that formatting the output again leaves it unchanged.
+fn assert_format(source: &str, expected: &str) {
+    let output = layout_source(source).expect("source to format");
+    assert_eq!(output, expected);
+
+    let again = layout_source(&output).expect("output to format");
+    assert_eq!(again, expected, "formatting is not idempotent");
+}
+
+#[test]
+fn test_format_items() {
+    assert_format(
+        "struct Foo{a,b}\nenum Bar{A,B(a,b),C{x,y}}\nuse std::{collections::{HashMap,HashSet},iter};",
+        "struct Foo { a, b }\nenum Bar { A, B(a, b), C { x, y } }\nuse std::{collections::{HashMap, HashSet}, iter};\n",
+    );
+}
+
+#[test]
+fn test_format_blocks() {
+    assert_format(
+        "fn foo(a){if a {1} else {2}}",
+        "fn foo(a) {\n    if a {\n        1\n    } else {\n        2\n    }\n}\n",
+    );
+
+    assert_format("fn foo(){}", "fn foo() {}\n");
+}
+
+#[test]
+fn test_format_comments() {
+    assert_format(
+        "// leading\n\n\nfn foo() { // trailing\n    1 /* inline */\n    // end\n}\n",
+        "// leading\n\nfn foo() { // trailing\n    1 /* inline */\n    // end\n}\n",
+    );
+}
+
+#[test]
+fn test_format_blank_lines() {
+    assert_format(
+        "fn foo() {\n\n    let a = 1;\n\n\n    let b = 2;\n    a + b\n\n}\n",
+        "fn foo() {\n    let a = 1;\n\n    let b = 2;\n    a + b\n}\n",
+    );
+}
+
+#[test]
+fn test_format_groups() {
+    assert_format(
+        "fn foo() { let t = (1,); let o = #{a:1,\"b\":2}; Foo{a:1} }",
+        "fn foo() {\n    let t = (1,);\n    let o = #{a: 1, \"b\": 2};\n    Foo { a: 1 }\n}\n",
+    );
+
+    // Groups which are broken up in the source are kept broken up, with a
+    // trailing comma added.
+    assert_format(
+        "fn foo() { bar(\n1, 2) }",
+        "fn foo() {\n    bar(\n        1,\n        2,\n    )\n}\n",
+    );
+}
+
+#[test]
+fn test_format_chains() {
+    assert_format(
+        "fn foo() { v.iter().map(|x| { x + 1 }).collect::<Vec>() }",
+        "fn foo() {\n    v.iter()\n        .map(|x| {\n            x + 1\n        })\n        .collect::<Vec>()\n}\n",
+    );
+}
+
+#[test]
+fn test_format_match() {
+    assert_format(
+        "fn foo(x) { match x { 1=>{ 2 } _=>3 } }",
+        "fn foo(x) {\n    match x {\n        1 => {\n            2\n        }\n        _ => 3,\n    }\n}\n",
+    );
+}
+
+#[test]
+fn test_format_templates_and_macros() {
+    assert_format(
+        "fn foo(x) { let s=`a ${x}  b`; println!(\"{}\",  s) }",
+        "fn foo(x) {\n    let s = `a ${x}  b`;\n    println!(\"{}\",  s)\n}\n",
+    );
+}
+
+#[test]
+fn test_format_scripts() {
+    let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../scripts");
+    let mut queue = vec![root];
+
+    while let Some(dir) = queue.pop() {
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+
+            if path.is_dir() {
+                queue.push(path);
+                continue;
+            }
+
+            if path.extension().and_then(|e| e.to_str()) != Some("rn") {
+                continue;
+            }
+
+            let source = std::fs::read_to_string(&path).unwrap();
+
+            let output = match layout_source(&source) {
+                Ok(output) => output,
+                Err(error) => panic!("{}: {}", path.display(), error),
+            };
+
+            assert_eq!(
+                layout_source(&output).unwrap(),
+                output,
+                "{}: formatting is not idempotent",
+                path.display()
+            );
+        }
+    }
+}
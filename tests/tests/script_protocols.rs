@@ -0,0 +1,117 @@
+use rune::compile::CompileErrorKind::*;
+use rune_tests::*;
+
+#[test]
+fn test_binary_protocols() {
+    let out: (i64, i64, i64) = rune! {
+        struct Point { x, y }
+
+        impl Point {
+            #[protocol(ADD)]
+            fn add(self, other) {
+                Point { x: self.x + other.x, y: self.y + other.y }
+            }
+
+            #[protocol(ADD_ASSIGN)]
+            fn add_assign(self, other) {
+                self.x += other.x;
+                self.y += other.y;
+            }
+        }
+
+        pub fn main() {
+            let a = Point { x: 1, y: 2 } + Point { x: 3, y: 4 };
+            let b = Point { x: 10, y: 20 };
+            b += a;
+            (a.x, a.y, b.x + b.y)
+        }
+    };
+    assert_eq!(out, (4, 6, 40));
+}
+
+#[test]
+fn test_display_and_eq() {
+    let out: (String, bool, bool) = rune_s! { r#"
+        struct Point { x, y }
+
+        impl Point {
+            #[protocol(STRING_DISPLAY)]
+            fn display(self, f) {
+                f.push_str(`(${self.x}, ${self.y})`);
+                Ok(())
+            }
+
+            #[protocol(EQ)]
+            fn eq(self, other) {
+                self.x == other.x
+            }
+        }
+
+        pub fn main() {
+            let a = Point { x: 1, y: 2 };
+            (`point: ${a}`, a == Point { x: 1, y: 3 }, a == Point { x: 2, y: 2 })
+        }
+    "# };
+    assert_eq!(out, (String::from("point: (1, 2)"), true, false));
+}
+
+#[test]
+fn test_iterator_protocols() {
+    let out: (i64, i64) = rune! {
+        struct Countdown { n }
+        struct Iter { n }
+
+        impl Countdown {
+            #[protocol(INTO_ITER)]
+            fn iter(self) {
+                Iter { n: self.n }
+            }
+
+            #[protocol(INDEX_GET)]
+            fn get(self, index) {
+                self.n * index
+            }
+        }
+
+        impl Iter {
+            #[protocol(NEXT)]
+            fn next(self) {
+                if self.n == 0 {
+                    return None;
+                }
+
+                self.n -= 1;
+                Some(self.n)
+            }
+        }
+
+        pub fn main() {
+            let countdown = Countdown { n: 4 };
+            let total = 0;
+
+            for n in countdown {
+                total += n;
+            }
+
+            (total, countdown[3])
+        }
+    };
+    assert_eq!(out, (6, 12));
+}
+
+#[test]
+fn test_protocol_errors() {
+    assert_compile_error! {
+        r#"struct Point; impl Point { #[protocol(PLUS)] fn add(self, other) {} } pub fn main() { Point }"#,
+        span, MissingProtocol { name } => {
+            assert_eq!(&*name, "PLUS");
+        }
+    };
+
+    assert_compile_error! {
+        r#"#[protocol(ADD)] fn add(a, b) {} pub fn main() {}"#,
+        span, Custom { message } => {
+            assert_eq!(message.as_ref(), "#[protocol] is only supported on member functions");
+        }
+    };
+}
@@ -0,0 +1,124 @@
+use rune::compile::CompileErrorKind::*;
+use rune_tests::*;
+
+#[test]
+fn test_default_method() {
+    let out: (i64, i64) = rune! {
+        trait Shape {
+            fn area(self);
+
+            fn double_area(self) {
+                self.area() * 2
+            }
+        }
+
+        struct Square { side }
+        struct Rect { w, h }
+
+        impl Shape for Square {
+            fn area(self) {
+                self.side * self.side
+            }
+        }
+
+        impl Shape for Rect {
+            fn area(self) {
+                self.w * self.h
+            }
+
+            fn double_area(self) {
+                self.area() + self.area() + 1
+            }
+        }
+
+        pub fn main() {
+            (Square { side: 3 }.double_area(), Rect { w: 2, h: 5 }.double_area())
+        }
+    };
+    assert_eq!(out, (18, 21));
+}
+
+#[test]
+fn test_dynamic_dispatch() {
+    let out: i64 = rune! {
+        trait Animal {
+            fn legs(self);
+        }
+
+        struct Dog;
+        struct Bird;
+
+        impl Animal for Dog {
+            fn legs(self) { 4 }
+        }
+
+        impl Animal for Bird {
+            fn legs(self) { 2 }
+        }
+
+        pub fn main() {
+            let total = 0;
+
+            for animal in [Dog, Bird, Dog] {
+                total += animal.legs();
+            }
+
+            total
+        }
+    };
+    assert_eq!(out, 10);
+}
+
+#[test]
+fn test_is_trait() {
+    let out: (bool, bool, bool) = rune! {
+        mod shapes {
+            pub trait Shape {
+                fn area(self);
+            }
+        }
+
+        struct Square;
+        struct Circle;
+
+        impl shapes::Shape for Square {
+            fn area(self) { 1 }
+        }
+
+        pub fn main() {
+            (Square is shapes::Shape, Circle is shapes::Shape, Square is not shapes::Shape)
+        }
+    };
+    assert_eq!(out, (true, false, false));
+}
+
+#[test]
+fn test_trait_errors() {
+    assert_compile_error! {
+        r#"trait Shape { fn area(self); } struct Square; impl Shape for Square {} pub fn main() { Square }"#,
+        span, MissingTraitFn { name, .. } => {
+            assert_eq!(&*name, "area");
+        }
+    };
+
+    assert_compile_error! {
+        r#"trait Shape { fn area(self); } struct Square; impl Shape for Square { fn area(self) {} fn other(self) {} } pub fn main() { Square }"#,
+        span, NotTraitFn { name, .. } => {
+            assert_eq!(&*name, "other");
+        }
+    };
+
+    assert_compile_error! {
+        r#"trait Shape { fn area(self); fn area(self); } pub fn main() {}"#,
+        span, TraitFnConflict { name, .. } => {
+            assert_eq!(&*name, "area");
+        }
+    };
+
+    assert_compile_error! {
+        r#"trait Shape { fn new(); } pub fn main() {}"#,
+        span, TraitFnNotInstance { name } => {
+            assert_eq!(&*name, "new");
+        }
+    };
+}
@@ -0,0 +1,149 @@
+use rune::runtime::{Protocol, UnitFileError};
+use rune::{Any, Context, FromValue, Module, Unit, Vm};
+use std::sync::Arc;
+
+fn native_module() -> rune::Result<Module> {
+    let mut module = Module::with_item(&["native"]);
+    module.function(&["add"], |a: i64, b: i64| a + b)?;
+    Ok(module)
+}
+
+#[derive(Any)]
+struct Counter {
+    value: i64,
+}
+
+/// A module with a native type, optionally with its instance functions.
+fn counter_module(functions: bool) -> rune::Result<Module> {
+    let mut module = Module::with_item(&["native"]);
+    module.ty::<Counter>()?;
+
+    if functions {
+        module.inst_fn("get", |c: &Counter| c.value)?;
+        module.inst_fn(Protocol::ADD_ASSIGN, |c: &mut Counter, n: i64| {
+            c.value += n;
+        })?;
+    }
+
+    Ok(module)
+}
+
+fn build(context: &Context) -> rune::Result<Unit> {
+    let mut sources = rune::sources! {
+        entry => {
+            pub fn main(n) {
+                let s = String::from_str("hello");
+                native::add(s.len(), n)
+            }
+        }
+    };
+
+    Ok(rune::prepare(&mut sources).with_context(context).build()?)
+}
+
+#[test]
+fn test_unit_file_roundtrip() -> rune::Result<()> {
+    let mut context = rune_modules::default_context()?;
+    context.install(&native_module()?)?;
+
+    let mut file = Vec::new();
+    build(&context)?.to_writer(&mut file, &context)?;
+    assert_eq!(&file[..4], b"RNC\0");
+
+    let unit = Unit::from_reader(&file[..], &context)?;
+    let mut vm = Vm::new(Arc::new(context.runtime()), Arc::new(unit));
+    let output = vm.call(["main"], (10i64,))?;
+    assert_eq!(i64::from_value(output)?, 15);
+    Ok(())
+}
+
+#[test]
+fn test_unit_file_missing_native() -> rune::Result<()> {
+    let mut context = rune_modules::default_context()?;
+    context.install(&native_module()?)?;
+
+    let mut file = Vec::new();
+    build(&context)?.to_writer(&mut file, &context)?;
+
+    // A context with every function but the one from `native_module`.
+    let context = rune_modules::default_context()?;
+
+    match Unit::from_reader(&file[..], &context) {
+        Err(UnitFileError::MissingNatives { missing }) => {
+            let names = missing.iter().map(|(_, name)| &**name).collect::<Vec<_>>();
+            assert_eq!(names.len(), 1);
+            assert!(names[0].starts_with("native::add"), "{:?}", names);
+        }
+        other => panic!("expected missing natives, got {:?}", other.map(|_| ())),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_unit_file_missing_instance_fns() -> rune::Result<()> {
+    let mut context = Context::with_default_modules()?;
+    context.install(&counter_module(true)?)?;
+
+    let mut sources = rune::sources! {
+        entry => {
+            pub fn main(c) {
+                c += 1;
+                c.get()
+            }
+        }
+    };
+
+    let unit = rune::prepare(&mut sources).with_context(&context).build()?;
+
+    let mut file = Vec::new();
+    unit.to_writer(&mut file, &context)?;
+
+    let mut context = Context::with_default_modules()?;
+    context.install(&counter_module(false)?)?;
+
+    match Unit::from_reader(&file[..], &context) {
+        Err(UnitFileError::MissingNatives { missing }) => {
+            let names = missing.iter().map(|(_, name)| &**name).collect::<Vec<_>>();
+            assert_eq!(names.len(), 2, "{:?}", names);
+            assert!(names.iter().any(|n| n.contains("<+=>")), "{:?}", names);
+            assert!(names.iter().any(|n| n.contains("get")), "{:?}", names);
+        }
+        other => panic!("expected missing natives, got {:?}", other.map(|_| ())),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_unit_file_size_limit() {
+    let context = Context::new();
+
+    // A header which claims the version string is `u64::MAX` bytes long.
+    let mut file = b"RNC\0".to_vec();
+    file.extend(1u32.to_le_bytes());
+    file.extend(u64::MAX.to_le_bytes());
+
+    assert!(matches!(
+        Unit::from_reader(&file[..], &context),
+        Err(UnitFileError::Encoding { .. })
+    ));
+}
+
+#[test]
+fn test_unit_file_bad_header() {
+    let context = Context::new();
+
+    assert!(matches!(
+        Unit::from_reader(&b"nope"[..], &context),
+        Err(UnitFileError::BadMagic)
+    ));
+
+    let mut file = b"RNC\0".to_vec();
+    file.extend(99u32.to_le_bytes());
+
+    assert!(matches!(
+        Unit::from_reader(&file[..], &context),
+        Err(UnitFileError::UnsupportedFormat { actual: 99, .. })
+    ));
+}
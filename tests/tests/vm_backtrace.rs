@@ -0,0 +1,137 @@
+use rune::runtime::{BacktraceFrame, VmError};
+use rune::termcolor::NoColor;
+use rune::{Diagnostics, Sources};
+use rune_tests::*;
+
+/// Run `main` in the given source, returning the error it raised together
+/// with the sources it was compiled from.
+fn error(source: &str) -> (VmError, Sources) {
+    let context = rune_modules::default_context().expect("default context");
+    let mut sources = sources(source);
+    let mut diagnostics = Diagnostics::new();
+
+    let result: Result<(), _> =
+        run_helper(&context, &mut sources, &mut diagnostics, ["main"], ());
+
+    match result {
+        Err(RunError::VmError(error)) => (error, sources),
+        _ => panic!("expected a virtual machine error"),
+    }
+}
+
+/// Describe each frame as a function name and, if available, a line.
+fn describe(error: &VmError, sources: &Sources) -> Vec<(String, Option<usize>)> {
+    let mut output = Vec::new();
+
+    for frame in error.backtrace() {
+        match frame {
+            BacktraceFrame::Native { name, .. } => {
+                output.push((name.expect("native name").into(), None));
+            }
+            BacktraceFrame::Script {
+                function, location, ..
+            } => {
+                let line = location.map(|(source_id, span)| {
+                    let source = sources.get(source_id).expect("source");
+                    source.pos_to_utf16cu_linecol(span.start.into_usize()).0 + 1
+                });
+
+                output.push((function.expect("function name"), line));
+            }
+            _ => (),
+        }
+    }
+
+    output
+}
+
+#[test]
+fn test_backtrace_script_frames() {
+    let (error, sources) = error(
+        r#"
+        fn c() {
+            1 / 0
+        }
+
+        fn b() {
+            c()
+        }
+
+        pub fn main() {
+            b()
+        }
+        "#,
+    );
+
+    assert_eq!(
+        describe(&error, &sources),
+        [
+            (String::from("c"), Some(3)),
+            (String::from("b"), Some(7)),
+            (String::from("main"), Some(11)),
+        ]
+    );
+}
+
+#[test]
+fn test_backtrace_native_frames() {
+    let (error, sources) = error(
+        r#"
+        pub fn main() {
+            [1, 2].iter().map(|v| {
+                panic!("failed");
+            }).collect::<Vec>()
+        }
+        "#,
+    );
+
+    let frames = describe(&error, &sources);
+    let names = frames.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+
+    assert_eq!(
+        names,
+        [
+            "::std::panic",
+            "main::$0::$0",
+            "::std::iter::Iterator::collect",
+            "main"
+        ]
+    );
+
+    assert_eq!(frames[1].1, Some(4));
+    assert_eq!(frames[3].1, Some(3));
+}
+
+#[test]
+fn test_backtrace_emit() {
+    let (error, sources) = error(
+        r#"
+        fn inner() {
+            let a = [];
+            a.push(1, 2)
+        }
+
+        pub fn main() {
+            inner()
+        }
+        "#,
+    );
+
+    let mut out = NoColor::new(Vec::new());
+    error.emit(&mut out, &sources).expect("emit");
+    let out = String::from_utf8(out.into_inner()).expect("utf-8");
+
+    let backtrace = out
+        .split_once("stack backtrace:\n")
+        .expect("backtrace")
+        .1;
+
+    assert_eq!(
+        backtrace,
+        "   0: ::std::vec::Vec::push\n   \
+            1: inner\n             \
+              at main:4:13\n   \
+            2: main\n             \
+              at main:8:13\n"
+    );
+}
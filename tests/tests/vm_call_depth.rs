@@ -0,0 +1,111 @@
+use rune::runtime::{BacktraceFrame, VmError, VmErrorKind};
+use rune::{FromValue, Vm};
+use std::sync::Arc;
+
+fn prepare_vm(sources: &mut rune::Sources) -> rune::Result<Vm> {
+    let context = rune_modules::default_context()?;
+    let unit = rune::prepare(sources).with_context(&context).build()?;
+    Ok(Vm::new(Arc::new(context.runtime()), Arc::new(unit)))
+}
+
+fn is_stack_overflow(error: &VmError) -> bool {
+    matches!(
+        error.as_unwound().0,
+        VmErrorKind::StackOverflow { limit: 100 }
+    )
+}
+
+/// Count the frames in the backtrace of an error which are in the given
+/// script function.
+fn count_frames(error: &VmError, name: &str) -> usize {
+    error
+        .backtrace()
+        .into_iter()
+        .filter(|frame| {
+            matches!(frame, BacktraceFrame::Script { function: Some(function), .. } if function == name)
+        })
+        .count()
+}
+
+#[test]
+fn test_call_depth_recursion() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            fn recurse(n) {
+                if n == 0 {
+                    return 0;
+                }
+
+                recurse(n - 1) + 1
+            }
+
+            pub fn main(n) {
+                recurse(n)
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_max_call_depth(100);
+
+    let output = vm.call(["main"], (50i64,))?;
+    assert_eq!(i64::from_value(output)?, 50);
+
+    let error = vm.call(["main"], (1000i64,)).unwrap_err();
+    assert!(is_stack_overflow(&error), "{}", error);
+    assert_eq!(count_frames(&error, "recurse"), 99);
+
+    // The virtual machine is usable after overflowing.
+    let output = vm.call(["main"], (10i64,))?;
+    assert_eq!(i64::from_value(output)?, 10);
+    Ok(())
+}
+
+#[test]
+fn test_call_depth_native_reentry() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            fn recurse(n) {
+                [n].iter().map(|n| recurse(n + 1)).collect::<Vec>()
+            }
+
+            pub fn main() {
+                recurse(0)
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_max_call_depth(100);
+
+    let error = vm.call(["main"], ()).unwrap_err();
+    assert!(is_stack_overflow(&error), "{}", error);
+    assert!(count_frames(&error, "recurse") > 10);
+    Ok(())
+}
+
+#[test]
+fn test_call_depth_unlimited() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            fn recurse(n) {
+                if n == 0 {
+                    return 0;
+                }
+
+                recurse(n - 1) + 1
+            }
+
+            pub fn main(n) {
+                recurse(n)
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&mut sources)?;
+    assert_eq!(vm.max_call_depth(), None);
+
+    let output = vm.call(["main"], (10000i64,))?;
+    assert_eq!(i64::from_value(output)?, 10000);
+    Ok(())
+}
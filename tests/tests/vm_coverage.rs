@@ -0,0 +1,101 @@
+use rune::runtime::Coverage;
+use rune::Vm;
+use rune_tests::sources;
+use std::sync::Arc;
+
+#[test]
+fn test_coverage_lines_and_branches() -> rune::Result<()> {
+    let context = rune_modules::default_context()?;
+
+    let mut sources = sources(
+        r#"
+        fn classify(n) {
+            if n > 0 {
+                1
+            } else if n < 0 {
+                2
+            } else {
+                3
+            }
+        }
+
+        pub fn main() {
+            [1, 0].iter().map(|n| classify(n)).collect::<Vec>()
+        }
+        "#,
+    );
+
+    let unit = rune::prepare(&mut sources).with_context(&context).build()?;
+    let unit = Arc::new(unit);
+    let coverage = Coverage::new(unit.clone());
+
+    let mut vm = Vm::new(Arc::new(context.runtime()), unit);
+    vm.set_coverage(coverage.clone());
+    vm.call(["main"], ())?;
+
+    let files = coverage.files(&sources);
+    assert_eq!(files.len(), 1);
+    let file = &files[0];
+
+    let hits = |line: usize| {
+        file.lines
+            .iter()
+            .find(|l| l.line == line)
+            .map(|l| l.hits)
+            .unwrap_or_else(|| panic!("no coverage for line {}", line))
+    };
+
+    // The first line of the sources is empty, so `classify` starts on line 2.
+    assert_eq!(hits(3), 2);
+    assert_eq!(hits(4), 1);
+    assert_eq!(hits(6), 0);
+    assert_eq!(hits(8), 1);
+    assert_eq!(file.lines_hit(), file.lines.len() - 1);
+
+    // Both directions of the first condition were taken, but only one of the
+    // second.
+    assert_eq!(file.branches_found(), 4);
+    assert_eq!(file.branches_hit(), 3);
+
+    let mut lcov = Vec::new();
+    coverage.write_lcov(&sources, &mut lcov)?;
+    let lcov = String::from_utf8(lcov)?;
+
+    assert!(lcov.starts_with("SF:main\n"), "{}", lcov);
+    assert!(lcov.contains("DA:6,0\n"), "{}", lcov);
+    assert!(lcov.contains("BRF:4\nBRH:3\n"), "{}", lcov);
+    assert!(lcov.ends_with("end_of_record\n"), "{}", lcov);
+    Ok(())
+}
+
+#[test]
+fn test_coverage_send_execute() -> rune::Result<()> {
+    let context = rune_modules::default_context()?;
+
+    let mut sources = sources(
+        r#"
+        pub async fn main(n) {
+            if n > 0 { 1 } else { 2 }
+        }
+        "#,
+    );
+
+    let unit = rune::prepare(&mut sources).with_context(&context).build()?;
+    let unit = Arc::new(unit);
+    let coverage = Coverage::new(unit.clone());
+
+    let mut vm = Vm::new(Arc::new(context.runtime()), unit);
+    vm.set_coverage(coverage.clone());
+
+    let execution = vm.send_execute(["main"], (1i64,))?;
+
+    std::thread::spawn(move || {
+        futures_executor::block_on(execution.async_complete()).map(|_| ())
+    })
+    .join()
+    .expect("thread panicked")?;
+
+    let files = coverage.files(&sources);
+    assert_eq!(files[0].branches_hit(), 1);
+    Ok(())
+}
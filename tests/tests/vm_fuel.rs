@@ -0,0 +1,235 @@
+use futures_executor::block_on;
+use rune::runtime::{GeneratorState, VmError, VmErrorKind, VmHaltInfo};
+use rune::{FromValue, Vm};
+use std::sync::Arc;
+
+fn prepare_vm(sources: &mut rune::Sources) -> rune::Result<Vm> {
+    let context = rune_modules::default_context()?;
+    let unit = rune::prepare(sources).with_context(&context).build()?;
+    Ok(Vm::new(Arc::new(context.runtime()), Arc::new(unit)))
+}
+
+fn is_out_of_fuel(error: &VmError) -> bool {
+    matches!(
+        error.as_unwound().0,
+        VmErrorKind::Halted {
+            halt: VmHaltInfo::OutOfFuel
+        }
+    )
+}
+
+#[test]
+fn test_fuel_resume() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            fn add(a, b) {
+                a + b
+            }
+
+            pub fn main() {
+                let n = 0;
+
+                for i in 0..100 {
+                    n = add(n, i);
+                }
+
+                n
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_fuel(50);
+
+    let mut execution = vm.execute(["main"], ())?;
+    let mut halts = 0;
+
+    let output = loop {
+        match execution.complete() {
+            Ok(output) => break output,
+            Err(error) => {
+                assert!(is_out_of_fuel(&error), "{}", error);
+                assert_eq!(execution.vm_mut().fuel(), Some(0));
+                execution.vm_mut().set_fuel(50);
+                halts += 1;
+            }
+        }
+    };
+
+    assert!(halts > 10);
+    assert_eq!(i64::from_value(output)?, 4950);
+    Ok(())
+}
+
+#[test]
+fn test_fuel_infinite_loop() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            pub fn main() {
+                loop {}
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_fuel(1000);
+
+    let error = vm.call(["main"], ()).unwrap_err();
+    assert!(is_out_of_fuel(&error), "{}", error);
+    assert_eq!(vm.fuel(), Some(0));
+    Ok(())
+}
+
+#[test]
+fn test_fuel_shared_with_native_calls() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            pub fn main() {
+                [1, 2, 3].iter().map(|v| {
+                    loop {}
+                }).collect::<Vec>()
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_fuel(1000);
+
+    let error = vm.call(["main"], ()).unwrap_err();
+    assert!(is_out_of_fuel(&error), "{}", error);
+    Ok(())
+}
+
+#[test]
+fn test_fuel_generator() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            pub fn main() {
+                for i in 0..10 {
+                    yield i * 2;
+                }
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_fuel(5);
+
+    let mut generator = vm.execute(["main"], ())?.into_generator()?;
+    let mut values = Vec::new();
+
+    loop {
+        match generator.next() {
+            Ok(Some(value)) => values.push(i64::from_value(value)?),
+            Ok(None) => break,
+            Err(error) => {
+                assert!(is_out_of_fuel(&error), "{}", error);
+                generator.vm_mut().expect("running generator").set_fuel(5);
+            }
+        }
+    }
+
+    assert_eq!(values, [0, 2, 4, 6, 8, 10, 12, 14, 16, 18]);
+    Ok(())
+}
+
+#[test]
+fn test_fuel_async() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            fn add(a, b) {
+                a + b
+            }
+
+            pub async fn main() {
+                let n = 0;
+
+                for i in 0..100 {
+                    n = add(n, i);
+                }
+
+                n
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_fuel(50);
+
+    let mut execution = vm.execute(["main"], ())?;
+    let mut halts = 0;
+
+    let output = loop {
+        match block_on(execution.async_resume()) {
+            Ok(GeneratorState::Complete(output)) => break output,
+            Ok(GeneratorState::Yielded(..)) => panic!("unexpected yield"),
+            Err(error) => {
+                assert!(is_out_of_fuel(&error), "{}", error);
+                execution.vm_mut().set_fuel(50);
+                halts += 1;
+            }
+        }
+    };
+
+    assert!(halts > 10);
+    assert_eq!(i64::from_value(output)?, 4950);
+
+    // Async functions awaited by the script draw from the same fuel, so an
+    // infinite loop in one is stopped.
+    let mut sources = rune::sources! {
+        entry => {
+            async fn spin() {
+                loop {}
+            }
+
+            pub async fn main() {
+                spin().await
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_fuel(1000);
+
+    let error = block_on(vm.async_call(["main"], ())).unwrap_err();
+    assert!(is_out_of_fuel(&error), "{}", error);
+    Ok(())
+}
+
+#[test]
+fn test_fuel_stepping() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            pub fn main() {
+                let n = 0;
+
+                for i in 0..10 {
+                    n += i;
+                }
+
+                n
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_fuel(10_000);
+    vm.call(["main"], ())?;
+    let used = 10_000 - vm.fuel().expect("fuel");
+
+    // Stepping yields after every instruction, which shouldn't use any more
+    // fuel than running to completion.
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_fuel(10_000);
+    let mut execution = vm.execute(["main"], ())?;
+
+    let output = loop {
+        if let Some(output) = execution.step()? {
+            break output;
+        }
+    };
+
+    assert_eq!(i64::from_value(output)?, 45);
+    assert_eq!(10_000 - execution.vm_mut().fuel().expect("fuel"), used);
+    Ok(())
+}
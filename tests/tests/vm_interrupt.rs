@@ -0,0 +1,175 @@
+use futures_executor::block_on;
+use rune::runtime::{BacktraceFrame, InterruptHandle, VmError, VmErrorKind};
+use rune::{Context, FromValue, Module, Vm};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+fn prepare_vm(context: &Context, sources: &mut rune::Sources) -> rune::Result<Vm> {
+    let unit = rune::prepare(sources).with_context(context).build()?;
+    Ok(Vm::new(Arc::new(context.runtime()), Arc::new(unit)))
+}
+
+fn is_interrupted(error: &VmError) -> bool {
+    matches!(error.as_unwound().0, VmErrorKind::Interrupted)
+}
+
+/// Interrupt the virtual machine from another thread after a short while.
+fn interrupt_later(handle: InterruptHandle) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    })
+}
+
+/// The names of the script functions in the backtrace of an error.
+fn functions(error: &VmError) -> Vec<String> {
+    let mut output = Vec::new();
+
+    for frame in error.backtrace() {
+        if let BacktraceFrame::Script {
+            function: Some(function),
+            ..
+        } = frame
+        {
+            output.push(function);
+        }
+    }
+
+    output
+}
+
+#[test]
+fn test_interrupt_loop() -> rune::Result<()> {
+    let context = rune_modules::default_context()?;
+
+    let mut sources = rune::sources! {
+        entry => {
+            fn spin() {
+                loop {}
+            }
+
+            pub fn main() {
+                spin()
+            }
+
+            pub fn add(a, b) {
+                a + b
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&context, &mut sources)?;
+    let handle = vm.interrupt_handle();
+    let interrupter = interrupt_later(handle.clone());
+
+    let error = vm.call(["main"], ()).unwrap_err();
+    interrupter.join().expect("interrupter panicked");
+
+    assert!(is_interrupted(&error), "{}", error);
+    assert_eq!(functions(&error), ["spin", "main"]);
+
+    // The interrupt stays pending until it's reset.
+    assert!(handle.is_interrupted());
+    let error = vm.call(["main"], ()).unwrap_err();
+    assert!(is_interrupted(&error), "{}", error);
+
+    handle.reset();
+    let output = vm.call(["add"], (1i64, 2i64))?;
+    assert_eq!(i64::from_value(output)?, 3);
+    Ok(())
+}
+
+#[test]
+fn test_interrupt_native_closure() -> rune::Result<()> {
+    let context = rune_modules::default_context()?;
+
+    let mut sources = rune::sources! {
+        entry => {
+            pub fn main() {
+                [1, 2, 3].iter().map(|v| {
+                    loop {}
+                }).collect::<Vec>()
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&context, &mut sources)?;
+    let interrupter = interrupt_later(vm.interrupt_handle());
+
+    let error = vm.call(["main"], ()).unwrap_err();
+    interrupter.join().expect("interrupter panicked");
+
+    assert!(is_interrupted(&error), "{}", error);
+    Ok(())
+}
+
+#[test]
+fn test_interrupt_nested_protocol() -> rune::Result<()> {
+    let context = rune_modules::default_context()?;
+
+    let mut sources = rune::sources! {
+        entry => {
+            struct Point { x }
+
+            impl Point {
+                #[protocol(EQ)]
+                fn eq(self, other) {
+                    loop {}
+                }
+            }
+
+            pub fn main() {
+                let a = Point { x: 1 };
+                let b = Point { x: 2 };
+
+                loop {
+                    if a == b {
+                        break;
+                    }
+                }
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&context, &mut sources)?;
+    let handle = vm.interrupt_handle();
+    let interrupter = interrupt_later(handle.clone());
+
+    let error = vm.call(["main"], ()).unwrap_err();
+    interrupter.join().expect("interrupter panicked");
+
+    // The protocol function runs in a nested virtual machine, which observes
+    // the interrupt without consuming it for the one it was started from.
+    assert!(is_interrupted(&error), "{}", error);
+    assert_eq!(functions(&error), ["Point::eq", "main"]);
+    assert!(handle.is_interrupted());
+    Ok(())
+}
+
+#[test]
+fn test_interrupt_pending_future() -> rune::Result<()> {
+    let mut module = Module::new();
+    module.async_function(&["pending"], std::future::pending::<()>)?;
+
+    let mut context = rune_modules::default_context()?;
+    context.install(&module)?;
+
+    let mut sources = rune::sources! {
+        entry => {
+            pub async fn main() {
+                pending().await
+            }
+        }
+    };
+
+    let mut vm = prepare_vm(&context, &mut sources)?;
+    let interrupter = interrupt_later(vm.interrupt_handle());
+
+    let error = block_on(vm.async_call(["main"], ())).unwrap_err();
+    interrupter.join().expect("interrupter panicked");
+
+    assert!(is_interrupted(&error), "{}", error);
+    assert_eq!(functions(&error), ["main"]);
+    Ok(())
+}
@@ -0,0 +1,257 @@
+use rune::runtime::{Shared, VmError, VmErrorKind};
+use rune::{FromValue, Value, Vm};
+use rune_tests::sources;
+use std::sync::Arc;
+
+fn prepare_vm(sources: &mut rune::Sources) -> rune::Result<Vm> {
+    let context = rune_modules::default_context()?;
+    let unit = rune::prepare(sources).with_context(&context).build()?;
+    Ok(Vm::new(Arc::new(context.runtime()), Arc::new(unit)))
+}
+
+fn is_memory_limit_exceeded(error: &VmError) -> bool {
+    matches!(
+        error.as_unwound().0,
+        VmErrorKind::MemoryLimitExceeded { limit, usage } if *limit == 1 << 16 && *usage > *limit
+    )
+}
+
+/// Run `main` with the given memory limit, expecting it to exceed it.
+fn expect_exceeded(mut sources: rune::Sources) -> rune::Result<()> {
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_memory_limit(1 << 16);
+
+    let error = vm.call(["main"], ()).unwrap_err();
+    assert!(is_memory_limit_exceeded(&error), "{}", error);
+    Ok(())
+}
+
+#[test]
+fn test_memory_limit_vec() -> rune::Result<()> {
+    expect_exceeded(sources(
+        r#"
+            pub fn main() {
+                let v = [1, 2, 3];
+
+                loop {
+                    v.push(v.clone());
+                }
+            }"#,
+    ))
+}
+
+#[test]
+fn test_memory_limit_string() -> rune::Result<()> {
+    expect_exceeded(sources(
+        r#"
+            pub fn main() {
+                let s = String::new();
+
+                loop {
+                    s += "0123456789";
+                }
+            }"#,
+    ))
+}
+
+#[test]
+fn test_memory_limit_object() -> rune::Result<()> {
+    expect_exceeded(sources(
+        r#"
+            pub fn main() {
+                let o = #{};
+                let n = 0;
+
+                loop {
+                    o[`key${n}`] = n;
+                    n += 1;
+                }
+            }"#,
+    ))
+}
+
+#[test]
+fn test_memory_limit_native_call() -> rune::Result<()> {
+    expect_exceeded(sources(
+        r#"
+            pub fn main() {
+                [1].iter().map(|_| {
+                    let v = [];
+
+                    loop {
+                        v.push(`${v.len()}`);
+                    }
+                }).collect::<Vec>()
+            }"#,
+    ))
+}
+
+#[test]
+fn test_memory_limit_generator() -> rune::Result<()> {
+    expect_exceeded(sources(
+        r#"
+            fn values() {
+                let v = [];
+
+                loop {
+                    v.push(`${v.len()}`);
+                    yield v.len();
+                }
+            }
+
+            pub fn main() {
+                for n in values() {
+                }
+            }"#,
+    ))
+}
+
+#[test]
+fn test_memory_limit_owned_generator() -> rune::Result<()> {
+    let mut sources = sources(
+        r#"
+            pub fn main() {
+                let v = [];
+
+                loop {
+                    v.push(`${v.len()}`);
+                    yield v.len();
+                }
+            }"#,
+    );
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_memory_limit(1 << 16);
+
+    let mut generator = vm.execute(["main"], ())?.into_generator()?.into_owned();
+
+    let error = (0..1_000_000)
+        .find_map(|_| generator.next().err())
+        .expect("generator should exceed the memory limit");
+
+    assert!(is_memory_limit_exceeded(&error), "{}", error);
+    Ok(())
+}
+
+#[test]
+fn test_memory_limit_reserve() -> rune::Result<()> {
+    let mut sources = sources(
+        r#"
+            pub fn main() {
+                let s = String::new();
+                s.reserve(1 << 30);
+            }"#,
+    );
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_memory_limit(1 << 16);
+
+    let error = vm.call(["main"], ()).unwrap_err();
+    assert!(is_memory_limit_exceeded(&error), "{}", error);
+    assert!(vm.memory_usage() <= 1 << 16);
+    Ok(())
+}
+
+#[test]
+fn test_memory_usage_is_released() -> rune::Result<()> {
+    let mut sources = sources(
+        r#"
+            pub fn main(n) {
+                for i in 0..n {
+                    let s = `value ${i}`;
+                    let v = [s, s, s];
+                }
+
+                let out = [];
+
+                for i in 0..n {
+                    out.push((i, `value ${i}`));
+                }
+
+                out
+            }"#,
+    );
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_memory_limit(1 << 20);
+
+    let output = vm.call(["main"], (1000i64,))?;
+    let usage = vm.memory_usage();
+    assert!(usage > 1000 * std::mem::size_of::<rune::Value>());
+
+    let output = Vec::<(i64, String)>::from_value(output)?;
+    assert_eq!(output.len(), 1000);
+    assert_eq!(output[999], (999, String::from("value 999")));
+    assert_eq!(vm.memory_usage(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_memory_usage_host_mutation() -> rune::Result<()> {
+    let mut sources = sources(
+        r#"
+            pub fn main() {
+                let s = String::new();
+                s.push_str("hello");
+                [s, s]
+            }"#,
+    );
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_memory_limit(1 << 20);
+
+    let output = Vec::<Value>::from_value(vm.call(["main"], ())?)?;
+    let usage = vm.memory_usage();
+
+    let string = output[0].clone().into_string()?;
+    let capacity = string.borrow_ref()?.capacity();
+    string.borrow_mut()?.push_str(&"x".repeat(1 << 12));
+    let grown = string.borrow_ref()?.capacity();
+    assert_eq!(vm.memory_usage(), usage + grown - capacity);
+
+    {
+        let mut string = string.borrow_mut()?;
+        string.clear();
+        string.shrink_to_fit();
+    }
+
+    assert_eq!(vm.memory_usage(), usage - capacity);
+
+    string.clone().into_mut()?.push_str("grown again");
+    assert!(vm.memory_usage() > 0);
+
+    drop(output);
+    assert!(vm.memory_usage() > 0);
+    assert_eq!(string.take()?, "grown again");
+    assert_eq!(vm.memory_usage(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_memory_usage_host_value() -> rune::Result<()> {
+    let mut sources = sources(
+        r#"
+            pub fn main(s, v) {
+                for n in 0..100 {
+                    s.push_str("0123456789");
+                    v.push(n);
+                }
+            }"#,
+    );
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_memory_limit(1 << 20);
+
+    let s = Shared::new(String::new());
+    let v = Shared::new(rune::runtime::Vec::new());
+
+    vm.call(
+        ["main"],
+        (Value::String(s.clone()), Value::Vec(v.clone())),
+    )?;
+
+    assert_eq!(s.borrow_ref()?.len(), 1000);
+    assert_eq!(v.borrow_ref()?.len(), 100);
+    assert_eq!(vm.memory_usage(), 0);
+    Ok(())
+}
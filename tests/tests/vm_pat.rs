@@ -41,3 +41,15 @@ fn test_match_binding() {
     };
     assert_eq!(out, true);
 }
+
+#[test]
+fn test_underscore_binding() {
+    let out: i64 = rune! {
+        pub fn main() {
+            let _a = 1;
+            let [_, _0, __] = [10, 2, 3];
+            _a + _0 + __
+        }
+    };
+    assert_eq!(out, 6);
+}
@@ -0,0 +1,169 @@
+use rune::compile::CompileErrorKind::*;
+use rune_tests::*;
+
+#[test]
+fn test_range_patterns() {
+    let out: (i64, i64, i64, i64, i64) = rune! {
+        fn classify(n) {
+            match n {
+                0 => 0,
+                1..=9 => 1,
+                10..100 => 2,
+                -10..0 => 3,
+                _ => 4,
+            }
+        }
+
+        pub fn main() {
+            (classify(0), classify(9), classify(99), classify(-10), classify(100))
+        }
+    };
+    assert_eq!(out, (0, 1, 2, 3, 4));
+
+    let out: (i64, i64, i64, i64) = rune! {
+        fn classify(c) {
+            match c {
+                'a'..='z' => 1,
+                '0'..='9' => 2,
+                b'a'..=b'f' => 3,
+                _ => 4,
+            }
+        }
+
+        pub fn main() {
+            (classify('q'), classify('7'), classify(b'c'), classify(7))
+        }
+    };
+    assert_eq!(out, (1, 2, 3, 4));
+}
+
+#[test]
+fn test_or_patterns() {
+    let out: (i64, i64, i64) = rune! {
+        fn classify(n) {
+            match n {
+                1 | 2 | 3 => 1,
+                4..=6 | 10 => 2,
+                _ => 3,
+            }
+        }
+
+        pub fn main() {
+            (classify(2), classify(10), classify(7))
+        }
+    };
+    assert_eq!(out, (1, 2, 3));
+
+    let out: (i64, i64, i64) = rune! {
+        enum Shape {
+            Circle(r),
+            Square(side),
+            Point,
+        }
+
+        fn size(shape) {
+            match shape {
+                Shape::Circle(n) | Shape::Square(n) => n,
+                Shape::Point => 0,
+            }
+        }
+
+        pub fn main() {
+            (size(Shape::Circle(2)), size(Shape::Square(3)), size(Shape::Point))
+        }
+    };
+    assert_eq!(out, (2, 3, 0));
+
+    let out: (i64, i64, i64) = rune! {
+        fn pick(value) {
+            match value {
+                (1, x) | (x, 1) => x,
+                (a, 2 | 3) => -a,
+                _ => 0,
+            }
+        }
+
+        pub fn main() {
+            (pick((1, 5)), pick((7, 1)), pick((4, 3)))
+        }
+    };
+    assert_eq!(out, (5, 7, -4));
+
+    let out: i64 = rune! {
+        pub fn main() {
+            let total = 0;
+
+            for value in [#{a: 1, b: 1}, #{a: 2, b: 2}, #{a: 4, b: 3}] {
+                if let #{a, b: 1} | #{a, b: 2} = value {
+                    total += a;
+                }
+            }
+
+            total
+        }
+    };
+    assert_eq!(out, 3);
+}
+
+#[test]
+fn test_or_patterns_let() {
+    let out: i64 = rune! {
+        pub fn main() {
+            let (x, 1) | (1, x) = (1, 4);
+            x
+        }
+    };
+    assert_eq!(out, 4);
+
+    let out: (bool, bool, bool) = rune! {
+        fn check(value) {
+            if let Some(1..=5) | None = value { true } else { false }
+        }
+
+        pub fn main() {
+            (check(Some(3)), check(None), check(Some(6)))
+        }
+    };
+    assert_eq!(out, (true, true, false));
+}
+
+#[test]
+fn test_const_patterns() {
+    let out: (bool, bool, String) = rune! {
+        const fn is_digit(c) {
+            if let '0'..='9' = c { true } else { false }
+        }
+
+        const fn weekend(day) {
+            if let "sat" | "sun" = day { true } else { false }
+        }
+
+        const fn sign(n) {
+            if let -100..0 = n { "negative" } else if let 0 = n { "zero" } else { "positive" }
+        }
+
+        const VALUES = (is_digit('5'), weekend("sun"), sign(0 - 5));
+
+        pub fn main() {
+            VALUES
+        }
+    };
+    assert_eq!(out, (true, true, String::from("negative")));
+}
+
+#[test]
+fn test_or_pattern_binding_mismatch() {
+    assert_compile_error! {
+        r#"pub fn main() { match (1, 2) { (a, 1) | (1, b) => a, _ => 0 } }"#,
+        span, OrPatternBindingMismatch { name } => {
+            assert_eq!(&*name, "b");
+        }
+    };
+
+    assert_compile_error! {
+        r#"pub fn main() { match (1, 2) { (a, 1) | (1, _) => 0, _ => 0 } }"#,
+        span, OrPatternBindingMismatch { name } => {
+            assert_eq!(&*name, "a");
+        }
+    };
+}
@@ -0,0 +1,150 @@
+use futures_executor::block_on;
+use rune::runtime::{FunctionProfile, Profiler};
+use rune::{FromValue, Vm};
+use std::sync::Arc;
+
+fn prepare_vm(sources: &mut rune::Sources) -> rune::Result<Vm> {
+    let context = rune_modules::default_context()?;
+    let unit = rune::prepare(sources).with_context(&context).build()?;
+    Ok(Vm::new(Arc::new(context.runtime()), Arc::new(unit)))
+}
+
+fn function<'a>(functions: &'a [FunctionProfile], name: &str) -> &'a FunctionProfile {
+    functions
+        .iter()
+        .find(|f| f.name == name)
+        .unwrap_or_else(|| panic!("missing function `{}`", name))
+}
+
+fn folded(profiler: &Profiler) -> rune::Result<Vec<String>> {
+    let mut out = Vec::new();
+    profiler.write_folded(&mut out)?;
+
+    let out = String::from_utf8(out)?;
+
+    Ok(out
+        .lines()
+        .map(|line| {
+            let (path, nanos) = line.rsplit_once(' ').expect("weight");
+            assert!(nanos.parse::<u64>().is_ok(), "{}", line);
+            path.to_owned()
+        })
+        .collect())
+}
+
+#[test]
+fn test_profiler_functions() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            fn fib(n) {
+                if n < 2 {
+                    return n;
+                }
+
+                fib(n - 1) + fib(n - 2)
+            }
+
+            pub fn main() {
+                fib(10)
+            }
+        }
+    };
+
+    let profiler = Profiler::new();
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_profiler(profiler.clone());
+
+    let output = vm.call(["main"], ())?;
+    assert_eq!(i64::from_value(output)?, 55);
+
+    let functions = profiler.functions();
+
+    let main = function(&functions, "main");
+    assert_eq!(main.calls, 1);
+    assert!(!main.native);
+
+    let fib = function(&functions, "fib");
+    assert_eq!(fib.calls, 177);
+    assert!(fib.instructions > fib.calls);
+    assert!(fib.exclusive <= fib.inclusive);
+    assert!(main.inclusive >= fib.inclusive);
+
+    let folded = folded(&profiler)?;
+    assert!(folded.contains(&String::from("main")));
+    assert!(folded.contains(&String::from("main;fib;fib;fib")));
+    Ok(())
+}
+
+#[test]
+fn test_profiler_native_and_async() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            fn double(n) {
+                n * 2
+            }
+
+            async fn later(n) {
+                double(n)
+            }
+
+            pub async fn main() {
+                let v = [1, 2, 3].iter().map(|n| double(n)).collect::<Vec>();
+                later(v[0]).await
+            }
+        }
+    };
+
+    let profiler = Profiler::new();
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_profiler(profiler.clone());
+
+    let output = block_on(vm.async_call(["main"], ()))?;
+    assert_eq!(i64::from_value(output)?, 4);
+
+    let functions = profiler.functions();
+    assert_eq!(function(&functions, "double").calls, 4);
+    assert!(function(&functions, "::std::iter::Iterator::collect").native);
+
+    let folded = folded(&profiler)?;
+    let closure = "main;::std::iter::Iterator::collect;main::$0::$0;double";
+    assert!(folded.iter().any(|path| path == closure), "{:?}", folded);
+    assert!(folded.iter().any(|path| path == "main;later;double"), "{:?}", folded);
+    Ok(())
+}
+
+#[test]
+fn test_profiler_send_execute() -> rune::Result<()> {
+    let mut sources = rune::sources! {
+        entry => {
+            fn double(n) {
+                n * 2
+            }
+
+            pub async fn main(n) {
+                double(n)
+            }
+        }
+    };
+
+    let profiler = Profiler::new();
+
+    let mut vm = prepare_vm(&mut sources)?;
+    vm.set_profiler(profiler.clone());
+
+    let execution = vm.send_execute(["main"], (21i64,))?;
+
+    let output = std::thread::spawn(move || {
+        let output = block_on(execution.async_complete())?;
+        i64::from_value(output)
+    })
+    .join()
+    .expect("thread panicked")?;
+
+    assert_eq!(output, 42);
+
+    let functions = profiler.functions();
+    assert_eq!(function(&functions, "double").calls, 1);
+    Ok(())
+}